@@ -0,0 +1,152 @@
+use ledge::prelude::*;
+use ledge::graphics::atlas::Atlas;
+use ledge::graphics::shader::*;
+use ledge::graphics::sprite::SpriteBatch;
+use ledge::graphics::{FrameGraph, InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+/// Where each frame's pivot goes. The untrimmed original is drawn faintly at each spot, so a
+/// correctly placed frame covers it exactly.
+const COLUMNS: [f32; 3] = [200.0, 500.0, 800.0];
+const ROW: f32 = 300.0;
+
+struct MainState {
+    shader: ShaderId,
+    original: Arc<Image>,
+    batch: Arc<SpriteBatch>,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs::load(renderer.device.clone()).unwrap(),
+            fragment: fs::load(renderer.device.clone()).unwrap(),
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        graphics::set_coordinate_system(renderer, CoordinateSystem::PixelsTopLeft);
+
+        // A 256x256 source with the art off center, and an atlas holding it trimmed to 128x128,
+        // once upright and once turned clockwise.
+        let original = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/pokeball-untrimmed.png",
+        ).expect("failed to load examples/images/pokeball-untrimmed.png");
+
+        let atlas = Atlas::load(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/atlas.json",
+        ).expect("failed to load examples/images/atlas.json");
+
+        let mut batch = SpriteBatch::new(atlas.image().clone());
+        for (x, name) in [(COLUMNS[1], "pokeball"), (COLUMNS[2], "pokeball-rotated")] {
+            let frame = atlas.frame(name).unwrap();
+            assert_eq!(frame.logical_size(), (original.width() as f32, original.height() as f32));
+            batch.insert_frame(frame, (x, ROW), 1.0, 0.0, Color::white());
+        }
+
+        MainState {
+            shader,
+            original: Arc::new(original),
+            batch: Arc::new(batch),
+        }
+    }
+
+    fn update(&mut self, _interface: &mut Interface) -> Result<()> {
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let (w, h) = (self.original.width() as f32, self.original.height() as f32);
+
+        let mut graph = FrameGraph::new(before_future);
+        graph.render_to_screen(renderer, 0, Color::grey().into(), |pass| {
+            for (i, x) in COLUMNS.iter().enumerate() {
+                let alpha = if i == 0 { 255 } else { 64 };
+                pass.draw_with(
+                    self.original.clone(),
+                    self.shader,
+                    DrawInfo::centered((*x, ROW), (w, h), 0.0, Color::rgba(255, 255, 255, alpha)),
+                )?;
+            }
+
+            pass.draw_with(self.batch.clone(), self.shader, DrawInfo::default())
+        })?;
+
+        graph.finish()
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("atlas", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
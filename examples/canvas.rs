@@ -0,0 +1,187 @@
+use ledge::prelude::*;
+use ledge::graphics::dynamic_image::DynamicImage;
+use ledge::graphics::render_pass::frame;
+use ledge::graphics::shader::*;
+use ledge::graphics::{InstanceData, Vertex};
+use ledge::input::mouse::MouseButton;
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+/// Side length in pixels of the world texture stamped into.
+const CANVAS_SIZE: u32 = 2048;
+const STAMP_SIZE: f32 = 96.0;
+
+struct MainState {
+    shader: ShaderId,
+    canvas: DynamicImage,
+    canvas_shader: ShaderId,
+    stamp: Image,
+    was_pressed: bool,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs::load(renderer.device.clone()).unwrap(),
+            fragment: fs::load(renderer.device.clone()).unwrap(),
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let screen_shader = render_pass.register_shader(shader.clone(), v_type.clone()).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        graphics::set_coordinate_system(renderer, CoordinateSystem::PixelsTopLeft);
+
+        let mut canvas = DynamicImage::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            CANVAS_SIZE,
+            CANVAS_SIZE,
+        ).unwrap();
+        let canvas_shader = canvas.register_shader(shader, v_type).unwrap();
+
+        let stamp = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/pokeball.png",
+        ).expect("failed to load examples/images/pokeball.png");
+
+        MainState {
+            shader: screen_shader,
+            canvas,
+            canvas_shader,
+            stamp,
+            was_pressed: false,
+        }
+    }
+
+    fn update(&mut self, interface: &mut Interface) -> Result<()> {
+        // Stamp once per click, where the cursor is over the canvas stretched across the window.
+        let pressed = interface.mouse_context.current_pressed == Some(MouseButton::Left);
+        if pressed && !self.was_pressed {
+            let viewport = interface.renderer.viewport();
+            let (x, y) = interface.cursor_position();
+            let center = (
+                x / viewport.w * CANVAS_SIZE as f32,
+                y / viewport.h * CANVAS_SIZE as f32,
+            );
+
+            let stamp = Arc::new(self.stamp.clone());
+            let shader = self.canvas_shader;
+            graphics::render_to_image(&mut interface.renderer, &mut self.canvas, |pass| {
+                pass.draw_with(
+                    stamp,
+                    shader,
+                    DrawInfo::centered(center, (STAMP_SIZE, STAMP_SIZE), 0.0, Color::white()),
+                )
+            })?;
+        }
+        self.was_pressed = pressed;
+
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let viewport = renderer.viewport();
+        let camera = renderer.default_camera();
+
+        let final_image = renderer.final_image();
+        let mut frame = renderer.render_passes[0].frame(
+            Color::grey().into(),
+            before_future,
+            final_image,
+            camera,
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    graphics::draw_sprite(
+                        &mut pass,
+                        self.canvas.image(),
+                        self.shader,
+                        (viewport.w / 2.0, viewport.h / 2.0),
+                        (viewport.w, viewport.h),
+                        0.0,
+                        Color::white(),
+                    )?;
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
+                }
+            }
+        }
+
+        Ok(after_future.unwrap())
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("canvas", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
@@ -0,0 +1,152 @@
+use ledge::prelude::*;
+use ledge::graphics::render_pass::frame;
+use ledge::graphics::shader::*;
+use ledge::graphics::{InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+struct MainState {
+    shader: ShaderId,
+    image: Image,
+    rotation: f32,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let vs = vs::load(renderer.device.clone()).unwrap();
+        let fs = fs::load(renderer.device.clone()).unwrap();
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs,
+            fragment: fs,
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        graphics::set_coordinate_system(renderer, CoordinateSystem::PixelsTopLeft);
+
+        let image = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/pokeball.png",
+        ).expect("failed to load examples/images/pokeball.png");
+
+        MainState {
+            shader,
+            image,
+            rotation: 0.0,
+        }
+    }
+
+    fn update(&mut self, _interface: &mut Interface) -> Result<()> {
+        self.rotation += 0.01;
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let viewport = renderer.viewport();
+        let center = (viewport.w / 2.0, viewport.h / 2.0);
+        let camera = renderer.default_camera();
+
+        let final_image = renderer.final_image();
+        let mut frame = renderer.render_passes[0].frame(
+            Color::grey().into(),
+            before_future,
+            final_image,
+            camera,
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    graphics::draw_sprite(
+                        &mut pass,
+                        &self.image,
+                        self.shader,
+                        center,
+                        (64.0, 64.0),
+                        self.rotation,
+                        Color::white(),
+                    )?;
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
+                }
+            }
+        }
+
+        Ok(after_future.unwrap())
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("centered sprite", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
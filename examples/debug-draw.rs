@@ -0,0 +1,213 @@
+use ledge::prelude::*;
+use ledge::graphics::debug::{self, DebugSpace};
+use ledge::graphics::font::Font;
+use ledge::graphics::render_pass::frame;
+use ledge::graphics::shader::*;
+use ledge::graphics::{InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+const SPRITE_SIZE: f32 = 0.2;
+
+struct Ball {
+    position: (f32, f32),
+    velocity: (f32, f32),
+}
+
+struct MainState {
+    shader: ShaderId,
+    camera: Arc<OrthographicCamera>,
+    image: Image,
+    balls: Vec<Ball>,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let vs = vs::load(renderer.device.clone()).unwrap();
+        let fs = fs::load(renderer.device.clone()).unwrap();
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs,
+            fragment: fs,
+            topology: VertexTopology::TriangleList,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        let font = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/font.png",
+        ).expect("failed to load examples/images/font.png");
+        debug::set_font(renderer, Arc::new(Font::new(font, 13, 6)));
+
+        let image = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/pokeball.png",
+        ).expect("failed to load examples/images/pokeball.png");
+
+        let balls = (0..8)
+            .map(|i| {
+                let t = i as f32 * 0.8;
+                Ball {
+                    position: (t.cos() * 0.5, t.sin() * 0.5),
+                    velocity: (t.sin() * 0.01, -t.cos() * 0.01),
+                }
+            })
+            .collect();
+
+        MainState {
+            shader,
+            camera: Arc::new(OrthographicCamera::new(1.0, 1000.0)),
+            image,
+            balls,
+        }
+    }
+
+    fn update(&mut self, interface: &mut Interface) -> Result<()> {
+        let renderer = &mut interface.renderer;
+
+        debug::set_space(renderer, DebugSpace::World);
+        renderer.debug.set_line_width(0.005);
+        debug::grid(renderer, 0.25);
+
+        for ball in self.balls.iter_mut() {
+            ball.position.0 += ball.velocity.0;
+            ball.position.1 += ball.velocity.1;
+
+            if ball.position.0 < -1.0 || ball.position.0 + SPRITE_SIZE > 1.0 {
+                ball.velocity.0 = -ball.velocity.0;
+            }
+            if ball.position.1 < -1.0 || ball.position.1 + SPRITE_SIZE > 1.0 {
+                ball.velocity.1 = -ball.velocity.1;
+            }
+
+            let bounds = Rect {
+                x: ball.position.0,
+                y: ball.position.1,
+                w: SPRITE_SIZE,
+                h: SPRITE_SIZE,
+            };
+            let center = (bounds.x + bounds.w / 2.0, bounds.y + bounds.h / 2.0);
+
+            debug::rect(renderer, bounds, Color::red());
+            debug::arrow(
+                renderer,
+                center,
+                (center.0 + ball.velocity.0 * 20.0, center.1 + ball.velocity.1 * 20.0),
+                Color::white(),
+            );
+        }
+
+        debug::set_space(renderer, DebugSpace::Screen);
+        renderer.debug.set_line_width(1.0);
+        debug::text(renderer, (8.0, 8.0), "AABBS AND VELOCITIES");
+
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let mut batch = SpriteBatch::new(self.image.clone());
+        for ball in self.balls.iter() {
+            let mut info = DrawInfo::default();
+            info.dest(ball.position.0, ball.position.1, 0.0);
+            info.scale(SPRITE_SIZE);
+            batch.insert(info);
+        }
+        let batch = Arc::new(batch);
+
+        let final_image = renderer.final_image();
+        let mut frame = renderer.render_passes[0].frame(
+            Color::grey().into(),
+            before_future,
+            final_image,
+            self.camera.clone(),
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    pass.draw_with(batch.clone(), self.shader, DrawInfo::default())?;
+                    debug::present(&mut pass, &mut renderer.debug, self.shader)?;
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
+                }
+            }
+        }
+
+        Ok(after_future.unwrap())
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("debug draw", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
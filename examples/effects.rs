@@ -0,0 +1,180 @@
+use ledge::prelude::*;
+use ledge::graphics::effects::{ColorGrade, Effect, EffectChain};
+use ledge::graphics::render_pass::frame;
+use ledge::graphics::shader::*;
+use ledge::graphics::{InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+struct MainState {
+    shader: ShaderId,
+    image: Image,
+    rotation: f32,
+    effects: EffectChain,
+}
+
+/// Number keys select the effect of the same index, 0 turns them all off.
+const EFFECT_KEYS: [KeyCode; 6] = [KeyCode::Key0, KeyCode::Key1, KeyCode::Key2, KeyCode::Key3, KeyCode::Key4, KeyCode::Key5];
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let vs = vs::load(renderer.device.clone()).unwrap();
+        let fs = fs::load(renderer.device.clone()).unwrap();
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs,
+            fragment: fs,
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        graphics::set_coordinate_system(renderer, CoordinateSystem::PixelsTopLeft);
+
+        let image = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/pokeball.png",
+        ).expect("failed to load examples/images/pokeball.png");
+
+        let mut effects = EffectChain::new(renderer.queue.clone(), renderer.output_format()).unwrap();
+        effects.push(Effect::grayscale(1.0));
+        effects.push(Effect::vignette(0.75, 0.45));
+        effects.push(Effect::pixelate(8.0));
+        effects.push(Effect::crt(0.1, 0.4));
+        // Grading through the identity LUT should look exactly like no effect at all.
+        let lut = ColorGrade::identity_lut(renderer.queue.clone(), 32).unwrap();
+        effects.push(Effect::color_grade(lut, 1.0));
+        for i in 0..effects.len() {
+            effects.set_enabled(i, false);
+        }
+
+        MainState {
+            shader,
+            image,
+            rotation: 0.0,
+            effects,
+        }
+    }
+
+    fn update(&mut self, interface: &mut Interface) -> Result<()> {
+        self.rotation += 0.01;
+
+        let selected = EFFECT_KEYS
+            .iter()
+            .position(|key| interface.keyboard_context.is_key_pressed(*key));
+        if let Some(selected) = selected {
+            for i in 0..self.effects.len() {
+                self.effects.set_enabled(i, i + 1 == selected);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let viewport = renderer.viewport();
+        let center = (viewport.w / 2.0, viewport.h / 2.0);
+        let camera = renderer.default_camera();
+
+        let scene = self.effects.scene_target(viewport)?;
+        let mut frame = renderer.render_passes[0].frame(
+            Color::grey().into(),
+            before_future,
+            scene,
+            camera,
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    graphics::draw_sprite(
+                        &mut pass,
+                        &self.image,
+                        self.shader,
+                        center,
+                        (64.0, 64.0),
+                        self.rotation,
+                        Color::white(),
+                    )?;
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
+                }
+            }
+        }
+
+        self.effects.apply(after_future.unwrap(), renderer.final_image())
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("effects", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
@@ -0,0 +1,34 @@
+use ledge::conf::Conf;
+use ledge::prelude::*;
+use winit::event::Event;
+use winit::event_loop::{ControlFlow, EventLoop};
+
+// An event loop owned by the application rather than ledge, as when it's shared with
+// another library. The interface is built on it and fed its window events.
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let event_loop: EventLoop<()> = EventLoop::with_user_event();
+    let mut interface = Interface::from_event_loop(Conf::new("external event loop"), &event_loop).unwrap();
+
+    event_loop.run(move |event, _, control_flow| {
+        *control_flow = ControlFlow::Poll;
+
+        match event {
+            Event::WindowEvent { event, .. } => {
+                if let Some(event) = WindowEvent::from_raw(&event) {
+                    interface.process_event(&event);
+                }
+
+                if interface.close_requested {
+                    *control_flow = ControlFlow::Exit;
+                }
+            }
+            Event::MainEventsCleared => {
+                let future = interface.renderer.begin_frame().unwrap();
+                interface.renderer.end_frame(future);
+            }
+            _ => {}
+        }
+    });
+}
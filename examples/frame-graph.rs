@@ -0,0 +1,194 @@
+use ledge::prelude::*;
+use ledge::graphics::dynamic_image::DynamicImage;
+use ledge::graphics::shader::*;
+use ledge::graphics::{FrameGraph, InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+use bytemuck::{Pod, Zeroable};
+
+use vulkano::sync::GpuFuture;
+
+/// Matches the push constant block in ripple.frag.
+#[repr(C)]
+#[derive(Clone, Copy, Pod, Zeroable)]
+struct RippleParams {
+    time: f32,
+}
+
+struct MainState {
+    /// Draws sprites into the scene target.
+    scene_shader: ShaderId,
+    /// Draws the scene target to the screen with a ripple.
+    post_shader: ShaderId,
+    scene: DynamicImage,
+    background: Arc<Image>,
+    sprite: Arc<Image>,
+    time: f32,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod basic_fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        mod ripple_fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/ripple.frag",
+            }
+        }
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let basic = Arc::new(Shader {
+            vertex: vs::load(renderer.device.clone()).unwrap(),
+            fragment: basic_fs::load(renderer.device.clone()).unwrap(),
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let ripple = Arc::new(Shader {
+            vertex: vs::load(renderer.device.clone()).unwrap(),
+            fragment: ripple_fs::load(renderer.device.clone()).unwrap(),
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let post_shader = render_pass.register_shader(ripple, v_type.clone()).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        graphics::set_coordinate_system(renderer, CoordinateSystem::PixelsTopLeft);
+
+        let viewport = renderer.viewport();
+        let mut scene = DynamicImage::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            viewport.w as u32,
+            viewport.h as u32,
+        ).unwrap();
+        let scene_shader = scene.register_shader(basic, v_type).unwrap();
+
+        let background = Image::from_color(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            Color::grey(),
+        );
+
+        let sprite = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/pokeball.png",
+        ).expect("failed to load examples/images/pokeball.png");
+
+        MainState {
+            scene_shader,
+            post_shader,
+            scene,
+            background: Arc::new(background),
+            sprite: Arc::new(sprite),
+            time: 0.0,
+        }
+    }
+
+    fn update(&mut self, _interface: &mut Interface) -> Result<()> {
+        self.time += 0.01;
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let viewport = renderer.viewport();
+        let (w, h) = (self.scene.width() as f32, self.scene.height() as f32);
+
+        let mut graph = FrameGraph::new(before_future);
+
+        // Pass A: the scene, into an offscreen target. The scene target keeps its contents
+        // between frames, so the background covers the last frame first.
+        let (scene_shader, background, sprite, time) = (self.scene_shader, self.background.clone(), self.sprite.clone(), self.time);
+        graph.render_to_image(&mut self.scene, |pass| {
+            pass.draw_with(
+                background,
+                scene_shader,
+                DrawInfo::centered((w / 2.0, h / 2.0), (w, h), 0.0, Color::white()),
+            )?;
+            pass.draw_with(
+                sprite,
+                scene_shader,
+                DrawInfo::centered((w / 2.0, h / 2.0), (256.0, 256.0), time, Color::white()),
+            )
+        })?;
+
+        // Pass B: samples A, declared after it, so it always sees this frame's scene.
+        let scene = Arc::new(self.scene.image().clone());
+        let post_shader = self.post_shader;
+        graph.render_to_screen(renderer, 0, Color::black().into(), |pass| {
+            graphics::draw_with_params(
+                pass,
+                scene,
+                post_shader,
+                DrawInfo::centered((viewport.w / 2.0, viewport.h / 2.0), (viewport.w, viewport.h), 0.0, Color::white()),
+                RippleParams { time },
+            )
+        })?;
+
+        graph.finish()
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("frame-graph", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
@@ -0,0 +1,141 @@
+use ledge::prelude::*;
+use ledge::graphics::shader::*;
+use ledge::graphics::sprite::SpriteBatch;
+use ledge::graphics::{FrameGraph, InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+struct MainState {
+    shader: ShaderId,
+    white: Arc<Image>,
+    batch: Arc<SpriteBatch>,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs::load(renderer.device.clone()).unwrap(),
+            fragment: fs::load(renderer.device.clone()).unwrap(),
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        graphics::set_coordinate_system(renderer, CoordinateSystem::PixelsTopLeft);
+
+        let white = Image::from_color(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            Color::white(),
+        );
+
+        let image = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/pokeball.png",
+        ).expect("failed to load examples/images/pokeball.png");
+
+        // A row of sprites, each shaded darker toward its bottom right.
+        let mut batch = SpriteBatch::new(image);
+        for i in 0..6 {
+            let mut info = DrawInfo::centered((120.0 + i as f32 * 150.0, 400.0), (128.0, 128.0), 0.0, Color::white());
+            info.diagonal_gradient(Color::white(), Color::rgba(120, 120, 160, 255));
+            batch.insert(info);
+        }
+
+        MainState {
+            shader,
+            white: Arc::new(white),
+            batch: Arc::new(batch),
+        }
+    }
+
+    fn update(&mut self, _interface: &mut Interface) -> Result<()> {
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let viewport = renderer.viewport();
+
+        let mut graph = FrameGraph::new(before_future);
+        graph.render_to_screen(renderer, 0, Color::black().into(), |pass| {
+            // The sky, one quad from deep blue overhead to a pale horizon.
+            let mut sky = DrawInfo::centered((viewport.w / 2.0, viewport.h / 2.0), (viewport.w, viewport.h), 0.0, Color::white());
+            sky.vertical_gradient(Color::rgba(24, 48, 120, 255), Color::rgba(250, 190, 140, 255));
+            pass.draw_with(self.white.clone(), self.shader, sky)?;
+
+            pass.draw_with(self.batch.clone(), self.shader, DrawInfo::default())
+        })?;
+
+        graph.finish()
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("gradient", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
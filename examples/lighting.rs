@@ -0,0 +1,189 @@
+use ledge::prelude::*;
+use ledge::graphics::lighting::LightMap;
+use ledge::graphics::render_pass::frame;
+use ledge::graphics::shader::*;
+use ledge::graphics::{InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+struct MainState {
+    shader: ShaderId,
+    batch: Arc<SpriteBatch>,
+    lights: LightMap,
+    time: f32,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let vs = vs::load(renderer.device.clone()).unwrap();
+        let fs = fs::load(renderer.device.clone()).unwrap();
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs,
+            fragment: fs,
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        let image = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/pokeball.png",
+        ).expect("failed to load examples/images/pokeball.png");
+
+        let mut lights = LightMap::new(renderer, 0.5).unwrap();
+        lights.set_ambient(Color::rgba(20, 20, 40, 255));
+
+        // Every sprite's outline casts shadows, 256 segments in all.
+        let mut batch = SpriteBatch::new(image);
+        for y in 0..8 {
+            for x in 0..8 {
+                let (left, bottom) = (-1.0 + x as f32 * 0.25, -1.0 + y as f32 * 0.25);
+                let mut info = DrawInfo::default();
+                info.dest(left, bottom, 0.0);
+                info.scale(0.2);
+                batch.insert(info);
+
+                let (right, top) = (left + 0.2, bottom + 0.2);
+                lights.add_occluder(&[(left, bottom), (right, bottom), (right, top), (left, top), (left, bottom)]);
+            }
+        }
+
+        MainState {
+            shader,
+            batch: Arc::new(batch),
+            lights,
+            time: 0.0,
+        }
+    }
+
+    fn update(&mut self, _interface: &mut Interface) -> Result<()> {
+        self.time += 0.01;
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let viewport = renderer.viewport();
+        let projection = renderer.projection();
+        let camera = renderer.default_camera();
+
+        let final_image = renderer.final_image();
+        let mut frame = renderer.render_passes[0].frame(
+            Color::grey().into(),
+            before_future,
+            final_image,
+            camera,
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    pass.draw_with(self.batch.clone(), self.shader, DrawInfo::default())?;
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
+                }
+            }
+        }
+
+        self.lights.begin();
+        self.lights.add_light(
+            (self.time.cos() * 0.6, self.time.sin() * 0.6),
+            0.7,
+            Color::rgba(255, 190, 120, 255),
+            1.5,
+        );
+        self.lights.add_light(
+            ((self.time * 0.7).sin() * 0.5, (self.time * 1.3).cos() * 0.4),
+            0.5,
+            Color::rgba(120, 160, 255, 255),
+            1.2,
+        );
+
+        // Enough small lights to make 20 in total, each shadowed by every sprite in reach.
+        for i in 0..18 {
+            let angle = self.time * 0.5 + i as f32 * std::f32::consts::TAU / 18.0;
+            self.lights.add_light(
+                (angle.cos() * 0.85, angle.sin() * 0.85),
+                0.3,
+                Color::rgba(255, 255, 255, 255),
+                0.6,
+            );
+        }
+
+        let lit = self.lights.end(after_future.unwrap(), projection, viewport)?;
+        self.lights.composite(lit, renderer.final_image())
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("lighting", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
@@ -0,0 +1,163 @@
+use ledge::conf::ArgsError;
+use ledge::prelude::*;
+use ledge::graphics::render_pass::frame;
+use ledge::graphics::shader::*;
+use ledge::graphics::{InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+struct MainState {
+    shader: ShaderId,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let vs = vs::load(renderer.device.clone()).unwrap();
+        let fs = fs::load(renderer.device.clone()).unwrap();
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs,
+            fragment: fs,
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        graphics::set_coordinate_system(renderer, CoordinateSystem::PixelsTopLeft);
+
+        MainState { shader }
+    }
+
+    fn update(&mut self, _interface: &mut Interface) -> Result<()> {
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let viewport = renderer.viewport();
+        let camera = renderer.default_camera();
+
+        let final_image = renderer.final_image();
+        let mut frame = renderer.render_passes[0].frame(
+            Color::grey().into(),
+            before_future,
+            final_image,
+            camera,
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    pass.set_shader(self.shader);
+
+                    // A sidebar down the left, a header across the top, and a translucent
+                    // dialog over the middle.
+                    let sidebar = Rect { x: 0.0, y: 0.0, w: 160.0, h: viewport.h };
+                    graphics::draw_quad(&mut pass, sidebar, Color::rgba(40, 44, 52, 255))?;
+
+                    let header = Rect { x: 160.0, y: 0.0, w: viewport.w - 160.0, h: 48.0 };
+                    graphics::draw_quad(&mut pass, header, Color::rgba(97, 175, 239, 255))?;
+
+                    for i in 0..4 {
+                        let item = Rect { x: 16.0, y: 64.0 + i as f32 * 40.0, w: 128.0, h: 28.0 };
+                        graphics::draw_quad(&mut pass, item, Color::rgba(171, 178, 191, 255))?;
+                    }
+
+                    let dialog = Rect {
+                        x: viewport.w / 2.0 - 120.0,
+                        y: viewport.h / 2.0 - 80.0,
+                        w: 240.0,
+                        h: 160.0,
+                    };
+                    graphics::draw_quad(&mut pass, dialog, Color::rgba(224, 108, 117, 192))?;
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
+                }
+            }
+        }
+
+        Ok(after_future.unwrap())
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    // Try `--width 1024 --height 768` or `--fullscreen`.
+    let mut builder = InterfaceBuilder::new("panels", "Dan");
+    match builder.apply_args(std::env::args()) {
+        Ok(_) => {}
+        Err(ArgsError::Help(help)) => {
+            println!("{}", help);
+            return;
+        }
+        Err(e) => {
+            eprintln!("{}", e);
+            std::process::exit(2);
+        }
+    }
+
+    let (interface, event_loop) = builder.build().unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
@@ -0,0 +1,202 @@
+use ledge::prelude::*;
+use ledge::graphics::debug;
+use ledge::graphics::render_pass::frame;
+use ledge::graphics::shader::*;
+use ledge::graphics::{InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+struct MainState {
+    scene_shader: ShaderId,
+    ui_shader: ShaderId,
+    batch: Arc<SpriteBatch>,
+}
+
+/// 1, 2, and 3 render the scene at full, three quarter, and half resolution.
+const SCALE_KEYS: [(KeyCode, f32); 3] = [(KeyCode::Key1, 1.0), (KeyCode::Key2, 0.75), (KeyCode::Key3, 0.5)];
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs::load(renderer.device.clone()).unwrap(),
+            fragment: fs::load(renderer.device.clone()).unwrap(),
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut scene_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::single_pass_renderpass!(renderer.device.clone(),
+                attachments: {
+                    color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {}
+                }
+            ).unwrap(),
+        ).unwrap();
+        let scene_shader = scene_pass.register_shader(shader.clone(), v_type.clone()).unwrap();
+
+        // The UI is drawn over the resolved scene at native resolution, so it loads instead of clearing.
+        let mut ui_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::single_pass_renderpass!(renderer.device.clone(),
+                attachments: {
+                    color: {
+                        load: Load,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                pass: {
+                    color: [color],
+                    depth_stencil: {}
+                }
+            ).unwrap(),
+        ).unwrap();
+        let ui_shader = ui_pass.register_shader(shader, v_type).unwrap();
+
+        renderer.render_passes.push(scene_pass);
+        renderer.render_passes.push(ui_pass);
+
+        let image = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/pokeball.png",
+        ).expect("failed to load examples/images/pokeball.png");
+
+        // Enough overlapping sprites that filling the window's pixels is the bottleneck.
+        let mut batch = SpriteBatch::new(image);
+        for i in 0..2000 {
+            let t = i as f32 * 0.37;
+            let mut info = DrawInfo::default();
+            info.dest(t.cos() * 0.6 - 0.5, t.sin() * 0.6 - 0.5, 0.0);
+            info.scale(1.0);
+            batch.insert(info);
+        }
+
+        graphics::show_diagnostics(renderer, true);
+
+        MainState {
+            scene_shader,
+            ui_shader,
+            batch: Arc::new(batch),
+        }
+    }
+
+    fn update(&mut self, interface: &mut Interface) -> Result<()> {
+        let selected = SCALE_KEYS
+            .iter()
+            .find(|(key, _)| interface.keyboard_context.is_key_pressed(*key));
+        if let Some((_, scale)) = selected {
+            if graphics::render_scale(&interface.renderer) != *scale {
+                graphics::set_render_scale(&mut interface.renderer, *scale);
+            }
+        }
+
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let camera = renderer.default_camera();
+
+        let scene = renderer.scene_image()?;
+        let mut frame = renderer.render_passes[0].frame(
+            Color::grey().into(),
+            before_future,
+            scene,
+            camera.clone(),
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    pass.draw_with(self.batch.clone(), self.scene_shader, DrawInfo::default())?;
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
+                }
+            }
+        }
+
+        let resolved = renderer.resolve_scene(after_future.unwrap())?;
+
+        let final_image = renderer.final_image();
+        let mut frame = renderer.render_passes[1].frame(
+            Color::grey().into(),
+            resolved,
+            final_image,
+            camera,
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    debug::present(&mut pass, &mut renderer.debug, self.ui_shader)?;
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
+                }
+            }
+        }
+
+        Ok(after_future.unwrap())
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("render scale", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
@@ -1,13 +1,9 @@
-use ledge::event;
-use ledge::graphics::camera::OrthographicCamera;
-use ledge::interface::*;
-use ledge::graphics::{self, shader::*};
+use ledge::prelude::*;
+use ledge::graphics::shader::*;
+use ledge::graphics::render_pass::frame;
 use std::sync::Arc;
 use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
 use bytemuck::{Pod, Zeroable};
-use ledge::graphics::render_pass::frame;
-use ledge::graphics::Color;
-use ledge::graphics::image::Image;
 
 use anyhow::Result;
 
@@ -53,9 +49,11 @@ pub struct InstanceData {
     src: [f32; 4],
     color: [f32; 4],
     transform: [[f32; 4]; 4],
+    flags: u32,
+    corner_radius: f32,
 }
 
-vulkano::impl_vertex!(InstanceData, src, color, transform);
+vulkano::impl_vertex!(InstanceData, src, color, transform, flags, corner_radius);
 
 struct MainState {
     test_shader: ShaderId,
@@ -92,8 +90,8 @@ impl event::EventHandler for MainState {
             .instance::<InstanceData>();
 
         let test_shader = Arc::new(ledge::graphics::shader::Shader {
-            vertex: vs.entry_point("main").unwrap(),
-            fragment: fs.entry_point("main").unwrap(),
+            vertex: vs,
+            fragment: fs,
             topology: graphics::shader::VertexTopology::TriangleFan,
             // vertex_definition: v_type,
         });
@@ -128,13 +126,13 @@ impl event::EventHandler for MainState {
                 renderer.queue.clone(), 
                 renderer.samplers[0].clone(), 
                 "examples/images/pokeball.png",
-            ),
+            ).expect("failed to load examples/images/pokeball.png"),
         );
         
         MainState{
             test_shader,
             camera: Arc::new(OrthographicCamera::new(1.0, 1000.0)),
-            image: image,
+            image,
         }
     }
 
@@ -185,10 +183,12 @@ impl event::EventHandler for MainState {
 }
 
 fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
     let (interface, event_loop) = 
         InterfaceBuilder::new("test", "Dan")
             .build()
             .unwrap();
     
-    event::run::<MainState>(interface, event_loop);
+    event::run::<MainState, ()>(interface, event_loop);
 }
\ No newline at end of file
@@ -0,0 +1,176 @@
+use ledge::prelude::*;
+use ledge::graphics::shader::*;
+use ledge::graphics::render_pass::frame;
+use ledge::graphics::font::{draw_rich_text, Font};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+use bytemuck::{Pod, Zeroable};
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+pub struct TestVertex {
+    pub pos: [f32; 3],
+    pub uv: [f32; 2],
+    pub vert_color: [f32; 4],
+}
+
+vulkano::impl_vertex!(TestVertex, pos, uv, vert_color);
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+pub struct InstanceData {
+    src: [f32; 4],
+    color: [f32; 4],
+    transform: [[f32; 4]; 4],
+    flags: u32,
+    corner_radius: f32,
+}
+
+vulkano::impl_vertex!(InstanceData, src, color, transform, flags, corner_radius);
+
+struct MainState {
+    test_shader: ShaderId,
+    camera: Arc<OrthographicCamera>,
+    font: Arc<Font>,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let vs = vs::load(renderer.device.clone()).unwrap();
+        let fs = fs::load(renderer.device.clone()).unwrap();
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<TestVertex>()
+            .instance::<InstanceData>();
+
+        let test_shader = Arc::new(ledge::graphics::shader::Shader {
+            vertex: vs,
+            fragment: fs,
+            topology: graphics::shader::VertexTopology::TriangleFan,
+            // vertex_definition: v_type,
+        });
+
+        let mut render_pass = crate::graphics::render_pass::RenderPass::new( 
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let test_shader = render_pass.register_shader(test_shader, v_type).unwrap();
+
+        renderer.render_passes.push(render_pass);
+
+        let image = Image::new(
+            renderer.queue.clone(), 
+            renderer.samplers[0].clone(), 
+            "examples/images/font.png",
+        ).expect("failed to load examples/images/font.png");
+        let font = Arc::new(Font::new(image, 13, 6));
+        
+        MainState{
+            test_shader,
+            camera: Arc::new(OrthographicCamera::new(1.0, 1000.0)),
+            font,
+        }
+    }
+
+    fn update(&mut self, _interface: &mut Interface) -> Result<()> {
+        Ok(())
+    }
+    
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let clear = Color::grey();
+
+        let final_image = renderer.final_image();
+        let mut frame = renderer.render_passes[0].frame(
+            clear.into(), 
+            before_future, 
+            final_image, 
+            self.camera.clone(),
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    draw_rich_text(
+                        &mut pass,
+                        &self.font,
+                        self.test_shader,
+                        &[
+                            ("THE ", Color::white()),
+                            ("RED", Color::red()),
+                            (" WORD\nSTANDS OUT", Color::white()),
+                        ],
+                        (-0.9, -0.5),
+                        0.1,
+                    )?;
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
+                }
+            }
+        }
+
+        Ok(after_future.unwrap())
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) = 
+        InterfaceBuilder::new("rich text", "Dan")
+            .build()
+            .unwrap();
+    
+    event::run::<MainState, ()>(interface, event_loop);
+}
\ No newline at end of file
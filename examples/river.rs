@@ -0,0 +1,140 @@
+use ledge::prelude::*;
+use ledge::graphics::shader::*;
+use ledge::graphics::{FrameGraph, InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+/// Size in pixels of one tile of the river bed texture on screen.
+const TILE: f32 = 128.0;
+const RIVER_HEIGHT: f32 = 256.0;
+
+struct MainState {
+    shader: ShaderId,
+    water: Arc<Image>,
+    time: f32,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs::load(renderer.device.clone()).unwrap(),
+            fragment: fs::load(renderer.device.clone()).unwrap(),
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        graphics::set_coordinate_system(renderer, CoordinateSystem::PixelsTopLeft);
+
+        // The default sampler repeats, so the texture tiles past its edges.
+        let water = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/rock.png",
+        ).expect("failed to load examples/images/rock.png");
+
+        MainState {
+            shader,
+            water: Arc::new(water),
+            time: 0.0,
+        }
+    }
+
+    fn update(&mut self, _interface: &mut Interface) -> Result<()> {
+        self.time += 1.0 / 60.0;
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let viewport = renderer.viewport();
+        let center = (viewport.w / 2.0, viewport.h / 2.0);
+        let size = (viewport.w, RIVER_HEIGHT);
+        let tiles = (viewport.w / TILE, RIVER_HEIGHT / TILE);
+
+        let mut graph = FrameGraph::new(before_future);
+        graph.render_to_screen(renderer, 0, Color::rgba(60, 110, 50, 255).into(), |pass| {
+            // The river bed drifts slowly downstream, a second faster layer on top reads as the
+            // current. Only the uv offset changes from frame to frame.
+            let mut bed = DrawInfo::centered(center, size, 0.0, Color::rgba(70, 110, 170, 255));
+            bed.uv_scale(tiles.0, tiles.1);
+            bed.uv_offset(-self.time * 0.1, 0.0);
+            pass.draw_with(self.water.clone(), self.shader, bed)?;
+
+            let mut current = DrawInfo::centered(center, size, 0.0, Color::rgba(170, 210, 255, 90));
+            current.uv_scale(tiles.0 * 0.5, tiles.1 * 0.5);
+            current.uv_offset(-self.time * 0.35, (self.time * 0.8).sin() * 0.02);
+            pass.draw_with(self.water.clone(), self.shader, current)
+        })?;
+
+        graph.finish()
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("river", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
@@ -1,124 +1,201 @@
-use ledge::event;
-use ledge::input;
-use ledge::interface::*;
-use ledge::graphics::{self, image};
-use ledge::error::GameResult;
+use ledge::prelude::*;
+use ledge::graphics::render_pass::frame;
+use ledge::graphics::shader::*;
+use ledge::graphics::{InstanceData, Vertex};
+use ledge::input::mouse::MouseButton;
 use rand::{thread_rng, Rng};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
 
-#[derive(Clone)]
-struct SandPixel {
-    image: image::Image,
-    updated: bool,
-    draw_info: graphics::DrawInfo,
-}
+use anyhow::Result;
 
-impl SandPixel {
-    pub fn new(ctx: &graphics::context::GraphicsContext, size: usize, color: graphics::Color) -> Self {
-        let mut draw_info = graphics::DrawInfo::new();
-        draw_info.scale(1./(size as f32/2.));
-        Self {
-            image: image::Image::from_color(ctx, color),
-            updated: false,
-            draw_info,
-        }
-    }
+use vulkano::sync::GpuFuture;
+
+const GRID_SIZE: usize = 96;
+
+#[derive(Clone, Copy)]
+struct SandPixel {
+    color: Color,
 }
 
 struct MainState {
+    shader: ShaderId,
+    image: Image,
     particles: Vec<Vec<Option<SandPixel>>>,
-    sprite_batch: graphics::sprite::SpriteBatch,
     size: usize,
 }
 
 impl event::EventHandler for MainState {
-    fn update(&mut self, interface: &mut Interface) -> GameResult {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let vs = vs::load(renderer.device.clone()).unwrap();
+        let fs = fs::load(renderer.device.clone()).unwrap();
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs,
+            fragment: fs,
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        graphics::set_coordinate_system(renderer, CoordinateSystem::PixelsTopLeft);
+
+        // Every grain is the same white pixel tinted by its draw color.
+        let image = Image::from_color(renderer.queue.clone(), renderer.samplers[0].clone(), Color::white());
+
+        MainState {
+            shader,
+            image,
+            particles: vec![vec![None; GRID_SIZE]; GRID_SIZE],
+            size: GRID_SIZE,
+        }
+    }
+
+    fn update(&mut self, interface: &mut Interface) -> Result<()> {
         let mut updated = Vec::new();
 
-        if let Some(button) = interface.mouse_context.current_pressed {
-            let x = ((1. + interface.mouse_context.last_position.0) * (1./2.) * self.size as f64) as usize;
-            let y = ((1. + interface.mouse_context.last_position.1) * (1./2.) * self.size as f64) as usize;
-            
-            if !(x > self.size-1 || y > self.size-1) && self.particles[x][y].is_none(){
-                if button == input::mouse::MouseButton::Left {
-                    self.particles[x][y] = Some(SandPixel::new(&interface.graphics_context, self.size, graphics::Color::rgba(194, 168, 128, 255)));
-                }
+        if interface.mouse_context.current_pressed == Some(MouseButton::Left) {
+            let viewport = interface.renderer.viewport();
+            let (x, y) = interface.cursor_position();
+            let x = (x / viewport.w * self.size as f32) as usize;
+            let y = (y / viewport.h * self.size as f32) as usize;
+
+            if x < self.size && y < self.size && self.particles[x][y].is_none() {
+                self.particles[x][y] = Some(SandPixel { color: Color::rgba(194, 168, 128, 255) });
             }
         }
 
         let mut rng = thread_rng();
         let n: u32 = rng.gen_range(0..10);
 
-        for i in 0..self.particles.len() {
-            if i >= self.particles.len() {
-                break;
-            }
-            for j in 0..self.particles[i].len() {
-                if j >= self.particles[i].len()-1 || self.particles[i][j].is_none() || updated.contains(&(i, j)) {
+        for i in 0..self.size {
+            for j in 0..self.size - 1 {
+                if self.particles[i][j].is_none() || updated.contains(&(i, j)) {
                     continue;
                 }
 
                 if self.particles[i][j+1].is_none() {
-                    self.particles[i][j+1] = self.particles[i][j].take();        
-                    updated.push((i,j+1));
+                    self.particles[i][j+1] = self.particles[i][j].take();
+                    updated.push((i, j+1));
                 } else if i < self.size-1 && self.particles[i+1][j+1].is_none() && n > 6 {
                     self.particles[i+1][j+1] = self.particles[i][j].take();
-                    updated.push((i+1,j+1));
-                } else if i > 0 && self.particles[i-1][j+1].is_none() && n > 6  {
+                    updated.push((i+1, j+1));
+                } else if i > 0 && self.particles[i-1][j+1].is_none() && n > 6 {
                     self.particles[i-1][j+1] = self.particles[i][j].take();
-                    updated.push((i-1,j+1));
+                    updated.push((i-1, j+1));
                 }
             }
         }
 
         Ok(())
     }
-    
-    fn draw(&mut self, interface: &mut Interface) -> GameResult {
-        let n = self.size as f32;
-
-        self.sprite_batch = graphics::sprite::SpriteBatch::new(image::Image::from_color(&interface.graphics_context, graphics::Color::rgba(194, 168, 128, 255)));
 
-        for i in 0..self.particles.len() {
-            for j in 0..self.particles[i].len() {
-                if let Some(pixel) = &mut self.particles[i][j] {
-                    pixel.draw_info.dest((i as f32 - n/2.) / (n/2.), ((j) as f32 - n/2.) / (n/2.), 0.);
-                    self.sprite_batch.insert(pixel.draw_info.clone());
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let viewport = renderer.viewport();
+        let cell = (viewport.w / self.size as f32, viewport.h / self.size as f32);
+
+        // Rebuilt every frame, all the grains go out in one instanced draw.
+        let mut batch = SpriteBatch::new(self.image.clone());
+        for (i, column) in self.particles.iter().enumerate() {
+            for (j, pixel) in column.iter().enumerate() {
+                if let Some(pixel) = pixel {
+                    let center = ((i as f32 + 0.5) * cell.0, (j as f32 + 0.5) * cell.1);
+                    batch.insert(DrawInfo::centered(center, cell, 0.0, pixel.color));
                 }
             }
         }
-
-        if self.sprite_batch.count() != 0 {
-            graphics::draw(&mut interface.graphics_context, &self.sprite_batch, graphics::DrawInfo::default());
+        let batch = Arc::new(batch);
+
+        let camera = renderer.default_camera();
+
+        let final_image = renderer.final_image();
+        let mut frame = renderer.render_passes[0].frame(
+            Color::black().into(),
+            before_future,
+            final_image,
+            camera,
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    if batch.count() != 0 {
+                        pass.draw_with(batch.clone(), self.shader, DrawInfo::default())?;
+                    }
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
+                }
+            }
         }
 
-        Ok(())
+        Ok(after_future.unwrap())
     }
 
-    fn resize(&mut self, _width: u32, _height: u32) -> GameResult {
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
         Ok(())
     }
 }
 
-impl MainState {
-    pub fn new(ctx: &graphics::context::GraphicsContext, n: usize) -> Self {
-        let mut v = Vec::new();
-        for _ in 0..n {
-            let mut nv: Vec<Option<SandPixel>> = Vec::new();
-            nv.resize(n as usize, None);
-            v.push(nv);
-        }
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
 
-        Self{
-            particles: v,
-            sprite_batch: graphics::sprite::SpriteBatch::new(image::Image::from_color(ctx, graphics::Color::rgba(194, 168, 128, 255))),
-            size: n,
-        }
-    }
-}
+    let (interface, event_loop) =
+        InterfaceBuilder::new("sand instancing", "Dan")
+            .build()
+            .unwrap();
 
-fn main() {
-    let builder = InterfaceBuilder::new("sand", "author");
-    let (interface, event_loop) = builder.build().unwrap();
-    let state = MainState::new(&interface.graphics_context, 96);
-    event::run(interface, event_loop, state);
-}
\ No newline at end of file
+    event::run::<MainState, ()>(interface, event_loop);
+}
@@ -7,6 +7,7 @@ layout(location = 2) in vec4 vert_color; // Color value.
 layout(location = 3) in vec4 src; // Chooses the texture to use in the texture array.
 layout(location = 4) in vec4 color;
 layout(location = 5) in mat4 transform;
+layout(location = 11) in uvec4 corner_colors; // RGBA8 tints of the quad's (0, 0), (1, 0), (0, 1), (1, 1) corners.
 
 layout(binding=0,set=0) uniform mvp {
     mat4 mvp;
@@ -18,7 +19,9 @@ layout(location = 1) out vec4 v_color;
 
 void main() {
     v_uv = vec2(uv * src.zw + src.xy);
-    v_color = vert_color * color;
+    vec4 top = mix(unpackUnorm4x8(corner_colors.x), unpackUnorm4x8(corner_colors.y), pos.x);
+    vec4 bottom = mix(unpackUnorm4x8(corner_colors.z), unpackUnorm4x8(corner_colors.w), pos.x);
+    v_color = vert_color * color * mix(top, bottom, pos.y);
     vec4 position = transform * vec4(pos, 1.0);
     // gl_Position = camera.model * camera.view * camera.projection * position;
     gl_Position = camera.mvp * position;
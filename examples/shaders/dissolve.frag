@@ -0,0 +1,24 @@
+#version 450
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec4 v_color;
+
+layout(binding=0,set=1) uniform sampler2D t_tex;
+
+layout(push_constant) uniform Params {
+    float threshold;
+} params;
+
+layout(location=0) out vec4 f_color;
+
+float hash(vec2 p) {
+    return fract(sin(dot(p, vec2(12.9898, 78.233))) * 43758.5453);
+}
+
+void main() {
+    if (hash(floor(v_uv * 64.0)) < params.threshold) {
+        discard;
+    }
+
+    f_color = texture(t_tex, v_uv) * v_color;
+}
@@ -0,0 +1,17 @@
+#version 450
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec4 v_color;
+
+layout(binding=0,set=1) uniform sampler2D t_tex;
+
+layout(push_constant) uniform Params {
+    float time;
+} params;
+
+layout(location=0) out vec4 f_color;
+
+void main() {
+    vec2 uv = v_uv + vec2(sin(v_uv.y * 40.0 + params.time * 4.0), 0.0) * 0.005;
+    f_color = texture(t_tex, uv) * v_color;
+}
@@ -1,57 +1,148 @@
-use ledge::conf;
-use ledge::graphics;
-// use cgmath::{Deg, Rad, Angle};
-use winit::{
-    event::{Event, WindowEvent},
-    event_loop::ControlFlow,
-};
+use ledge::prelude::*;
+use ledge::graphics::render_pass::frame;
+use ledge::graphics::shader::*;
+use ledge::graphics::{debug, InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
 
-fn main() {
-    let (mut context, event_loop) =
-        graphics::context::GraphicsContext::new(conf::Conf::new("Texture")); // Creating a new context.
-
-    let image = graphics::image::Image::new(&context, "examples/images/pokeball.png");
-    let mut batch = graphics::sprite::SpriteBatch::new(image);
-    let mut params = graphics::DrawInfo::default();
-    params.translate(0.5, 0.5, 6.0);
-    // params.scale(0.01);
-    batch.insert(params);
-    let mut params = graphics::DrawInfo::default();
-    params.translate(-0.5, 0.5, 6.0);
-    // params.scale(0.01);
-    batch.insert(params);
-    let mut params = graphics::DrawInfo::default();
-    params.translate(0.5, -0.5, 6.0);
-    // params.scale(0.01);
-    batch.insert(params);
-    let mut params = graphics::DrawInfo::default();
-    params.translate(-0.5, -0.5, 6.0);
-    // params.scale(0.01);
-    batch.insert(params);
-
-    event_loop.run(move |event, _, control_flow| {
-        let now = std::time::Instant::now();
-
-        match event {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
-                }
-                WindowEvent::Resized(_) => {
-                    context.recreate_swapchain();
-                }
-                _ => {}
-            },
-            Event::MainEventsCleared => {
-                graphics::begin_frame(&mut context, graphics::Color::black());
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+struct MainState {
+    shader: ShaderId,
+    batch: Arc<SpriteBatch>,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let vs = vs::load(renderer.device.clone()).unwrap();
+        let fs = fs::load(renderer.device.clone()).unwrap();
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs,
+            fragment: fs,
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
 
-                graphics::draw(&mut context, &batch, graphics::DrawInfo::default());
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
 
-                graphics::present(&mut context);
+        let image = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/pokeball.png",
+        ).expect("failed to load examples/images/pokeball.png");
 
-                print!("{:.2}\r", now.elapsed().as_secs_f32() * 1000.0);
+        // One sprite in each quarter of the window, drawn with a single instanced draw.
+        let mut batch = SpriteBatch::new(image);
+        for (x, y) in [(0.5, 0.5), (-0.5, 0.5), (0.5, -0.5), (-0.5, -0.5)] {
+            let mut params = DrawInfo::default();
+            params.translate(x, y, 6.0);
+            batch.insert(params);
+        }
+
+        graphics::show_diagnostics(renderer, true);
+
+        MainState {
+            shader,
+            batch: Arc::new(batch),
+        }
+    }
+
+    fn update(&mut self, _interface: &mut Interface) -> Result<()> {
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let camera = renderer.default_camera();
+
+        let final_image = renderer.final_image();
+        let mut frame = renderer.render_passes[0].frame(
+            Color::black().into(),
+            before_future,
+            final_image,
+            camera,
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    pass.draw_with(self.batch.clone(), self.shader, DrawInfo::default())?;
+                    debug::present(&mut pass, &mut renderer.debug, self.shader)?;
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
+                }
             }
-            _ => {}
         }
-    });
+
+        Ok(after_future.unwrap())
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("sprite batch", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
 }
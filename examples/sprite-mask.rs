@@ -0,0 +1,163 @@
+use ledge::prelude::*;
+use ledge::graphics::mask::SpriteMask;
+use ledge::graphics::shader::*;
+use ledge::graphics::sprite::SpriteBatch;
+use ledge::graphics::{FrameGraph, InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+struct MainState {
+    shader: ShaderId,
+    batch: Arc<SpriteBatch>,
+    keyhole: Image,
+    mask: SpriteMask,
+    time: f32,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let shader = Arc::new(Shader {
+            vertex: vs::load(renderer.device.clone()).unwrap(),
+            fragment: fs::load(renderer.device.clone()).unwrap(),
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(
+            shader,
+            BuffersDefinition::new().vertex::<Vertex>().instance::<InstanceData>(),
+        ).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        graphics::set_coordinate_system(renderer, CoordinateSystem::PixelsTopLeft);
+
+        let pokeball = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/pokeball.png",
+        ).expect("failed to load examples/images/pokeball.png");
+
+        let keyhole = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/keyhole.png",
+        ).expect("failed to load examples/images/keyhole.png");
+
+        // A grid of sprites covering the window, only seen through the keyhole.
+        let viewport = renderer.viewport();
+        let mut batch = SpriteBatch::new(pokeball);
+        for y in 0..(viewport.h / 64.0) as u32 + 1 {
+            for x in 0..(viewport.w / 64.0) as u32 + 1 {
+                batch.insert(DrawInfo::centered(
+                    (x as f32 * 64.0 + 32.0, y as f32 * 64.0 + 32.0),
+                    (56.0, 56.0),
+                    0.0,
+                    Color::white(),
+                ));
+            }
+        }
+
+        MainState {
+            shader,
+            batch: Arc::new(batch),
+            keyhole,
+            mask: SpriteMask::new(renderer).unwrap(),
+            time: 0.0,
+        }
+    }
+
+    fn update(&mut self, _interface: &mut Interface) -> Result<()> {
+        self.time += 0.01;
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let viewport = renderer.viewport();
+        let camera = renderer.default_camera().as_mvp();
+
+        // The keyhole wanders around the window, the mask follows its draw info.
+        let center = (
+            viewport.w / 2.0 + self.time.cos() * viewport.w / 4.0,
+            viewport.h / 2.0 + (self.time * 1.3).sin() * viewport.h / 4.0,
+        );
+        let keyhole = DrawInfo::centered(center, (192.0, 288.0), 0.0, Color::white());
+
+        let mut graph = FrameGraph::new(before_future);
+
+        let (mask, image) = (&mut self.mask, &self.keyhole);
+        graph.then(|future| mask.render(future, image, keyhole, camera, viewport))?;
+
+        let (mask, batch, shader) = (&mut self.mask, self.batch.clone(), self.shader);
+        graph.render_to_screen(renderer, 0, Color::black().into(), |pass| {
+            mask.begin(pass)?;
+            pass.draw_with(batch, shader, DrawInfo::default())?;
+            mask.end(pass);
+            Ok(())
+        })?;
+
+        graph.finish()
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("sprite-mask", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
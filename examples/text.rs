@@ -1,91 +1,149 @@
-use ledge::conf;
-use ledge::graphics;
-use ledge::graphics::text::*;
-use ledge::graphics::Color;
-use winit::{
-    event::{Event, WindowEvent},
-    event_loop::ControlFlow,
-};
+use ledge::prelude::*;
+use ledge::graphics::render_pass::frame;
+use ledge::graphics::shader::*;
+use ledge::graphics::font::{Font, TextBatch};
+use ledge::graphics::{InstanceData, Vertex};
 use std::sync::Arc;
-use ledge::graphics::camera::*;
-use vulkano::pipeline::Pipeline;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
 
+use anyhow::Result;
 
-fn main() {
-    let (mut ctx, event_loop) =
-        graphics::context::GraphicsContext::new(conf::Conf::new("Text")); // Creating a new context.
+use vulkano::sync::GpuFuture;
 
-    let image = graphics::image::Image::new(&ctx, "examples/images/font.png");
-    let font = Arc::new(Font::new(image, 13, 6));
+struct MainState {
+    shader: ShaderId,
+    font: Arc<Font>,
+    text: TextBatch,
+}
 
-    let mut dom = DocumentContext::new();
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
 
-    let hello_world = Box::new(
-        Text::with_font(font.clone())
-            .text("HELLO\nWORLD".to_string())
-    );
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
 
-    let foo_bar = Box::new(
-        Text::with_font(font.clone())
-            .text("FOO\nBAR".to_string())
-    );
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
 
-    dom.insert("root", "container", Box::new(Div::new()));
-    dom.insert("container", "text", hello_world);
-    dom.insert("root", "text2", foo_bar);
+        let vs = vs::load(renderer.device.clone()).unwrap();
+        let fs = fs::load(renderer.device.clone()).unwrap();
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs,
+            fragment: fs,
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        graphics::set_coordinate_system(renderer, CoordinateSystem::PixelsTopLeft);
+
+        let image = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/font.png",
+        ).expect("failed to load examples/images/font.png");
+        let font = Arc::new(Font::new(image, 13, 6));
+
+        MainState {
+            shader,
+            font,
+            text: TextBatch::new(),
+        }
+    }
+
+    fn update(&mut self, _interface: &mut Interface) -> Result<()> {
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        // Both strings share a font, so they go out as one instanced draw.
+        self.text.draw_text(&self.font, "HELLO\nWORLD", (16.0, 16.0), 32.0, Color::white());
+        self.text.draw_text(&self.font, "FOO\nBAR", (16.0, 144.0), 32.0, Color::white());
+
+        let viewport = renderer.viewport();
+        let camera = renderer.default_camera();
+
+        let final_image = renderer.final_image();
+        let mut frame = renderer.render_passes[0].frame(
+            Color::black().into(),
+            before_future,
+            final_image,
+            camera,
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    graphics::draw_quad(&mut pass, Rect { x: 0.0, y: 0.0, w: viewport.w, h: 96.0 }, Color::grey())?;
+                    self.text.flush(&mut pass, self.shader)?;
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
+                }
+            }
+        }
 
-    dom.select_mut("container").style.position = (-1.0, -1.0, 0.0);
-    dom.select_mut("container").style.background_color = Color::grey();
-    dom.select_mut("container").style.width = 2.0;
+        Ok(after_future.unwrap())
+    }
 
-    dom.select_mut("text").style.font_size = 104;
-    dom.select_mut("text").style.letter_spacing = 100;
-    dom.select_mut("text").style.line_height = 16;
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
 
-    event_loop.run(move |event, _, control_flow| {
-        let now = std::time::Instant::now();
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
 
-        match event {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
-                }
-                WindowEvent::Resized(_) => {
-                    ctx.recreate_swapchain();
-                }
-                _ => {}
-            },
-            Event::MainEventsCleared => {
-                graphics::begin_frame(&mut ctx, graphics::Color::black());
-
-                let camera = OrthographicCamera::new(0.001, 1000.0);
-                let cam_buf = vulkano::buffer::CpuAccessibleBuffer::from_data(
-                    ctx.device.clone(), 
-                    vulkano::buffer::BufferUsage::uniform_buffer(), 
-                    false, 
-                    camera.as_mvp(),
-                ).unwrap();
-            
-                let shader = ctx.shaders[ctx.default_shader].clone();
-                let set = vulkano::descriptor_set::PersistentDescriptorSet::new(
-                    shader.layout()[0].clone(),
-                    [vulkano::descriptor_set::WriteDescriptorSet::buffer(0, cam_buf)],
-                ).unwrap();
-
-                ctx.command_buffer.as_mut().unwrap().bind_descriptor_sets(
-                    vulkano::pipeline::PipelineBindPoint::Graphics,
-                    shader.pipeline().layout().clone(),
-                    0,
-                    set,
-                );
-
-                graphics::draw(&mut ctx, &dom, graphics::DrawInfo::default());
-
-                graphics::present(&mut ctx);
-
-                print!("{:.2}\r", now.elapsed().as_secs_f32() * 1000.0);
-            }
-            _ => {}
-        }
-    });
-}
\ No newline at end of file
+    let (interface, event_loop) =
+        InterfaceBuilder::new("Text", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
@@ -1,75 +1,144 @@
-use ledge::conf;
-use ledge::graphics;
-use winit::{
-    event::{ElementState, Event, KeyboardInput, VirtualKeyCode, WindowEvent},
-    event_loop::ControlFlow,
-};
-use crate::graphics::camera::*;
-use vulkano::pipeline::Pipeline;
+use ledge::prelude::*;
+use ledge::graphics::render_pass::frame;
+use ledge::graphics::shader::*;
+use ledge::graphics::{InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
 
-fn main() {
-    let (mut context, event_loop) =
-        graphics::context::GraphicsContext::new(conf::Conf::new("Texture")); // Creating a new context.
-
-    let image = graphics::image::Image::new(&context, "examples/images/pokeball.png");
-    let params = graphics::DrawInfo::default();
-
-    println!("{:?}", params.transform);
-    println!("{:?}", params.transform.as_mat4());
-    
-    event_loop.run(move |event, _, control_flow| {
-        let now = std::time::Instant::now();
-
-        match event {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::KeyboardInput {
-                    input:
-                        KeyboardInput {
-                            virtual_keycode: Some(VirtualKeyCode::Escape),
-                            state: ElementState::Pressed,
-                            ..
-                        },
-                    ..
-                }
-                | WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
-                }
-                WindowEvent::Resized(_) => {
-                    context.recreate_swapchain();
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+struct MainState {
+    shader: ShaderId,
+    image: Arc<Image>,
+    params: DrawInfo,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let vs = vs::load(renderer.device.clone()).unwrap();
+        let fs = fs::load(renderer.device.clone()).unwrap();
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs,
+            fragment: fs,
+            topology: VertexTopology::TriangleStrip,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        let image = Image::new(
+            renderer.queue.clone(),
+            renderer.samplers[0].clone(),
+            "examples/images/pokeball.png",
+        ).expect("failed to load examples/images/pokeball.png");
+
+        let params = DrawInfo::default();
+
+        println!("{:?}", params.transform);
+        println!("{:?}", params.transform.as_mat4());
+
+        MainState {
+            shader,
+            image: Arc::new(image),
+            params,
+        }
+    }
+
+    fn update(&mut self, _interface: &mut Interface) -> Result<()> {
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        let camera = renderer.default_camera();
+
+        let final_image = renderer.final_image();
+        let mut frame = renderer.render_passes[0].frame(
+            Color::black().into(),
+            before_future,
+            final_image,
+            camera,
+        )?;
+
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            after_future = match pass {
+                frame::PassState::DrawPass(mut pass) => {
+                    pass.draw_with(self.image.clone(), self.shader, self.params)?;
+                    None
+                },
+                frame::PassState::Finished(af) => {
+                    Some(af)
                 }
-                _ => {}
-            },
-            Event::MainEventsCleared => {
-                graphics::begin_frame(&mut context, graphics::Color::black());
-
-                let camera = OrthographicCamera::new(0.001, 1000.0);
-                let cam_buf = vulkano::buffer::CpuAccessibleBuffer::from_data(
-                    context.device.clone(), 
-                    vulkano::buffer::BufferUsage::uniform_buffer(), 
-                    false, 
-                    camera.as_mvp(),
-                ).unwrap();
-            
-                let shader = context.shaders[context.default_shader].clone();
-                let set = vulkano::descriptor_set::PersistentDescriptorSet::new(
-                    shader.layout()[0].clone(),
-                    [vulkano::descriptor_set::WriteDescriptorSet::buffer(0, cam_buf)],
-                ).unwrap();
-
-                context.command_buffer.as_mut().unwrap().bind_descriptor_sets(
-                    vulkano::pipeline::PipelineBindPoint::Graphics,
-                    shader.pipeline().layout().clone(),
-                    0,
-                    set,
-                );
-
-                graphics::draw(&mut context, &image, params.clone());
-
-                graphics::present(&mut context);
-
-                print!("{:.2}\r", now.elapsed().as_secs_f32() * 1000.0);
             }
-            _ => {}
         }
-    });
+
+        Ok(after_future.unwrap())
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("Texture", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
 }
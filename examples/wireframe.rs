@@ -0,0 +1,149 @@
+use ledge::prelude::*;
+use ledge::graphics::immediate::ImmediateBatch;
+use ledge::graphics::ribbon::{Ribbon, RibbonPoint};
+use ledge::graphics::shader::*;
+use ledge::graphics::{FrameGraph, InstanceData, Vertex};
+use std::sync::Arc;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+use anyhow::Result;
+
+use vulkano::sync::GpuFuture;
+
+/// Toggles between filled and wireframe drawing.
+const WIREFRAME_KEY: KeyCode = KeyCode::Tab;
+
+struct MainState {
+    shader: ShaderId,
+    batch: ImmediateBatch,
+    ribbon: Ribbon,
+    was_pressed: bool,
+}
+
+impl event::EventHandler for MainState {
+    fn start(interface: &mut Interface) -> Self {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        mod vs {
+            vulkano_shaders::shader! {
+                ty: "vertex",
+                path: "examples/shaders/basic.vert",
+            }
+        }
+
+        mod fs {
+            vulkano_shaders::shader! {
+                ty: "fragment",
+                path: "examples/shaders/basic.frag",
+            }
+        }
+
+        let v_type = BuffersDefinition::new()
+            .vertex::<Vertex>()
+            .instance::<InstanceData>();
+
+        let shader = Arc::new(Shader {
+            vertex: vs::load(renderer.device.clone()).unwrap(),
+            fragment: fs::load(renderer.device.clone()).unwrap(),
+            topology: VertexTopology::TriangleList,
+        });
+
+        let mut render_pass = graphics::render_pass::RenderPass::new(
+            renderer.queue.clone(),
+            vulkano::ordered_passes_renderpass!(renderer.device.clone(),
+                attachments: {
+                    final_color: {
+                        load: Clear,
+                        store: Store,
+                        format: renderer.output_format(),
+                        samples: 1,
+                    }
+                },
+                passes: [
+                    {
+                        color: [final_color],
+                        depth_stencil: {},
+                        input: []
+                    }
+                ]
+            ).unwrap(),
+        ).unwrap();
+
+        let shader = render_pass.register_shader(shader, v_type).unwrap();
+        renderer.render_passes.push(render_pass);
+
+        graphics::set_coordinate_system(renderer, CoordinateSystem::PixelsTopLeft);
+
+        MainState {
+            shader,
+            batch: ImmediateBatch::new(renderer.queue.clone(), renderer.samplers[0].clone()),
+            ribbon: Ribbon::new(48),
+            was_pressed: false,
+        }
+    }
+
+    fn update(&mut self, interface: &mut Interface) -> Result<()> {
+        let pressed = interface.keyboard_context.is_key_pressed(WIREFRAME_KEY);
+        if pressed && !self.was_pressed {
+            let wireframe = !interface.renderer.wireframe();
+            interface.renderer.set_wireframe(wireframe);
+        }
+        self.was_pressed = pressed;
+
+        self.ribbon.push(RibbonPoint {
+            position: interface.cursor_position(),
+            width: 32.0,
+            color: Color::rgba(120, 200, 255, 255),
+        });
+        Ok(())
+    }
+
+    fn draw(&mut self, interface: &mut Interface, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let Interface {
+            renderer,
+            ..
+        } = interface;
+
+        // Generated geometry worth seeing the triangles of: a fan, a grid of quads, and the ribbon.
+        self.batch.circle((200.0, 200.0), 120.0, 24, Color::rgba(240, 180, 60, 255));
+        for y in 0..4 {
+            for x in 0..6 {
+                let rect = Rect {
+                    x: 400.0 + x as f32 * 60.0,
+                    y: 100.0 + y as f32 * 60.0,
+                    w: 56.0,
+                    h: 56.0,
+                };
+                self.batch.rectangle(rect, Color::rgba(90, 200, 120, 255));
+            }
+        }
+        self.ribbon.draw(&mut self.batch);
+
+        let batch = &mut self.batch;
+        let shader = self.shader;
+        let mut graph = FrameGraph::new(before_future);
+        graph.render_to_screen(renderer, 0, Color::black().into(), |pass| {
+            pass.draw_immediate(batch, shader)
+        })?;
+
+        graph.finish()
+    }
+
+    fn resize(&mut self, _width: u32, _height: u32) -> Result<()> {
+        Ok(())
+    }
+}
+
+fn main() {
+    ledge::init_simple_logger(log::LevelFilter::Info).unwrap();
+
+    let (interface, event_loop) =
+        InterfaceBuilder::new("wireframe", "Dan")
+            .build()
+            .unwrap();
+
+    event::run::<MainState, ()>(interface, event_loop);
+}
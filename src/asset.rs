@@ -0,0 +1,133 @@
+//! Reading asset files into plain CPU-side data, with no GPU involved.
+//!
+//! This is the decode half of loading an image, [`crate::graphics::image::Image`] uploads
+//! what it returns. It builds without the `graphics` feature, so tools and servers can read
+//! the same files the game does.
+
+use std::fmt;
+use std::fs;
+use std::io::{Cursor, Read};
+use std::path;
+
+/// Why an image couldn't be loaded, by [`load_png`] or
+/// [`Image::new`](crate::graphics::image::Image::new).
+#[derive(Debug)]
+pub enum ImageError {
+    /// The file couldn't be read, most often because it doesn't exist.
+    Io(std::io::Error),
+    /// The file was read but isn't a PNG this crate can decode.
+    Decode(png::DecodingError),
+    /// The file was read but its contents aren't in a format this crate can load.
+    Format(String),
+    /// The decoded pixels couldn't be copied into a GPU image.
+    Upload(anyhow::Error),
+}
+
+impl fmt::Display for ImageError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            ImageError::Io(e) => write!(f, "failed to read image file: {}", e),
+            ImageError::Decode(e) => write!(f, "failed to decode image: {}", e),
+            ImageError::Format(e) => write!(f, "unsupported image format: {}", e),
+            ImageError::Upload(e) => write!(f, "failed to upload image: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for ImageError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            ImageError::Io(e) => Some(e),
+            ImageError::Decode(e) => Some(e),
+            ImageError::Format(_) => None,
+            ImageError::Upload(e) => Some(e.as_ref()),
+        }
+    }
+}
+
+impl From<std::io::Error> for ImageError {
+    fn from(e: std::io::Error) -> Self {
+        ImageError::Io(e)
+    }
+}
+
+impl From<png::DecodingError> for ImageError {
+    fn from(e: png::DecodingError) -> Self {
+        ImageError::Decode(e)
+    }
+}
+
+/// Decoded pixels, laid out row by row from the top left.
+#[derive(Clone, Debug)]
+pub struct DecodedImage {
+    pub width: u32,
+    pub height: u32,
+    /// As many bytes a pixel as the source's color type and bit depth call for, 4 for the
+    /// 8 bit RGBA images the renderer uploads.
+    pub pixels: Vec<u8>,
+}
+
+/// Reads and decodes the PNG at `path`.
+pub fn load_png<P: AsRef<path::Path>>(path: P) -> Result<DecodedImage, ImageError> {
+    let mut png_bytes = Vec::new();
+    fs::File::open(path)?.read_to_end(&mut png_bytes)?;
+    decode_png(&png_bytes)
+}
+
+/// Decodes a PNG already in memory.
+pub fn decode_png(bytes: &[u8]) -> Result<DecodedImage, ImageError> {
+    let decoder = png::Decoder::new(Cursor::new(bytes));
+    let mut reader = decoder.read_info()?;
+    let width = reader.info().width;
+    let height = reader.info().height;
+    let mut pixels = vec![0; reader.output_buffer_size()];
+    reader.next_frame(&mut pixels)?;
+
+    Ok(DecodedImage { width, height, pixels })
+}
+
+/// Multiplies the color of every RGBA pixel in `pixels` by its alpha, rounding to nearest.
+pub fn premultiply_alpha(pixels: &mut [u8]) {
+    for pixel in pixels.chunks_exact_mut(4) {
+        let alpha = pixel[3] as u32;
+        for channel in &mut pixel[..3] {
+            *channel = ((*channel as u32 * alpha + 127) / 255) as u8;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn encode_rgba(width: u32, height: u32, pixels: &[u8]) -> Vec<u8> {
+        let mut bytes = Vec::new();
+        let mut encoder = png::Encoder::new(&mut bytes, width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+        bytes
+    }
+
+    #[test]
+    fn decode_png_reads_rows_from_the_top_left() {
+        let pixels = [255, 0, 0, 255, 0, 255, 0, 128, 0, 0, 255, 0, 10, 20, 30, 40];
+        let image = decode_png(&encode_rgba(2, 2, &pixels)).unwrap();
+
+        assert_eq!((image.width, image.height), (2, 2));
+        assert_eq!(image.pixels, pixels);
+    }
+
+    #[test]
+    fn decode_png_rejects_other_files() {
+        assert!(matches!(decode_png(b"not a png"), Err(ImageError::Decode(_))));
+    }
+
+    #[test]
+    fn premultiply_alpha_rounds_to_nearest() {
+        let mut pixels = [255, 128, 1, 128, 200, 100, 50, 0, 10, 20, 30, 255];
+        premultiply_alpha(&mut pixels);
+
+        assert_eq!(pixels, [128, 64, 1, 128, 0, 0, 0, 0, 10, 20, 30, 255]);
+    }
+}
@@ -3,16 +3,19 @@ pub struct WindowMode {
     pub(crate) width: f32,
     pub(crate) height: f32,
     pub(crate) maximized: bool,
-    fullscreen_type: FullscreenType,
+    pub(crate) fullscreen_type: FullscreenType,
     borderless: bool,
     pub(crate) min_width: f32,
     max_width: f32,
     pub(crate) min_height: f32,
     max_height: f32,
     pub(crate) resizable: bool,
+    pub(crate) decorations: bool,
+    pub(crate) always_on_top: bool,
 }
 
 impl WindowMode {
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         Self {
             width: 800.0,
@@ -25,6 +28,8 @@ impl WindowMode {
             max_width: 0.0,
             max_height: 0.0,
             resizable: true,
+            decorations: true,
+            always_on_top: false,
         }
     }
 }
@@ -33,12 +38,13 @@ impl WindowMode {
 pub struct WindowSetup {
     pub(crate) title: String,
     // samples:
-    vsync: bool,
+    pub(crate) vsync: bool,
     icon: String,
     srgb: bool,
 }
 
 impl WindowSetup {
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         Self {
             title: "Empty Title".to_string(),
@@ -50,7 +56,8 @@ impl WindowSetup {
 }
 
 #[allow(unused)]
-enum FullscreenType {
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub(crate) enum FullscreenType {
     Windowed,
     TFullScreen,
     WFullScreen,
@@ -59,6 +66,9 @@ enum FullscreenType {
 pub struct Conf {
     pub(crate) window_mode: WindowMode,
     pub(crate) window_setup: WindowSetup,
+    pub(crate) placeholder_on_error: bool,
+    pub(crate) premultiply_images: bool,
+    pub(crate) validation: bool,
 }
 
 impl Conf {
@@ -68,10 +78,248 @@ impl Conf {
         conf
     }
 
+    #[allow(clippy::should_implement_trait)]
     pub fn default() -> Self {
         Self {
             window_mode: WindowMode::default(),
             window_setup: WindowSetup::default(),
+            placeholder_on_error: false,
+            premultiply_images: true,
+            validation: false,
         }
     }
+
+    /// Whether the window has a title bar and borders, `true` by default.
+    ///
+    /// Ignored on Android, iOS, and the web.
+    pub fn decorations(mut self, decorations: bool) -> Self {
+        self.window_mode.decorations = decorations;
+        self
+    }
+
+    /// Keeps the window above every other window, `false` by default.
+    ///
+    /// Supported on Windows, macOS, and X11. Ignored on Wayland, Android, iOS, and the web.
+    pub fn always_on_top(mut self, always_on_top: bool) -> Self {
+        self.window_mode.always_on_top = always_on_top;
+        self
+    }
+
+    /// Makes [`crate::graphics::renderer::Renderer::load_image`] log a warning and return a
+    /// checkerboard placeholder for images that fail to load, instead of an error. `false` by
+    /// default, see [`crate::graphics::renderer::Renderer::image_spec`].
+    pub fn use_placeholder_on_error(mut self, enabled: bool) -> Self {
+        self.placeholder_on_error = enabled;
+        self
+    }
+
+    /// Makes [`crate::graphics::renderer::Renderer::load_image`] multiply PNG colors by their
+    /// alpha, so they filter without dark edges. `true` by default, see
+    /// [`crate::graphics::image::ImageSpec::premultiply`].
+    pub fn premultiply_images(mut self, enabled: bool) -> Self {
+        self.premultiply_images = enabled;
+        self
+    }
+
+    /// Waits for the display's vertical blank to present frames, `false` by default.
+    pub fn vsync(mut self, vsync: bool) -> Self {
+        self.window_setup.vsync = vsync;
+        self
+    }
+
+    /// The window's initial drawable area in logical pixels, 800 by 600 by default.
+    pub fn window_size(mut self, width: f32, height: f32) -> Self {
+        self.window_mode.width = width;
+        self.window_mode.height = height;
+        self
+    }
+
+    /// Covers the monitor the window opens on with a borderless window, `false` by default.
+    pub fn fullscreen(mut self, fullscreen: bool) -> Self {
+        self.window_mode.fullscreen_type = if fullscreen {
+            FullscreenType::WFullScreen
+        } else {
+            FullscreenType::Windowed
+        };
+        self
+    }
+
+    /// Enables the Khronos validation layer, which reports misuse of Vulkan to the log at
+    /// a large cost to performance. Ignored with a warning if the layer isn't installed.
+    /// `false` by default.
+    pub fn validation(mut self, enabled: bool) -> Self {
+        self.validation = enabled;
+        self
+    }
+
+    /// Overrides settings from command line flags, returning the arguments it didn't
+    /// recognize in order so the game can parse its own. The first argument is skipped as
+    /// the program's name, so `std::env::args()` can be passed as is.
+    ///
+    /// Call it after loading any settings from files and before the builder calls the game
+    /// wants to force, so flags override files and code overrides flags. `--help` stops
+    /// parsing with [`ArgsError::Help`], leaving it to the game to print [`ARGS_HELP`] and its
+    /// own flags. Values can follow their flag as the next argument or after an `=`, flags
+    /// that take no value are rejected if given one.
+    ///
+    /// ```
+    /// let mut conf = ledge::conf::Conf::new("game");
+    /// let rest = conf
+    ///     .apply_args(["game", "--windowed", "--width", "1600", "--vsync=off", "--god-mode"].map(String::from))
+    ///     .unwrap();
+    /// assert_eq!(rest, ["--god-mode"]);
+    /// assert!(conf.apply_args(["game", "--width", "wide"].map(String::from)).is_err());
+    /// ```
+    pub fn apply_args<I: IntoIterator<Item = String>>(&mut self, args: I) -> Result<Vec<String>, ArgsError> {
+        let mut args = args.into_iter().skip(1);
+        let mut rest = Vec::new();
+
+        while let Some(arg) = args.next() {
+            let (flag, inline) = match arg.split_once('=') {
+                Some((flag, value)) if flag.starts_with("--") => (flag.to_string(), Some(value.to_string())),
+                _ => (arg.clone(), None),
+            };
+            let value = |args: &mut dyn Iterator<Item = String>| {
+                inline.clone().or_else(|| args.next()).ok_or_else(|| ArgsError::MissingValue(flag.clone()))
+            };
+
+            // Switches take no value, `--validation=false` is a mistake rather than a way to turn one off.
+            if let ("--windowed" | "--fullscreen" | "--validation" | "--help", Some(value)) = (flag.as_str(), &inline) {
+                return Err(ArgsError::InvalidValue { flag, value: value.clone() });
+            }
+
+            match flag.as_str() {
+                "--windowed" => self.window_mode.fullscreen_type = FullscreenType::Windowed,
+                "--fullscreen" => self.window_mode.fullscreen_type = FullscreenType::WFullScreen,
+                "--width" => self.window_mode.width = parse_size(&flag, value(&mut args)?)?,
+                "--height" => self.window_mode.height = parse_size(&flag, value(&mut args)?)?,
+                "--vsync" => {
+                    let value = value(&mut args)?;
+                    self.window_setup.vsync = match value.as_str() {
+                        "on" => true,
+                        "off" => false,
+                        _ => return Err(ArgsError::InvalidValue { flag, value }),
+                    };
+                }
+                "--validation" => self.validation = true,
+                "--help" | "-h" => return Err(ArgsError::Help(ARGS_HELP)),
+                _ => rest.push(arg),
+            }
+        }
+
+        Ok(rest)
+    }
+}
+
+/// The flags [`Conf::apply_args`] recognizes, for printing when it returns [`ArgsError::Help`].
+pub const ARGS_HELP: &str = "\
+Options:
+    --windowed            Open in a window
+    --fullscreen          Open as a borderless window covering the monitor
+    --width <PIXELS>      Window width in logical pixels
+    --height <PIXELS>     Window height in logical pixels
+    --vsync <on|off>      Wait for the vertical blank to present frames
+    --validation          Enable the Vulkan validation layer
+    -h, --help            Print this help";
+
+/// Why [`Conf::apply_args`] couldn't apply the command line.
+#[derive(Clone, Debug, PartialEq)]
+pub enum ArgsError {
+    /// The flag was last on the command line but needs a value.
+    MissingValue(String),
+    /// The flag's value isn't one it accepts.
+    InvalidValue { flag: String, value: String },
+    /// `--help` or `-h` was passed, with [`ARGS_HELP`] to print. Flags before it were applied.
+    Help(&'static str),
+}
+
+impl std::fmt::Display for ArgsError {
+    fn fmt(&self, f: &mut std::fmt::Formatter) -> std::fmt::Result {
+        match self {
+            ArgsError::MissingValue(flag) => write!(f, "{} needs a value", flag),
+            ArgsError::InvalidValue { flag, value } => write!(f, "invalid value {:?} for {}", value, flag),
+            ArgsError::Help(help) => f.write_str(help),
+        }
+    }
+}
+
+impl std::error::Error for ArgsError {}
+
+/// A window dimension of at least one pixel.
+fn parse_size(flag: &str, value: String) -> Result<f32, ArgsError> {
+    match value.parse::<u32>() {
+        Ok(size) if size > 0 => Ok(size as f32),
+        _ => Err(ArgsError::InvalidValue {
+            flag: flag.to_string(),
+            value,
+        }),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn args(args: &[&str]) -> Vec<String> {
+        std::iter::once("game").chain(args.iter().copied()).map(String::from).collect()
+    }
+
+    #[test]
+    fn flags_override_settings() {
+        let mut conf = Conf::new("game").vsync(true);
+        let rest = conf
+            .apply_args(args(&["--fullscreen", "--width", "1600", "--height=900", "--vsync", "off", "--validation"]))
+            .unwrap();
+
+        assert!(rest.is_empty());
+        assert_eq!(conf.window_mode.fullscreen_type, FullscreenType::WFullScreen);
+        assert_eq!((conf.window_mode.width, conf.window_mode.height), (1600.0, 900.0));
+        assert!(!conf.window_setup.vsync);
+        assert!(conf.validation);
+    }
+
+    #[test]
+    fn unknown_arguments_pass_through_in_order() {
+        let mut conf = Conf::new("game");
+        let rest = conf.apply_args(args(&["--god-mode", "--windowed", "level2", "--seed=4"])).unwrap();
+
+        assert_eq!(rest, ["--god-mode", "level2", "--seed=4"]);
+    }
+
+    #[test]
+    fn malformed_values_are_rejected() {
+        let invalid = |flag: &str, value: &str| ArgsError::InvalidValue {
+            flag: flag.to_string(),
+            value: value.to_string(),
+        };
+
+        let mut conf = Conf::new("game");
+        assert_eq!(conf.apply_args(args(&["--width", "abc"])), Err(invalid("--width", "abc")));
+        assert_eq!(conf.apply_args(args(&["--height=0"])), Err(invalid("--height", "0")));
+        assert_eq!(conf.apply_args(args(&["--vsync", "maybe"])), Err(invalid("--vsync", "maybe")));
+        assert_eq!(conf.apply_args(args(&["--validation=false"])), Err(invalid("--validation", "false")));
+        assert_eq!(conf.apply_args(args(&["--fullscreen=false"])), Err(invalid("--fullscreen", "false")));
+        assert_eq!(conf.apply_args(args(&["--windowed=no"])), Err(invalid("--windowed", "no")));
+        assert_eq!(conf.window_mode.width, 800.0);
+        assert_eq!(conf.window_mode.fullscreen_type, FullscreenType::Windowed);
+        assert!(!conf.validation);
+    }
+
+    #[test]
+    fn missing_value_is_rejected() {
+        let mut conf = Conf::new("game");
+
+        assert_eq!(
+            conf.apply_args(args(&["--windowed", "--width"])),
+            Err(ArgsError::MissingValue("--width".to_string()))
+        );
+    }
+
+    #[test]
+    fn help_is_returned_to_the_caller() {
+        let mut conf = Conf::new("game");
+
+        assert_eq!(conf.apply_args(args(&["--vsync=on", "-h", "--width", "abc"])), Err(ArgsError::Help(ARGS_HELP)));
+        assert!(conf.window_setup.vsync);
+    }
 }
@@ -4,39 +4,187 @@ use std::time;
 use std::thread;
 use vulkano::sync::GpuFuture;
 use winit::{
-    event::{Event, WindowEvent},
-    event_loop::{ControlFlow, EventLoop},
+    event::Event,
+    event_loop::ControlFlow,
 };
 
+use crate::input::keyboard::{KeyCode, ModifiersState, ScanCode};
+use crate::input::mouse::MouseButton;
+
 use anyhow::Result;
 
-pub fn run<S: 'static>(mut interface: Interface, event_loop: EventLoop<()>) -> !
+/// The window's event loop, handed to [`run`].
+pub struct EventLoop<E: 'static = ()> {
+    inner: winit::event_loop::EventLoop<E>,
+}
+
+impl<E: 'static> EventLoop<E> {
+    pub(crate) fn new(inner: winit::event_loop::EventLoop<E>) -> Self {
+        Self { inner }
+    }
+
+    pub(crate) fn proxy(&self) -> EventProxy<E> {
+        EventProxy::new(&self.inner)
+    }
+
+    #[cfg(feature = "unstable-raw")]
+    pub fn raw(&self) -> &winit::event_loop::EventLoop<E> {
+        &self.inner
+    }
+
+    #[cfg(feature = "unstable-raw")]
+    pub fn into_raw(self) -> winit::event_loop::EventLoop<E> {
+        self.inner
+    }
+}
+
+/// Sends user events to the event loop from any thread, see [`Interface::event_proxy`].
+pub struct EventProxy<E: 'static> {
+    inner: winit::event_loop::EventLoopProxy<E>,
+}
+
+impl<E: 'static> Clone for EventProxy<E> {
+    fn clone(&self) -> Self {
+        Self {
+            inner: self.inner.clone(),
+        }
+    }
+}
+
+impl<E: 'static> EventProxy<E> {
+    pub(crate) fn new(event_loop: &winit::event_loop::EventLoop<E>) -> Self {
+        Self {
+            inner: event_loop.create_proxy(),
+        }
+    }
+
+    /// Queues `event` for the loop, giving it back if the loop has already exited.
+    pub fn send(&self, event: E) -> std::result::Result<(), E> {
+        self.inner.send_event(event).map_err(|e| e.0)
+    }
+
+    #[cfg(feature = "unstable-raw")]
+    pub fn raw(&self) -> &winit::event_loop::EventLoopProxy<E> {
+        &self.inner
+    }
+}
+
+/// A key press or release.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct KeyEvent {
+    /// The key by its label in the user's layout, `None` for keys winit can't name.
+    pub key: Option<KeyCode>,
+    /// The key by its physical position, see [`crate::input::keyboard::scancode`].
+    pub scancode: ScanCode,
+    pub pressed: bool,
+}
+
+/// The window events ledge passes on to [`EventHandler::event`].
+///
+/// These are ledge's own types so a winit upgrade doesn't change them. `KeyCode` and
+/// `ModifiersState` are still winit's, so their variants and flags follow winit.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WindowEvent {
+    /// The window's new inner size in pixels.
+    Resized { width: u32, height: u32 },
+    CloseRequested,
+    Focused(bool),
+    Key(KeyEvent),
+    ModifiersChanged(ModifiersState),
+    /// The cursor's position in pixels from the window's top left corner.
+    CursorMoved { x: f64, y: f64 },
+    MouseButton { button: MouseButton, pressed: bool },
+}
+
+impl WindowEvent {
+    /// Converts a winit event for [`Interface::process_event`] when driving an event loop
+    /// passed to [`Interface::from_event_loop`]. `None` for events ledge doesn't handle.
+    #[cfg(feature = "unstable-raw")]
+    pub fn from_raw(event: &winit::event::WindowEvent) -> Option<Self> {
+        Self::from_winit(event)
+    }
+
+    pub(crate) fn from_winit(event: &winit::event::WindowEvent) -> Option<Self> {
+        use winit::event::{ElementState, WindowEvent as Raw};
+
+        let pressed = |state: &ElementState| *state == ElementState::Pressed;
+
+        Some(match event {
+            Raw::Resized(size) => WindowEvent::Resized {
+                width: size.width,
+                height: size.height,
+            },
+            Raw::CloseRequested => WindowEvent::CloseRequested,
+            Raw::Focused(focused) => WindowEvent::Focused(*focused),
+            Raw::KeyboardInput { input, .. } => WindowEvent::Key(KeyEvent {
+                key: input.virtual_keycode,
+                scancode: input.scancode,
+                pressed: pressed(&input.state),
+            }),
+            Raw::ModifiersChanged(modifiers) => WindowEvent::ModifiersChanged(*modifiers),
+            Raw::CursorMoved { position, .. } => WindowEvent::CursorMoved {
+                x: position.x,
+                y: position.y,
+            },
+            Raw::MouseInput { state, button, .. } => WindowEvent::MouseButton {
+                button: match button {
+                    winit::event::MouseButton::Left => MouseButton::Left,
+                    winit::event::MouseButton::Right => MouseButton::Right,
+                    winit::event::MouseButton::Middle => MouseButton::Middle,
+                    winit::event::MouseButton::Other(val) => MouseButton::Misc(*val),
+                },
+                pressed: pressed(state),
+            },
+            _ => return None,
+        })
+    }
+}
+
+pub fn run<S, E: 'static>(mut interface: Interface<E>, event_loop: EventLoop<E>) -> !
 where
-    S: EventHandler,
+    S: EventHandler<E> + 'static,
 {
     let mut game_state = S::start(&mut interface);
 
-    event_loop.run(move |event, _, control_flow| {
+    event_loop.inner.run(move |event, _, control_flow| {
         let interface = &mut interface;
 
-        interface.process_event(&event);
-
         match event {
-            Event::WindowEvent { event, .. } => match event {
-                WindowEvent::CloseRequested => {
-                    *control_flow = ControlFlow::Exit;
+            Event::WindowEvent { event, .. } => {
+                // The window may have crossed onto a monitor with a different refresh rate.
+                if let winit::event::WindowEvent::Moved(_) | winit::event::WindowEvent::ScaleFactorChanged { .. } = event {
+                    let refresh_rate = interface.renderer.monitor_refresh_rate();
+                    if refresh_rate != interface.timer_state.refresh_rate() {
+                        log::debug!(target: "ledge::event", "monitor refresh rate changed to {:?}Hz", refresh_rate);
+                        interface.timer_state.set_refresh_rate(refresh_rate);
+                    }
                 }
-                WindowEvent::Resized(size) => {
-                    interface.renderer.recreate_swapchain = true;
-                    game_state.resize(size.width, size.height).unwrap();
+
+                if let Some(event) = WindowEvent::from_winit(&event) {
+                    interface.process_event(&event);
+
+                    match event {
+                        WindowEvent::CloseRequested => {
+                            *control_flow = ControlFlow::Exit;
+                        }
+                        WindowEvent::Resized { width, height } => {
+                            game_state.resize(width, height).unwrap();
+                        }
+                        _ => {}
+                    }
+
+                    if let Err(e) = game_state.event(interface, &event) {
+                        log::error!(target: "ledge::event", "EventHandler::event() failed: {:?}", e);
+                    }
                 }
-                _ => {}
-            },
+            }
             Event::DeviceEvent { .. } => {}
             Event::Resumed => {}
             Event::Suspended => {}
             Event::NewEvents(_) => {}
-            Event::UserEvent(_) => {}
+            Event::UserEvent(e) => {
+                interface.push_user_event(e);
+            }
             Event::LoopDestroyed => {}
             Event::MainEventsCleared => {
                 let start = time::Instant::now();
@@ -45,7 +193,7 @@ where
 
                 let upda = time::Instant::now();
                 if let Err(e) = game_state.update(interface) {
-                    println!("Error on EventHandler::update(): {:?}", e);
+                    log::error!(target: "ledge::event", "EventHandler::update() failed: {:?}", e);
                 }
 
                 let update_time = 1000. * upda.elapsed().as_secs_f32();
@@ -60,15 +208,16 @@ where
 
                 let draw_time = 1000. * draw.elapsed().as_secs_f32();
 
-                if start.elapsed().as_secs_f32() < 0.016 {
-                    let diff = 0.016 - start.elapsed().as_secs_f32();
-                    thread::sleep(time::Duration::from_secs_f32(diff));
+                let budget = interface.timer_state.frame_budget();
+                if let Some(remaining) = budget.checked_sub(start.elapsed()) {
+                    thread::sleep(remaining);
                 }
 
                 let frame_time = 1000. * start.elapsed().as_secs_f32();
 
-                print!(
-                    "frame time: {:.2}ms u: {:.2}ms d: {:.2}ms i: {:.2}ms\r",
+                log::trace!(
+                    target: "ledge::event",
+                    "frame time: {:.2}ms u: {:.2}ms d: {:.2}ms i: {:.2}ms",
                     frame_time,
                     update_time,
                     draw_time,
@@ -81,9 +230,41 @@ where
     });
 }
 
-pub trait EventHandler {
-    fn start(interface: &mut Interface) -> Self;
-    fn update(&mut self, interface: &mut Interface) -> Result<()>;
-    fn draw(&mut self, interface: &mut Interface, future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>>;
+pub trait EventHandler<E: 'static = ()> {
+    fn start(interface: &mut Interface<E>) -> Self;
+    fn update(&mut self, interface: &mut Interface<E>) -> Result<()>;
+    fn draw(&mut self, interface: &mut Interface<E>, future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>>;
     fn resize(&mut self, width: u32, height: u32) -> Result<()>;
+    /// Called for every window event after the interface has processed it.
+    fn event(&mut self, _interface: &mut Interface<E>, _event: &WindowEvent) -> Result<()> {
+        Ok(())
+    }
+}
+
+// Other platforms only run event loops on the main thread, which the test harness doesn't use.
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use winit::platform::run_return::EventLoopExtRunReturn;
+    use winit::platform::unix::EventLoopExtUnix;
+
+    #[test]
+    #[ignore = "needs a display"]
+    fn events_sent_from_another_thread_reach_the_loop() {
+        let mut event_loop = EventLoop::new(winit::event_loop::EventLoop::<u32>::new_any_thread());
+        let proxy = event_loop.proxy();
+        let sender = thread::spawn(move || proxy.send(42));
+
+        let mut received = Vec::new();
+        event_loop.inner.run_return(|event, _, control_flow| {
+            *control_flow = ControlFlow::Wait;
+            if let Event::UserEvent(e) = event {
+                received.push(e);
+                *control_flow = ControlFlow::Exit;
+            }
+        });
+
+        assert_eq!(sender.join().unwrap(), Ok(()));
+        assert_eq!(received, vec![42]);
+    }
 }
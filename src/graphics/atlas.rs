@@ -0,0 +1,693 @@
+//! Sprite sheets packed by tools such as TexturePacker.
+//!
+//! Packers save space by trimming each sprite's transparent border and sometimes storing it
+//! turned 90 degrees clockwise. An [`AtlasFrame`] keeps what's needed to undo that: drawn with
+//! [`AtlasFrame::draw_info`] or [`SpriteBatch::insert_frame`](crate::graphics::sprite::SpriteBatch::insert_frame),
+//! the trimmed art lands exactly where it sat in the untrimmed source, so layout can use
+//! [`AtlasFrame::logical_size`] as if nothing was trimmed.
+//!
+//! Frames given [`NinePatch`] margins, such as panel and button backgrounds, stretch to any
+//! size with [`AtlasFrame::nine_patch_draws`] or
+//! [`SpriteBatch::insert_nine_patch`](crate::graphics::sprite::SpriteBatch::insert_nine_patch),
+//! keeping their corners at their drawn size.
+//!
+//! With the `serde` feature, [`Atlas::load`] reads TexturePacker's JSON data files in either
+//! the hash or array layout.
+//!
+//! Drawn at non-integer scales with linear filtering, a frame's edges blend in texels of its
+//! neighbours. Two fixes work together, and each can be used alone:
+//!
+//! - [`Atlas::set_half_texel_inset`] pulls every frame's uvs in by half a texel, so samples
+//!   at the edges stay inside the frame. The art loses half a texel on each side.
+//! - [`extrude_edges`] repeats each frame's edge pixels into the padding around it before the
+//!   atlas is uploaded, so whatever is sampled past the edge is the edge's own color.
+//!
+//! Pixel art drawn with [`FilterMode::Nearest`](crate::graphics::sampler::FilterMode::Nearest)
+//! never blends neighbours and wants neither.
+
+use std::collections::HashMap;
+#[cfg(feature = "serde")]
+use std::{path, sync::Arc};
+
+#[cfg(feature = "serde")]
+use anyhow::{anyhow, Context, Result};
+use cgmath::{Matrix4, Rad, Vector3, Vector4};
+#[cfg(feature = "serde")]
+use vulkano::device::Queue;
+#[cfg(feature = "serde")]
+use vulkano::sampler::Sampler;
+
+use crate::graphics::image::Image;
+use crate::graphics::{Color, DrawInfo, Rect};
+
+/// One sprite's place in an [`Atlas`], see the [module docs](self).
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct AtlasFrame {
+    /// The region of the atlas image holding the frame, in uvs. For rotated frames this is the
+    /// region as packed, `size` turned on its side.
+    pub tex_rect: Rect,
+    /// Size in pixels of the trimmed art, upright.
+    pub size: (f32, f32),
+    /// Whether the art is stored turned 90 degrees clockwise.
+    pub rotated: bool,
+    /// Offset in pixels of the trimmed art from the top left of the untrimmed source.
+    pub trim_offset: (f32, f32),
+    /// Size in pixels of the untrimmed source.
+    pub source_size: (f32, f32),
+    /// The point frames are positioned and rotated about, as a fraction of `source_size`.
+    pub pivot: (f32, f32),
+    /// The margins the frame keeps when stretched as a nine-patch, `None` to stretch it whole.
+    pub nine_patch: Option<NinePatch>,
+}
+
+/// Margins in pixels of a frame's art, upright, that keep their size when it's stretched.
+///
+/// The corners are drawn as is, the edges stretch along their length, and the center
+/// stretches both ways. Margins apply to the trimmed art, so pack nine-patch frames untrimmed.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct NinePatch {
+    pub left: f32,
+    pub top: f32,
+    pub right: f32,
+    pub bottom: f32,
+}
+
+impl AtlasFrame {
+    /// An untrimmed, upright frame covering `tex_rect` of an atlas `size` pixels across, pivoting
+    /// about its center.
+    pub fn new(tex_rect: Rect, size: (f32, f32)) -> Self {
+        Self {
+            tex_rect,
+            size,
+            rotated: false,
+            trim_offset: (0.0, 0.0),
+            source_size: size,
+            pivot: (0.5, 0.5),
+            nine_patch: None,
+        }
+    }
+
+    /// The frame sliced with `margins` when drawn with [`AtlasFrame::nine_patch_draws`].
+    pub fn with_nine_patch(mut self, margins: NinePatch) -> Self {
+        self.nine_patch = Some(margins);
+        self
+    }
+
+    /// The size of the untrimmed source in pixels, to lay the sprite out with.
+    pub fn logical_size(&self) -> (f32, f32) {
+        self.source_size
+    }
+
+    /// Maps the unit quad onto the trimmed art, placed within the untrimmed source so that
+    /// the pivot is at the origin.
+    pub fn local_matrix(&self) -> Matrix4<f32> {
+        let (w, h) = self.size;
+
+        // Quad corner (s, t) samples the packed region at (s, t). Art turned clockwise puts
+        // the source point (x, y) there at (h - y, x), so the corner goes back to (t * w, (1 - s) * h).
+        let quad = if self.rotated {
+            Matrix4::from_cols(
+                Vector4::new(0.0, -h, 0.0, 0.0),
+                Vector4::new(w, 0.0, 0.0, 0.0),
+                Vector4::new(0.0, 0.0, 1.0, 0.0),
+                Vector4::new(0.0, h, 0.0, 1.0),
+            )
+        } else {
+            Matrix4::from_nonuniform_scale(w, h, 1.0)
+        };
+
+        let origin = (
+            self.trim_offset.0 - self.pivot.0 * self.source_size.0,
+            self.trim_offset.1 - self.pivot.1 * self.source_size.1,
+        );
+
+        Matrix4::from_translation(Vector3::new(origin.0, origin.1, 0.0)) * quad
+    }
+
+    /// A draw of the frame with its pivot on `position`, scaled by `scale` and rotated
+    /// `rotation` radians about the pivot. Sizes are in the atlas's pixels, so pair it with
+    /// [`CoordinateSystem::PixelsTopLeft`](crate::graphics::CoordinateSystem::PixelsTopLeft) or
+    /// scale accordingly.
+    pub fn draw_info(&self, position: (f32, f32), scale: f32, rotation: f32, color: Color) -> DrawInfo {
+        let matrix = Matrix4::from_translation(Vector3::new(position.0, position.1, 0.0))
+            * Matrix4::from_angle_z(Rad(rotation))
+            * Matrix4::from_scale(scale)
+            * self.local_matrix();
+
+        DrawInfo::with_matrix(matrix, color, self.tex_rect)
+    }
+
+    /// Draws covering the rectangle `size` pixels across with its top left on `position`, one
+    /// per non-empty slice of the frame's [`NinePatch`], or a single stretched draw without one.
+    ///
+    /// Margins are scaled down together where `size` is too small to fit them. Trim offsets
+    /// and the pivot are ignored, the art fills the rectangle.
+    pub fn nine_patch_draws(&self, position: (f32, f32), size: (f32, f32), color: Color) -> Vec<DrawInfo> {
+        let (w, h) = self.size;
+        let margins = self.nine_patch.unwrap_or_default();
+
+        // Source and destination edges along one axis: margin, stretched middle, margin.
+        let slices = |len: f32, start: f32, end: f32, dest: f32| {
+            let fit = if start + end > dest && start + end > 0.0 { dest / (start + end) } else { 1.0 };
+            let (start_to, end_to) = (start * fit, end * fit);
+            [
+                ((0.0, start), (0.0, start_to)),
+                ((start, len - end), (start_to, dest - end_to)),
+                ((len - end, len), (dest - end_to, dest)),
+            ]
+        };
+
+        let mut draws = Vec::with_capacity(9);
+        for ((sy0, sy1), (dy0, dy1)) in slices(h, margins.top, margins.bottom, size.1) {
+            for ((sx0, sx1), (dx0, dx1)) in slices(w, margins.left, margins.right, size.0) {
+                let (from_w, from_h) = (sx1 - sx0, sy1 - sy0);
+                if from_w <= 0.0 || from_h <= 0.0 || dx1 <= dx0 || dy1 <= dy0 {
+                    continue;
+                }
+
+                let slice = self.slice(sx0, sy0, from_w, from_h);
+                let matrix = Matrix4::from_translation(Vector3::new(position.0 + dx0, position.1 + dy0, 0.0))
+                    * Matrix4::from_nonuniform_scale((dx1 - dx0) / from_w, (dy1 - dy0) / from_h, 1.0)
+                    * slice.local_matrix();
+                draws.push(DrawInfo::with_matrix(matrix, color, slice.tex_rect));
+            }
+        }
+        draws
+    }
+
+    /// The part of the art `w` by `h` pixels from `(x, y)`, upright, as an untrimmed frame
+    /// pivoting about its top left.
+    fn slice(&self, x: f32, y: f32, w: f32, h: f32) -> AtlasFrame {
+        let (art_w, art_h) = self.size;
+        let tex = self.tex_rect;
+
+        // Packed clockwise, the art's (x, y) sits at (art_h - y, x) of the packed region.
+        let tex_rect = if self.rotated {
+            Rect {
+                x: tex.x + (art_h - y - h) / art_h * tex.w,
+                y: tex.y + x / art_w * tex.h,
+                w: h / art_h * tex.w,
+                h: w / art_w * tex.h,
+            }
+        } else {
+            Rect {
+                x: tex.x + x / art_w * tex.w,
+                y: tex.y + y / art_h * tex.h,
+                w: w / art_w * tex.w,
+                h: h / art_h * tex.h,
+            }
+        };
+
+        AtlasFrame {
+            tex_rect,
+            size: (w, h),
+            rotated: self.rotated,
+            trim_offset: (0.0, 0.0),
+            source_size: (w, h),
+            pivot: (0.0, 0.0),
+            nine_patch: None,
+        }
+    }
+}
+
+/// An image and the named frames packed into it.
+pub struct Atlas {
+    image: Image,
+    frames: HashMap<String, AtlasFrame>,
+    half_texel_inset: bool,
+}
+
+impl Atlas {
+    /// An atlas over `image` with no frames yet, add them with [`Atlas::insert`].
+    pub fn new(image: Image) -> Self {
+        Self {
+            image,
+            frames: HashMap::new(),
+            half_texel_inset: false,
+        }
+    }
+
+    /// Insets the uvs of every frame, those already inserted and those inserted later, by half
+    /// a texel of the atlas image, see the [module docs](self). Off by default.
+    pub fn set_half_texel_inset(&mut self, inset: bool) {
+        if inset == self.half_texel_inset {
+            return;
+        }
+
+        let (dx, dy) = self.half_texel();
+        let (dx, dy) = if inset { (dx, dy) } else { (-dx, -dy) };
+        for frame in self.frames.values_mut() {
+            frame.tex_rect = frame.tex_rect.inset(dx, dy);
+        }
+        self.half_texel_inset = inset;
+    }
+
+    pub fn half_texel_inset(&self) -> bool {
+        self.half_texel_inset
+    }
+
+    /// Half a texel of the atlas image in uvs.
+    fn half_texel(&self) -> (f32, f32) {
+        (0.5 / self.image.width().max(1) as f32, 0.5 / self.image.height().max(1) as f32)
+    }
+
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    pub fn frame(&self, name: &str) -> Option<&AtlasFrame> {
+        self.frames.get(name)
+    }
+
+    /// Adds `frame` under `name`, insetting its uvs if [`Atlas::set_half_texel_inset`] is on.
+    pub fn insert(&mut self, name: &str, mut frame: AtlasFrame) {
+        if self.half_texel_inset {
+            let (dx, dy) = self.half_texel();
+            frame.tex_rect = frame.tex_rect.inset(dx, dy);
+        }
+        self.frames.insert(name.to_string(), frame);
+    }
+
+    /// The names of every frame, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.frames.keys().map(String::as_str)
+    }
+
+    pub fn len(&self) -> usize {
+        self.frames.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.frames.is_empty()
+    }
+}
+
+/// Repeats the outermost pixels of `region`, given as `(x, y, w, h)` in pixels, outward by
+/// `padding` pixels on every side, corners included, in a tightly packed RGBA8 image `width`
+/// by `height` pixels. Pixels that would land outside the image are skipped.
+///
+/// Run it over each frame of an atlas packed with at least `padding` pixels between frames,
+/// before uploading the pixels, see the [module docs](self).
+pub fn extrude_edges(pixels: &mut [u8], width: u32, height: u32, region: (u32, u32, u32, u32), padding: u32) {
+    let (x, y, w, h) = region;
+    if w == 0 || h == 0 || pixels.len() < (width * height * 4) as usize {
+        return;
+    }
+
+    let (x, y, w, h, padding) = (x as i64, y as i64, w as i64, h as i64, padding as i64);
+    for dy in -padding..h + padding {
+        for dx in -padding..w + padding {
+            if (0..w).contains(&dx) && (0..h).contains(&dy) {
+                continue;
+            }
+
+            let (to_x, to_y) = (x + dx, y + dy);
+            if to_x < 0 || to_y < 0 || to_x >= width as i64 || to_y >= height as i64 {
+                continue;
+            }
+
+            let from_x = x + dx.clamp(0, w - 1);
+            let from_y = y + dy.clamp(0, h - 1);
+            if from_x >= width as i64 || from_y >= height as i64 {
+                continue;
+            }
+
+            let from = ((from_y * width as i64 + from_x) * 4) as usize;
+            let to = ((to_y * width as i64 + to_x) * 4) as usize;
+            pixels.copy_within(from..from + 4, to);
+        }
+    }
+}
+
+#[cfg(feature = "serde")]
+impl Atlas {
+    /// Loads a TexturePacker JSON data file and the image it names, which is looked up next
+    /// to it.
+    pub fn load<P: AsRef<path::Path>>(queue: Arc<Queue>, sampler: Arc<Sampler>, path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let json = std::fs::read_to_string(path)
+            .with_context(|| format!("failed to read atlas {:?}", path))?;
+        let data: texture_packer::Data = serde_json::from_str(&json)
+            .with_context(|| format!("failed to parse atlas {:?}", path))?;
+
+        let image_path = path
+            .parent()
+            .unwrap_or_else(|| path::Path::new(""))
+            .join(&data.meta.image);
+        let image = Image::new(queue, sampler, &image_path)
+            .map_err(|e| anyhow!("failed to load atlas image {:?}: {:?}", image_path, e))?;
+
+        Self::from_data(data, image)
+    }
+
+    /// Reads the frames of a TexturePacker JSON data file already in memory, over an already
+    /// loaded `image`.
+    pub fn from_texture_packer(json: &str, image: Image) -> Result<Self> {
+        let data: texture_packer::Data = serde_json::from_str(json).context("failed to parse atlas")?;
+        Self::from_data(data, image)
+    }
+
+    fn from_data(data: texture_packer::Data, image: Image) -> Result<Self> {
+        let (atlas_w, atlas_h) = match data.meta.size {
+            Some(size) => (size.w, size.h),
+            None => (image.width() as f32, image.height() as f32),
+        };
+        if atlas_w <= 0.0 || atlas_h <= 0.0 {
+            return Err(anyhow!("atlas has an empty size {}x{}", atlas_w, atlas_h));
+        }
+
+        let mut atlas = Atlas::new(image);
+        for (name, frame) in data.frames.into_named() {
+            atlas.insert(&name, frame.to_atlas_frame(atlas_w, atlas_h));
+        }
+
+        Ok(atlas)
+    }
+}
+
+/// The parts of TexturePacker's JSON format that frames are built from.
+#[cfg(feature = "serde")]
+mod texture_packer {
+    use std::collections::HashMap;
+
+    use serde::Deserialize;
+
+    use super::{AtlasFrame, NinePatch};
+
+    #[derive(Deserialize)]
+    pub struct Data {
+        pub frames: Frames,
+        pub meta: Meta,
+    }
+
+    /// The "JSON (Hash)" layout keys frames by name, "JSON (Array)" lists them with a filename.
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    pub enum Frames {
+        Hash(HashMap<String, Frame>),
+        Array(Vec<NamedFrame>),
+    }
+
+    impl Frames {
+        pub fn into_named(self) -> Box<dyn Iterator<Item = (String, Frame)>> {
+            match self {
+                Frames::Hash(frames) => Box::new(frames.into_iter()),
+                Frames::Array(frames) => Box::new(frames.into_iter().map(|f| (f.filename, f.frame))),
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct NamedFrame {
+        pub filename: String,
+        #[serde(flatten)]
+        pub frame: Frame,
+    }
+
+    #[derive(Deserialize)]
+    #[serde(rename_all = "camelCase")]
+    pub struct Frame {
+        pub frame: Rect,
+        #[serde(default)]
+        pub rotated: bool,
+        #[serde(default)]
+        pub trimmed: bool,
+        pub sprite_source_size: Option<Rect>,
+        pub source_size: Option<Size>,
+        pub pivot: Option<Point>,
+        /// Nine-patch margins as the center's rect within the frame, as TexturePacker writes
+        /// them for sprites with scale9 enabled.
+        pub scale9_borders: Option<Rect>,
+    }
+
+    impl Frame {
+        /// The frame's place in an atlas `atlas_w` by `atlas_h` pixels.
+        pub fn to_atlas_frame(&self, atlas_w: f32, atlas_h: f32) -> AtlasFrame {
+            let rect = self.frame;
+            let (packed_w, packed_h) = if self.rotated { (rect.h, rect.w) } else { (rect.w, rect.h) };
+
+            let (trim_offset, source_size) = match (self.trimmed, self.sprite_source_size, self.source_size) {
+                (true, Some(trim), Some(source)) => ((trim.x, trim.y), (source.w, source.h)),
+                _ => ((0.0, 0.0), (rect.w, rect.h)),
+            };
+
+            AtlasFrame {
+                tex_rect: crate::graphics::Rect {
+                    x: rect.x / atlas_w,
+                    y: rect.y / atlas_h,
+                    w: packed_w / atlas_w,
+                    h: packed_h / atlas_h,
+                },
+                size: (rect.w, rect.h),
+                rotated: self.rotated,
+                trim_offset,
+                source_size,
+                pivot: self.pivot.map_or((0.5, 0.5), |p| (p.x, p.y)),
+                nine_patch: self.scale9_borders.map(|center| NinePatch {
+                    left: center.x,
+                    top: center.y,
+                    right: rect.w - center.x - center.w,
+                    bottom: rect.h - center.y - center.h,
+                }),
+            }
+        }
+    }
+
+    #[derive(Deserialize)]
+    pub struct Meta {
+        pub image: String,
+        pub size: Option<Size>,
+    }
+
+    #[derive(Clone, Copy, Deserialize)]
+    pub struct Rect {
+        pub x: f32,
+        pub y: f32,
+        pub w: f32,
+        pub h: f32,
+    }
+
+    #[derive(Clone, Copy, Deserialize)]
+    pub struct Size {
+        pub w: f32,
+        pub h: f32,
+    }
+
+    #[derive(Clone, Copy, Deserialize)]
+    pub struct Point {
+        pub x: f32,
+        pub y: f32,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use std::f32::consts::FRAC_PI_2;
+
+    use super::*;
+
+    /// Where `frame` drawn at `position` puts the quad corners (0, 0), (1, 0), (0, 1) and (1, 1).
+    fn corners(frame: &AtlasFrame, position: (f32, f32), rotation: f32) -> Vec<(f32, f32)> {
+        let matrix = frame.draw_info(position, 1.0, rotation, Color::white()).transform.as_mat4();
+        [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+            .iter()
+            .map(|&(s, t)| {
+                let p = matrix * Vector4::new(s, t, 0.0, 1.0);
+                (p.x.round(), p.y.round())
+            })
+            .collect()
+    }
+
+    /// The 256 pixel pokeball from `examples/images/atlas.json`, trimmed to its 128 pixel
+    /// center and packed at `x` in a 256 by 128 atlas.
+    fn pokeball(x: f32, rotated: bool) -> AtlasFrame {
+        AtlasFrame {
+            tex_rect: Rect { x: x / 256.0, y: 0.0, w: 0.5, h: 1.0 },
+            size: (128.0, 128.0),
+            rotated,
+            trim_offset: (96.0, 32.0),
+            source_size: (256.0, 256.0),
+            pivot: (0.5, 0.5),
+            nine_patch: None,
+        }
+    }
+
+    /// Where each of `draws` puts its quad, as `(x, y, w, h)`, and the uvs it samples.
+    fn placed(draws: &[DrawInfo]) -> Vec<((f32, f32, f32, f32), Rect)> {
+        draws
+            .iter()
+            .map(|draw| {
+                let matrix = draw.transform.as_mat4();
+                let a = matrix * Vector4::new(0.0, 0.0, 0.0, 1.0);
+                let b = matrix * Vector4::new(1.0, 1.0, 0.0, 1.0);
+                let (x0, y0, x1, y1) = (a.x.min(b.x), a.y.min(b.y), a.x.max(b.x), a.y.max(b.y));
+                ((x0.round(), y0.round(), (x1 - x0).round(), (y1 - y0).round()), draw.tex_rect)
+            })
+            .collect()
+    }
+
+    /// A 32 pixel frame with 8 pixel margins, filling a 64 by 32 atlas at `x`.
+    fn panel(x: f32, rotated: bool) -> AtlasFrame {
+        AtlasFrame::new(Rect { x: x / 64.0, y: 0.0, w: 0.5, h: 1.0 }, (32.0, 32.0))
+            .with_nine_patch(NinePatch { left: 8.0, top: 8.0, right: 8.0, bottom: 8.0 })
+            .rotated(rotated)
+    }
+
+    impl AtlasFrame {
+        fn rotated(mut self, rotated: bool) -> Self {
+            self.rotated = rotated;
+            self
+        }
+    }
+
+    #[test]
+    fn trimmed_frame_sits_where_it_was_in_the_source() {
+        let frame = pokeball(0.0, false);
+
+        // The source's center is on (100, 200), so the trimmed art's corner is 96 - 128 and
+        // 32 - 128 pixels from it.
+        assert_eq!(
+            corners(&frame, (100.0, 200.0), 0.0),
+            vec![(68.0, 104.0), (196.0, 104.0), (68.0, 232.0), (196.0, 232.0)]
+        );
+        assert_eq!(frame.logical_size(), (256.0, 256.0));
+    }
+
+    #[test]
+    fn trimmed_frame_rotates_about_its_pivot() {
+        let frame = pokeball(0.0, false);
+
+        assert_eq!(
+            corners(&frame, (100.0, 200.0), FRAC_PI_2),
+            vec![(196.0, 168.0), (196.0, 296.0), (68.0, 168.0), (68.0, 296.0)]
+        );
+    }
+
+    #[test]
+    fn rotated_frame_is_turned_back_upright() {
+        let frame = pokeball(128.0, true);
+
+        // The packed region's top left holds the art's bottom left, and so on around.
+        assert_eq!(
+            corners(&frame, (100.0, 200.0), 0.0),
+            vec![(68.0, 232.0), (68.0, 104.0), (196.0, 232.0), (196.0, 104.0)]
+        );
+    }
+
+    #[test]
+    fn nine_patch_keeps_its_corners_and_stretches_the_rest() {
+        let placed = placed(&panel(0.0, false).nine_patch_draws((10.0, 20.0), (100.0, 50.0), Color::white()));
+        let quads: Vec<_> = placed.iter().map(|(quad, _)| *quad).collect();
+
+        assert_eq!(
+            quads,
+            vec![
+                (10.0, 20.0, 8.0, 8.0), (18.0, 20.0, 84.0, 8.0), (102.0, 20.0, 8.0, 8.0),
+                (10.0, 28.0, 8.0, 34.0), (18.0, 28.0, 84.0, 34.0), (102.0, 28.0, 8.0, 34.0),
+                (10.0, 62.0, 8.0, 8.0), (18.0, 62.0, 84.0, 8.0), (102.0, 62.0, 8.0, 8.0),
+            ]
+        );
+        // The top left corner samples the top left quarter of the frame's 32 pixels.
+        assert_eq!(placed[0].1, Rect { x: 0.0, y: 0.0, w: 0.125, h: 0.25 });
+        assert_eq!(placed[4].1, Rect { x: 0.125, y: 0.25, w: 0.25, h: 0.5 });
+    }
+
+    #[test]
+    fn nine_patch_margins_shrink_to_fit() {
+        let placed = placed(&panel(0.0, false).nine_patch_draws((0.0, 0.0), (8.0, 32.0), Color::white()));
+        let quads: Vec<_> = placed.iter().map(|(quad, _)| *quad).collect();
+
+        // No room for the middle column, the margins split the width between them.
+        assert_eq!(
+            quads,
+            vec![
+                (0.0, 0.0, 4.0, 8.0), (4.0, 0.0, 4.0, 8.0),
+                (0.0, 8.0, 4.0, 16.0), (4.0, 8.0, 4.0, 16.0),
+                (0.0, 24.0, 4.0, 8.0), (4.0, 24.0, 4.0, 8.0),
+            ]
+        );
+    }
+
+    #[test]
+    fn rotated_nine_patch_slices_match_the_upright_frame() {
+        let upright = placed(&panel(0.0, false).nine_patch_draws((0.0, 0.0), (64.0, 48.0), Color::white()));
+        let rotated = placed(&panel(32.0, true).nine_patch_draws((0.0, 0.0), (64.0, 48.0), Color::white()));
+
+        for ((upright, upright_uvs), (rotated, rotated_uvs)) in upright.iter().zip(&rotated) {
+            assert_eq!(upright, rotated);
+            // The same slice turned on its side in the packed region, in the atlas's pixels.
+            assert_eq!((upright_uvs.w * 64.0, upright_uvs.h * 32.0), (rotated_uvs.h * 32.0, rotated_uvs.w * 64.0));
+        }
+        // The art's top left corner is packed at the region's top right.
+        assert_eq!(rotated[0].1, Rect { x: 0.875, y: 0.0, w: 0.125, h: 0.25 });
+    }
+
+    #[test]
+    fn frames_without_margins_stretch_whole() {
+        let frame = AtlasFrame::new(Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 }, (32.0, 32.0));
+        let placed = placed(&frame.nine_patch_draws((5.0, 5.0), (100.0, 10.0), Color::white()));
+
+        assert_eq!(placed, vec![((5.0, 5.0, 100.0, 10.0), frame.tex_rect)]);
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn texture_packer_scale9_borders_become_margins() {
+        let json = r#"{
+            "frames": { "panel": {
+                "frame": { "x": 0, "y": 0, "w": 32, "h": 24 },
+                "scale9Borders": { "x": 4, "y": 6, "w": 20, "h": 10 }
+            } },
+            "meta": { "image": "panel.png", "size": { "w": 32, "h": 24 } }
+        }"#;
+        let data: texture_packer::Data = serde_json::from_str(json).unwrap();
+        let (_, frame) = data.frames.into_named().next().unwrap();
+
+        assert_eq!(
+            frame.to_atlas_frame(32.0, 24.0).nine_patch,
+            Some(NinePatch { left: 4.0, top: 6.0, right: 8.0, bottom: 8.0 })
+        );
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn texture_packer_fixture_reads_trim_and_rotation() {
+        let data: texture_packer::Data = serde_json::from_str(include_str!("../../examples/images/atlas.json")).unwrap();
+        let size = data.meta.size.unwrap();
+        let frames: HashMap<String, AtlasFrame> = data
+            .frames
+            .into_named()
+            .map(|(name, frame)| (name, frame.to_atlas_frame(size.w, size.h)))
+            .collect();
+
+        assert_eq!(frames["pokeball"], pokeball(0.0, false));
+        assert_eq!(frames["pokeball-rotated"], pokeball(128.0, true));
+    }
+
+    /// Whether any pixel of the red frame of a red and green atlas, drawn zoomed to 1.37x at a
+    /// fractional position with linear filtering, picks up the green frame beside it.
+    fn red_frame_bleeds_green(inset: bool) -> bool {
+        use crate::graphics::{render_offscreen, test_device, test_pass};
+
+        let (queue, sampler) = test_device();
+        let pixels = (0..16 * 32).flat_map(|i| if i % 32 < 16 { [255, 0, 0, 255] } else { [0, 255, 0, 255] }).collect();
+        let image = Image::from_u8(queue.clone(), sampler, 32, 16, pixels).unwrap();
+
+        let mut atlas = Atlas::new(image);
+        atlas.insert("red", AtlasFrame::new(Rect { x: 0.0, y: 0.0, w: 0.5, h: 1.0 }, (16.0, 16.0)));
+        atlas.set_half_texel_inset(inset);
+        let info = atlas.frame("red").unwrap().draw_info((16.3, 16.6), 1.37, 0.0, Color::white());
+
+        let sheet = Arc::new(atlas.image().clone());
+        let mut pass = test_pass(queue.clone());
+        let pixels = render_offscreen(&queue, &mut pass, [32, 32], Color::black(), |pass| pass.draw(sheet, info));
+        pixels.chunks_exact(4).any(|pixel| pixel[1] > 0)
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn inset_frames_never_sample_their_neighbours() {
+        assert!(red_frame_bleeds_green(false), "the edges should reach the neighbour without the inset");
+        assert!(!red_frame_bleeds_green(true));
+    }
+}
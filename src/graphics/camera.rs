@@ -1,5 +1,85 @@
 use cgmath::prelude::*;
 use cgmath::{Deg, Matrix4, Rad, Vector3, Vector4};
+use crate::graphics::Rect;
+use bytemuck::{Pod, Zeroable};
+use std::sync::Arc;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::device::Device;
+
+/// Descriptor set the camera uniform is bound to for every draw.
+pub const CAMERA_UNIFORM_SET: usize = 0;
+/// Binding of the camera uniform within [`CAMERA_UNIFORM_SET`].
+pub const CAMERA_UNIFORM_BINDING: u32 = 0;
+/// Number of camera uniform buffers a render pass cycles through, one per frame that may be
+/// in flight.
+pub const CAMERA_SLOTS: usize = 3;
+
+/// The layout of the camera uniform uploaded once per frame, declare it in custom shaders as
+/// `layout(set = 0, binding = 0) uniform Camera { mat4 view_proj; }`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, PartialEq, Zeroable, Pod)]
+pub struct CameraUniform {
+    pub view_proj: [[f32; 4]; 4],
+}
+
+impl From<[[f32; 4]; 4]> for CameraUniform {
+    fn from(view_proj: [[f32; 4]; 4]) -> Self {
+        Self { view_proj }
+    }
+}
+
+/// Camera uniforms reused across frames, so a frame's camera is written into a buffer the GPU
+/// finished reading frames ago instead of one the previous frame may still be using.
+pub(crate) struct CameraRing {
+    buffers: Vec<Arc<CpuAccessibleBuffer<CameraUniform>>>,
+    next: usize,
+}
+
+impl CameraRing {
+    pub(crate) fn new(device: Arc<Device>) -> anyhow::Result<Self> {
+        let buffers = (0..CAMERA_SLOTS)
+            .map(|_| {
+                CpuAccessibleBuffer::from_data(
+                    device.clone(),
+                    BufferUsage::uniform_buffer(),
+                    false,
+                    CameraUniform::default(),
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Self { buffers, next: 0 })
+    }
+
+    /// Writes `view_proj` into the next buffer in the ring and returns it.
+    ///
+    /// Never waits on the GPU: if the buffer is somehow still in use, more frames are in flight
+    /// than [`CAMERA_SLOTS`], and it's replaced with a newly allocated one.
+    pub(crate) fn write(&mut self, device: Arc<Device>, view_proj: [[f32; 4]; 4]) -> anyhow::Result<Arc<CpuAccessibleBuffer<CameraUniform>>> {
+        let slot = self.next;
+        self.next = (self.next + 1) % self.buffers.len();
+
+        let written = match self.buffers[slot].write() {
+            Ok(mut uniform) => {
+                *uniform = CameraUniform::from(view_proj);
+                true
+            }
+            Err(_) => false,
+        };
+
+        if !written {
+            log::debug!(target: "ledge::graphics", "camera uniform slot {} still in use, allocating another", slot);
+            self.buffers[slot] = CpuAccessibleBuffer::from_data(
+                device,
+                BufferUsage::uniform_buffer(),
+                false,
+                CameraUniform::from(view_proj),
+            )?;
+        }
+
+        Ok(self.buffers[slot].clone())
+    }
+}
 
 pub trait Camera {
     fn model_array(&self) -> [[f32; 4]; 4];
@@ -41,14 +121,12 @@ pub trait Camera {
 ///
 /// # Examples
 /// ```
-/// use ledge_engine::graphics::camera;
+/// use ledge::graphics::camera::{Camera, PerspectiveCamera};
 /// use cgmath::Deg;
 ///
-/// pub fn main() {
-///     let camera = camera::PerspectiveCamera::new(75, 800.0/600.0, 5, 1000);
-///     camera.rotate_x(Deg(20.0));
-///     camera.translate_z(100.0);
-/// }
+/// let mut camera = PerspectiveCamera::new(75.0, 800.0 / 600.0, 5.0, 1000.0);
+/// camera.rotate_x(Deg(20.0));
+/// camera.translate_z(100.0);
 /// ```
 #[derive(Debug)]
 pub struct PerspectiveCamera {
@@ -113,7 +191,7 @@ impl PerspectiveCamera {
 
         // proj = proj * x;
 
-        println!("m: {:?}\nv: {:?}\np: {:?}", model, view, proj);
+        log::debug!(target: "ledge::graphics::camera", "m: {:?}\nv: {:?}\np: {:?}", model, view, proj);
 
         Self {
             fov,
@@ -217,6 +295,14 @@ impl OrthographicCamera {
             proj: Matrix4::from_cols(x, y, z, w),
         }
     }
+
+    /// A camera drawing through `projection`, see [`crate::graphics::CoordinateSystem::projection`].
+    pub fn with_projection(projection: [[f32; 4]; 4]) -> Self {
+        Self {
+            proj: projection.into(),
+            ..Self::new(0.0, 1.0)
+        }
+    }
 }
 
 impl Camera for OrthographicCamera {
@@ -274,22 +360,174 @@ impl Camera for OrthographicCamera {
 
     fn as_mvp(&self) -> [[f32; 4]; 4] {
         // (self.model * self.view * self.proj).into()
-        Matrix4::identity().into()
+        self.proj.into()
     }
 }
 
-#[derive(Clone, Copy)]
+/// A 2D camera looking at `position` in world units, showing `viewport / zoom` units of the world.
+#[derive(Clone, Copy, Debug)]
 pub struct Camera2D {
+    position: (f32, f32),
+    zoom: f32,
+    viewport: (f32, f32),
+    bounds: Option<Rect>,
+}
 
+impl Default for Camera2D {
+    fn default() -> Self {
+        Camera2D::new(800.0, 600.0)
+    }
 }
 
 impl Camera2D {
+    /// `width` and `height` are the size of the viewport in pixels, one pixel is one world
+    /// unit at a zoom of 1.
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            position: (0.0, 0.0),
+            zoom: 1.0,
+            viewport: (width, height),
+            bounds: None,
+        }
+    }
+
+    pub fn position(&self) -> (f32, f32) {
+        self.position
+    }
+
+    pub fn zoom(&self) -> f32 {
+        self.zoom
+    }
+
+    /// Size of the region of the world the camera can see.
+    pub fn view_size(&self) -> (f32, f32) {
+        (self.viewport.0 / self.zoom, self.viewport.1 / self.zoom)
+    }
+
+    pub fn set_viewport(&mut self, width: f32, height: f32) {
+        self.viewport = (width, height);
+        self.clamp();
+    }
+
+    pub fn set_zoom(&mut self, zoom: f32) {
+        self.zoom = zoom.max(f32::EPSILON);
+        self.clamp();
+    }
+
+    /// Keeps the visible region inside `bounds` after every move, zoom, or resize.
+    ///
+    /// On an axis where the world is smaller than the view, the camera is centered on the world instead.
+    pub fn set_bounds(&mut self, bounds: Option<Rect>) {
+        self.bounds = bounds;
+        self.clamp();
+    }
+
+    /// Centers the camera on `target`.
+    pub fn follow(&mut self, target: (f32, f32)) {
+        self.position = target;
+        self.clamp();
+    }
+
+    pub fn translate(&mut self, dx: f32, dy: f32) {
+        self.position = (self.position.0 + dx, self.position.1 + dy);
+        self.clamp();
+    }
+
+    /// Centers the camera on `rect` and zooms so all of it, plus `padding` world units on
+    /// every side, is in view. Pair it with [`crate::graphics::Bounded::bounds`] to frame a
+    /// drawable.
+    pub fn fit_bounds(&mut self, rect: Rect, padding: f32) {
+        let w = (rect.w + padding * 2.0).max(f32::EPSILON);
+        let h = (rect.h + padding * 2.0).max(f32::EPSILON);
+        self.zoom = (self.viewport.0 / w).min(self.viewport.1 / h).max(f32::EPSILON);
+        self.position = (rect.x + rect.w / 2.0, rect.y + rect.h / 2.0);
+        self.clamp();
+    }
+
+    fn clamp(&mut self) {
+        let bounds = match self.bounds {
+            Some(bounds) => bounds,
+            None => return,
+        };
+
+        let (view_w, view_h) = self.view_size();
+        self.position = (
+            clamp_axis(self.position.0, bounds.x, bounds.w, view_w),
+            clamp_axis(self.position.1, bounds.y, bounds.h, view_h),
+        );
+    }
+
+    /// Maps the visible region of the world to normalized device coordinates.
     pub fn as_mvp(&self) -> [[f32; 4]; 4]{
+        let (view_w, view_h) = self.view_size();
+        let sx = 2.0 / view_w;
+        let sy = 2.0 / view_h;
+
         [
-            [1.0,0.0,0.0,0.0],
-            [0.0,1.0,0.0,0.0],
+            [sx,0.0,0.0,0.0],
+            [0.0,sy,0.0,0.0],
             [0.0,0.0,1.0,0.0],
-            [0.0,0.0,0.0,1.0],
+            [-self.position.0 * sx,-self.position.1 * sy,0.0,1.0],
         ]
     }
-}
\ No newline at end of file
+}
+
+fn clamp_axis(center: f32, min: f32, size: f32, view: f32) -> f32 {
+    if size <= view {
+        min + size / 2.0
+    } else {
+        center.max(min + view / 2.0).min(min + size - view / 2.0)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn frames_in_flight_write_alternate_camera_buffers() {
+        let (queue, _) = crate::graphics::test_device();
+        let device = queue.device().clone();
+        let mut ring = CameraRing::new(device.clone()).unwrap();
+        let view_proj = |x: f32| Matrix4::from_translation(Vector3::new(x, 0.0, 0.0)).into();
+
+        // The previous frame's buffer is still held while the next frame writes its camera.
+        let previous = ring.write(device.clone(), view_proj(1.0)).unwrap();
+        let current = ring.write(device.clone(), view_proj(2.0)).unwrap();
+        assert!(!Arc::ptr_eq(&previous, &current));
+        assert_eq!(*previous.read().unwrap(), CameraUniform::from(view_proj(1.0)));
+        assert_eq!(*current.read().unwrap(), CameraUniform::from(view_proj(2.0)));
+
+        for _ in 2..CAMERA_SLOTS {
+            ring.write(device.clone(), view_proj(3.0)).unwrap();
+        }
+        assert!(Arc::ptr_eq(&ring.write(device, view_proj(4.0)).unwrap(), &previous));
+    }
+
+    #[test]
+    fn camera_clamps_to_its_bounds() {
+        let mut camera = Camera2D::new(800.0, 600.0);
+        camera.set_bounds(Some(Rect { x: 0.0, y: 0.0, w: 2000.0, h: 1000.0 }));
+
+        camera.follow((1000.0, 500.0));
+        assert_eq!(camera.position(), (1000.0, 500.0));
+
+        camera.translate(5000.0, -5000.0);
+        assert_eq!(camera.position(), (1600.0, 300.0));
+
+        // Zoomed out the view is 1600x1200: the right edge pulls the camera in, and the world
+        // is now shorter than the view so it's centered vertically.
+        camera.set_zoom(0.5);
+        assert_eq!(camera.position(), (1200.0, 500.0));
+    }
+
+    #[test]
+    fn camera_centers_worlds_smaller_than_its_view() {
+        let mut camera = Camera2D::new(800.0, 600.0);
+        camera.set_bounds(Some(Rect { x: 100.0, y: 0.0, w: 400.0, h: 1000.0 }));
+
+        camera.follow((0.0, 900.0));
+        assert_eq!(camera.position(), (300.0, 700.0));
+    }
+}
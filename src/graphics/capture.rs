@@ -0,0 +1,284 @@
+//! Frames read back from the GPU without waiting on them, for save slot thumbnails.
+//!
+//! [`crate::graphics::capture_thumbnail`] asks for a copy of the frame being drawn. When the
+//! frame ends, a copy of the swapchain image into a host visible buffer is recorded between
+//! the frame's draws and its presentation, which is all the capture adds to the frame. Once
+//! the GPU has finished with it, noticed at the start of a later frame, a worker thread
+//! shrinks the pixels and encodes them as a PNG, and [`ThumbnailHandle::poll`] hands them over.
+
+use std::sync::mpsc::{self, Receiver, Sender, TryRecvError};
+use std::sync::Arc;
+
+use anyhow::Result;
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage};
+use vulkano::device::Queue;
+use vulkano::format::Format;
+use vulkano::image::ImageAccess;
+use vulkano::sync::GpuFuture;
+
+use crate::graphics::error::GraphicsError;
+
+type ThumbnailResult = std::result::Result<Vec<u8>, GraphicsError>;
+
+/// A thumbnail being captured, see the [module docs](self).
+pub struct ThumbnailHandle {
+    receiver: Receiver<ThumbnailResult>,
+    done: bool,
+}
+
+impl ThumbnailHandle {
+    /// The encoded PNG once it's ready, `None` while it's still being captured.
+    ///
+    /// Returns the result once, and `None` on every call after.
+    pub fn poll(&mut self) -> Option<ThumbnailResult> {
+        if self.done {
+            return None;
+        }
+
+        match self.receiver.try_recv() {
+            Ok(result) => {
+                self.done = true;
+                Some(result)
+            }
+            Err(TryRecvError::Empty) => None,
+            Err(TryRecvError::Disconnected) => {
+                self.done = true;
+                Some(Err(GraphicsError::Capture("the renderer was dropped before the frame was copied".to_string())))
+            }
+        }
+    }
+}
+
+struct Request {
+    max_size: (u32, u32),
+    sender: Sender<ThumbnailResult>,
+}
+
+/// A frame copy the GPU may still be writing.
+struct FrameCopy {
+    buffer: Arc<CpuAccessibleBuffer<[u8]>>,
+    extent: [u32; 2],
+    format: Format,
+    request: Request,
+}
+
+/// The captures the renderer has been asked for, from request to handing them to a worker.
+#[derive(Default)]
+pub(crate) struct Captures {
+    requested: Vec<Request>,
+    copying: Vec<FrameCopy>,
+}
+
+impl Captures {
+    pub(crate) fn request(&mut self, max_size: (u32, u32)) -> ThumbnailHandle {
+        let (sender, receiver) = mpsc::channel();
+        self.requested.push(Request { max_size, sender });
+
+        ThumbnailHandle {
+            receiver,
+            done: false,
+        }
+    }
+
+    /// Copies `image` for every capture requested since the last frame, after `before_future`.
+    /// `readable` is whether the image was created with transfer source usage.
+    pub(crate) fn record(
+        &mut self,
+        queue: &Arc<Queue>,
+        image: Arc<dyn ImageAccess>,
+        readable: bool,
+        before_future: Box<dyn GpuFuture>,
+    ) -> Result<Box<dyn GpuFuture>> {
+        if self.requested.is_empty() {
+            return Ok(before_future);
+        }
+        let requests = std::mem::take(&mut self.requested);
+
+        let format = image.format();
+        if let Err(e) = check_readable(format, readable) {
+            log::warn!(target: "ledge::graphics", "{}", e);
+            for request in requests {
+                let _ = request.sender.send(check_readable(format, readable).map(|_| Vec::new()));
+            }
+            return Ok(before_future);
+        }
+
+        let extent = image.dimensions().width_height();
+        let len = extent[0] as usize * extent[1] as usize * 4;
+        let buffer = CpuAccessibleBuffer::from_iter(
+            queue.device().clone(),
+            BufferUsage::transfer_destination(),
+            true,
+            std::iter::repeat_n(0u8, len),
+        )?;
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            queue.device().clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        builder.copy_image_to_buffer(image, buffer.clone())?;
+        let commands = builder.build()?;
+
+        self.copying.extend(requests.into_iter().map(|request| FrameCopy {
+            buffer: buffer.clone(),
+            extent,
+            format,
+            request,
+        }));
+
+        Ok(before_future.then_execute(queue.clone(), commands)?.boxed())
+    }
+
+    /// Hands every copy the GPU has finished to a worker thread. The buffers only become
+    /// readable once the frame's future has been cleaned up, so call this after that.
+    pub(crate) fn poll(&mut self) {
+        let (finished, copying): (Vec<_>, Vec<_>) = std::mem::take(&mut self.copying)
+            .into_iter()
+            .partition(|copy| copy.buffer.read().is_ok());
+        self.copying = copying;
+
+        for copy in finished {
+            std::thread::spawn(move || {
+                let result = thumbnail(&copy.buffer, copy.extent, copy.format, copy.request.max_size);
+                let _ = copy.request.sender.send(result);
+            });
+        }
+    }
+}
+
+/// Whether an image in `format` can be read back, readback only converts four 8 bit channels.
+fn check_readable(format: Format, readable: bool) -> std::result::Result<(), GraphicsError> {
+    if !readable {
+        return Err(GraphicsError::Capture("the surface doesn't support copying from swapchain images".to_string()));
+    }
+
+    match format {
+        Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB | Format::R8G8B8A8_UNORM | Format::R8G8B8A8_SRGB => Ok(()),
+        _ => Err(GraphicsError::UnsupportedFormat(format)),
+    }
+}
+
+/// Shrinks the copied frame to fit `max_size` and encodes it as an opaque RGBA PNG.
+fn thumbnail(buffer: &CpuAccessibleBuffer<[u8]>, extent: [u32; 2], format: Format, max_size: (u32, u32)) -> ThumbnailResult {
+    let pixels = buffer
+        .read()
+        .map_err(|e| GraphicsError::Capture(format!("{:?}", e)))?;
+
+    let bgra = matches!(format, Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB);
+    let (w, h) = fit(extent, max_size);
+    let mut rgba = downscale(&pixels, extent, (w, h));
+    for pixel in rgba.chunks_exact_mut(4) {
+        if bgra {
+            pixel.swap(0, 2);
+        }
+        pixel[3] = 255;
+    }
+
+    let mut bytes = Vec::new();
+    {
+        let mut encoder = png::Encoder::new(&mut bytes, w, h);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        let mut writer = encoder.write_header()?;
+        writer.write_image_data(&rgba)?;
+    }
+
+    Ok(bytes)
+}
+
+/// The largest size with the aspect ratio of `extent` that fits in `max_size`, never larger
+/// than `extent` itself.
+fn fit(extent: [u32; 2], max_size: (u32, u32)) -> (u32, u32) {
+    let [w, h] = extent;
+    let scale = (max_size.0 as f32 / w as f32)
+        .min(max_size.1 as f32 / h as f32)
+        .min(1.0);
+
+    (
+        ((w as f32 * scale).round() as u32).max(1),
+        ((h as f32 * scale).round() as u32).max(1),
+    )
+}
+
+/// Box filters 4 byte pixels from `extent` down to `size`, each output pixel averaging the
+/// block of source pixels it covers.
+fn downscale(pixels: &[u8], extent: [u32; 2], size: (u32, u32)) -> Vec<u8> {
+    let [sw, sh] = [extent[0] as usize, extent[1] as usize];
+    let (dw, dh) = (size.0 as usize, size.1 as usize);
+    let mut out = Vec::with_capacity(dw * dh * 4);
+
+    for dy in 0..dh {
+        let y0 = dy * sh / dh;
+        let y1 = ((dy + 1) * sh / dh).max(y0 + 1);
+
+        for dx in 0..dw {
+            let x0 = dx * sw / dw;
+            let x1 = ((dx + 1) * sw / dw).max(x0 + 1);
+
+            let mut sum = [0u32; 4];
+            for y in y0..y1 {
+                let row = &pixels[(y * sw + x0) * 4..(y * sw + x1) * 4];
+                for pixel in row.chunks_exact(4) {
+                    for (total, channel) in sum.iter_mut().zip(pixel) {
+                        *total += *channel as u32;
+                    }
+                }
+            }
+
+            let count = ((y1 - y0) * (x1 - x0)) as u32;
+            out.extend(sum.iter().map(|total| ((total + count / 2) / count) as u8));
+        }
+    }
+
+    out
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{draw_quad, render_into, test_device, test_pass, test_target, Color, Rect};
+    use vulkano::image::view::ImageView;
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn thumbnails_decode_to_the_frame_dominant_color() {
+        let (queue, _) = test_device();
+        let target = test_target(&queue, [64, 64]);
+        let view = ImageView::new_default(target.clone()).unwrap();
+        render_into(&mut test_pass(queue.clone()), view, Color::black(), |pass| {
+            draw_quad(pass, Rect { x: 0.0, y: 0.0, w: 64.0, h: 48.0 }, Color::red())
+        });
+
+        let mut captures = Captures::default();
+        let mut handle = captures.request((16, 16));
+        captures
+            .record(&queue, target, true, vulkano::sync::now(queue.device().clone()).boxed())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        captures.poll();
+
+        let png = loop {
+            match handle.poll() {
+                Some(result) => break result.unwrap(),
+                None => std::thread::sleep(std::time::Duration::from_millis(1)),
+            }
+        };
+
+        let mut reader = png::Decoder::new(png.as_slice()).read_info().unwrap();
+        let mut pixels = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut pixels).unwrap();
+        assert_eq!((info.width, info.height), (16, 16));
+
+        let mut counts = std::collections::HashMap::new();
+        for pixel in pixels[..info.buffer_size()].chunks_exact(4) {
+            *counts.entry([pixel[0], pixel[1], pixel[2], pixel[3]]).or_insert(0) += 1;
+        }
+        let dominant = counts.into_iter().max_by_key(|(_, count)| *count).unwrap();
+        assert_eq!(dominant, ([255, 0, 0, 255], 16 * 12));
+    }
+}
@@ -0,0 +1,278 @@
+//! Block compressed textures loaded from DDS files.
+//!
+//! BC1 (DXT1) and BC3 (DXT5) blocks are uploaded as is when the device can sample them,
+//! taking a quarter or an eighth of the memory of the same texture as RGBA. Devices without
+//! BC support get the blocks decompressed on the CPU instead, see
+//! [`crate::graphics::image::Image::from_dds`].
+
+use std::sync::Arc;
+
+use vulkano::device::Device;
+use vulkano::format::Format;
+
+const DDS_MAGIC: &[u8; 4] = b"DDS ";
+/// Size of the magic number and the header following it.
+const DDS_HEADER_SIZE: usize = 128;
+/// Size of the extended header present when the four character code is `DX10`.
+const DX10_HEADER_SIZE: usize = 20;
+const DXGI_FORMAT_BC1_UNORM: u32 = 71;
+const DXGI_FORMAT_BC3_UNORM: u32 = 77;
+
+/// A block compression format, each block covering 4x4 pixels.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum BlockFormat {
+    /// RGB with 1-bit alpha, 8 bytes per block.
+    Bc1,
+    /// RGB with interpolated alpha, 16 bytes per block.
+    Bc3,
+}
+
+impl BlockFormat {
+    pub fn format(&self) -> Format {
+        match self {
+            BlockFormat::Bc1 => Format::BC1_RGBA_UNORM_BLOCK,
+            BlockFormat::Bc3 => Format::BC3_UNORM_BLOCK,
+        }
+    }
+
+    pub fn block_size(&self) -> usize {
+        match self {
+            BlockFormat::Bc1 => 8,
+            BlockFormat::Bc3 => 16,
+        }
+    }
+
+    /// Whether `device` was created with BC support and can sample this format.
+    pub fn is_supported(&self, device: &Arc<Device>) -> bool {
+        device.enabled_features().texture_compression_bc
+            && device
+                .physical_device()
+                .format_properties(self.format())
+                .optimal_tiling_features
+                .sampled_image
+    }
+}
+
+/// The top mip level of a DDS file.
+pub(crate) struct Dds {
+    pub(crate) width: u32,
+    pub(crate) height: u32,
+    pub(crate) format: BlockFormat,
+    pub(crate) blocks: Vec<u8>,
+}
+
+impl Dds {
+    pub(crate) fn parse(bytes: &[u8]) -> Result<Self, String> {
+        if bytes.len() < DDS_HEADER_SIZE || &bytes[0..4] != DDS_MAGIC {
+            return Err("not a DDS file".to_string());
+        }
+
+        let read_u32 = |offset: usize| u32::from_le_bytes([bytes[offset], bytes[offset + 1], bytes[offset + 2], bytes[offset + 3]]);
+        let height = read_u32(12);
+        let width = read_u32(16);
+        let four_cc = &bytes[84..88];
+
+        let (format, data_start) = match four_cc {
+            b"DXT1" => (BlockFormat::Bc1, DDS_HEADER_SIZE),
+            b"DXT5" => (BlockFormat::Bc3, DDS_HEADER_SIZE),
+            b"DX10" if bytes.len() >= DDS_HEADER_SIZE + DX10_HEADER_SIZE => {
+                let format = match read_u32(DDS_HEADER_SIZE) {
+                    DXGI_FORMAT_BC1_UNORM => BlockFormat::Bc1,
+                    DXGI_FORMAT_BC3_UNORM => BlockFormat::Bc3,
+                    other => return Err(format!("unsupported DXGI format {} in DDS file", other)),
+                };
+                (format, DDS_HEADER_SIZE + DX10_HEADER_SIZE)
+            }
+            other => {
+                return Err(format!(
+                    "unsupported DDS pixel format {:?}, only BC1 (DXT1) and BC3 (DXT5) can be loaded",
+                    String::from_utf8_lossy(other),
+                ))
+            }
+        };
+
+        if width == 0 || height == 0 {
+            return Err(format!("DDS file has an empty {}x{} image", width, height));
+        }
+
+        let size = blocks_wide(width)
+            .checked_mul(blocks_wide(height))
+            .and_then(|blocks| blocks.checked_mul(format.block_size()))
+            .ok_or_else(|| format!("DDS file has an impossibly large {}x{} image", width, height))?;
+        let data_end = data_start
+            .checked_add(size)
+            .ok_or_else(|| format!("DDS file has an impossibly large {}x{} image", width, height))?;
+        let blocks = bytes
+            .get(data_start..data_end)
+            .ok_or_else(|| format!("DDS file is truncated, a {}x{} {:?} image needs {} bytes", width, height, format, size))?
+            .to_vec();
+
+        Ok(Self {
+            width,
+            height,
+            format,
+            blocks,
+        })
+    }
+
+    /// The image as RGBA8 pixels, for devices that can't sample the blocks directly.
+    pub(crate) fn decompress(&self) -> Vec<u8> {
+        let (width, height) = (self.width as usize, self.height as usize);
+        let mut pixels = vec![0u8; width * height * 4];
+
+        for (i, block) in self.blocks.chunks_exact(self.format.block_size()).enumerate() {
+            let (bx, by) = (i % blocks_wide(self.width) * 4, i / blocks_wide(self.width) * 4);
+
+            let texels = match self.format {
+                BlockFormat::Bc1 => decode_color_block(block, true),
+                BlockFormat::Bc3 => {
+                    let mut texels = decode_color_block(&block[8..16], false);
+                    for (texel, alpha) in texels.iter_mut().zip(decode_alpha_block(&block[0..8])) {
+                        texel[3] = alpha;
+                    }
+                    texels
+                }
+            };
+
+            for (j, texel) in texels.iter().enumerate() {
+                let (x, y) = (bx + j % 4, by + j / 4);
+                if x < width && y < height {
+                    let offset = (y * width + x) * 4;
+                    pixels[offset..offset + 4].copy_from_slice(texel);
+                }
+            }
+        }
+
+        pixels
+    }
+}
+
+fn blocks_wide(size: u32) -> usize {
+    (size as usize).div_ceil(4)
+}
+
+fn rgb565(color: u16) -> [u8; 4] {
+    let r = (color >> 11) & 0x1f;
+    let g = (color >> 5) & 0x3f;
+    let b = color & 0x1f;
+    [
+        ((r << 3) | (r >> 2)) as u8,
+        ((g << 2) | (g >> 4)) as u8,
+        ((b << 3) | (b >> 2)) as u8,
+        255,
+    ]
+}
+
+fn mix(a: [u8; 4], b: [u8; 4], wa: u16, wb: u16) -> [u8; 4] {
+    let channel = |i: usize| ((a[i] as u16 * wa + b[i] as u16 * wb) / (wa + wb)) as u8;
+    [channel(0), channel(1), channel(2), 255]
+}
+
+/// The 16 texels of a BC1 color block, row by row. `punch_through` enables BC1's mode with a
+/// transparent fourth color, which BC3's color blocks never use.
+fn decode_color_block(block: &[u8], punch_through: bool) -> [[u8; 4]; 16] {
+    let c0 = u16::from_le_bytes([block[0], block[1]]);
+    let c1 = u16::from_le_bytes([block[2], block[3]]);
+    let (a, b) = (rgb565(c0), rgb565(c1));
+
+    let palette = if c0 > c1 || !punch_through {
+        [a, b, mix(a, b, 2, 1), mix(a, b, 1, 2)]
+    } else {
+        [a, b, mix(a, b, 1, 1), [0, 0, 0, 0]]
+    };
+
+    let indices = u32::from_le_bytes([block[4], block[5], block[6], block[7]]);
+    let mut texels = [[0u8; 4]; 16];
+    for (i, texel) in texels.iter_mut().enumerate() {
+        *texel = palette[((indices >> (i * 2)) & 0b11) as usize];
+    }
+    texels
+}
+
+/// The 16 alphas of a BC3 alpha block, row by row.
+fn decode_alpha_block(block: &[u8]) -> [u8; 16] {
+    let (a0, a1) = (block[0] as u16, block[1] as u16);
+    let mut palette = [a0, a1, 0, 0, 0, 0, 0, 0];
+    if a0 > a1 {
+        for (i, entry) in (1..).zip(palette[2..8].iter_mut()) {
+            *entry = ((7 - i) * a0 + i * a1) / 7;
+        }
+    } else {
+        for (i, entry) in (1..).zip(palette[2..6].iter_mut()) {
+            *entry = ((5 - i) * a0 + i * a1) / 5;
+        }
+        palette[6] = 0;
+        palette[7] = 255;
+    }
+
+    let mut bits = 0u64;
+    for (i, byte) in block[2..8].iter().enumerate() {
+        bits |= (*byte as u64) << (i * 8);
+    }
+
+    let mut alphas = [0u8; 16];
+    for (i, alpha) in alphas.iter_mut().enumerate() {
+        *alpha = palette[((bits >> (i * 3)) & 0b111) as usize] as u8;
+    }
+    alphas
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// An 8x8 BC1 checkerboard of solid red and blue 4x4 blocks.
+    const CHECKER: &[u8] = include_bytes!("../../examples/images/checker-bc1.dds");
+
+    fn with_size(width: u32, height: u32) -> Vec<u8> {
+        let mut bytes = CHECKER.to_vec();
+        bytes[12..16].copy_from_slice(&height.to_le_bytes());
+        bytes[16..20].copy_from_slice(&width.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn bc1_fixture_parses() {
+        let dds = Dds::parse(CHECKER).unwrap();
+        assert_eq!((dds.width, dds.height), (8, 8));
+        assert_eq!(dds.format, BlockFormat::Bc1);
+        assert_eq!(dds.format.format(), Format::BC1_RGBA_UNORM_BLOCK);
+        assert_eq!(dds.blocks.len(), 4 * dds.format.block_size());
+    }
+
+    #[test]
+    fn bc1_fixture_decompresses_to_a_checkerboard() {
+        let pixels = Dds::parse(CHECKER).unwrap().decompress();
+        let pixel = |x: usize, y: usize| &pixels[(y * 8 + x) * 4..(y * 8 + x) * 4 + 4];
+        assert_eq!(pixel(0, 0), [255, 0, 0, 255]);
+        assert_eq!(pixel(7, 0), [0, 0, 255, 255]);
+        assert_eq!(pixel(0, 7), [0, 0, 255, 255]);
+        assert_eq!(pixel(7, 7), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    fn truncated_files_are_rejected() {
+        assert!(Dds::parse(&CHECKER[..CHECKER.len() - 1]).is_err());
+        assert!(Dds::parse(&with_size(16, 16)).is_err());
+    }
+
+    #[test]
+    fn oversized_headers_are_rejected_without_overflowing() {
+        assert!(Dds::parse(&with_size(u32::MAX, u32::MAX)).is_err());
+        assert!(Dds::parse(&with_size(u32::MAX, 1)).is_err());
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn bc1_fixture_loads_as_bc1_or_decompressed() {
+        let (queue, sampler) = crate::graphics::test_device();
+        let image = crate::graphics::image::Image::from_dds(queue.clone(), sampler, "examples/images/checker-bc1.dds").unwrap();
+        let expected = if BlockFormat::Bc1.is_supported(queue.device()) {
+            Format::BC1_RGBA_UNORM_BLOCK
+        } else {
+            Format::R8G8B8A8_UNORM
+        };
+        assert_eq!(image.format(), expected);
+        assert_eq!((image.width(), image.height()), (8, 8));
+    }
+}
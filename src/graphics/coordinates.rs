@@ -0,0 +1,319 @@
+//! The coordinate conventions used for positions, sizes, and the default camera.
+//!
+//! Both systems put the origin at the top left and point y down, matching texture space and
+//! Vulkan's clip space, so nothing is flipped between them. The unit quad every sprite is drawn
+//! from spans `(0, 0)` to `(1, 1)`, so a sprite's position is its top left corner and its scale
+//! is its size.
+//!
+//! - [`CoordinateSystem::NdcCentered`]: normalized device coordinates, `(-1, -1)` at the top left
+//!   of the viewport, `(0, 0)` at its center, and `(1, 1)` at the bottom right. The default.
+//! - [`CoordinateSystem::PixelsTopLeft`]: pixels, `(0, 0)` at the top left of the viewport and
+//!   `(width, height)` at the bottom right.
+//!
+//! The system only changes what [`Renderer::projection`] and the helpers built on it return,
+//! draws made with a custom camera are unaffected.
+//!
+//! Converting between the window, the viewport, and a camera's world goes through a
+//! [`CoordinateMapper`] from [`Renderer::coords`], which knows the sizes involved.
+
+use cgmath::{Matrix4, SquareMatrix, Vector4};
+
+use crate::graphics::renderer::Renderer;
+use crate::graphics::Rect;
+
+/// The units and origin of positions given to the default camera and input helpers.
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq)]
+pub enum CoordinateSystem {
+    /// Normalized device coordinates, `(-1, -1)` to `(1, 1)` with the origin at the center.
+    #[default]
+    NdcCentered,
+    /// Pixels from the top left corner of the viewport.
+    PixelsTopLeft,
+}
+
+impl CoordinateSystem {
+    /// Maps this system over `viewport` to normalized device coordinates, for use as a camera.
+    pub fn projection(&self, viewport: &Rect) -> [[f32; 4]; 4] {
+        match self {
+            CoordinateSystem::NdcCentered => [
+                [1.0, 0.0, 0.0, 0.0],
+                [0.0, 1.0, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [0.0, 0.0, 0.0, 1.0],
+            ],
+            CoordinateSystem::PixelsTopLeft => [
+                [2.0 / viewport.w, 0.0, 0.0, 0.0],
+                [0.0, 2.0 / viewport.h, 0.0, 0.0],
+                [0.0, 0.0, 1.0, 0.0],
+                [-1.0, -1.0, 0.0, 1.0],
+            ],
+        }
+    }
+
+    /// Converts `point` in this system to normalized device coordinates.
+    pub fn to_ndc(&self, point: (f32, f32), viewport: &Rect) -> (f32, f32) {
+        match self {
+            CoordinateSystem::NdcCentered => point,
+            CoordinateSystem::PixelsTopLeft => pixels_to_ndc(point, viewport),
+        }
+    }
+
+    /// Converts `point` in normalized device coordinates to this system.
+    pub fn from_ndc(&self, point: (f32, f32), viewport: &Rect) -> (f32, f32) {
+        match self {
+            CoordinateSystem::NdcCentered => point,
+            CoordinateSystem::PixelsTopLeft => ndc_to_pixels(point, viewport),
+        }
+    }
+
+    /// Converts `point` in pixels from the top left of `viewport` to this system.
+    pub fn from_pixels(&self, point: (f32, f32), viewport: &Rect) -> (f32, f32) {
+        match self {
+            CoordinateSystem::NdcCentered => pixels_to_ndc(point, viewport),
+            CoordinateSystem::PixelsTopLeft => point,
+        }
+    }
+
+    /// The size of one pixel of `viewport` in this system.
+    ///
+    /// Multiply an image's width and height by it to get the scale that draws the image at its
+    /// native size.
+    pub fn pixel_size(&self, viewport: &Rect) -> (f32, f32) {
+        match self {
+            CoordinateSystem::NdcCentered => (2.0 / viewport.w, 2.0 / viewport.h),
+            CoordinateSystem::PixelsTopLeft => (1.0, 1.0),
+        }
+    }
+}
+
+/// Converts pixels from the top left of `viewport` to normalized device coordinates.
+pub fn pixels_to_ndc(point: (f32, f32), viewport: &Rect) -> (f32, f32) {
+    (
+        (point.0 - viewport.x) / viewport.w * 2.0 - 1.0,
+        (point.1 - viewport.y) / viewport.h * 2.0 - 1.0,
+    )
+}
+
+/// Converts normalized device coordinates to pixels from the top left of `viewport`.
+pub fn ndc_to_pixels(point: (f32, f32), viewport: &Rect) -> (f32, f32) {
+    (
+        (point.0 + 1.0) / 2.0 * viewport.w + viewport.x,
+        (point.1 + 1.0) / 2.0 * viewport.h + viewport.y,
+    )
+}
+
+/// Converts points between the spaces a frame passes through, see [`Renderer::coords`].
+///
+/// - Window: physical pixels from the top left of the window's drawable area, as winit
+///   reports the cursor.
+/// - Viewport: the current [`CoordinateSystem`] over the swapchain image, what the default
+///   camera and [`crate::interface::Interface::cursor_position`] use.
+/// - NDC: normalized device coordinates over the viewport, which is the whole swapchain image
+///   unless [`Renderer::set_render_region`] says otherwise.
+/// - World: whatever a camera's view-projection matrix maps to NDC.
+///
+/// The swapchain can lag the window by a frame while resizing, window points are stretched
+/// over it so the edges still line up. [`crate::graphics::set_render_scale`] doesn't change
+/// any of these, the scene it shrinks is stretched back over the same viewport.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct CoordinateMapper {
+    window: (f32, f32),
+    /// Size of the swapchain image the viewport is a part of.
+    extent: (f32, f32),
+    viewport: Rect,
+    system: CoordinateSystem,
+}
+
+impl CoordinateMapper {
+    /// A mapper for a window `window` physical pixels across, showing `system` over `viewport`.
+    /// The viewport is taken to cover the whole swapchain image, see
+    /// [`CoordinateMapper::with_extent`] for one that covers only part of it.
+    pub fn new(window: (f32, f32), viewport: Rect, system: CoordinateSystem) -> Self {
+        Self {
+            window: (window.0.max(1.0), window.1.max(1.0)),
+            extent: (viewport.x + viewport.w, viewport.y + viewport.h),
+            viewport,
+            system,
+        }
+    }
+
+    /// Places the viewport inside a swapchain image `extent` pixels across, for a viewport
+    /// limited by [`Renderer::set_render_region`].
+    pub fn with_extent(mut self, extent: (f32, f32)) -> Self {
+        self.extent = (extent.0.max(1.0), extent.1.max(1.0));
+        self
+    }
+
+    pub fn viewport(&self) -> Rect {
+        self.viewport
+    }
+
+    pub fn coordinate_system(&self) -> CoordinateSystem {
+        self.system
+    }
+
+    /// Window pixels to normalized device coordinates.
+    pub fn window_to_ndc(&self, point: (f32, f32)) -> (f32, f32) {
+        let x = point.0 / self.window.0 * self.extent.0;
+        let y = point.1 / self.window.1 * self.extent.1;
+        (
+            (x - self.viewport.x) / self.viewport.w.max(1.0) * 2.0 - 1.0,
+            (y - self.viewport.y) / self.viewport.h.max(1.0) * 2.0 - 1.0,
+        )
+    }
+
+    /// Normalized device coordinates to window pixels.
+    pub fn ndc_to_window(&self, point: (f32, f32)) -> (f32, f32) {
+        let x = (point.0 + 1.0) / 2.0 * self.viewport.w + self.viewport.x;
+        let y = (point.1 + 1.0) / 2.0 * self.viewport.h + self.viewport.y;
+        (x / self.extent.0 * self.window.0, y / self.extent.1 * self.window.1)
+    }
+
+    /// Window pixels to the viewport's coordinate system.
+    pub fn window_to_viewport(&self, point: (f32, f32)) -> (f32, f32) {
+        self.system.from_ndc(self.window_to_ndc(point), &self.viewport)
+    }
+
+    /// The viewport's coordinate system to window pixels.
+    pub fn viewport_to_window(&self, point: (f32, f32)) -> (f32, f32) {
+        self.ndc_to_window(self.system.to_ndc(point, &self.viewport))
+    }
+
+    /// The viewport's coordinate system to the world seen through `camera`, a view-projection
+    /// matrix such as a camera's `as_mvp()`. `None` if the matrix can't be inverted.
+    ///
+    /// Points are unprojected on the plane z = 0 in clip space, which is the world's z = 0
+    /// plane for the orthographic cameras 2D scenes use.
+    pub fn viewport_to_world(&self, point: (f32, f32), camera: [[f32; 4]; 4]) -> Option<(f32, f32)> {
+        let (x, y) = self.system.to_ndc(point, &self.viewport);
+        let world = Matrix4::from(camera).invert()? * Vector4::new(x, y, 0.0, 1.0);
+        Some((world.x / world.w, world.y / world.w))
+    }
+
+    /// The world seen through `camera` to the viewport's coordinate system.
+    pub fn world_to_viewport(&self, point: (f32, f32), camera: [[f32; 4]; 4]) -> (f32, f32) {
+        let clip = Matrix4::from(camera) * Vector4::new(point.0, point.1, 0.0, 1.0);
+        self.system.from_ndc((clip.x / clip.w, clip.y / clip.w), &self.viewport)
+    }
+
+    /// The world seen through `camera` to window pixels, e.g. to place OS level UI over a
+    /// sprite.
+    pub fn world_to_window(&self, point: (f32, f32), camera: [[f32; 4]; 4]) -> (f32, f32) {
+        self.viewport_to_window(self.world_to_viewport(point, camera))
+    }
+
+    /// Window pixels to the world seen through `camera`, e.g. to pick what's under the cursor.
+    pub fn window_to_world(&self, point: (f32, f32), camera: [[f32; 4]; 4]) -> Option<(f32, f32)> {
+        self.viewport_to_world(self.window_to_viewport(point), camera)
+    }
+}
+
+/// Sets the system used by [`Renderer::projection`], [`Renderer::default_camera`], and
+/// [`crate::interface::Interface::cursor_position`].
+pub fn set_coordinate_system(ctx: &mut Renderer, system: CoordinateSystem) {
+    ctx.coordinate_system = system;
+}
+
+pub fn coordinate_system(ctx: &Renderer) -> CoordinateSystem {
+    ctx.coordinate_system
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::camera::Camera2D;
+
+    /// A 1600x1200 window, as with a scale factor of 2, over an 800x600 swapchain image
+    /// letterboxed to a 600x600 viewport.
+    fn letterboxed(system: CoordinateSystem) -> CoordinateMapper {
+        CoordinateMapper::new((1600.0, 1200.0), Rect { x: 100.0, y: 0.0, w: 600.0, h: 600.0 }, system)
+            .with_extent((800.0, 600.0))
+    }
+
+    fn camera() -> [[f32; 4]; 4] {
+        let mut camera = Camera2D::new(600.0, 600.0);
+        camera.set_zoom(2.0);
+        camera.follow((40.0, -25.0));
+        camera.as_mvp()
+    }
+
+    fn assert_near(a: (f32, f32), b: (f32, f32)) {
+        assert!((a.0 - b.0).abs() < 1e-3 && (a.1 - b.1).abs() < 1e-3, "{:?} != {:?}", a, b);
+    }
+
+    const WINDOW_POINTS: [(f32, f32); 4] = [(0.0, 0.0), (200.0, 0.0), (800.0, 600.0), (1399.0, 1199.0)];
+    const SYSTEMS: [CoordinateSystem; 2] = [CoordinateSystem::NdcCentered, CoordinateSystem::PixelsTopLeft];
+
+    #[test]
+    fn letterbox_bars_fall_outside_ndc() {
+        let mapper = letterboxed(CoordinateSystem::NdcCentered);
+        assert_near(mapper.window_to_ndc((200.0, 0.0)), (-1.0, -1.0));
+        assert_near(mapper.window_to_ndc((800.0, 600.0)), (0.0, 0.0));
+        assert_near(mapper.window_to_ndc((1400.0, 1200.0)), (1.0, 1.0));
+        assert!(mapper.window_to_ndc((100.0, 600.0)).0 < -1.0);
+    }
+
+    #[test]
+    fn window_points_round_trip_through_every_space() {
+        for system in SYSTEMS {
+            let mapper = letterboxed(system);
+            for point in WINDOW_POINTS {
+                assert_near(mapper.ndc_to_window(mapper.window_to_ndc(point)), point);
+                assert_near(mapper.viewport_to_window(mapper.window_to_viewport(point)), point);
+                let world = mapper.window_to_world(point, camera()).unwrap();
+                assert_near(mapper.world_to_window(world, camera()), point);
+            }
+        }
+    }
+
+    #[test]
+    fn world_points_round_trip_through_the_viewport() {
+        for system in SYSTEMS {
+            let mapper = letterboxed(system);
+            for point in [(0.0, 0.0), (40.0, -25.0), (-120.0, 90.0)] {
+                let viewport = mapper.world_to_viewport(point, camera());
+                assert_near(mapper.viewport_to_world(viewport, camera()).unwrap(), point);
+            }
+        }
+    }
+
+    #[test]
+    fn render_scale_leaves_window_positions_unchanged() {
+        // At a render scale of 0.5 the scene is drawn into a target half the swapchain's size,
+        // covering the same viewport, then stretched back over the swapchain image.
+        let mapper = letterboxed(CoordinateSystem::PixelsTopLeft);
+        let scale = 0.5;
+        let scaled = Rect { x: 100.0 * scale, y: 0.0, w: 600.0 * scale, h: 600.0 * scale };
+
+        for point in [(0.0, 0.0), (40.0, -25.0), (-120.0, 90.0)] {
+            let clip = Matrix4::from(camera()) * Vector4::new(point.0, point.1, 0.0, 1.0);
+            let (x, y) = ndc_to_pixels((clip.x / clip.w, clip.y / clip.w), &scaled);
+            let stretched = (x / scale / 800.0 * 1600.0, y / scale / 600.0 * 1200.0);
+            assert_near(stretched, mapper.world_to_window(point, camera()));
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn pixel_positions_land_on_those_pixels() {
+        use crate::graphics::image::Image;
+        use crate::graphics::{pixel_at, render_offscreen, test_device, test_pass, Color, DrawInfo};
+        use std::sync::Arc;
+
+        let (queue, sampler) = test_device();
+        let mut pass = test_pass(queue.clone());
+        let white = Arc::new(Image::from_color(queue.clone(), sampler, Color::white()));
+
+        // A 2x2 sprite whose top left corner is pixel (5, 3), in a camera of PixelsTopLeft.
+        let pixels = render_offscreen(&queue, &mut pass, [16, 8], Color::black(), |pass| {
+            pass.draw(white, DrawInfo::centered((6.0, 4.0), (2.0, 2.0), 0.0, Color::white()))
+        });
+
+        for (x, y) in [(5, 3), (6, 3), (5, 4), (6, 4)] {
+            assert_eq!(pixel_at(&pixels, 16, (x, y)), [255; 4], "pixel ({}, {})", x, y);
+        }
+        for (x, y) in [(4, 3), (7, 3), (5, 2), (5, 5), (10, 1)] {
+            assert_eq!(pixel_at(&pixels, 16, (x, y)), [0, 0, 0, 255], "pixel ({}, {})", x, y);
+        }
+    }
+}
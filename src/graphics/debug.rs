@@ -0,0 +1,578 @@
+//! One-call visualizations for development, drawn as an overlay on top of the frame.
+//!
+//! Shapes and text are queued from anywhere with access to the [`Renderer`] and drawn by
+//! [`present`], which should be the last draw of the final pass. Whatever was queued is
+//! dropped at the end of every frame, drawn or not.
+//!
+//! Everything here compiles to no-ops unless the `debug-draw` feature is enabled, so calls can
+//! be left in release builds. The diagnostics overlay shown by [`crate::graphics::show_diagnostics`]
+//! is the exception, it's drawn by [`present`] whenever it's shown.
+
+use std::collections::VecDeque;
+use std::time::{Duration, Instant};
+
+use cgmath::{Matrix4, SquareMatrix, Vector4};
+
+use crate::graphics::font::{Font, TextBatch};
+use crate::graphics::immediate::ImmediateBatch;
+use crate::graphics::render_pass::frame::Pass;
+use crate::graphics::renderer::Renderer;
+use crate::graphics::shader::ShaderId;
+use crate::graphics::*;
+
+/// Segments used for [`circle`] outlines.
+const CIRCLE_SEGMENTS: u32 = 32;
+/// Length of the head of an [`arrow`] relative to its shaft.
+const ARROW_HEAD: f32 = 0.2;
+/// Frames the diagnostics frame time percentiles are taken over.
+const DIAGNOSTICS_FRAMES: usize = 120;
+/// Height in pixels of the diagnostics text.
+const DIAGNOSTICS_TEXT_SCALE: f32 = 12.0;
+/// Distance in pixels between the diagnostics overlay and the edges of the viewport.
+const DIAGNOSTICS_MARGIN: f32 = 8.0;
+
+/// The coordinates debug shapes are given in.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum DebugSpace {
+    /// Pixels from the top left of the viewport, ignoring the camera.
+    Screen,
+    /// World units, transformed by the camera like everything else in the pass.
+    World,
+}
+
+/// The corner of the viewport an overlay is anchored to.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Corner {
+    TopLeft,
+    TopRight,
+    BottomLeft,
+    BottomRight,
+}
+
+/// Frame timings and counters collected at the end of each frame while the diagnostics overlay
+/// is shown.
+pub struct Diagnostics {
+    enabled: bool,
+    pub(crate) corner: Corner,
+    last_frame: Option<Instant>,
+    frame_times: VecDeque<Duration>,
+    stats: DrawStats,
+    memory_usage: u64,
+    memory_budget: u64,
+    render_extent: [u32; 2],
+    render_scale: f32,
+    gpu_utilization: Option<f32>,
+    panel_color: Color,
+    text_color: Color,
+}
+
+impl Diagnostics {
+    fn new() -> Self {
+        Self {
+            enabled: false,
+            corner: Corner::TopLeft,
+            last_frame: None,
+            frame_times: VecDeque::with_capacity(DIAGNOSTICS_FRAMES),
+            stats: DrawStats::default(),
+            memory_usage: 0,
+            memory_budget: 0,
+            render_extent: [0, 0],
+            render_scale: 1.0,
+            gpu_utilization: None,
+            panel_color: Color::rgba(0, 0, 0, 160),
+            text_color: Color::white(),
+        }
+    }
+
+    pub fn enabled(&self) -> bool {
+        self.enabled
+    }
+
+    pub(crate) fn set_enabled(&mut self, enabled: bool) {
+        if !enabled {
+            self.last_frame = None;
+            self.frame_times.clear();
+        }
+        self.enabled = enabled;
+    }
+
+    /// Records the end of a frame, along with the palette's colors for drawing the overlay.
+    pub(crate) fn record(
+        &mut self,
+        stats: DrawStats,
+        memory: &memory::MemoryInfo,
+        palette: &Palette,
+        render_extent: [u32; 2],
+        render_scale: f32,
+        gpu_utilization: Option<f32>,
+    ) {
+        let now = Instant::now();
+        if let Some(last) = self.last_frame.replace(now) {
+            if self.frame_times.len() == DIAGNOSTICS_FRAMES {
+                self.frame_times.pop_front();
+            }
+            self.frame_times.push_back(now - last);
+        }
+
+        self.stats = stats;
+        self.memory_usage = memory.usage;
+        self.memory_budget = memory.budget;
+        self.render_extent = render_extent;
+        self.render_scale = render_scale;
+        self.gpu_utilization = gpu_utilization;
+        self.panel_color = palette.get(palette::names::PANEL);
+        self.text_color = palette.get(palette::names::TEXT);
+    }
+
+    /// The frame time below which `percentile` of the recorded frames fall, in milliseconds.
+    fn percentile(sorted: &[Duration], percentile: f32) -> f32 {
+        if sorted.is_empty() {
+            return 0.0;
+        }
+
+        let index = ((sorted.len() - 1) as f32 * percentile).round() as usize;
+        sorted[index].as_secs_f32() * 1000.0
+    }
+
+    fn lines(&self) -> [String; 6] {
+        let mut sorted = self.frame_times.iter().copied().collect::<Vec<_>>();
+        sorted.sort();
+
+        let total = sorted.iter().sum::<Duration>().as_secs_f32();
+        let fps = if total > 0.0 { sorted.len() as f32 / total } else { 0.0 };
+        let mb = |bytes: u64| bytes as f32 / (1024.0 * 1024.0);
+
+        [
+            format!("FPS {:.1}", fps),
+            format!(
+                "MS P50 {:.2} P95 {:.2} P99 {:.2}",
+                Self::percentile(&sorted, 0.5),
+                Self::percentile(&sorted, 0.95),
+                Self::percentile(&sorted, 0.99),
+            ),
+            format!("DRAWS {} INSTANCES {}", self.stats.draw_calls, self.stats.instances),
+            format!("VRAM {:.1}/{:.0} MB", mb(self.memory_usage), mb(self.memory_budget)),
+            format!(
+                "RES {}X{} {:.0}%",
+                self.render_extent[0],
+                self.render_extent[1],
+                self.render_scale * 100.0,
+            ),
+            match self.gpu_utilization {
+                Some(utilization) => format!("GPU {:.0}%", utilization * 100.0),
+                None => "GPU -".to_string(),
+            },
+        ]
+    }
+}
+
+/// The debug overlay queued for the current frame, owned by the [`Renderer`].
+pub struct DebugDraw {
+    enabled: bool,
+    space: DebugSpace,
+    line_width: f32,
+    text_scale: f32,
+    font: Option<Arc<Font>>,
+    screen: ImmediateBatch,
+    world: ImmediateBatch,
+    screen_text: TextBatch,
+    world_text: TextBatch,
+    grids: Vec<(DebugSpace, f32, Color)>,
+    pub(crate) diagnostics: Diagnostics,
+}
+
+impl DebugDraw {
+    pub(crate) fn new(queue: Arc<Queue>, sampler: Arc<Sampler>) -> Self {
+        Self {
+            enabled: true,
+            space: DebugSpace::Screen,
+            line_width: 1.0,
+            text_scale: 16.0,
+            font: None,
+            screen: ImmediateBatch::new(queue.clone(), sampler.clone()),
+            world: ImmediateBatch::new(queue, sampler),
+            screen_text: TextBatch::new(),
+            world_text: TextBatch::new(),
+            grids: Vec::new(),
+            diagnostics: Diagnostics::new(),
+        }
+    }
+
+    /// Sets the width of lines and outlines, in units of the current [`DebugSpace`].
+    pub fn set_line_width(&mut self, width: f32) {
+        self.line_width = width;
+    }
+
+    /// Sets the height of [`text`] glyphs, in units of the current [`DebugSpace`].
+    pub fn set_text_scale(&mut self, scale: f32) {
+        self.text_scale = scale;
+    }
+
+    /// Drops everything queued without drawing it.
+    pub fn clear(&mut self) {
+        self.screen.clear();
+        self.world.clear();
+        self.screen_text.clear();
+        self.world_text.clear();
+        self.grids.clear();
+    }
+
+    fn active(&self) -> bool {
+        cfg!(feature = "debug-draw") && self.enabled
+    }
+
+    fn batch(&mut self) -> &mut ImmediateBatch {
+        match self.space {
+            DebugSpace::Screen => &mut self.screen,
+            DebugSpace::World => &mut self.world,
+        }
+    }
+
+    fn push_line(&mut self, a: (f32, f32), b: (f32, f32), color: Color) {
+        let width = self.line_width;
+        self.batch().line(a, b, width, color);
+    }
+
+    /// Draw calls the shapes queued so far will be flushed in, at most one per space however
+    /// many shapes there are. Text is drawn separately.
+    pub fn batch_count(&self) -> usize {
+        self.screen.batch_count() + self.world.batch_count()
+    }
+
+    pub fn line(&mut self, a: (f32, f32), b: (f32, f32), color: Color) {
+        if !self.active() {
+            return;
+        }
+
+        self.push_line(a, b, color);
+    }
+
+    /// Outlines `rect`.
+    pub fn rect(&mut self, rect: Rect, color: Color) {
+        if !self.active() {
+            return;
+        }
+
+        let (x0, y0, x1, y1) = (rect.x, rect.y, rect.x + rect.w, rect.y + rect.h);
+        self.push_line((x0, y0), (x1, y0), color);
+        self.push_line((x1, y0), (x1, y1), color);
+        self.push_line((x1, y1), (x0, y1), color);
+        self.push_line((x0, y1), (x0, y0), color);
+    }
+
+    /// Outlines a circle, e.g. a collider. Circles are queued with every other shape in the
+    /// same space, so any number of them draw in a single batch.
+    pub fn circle(&mut self, center: (f32, f32), radius: f32, color: Color) {
+        if !self.active() {
+            return;
+        }
+
+        let point = |i: u32| {
+            let theta = i as f32 / CIRCLE_SEGMENTS as f32 * std::f32::consts::PI * 2.0;
+            (center.0 + radius * theta.cos(), center.1 + radius * theta.sin())
+        };
+
+        for i in 0..CIRCLE_SEGMENTS {
+            self.push_line(point(i), point(i + 1), color);
+        }
+    }
+
+    /// A line from `from` to `to` with a head at `to`.
+    pub fn arrow(&mut self, from: (f32, f32), to: (f32, f32), color: Color) {
+        if !self.active() {
+            return;
+        }
+
+        let (dx, dy) = ((to.0 - from.0) * ARROW_HEAD, (to.1 - from.1) * ARROW_HEAD);
+        self.push_line(from, to, color);
+        self.push_line(to, (to.0 - dx - dy * 0.5, to.1 - dy + dx * 0.5), color);
+        self.push_line(to, (to.0 - dx + dy * 0.5, to.1 - dy - dx * 0.5), color);
+    }
+}
+
+/// Turns the overlay on or off, nothing is queued while it's off.
+pub fn set_enabled(ctx: &mut Renderer, enabled: bool) {
+    ctx.debug.enabled = enabled;
+    if !enabled {
+        ctx.debug.clear();
+    }
+}
+
+pub fn is_enabled(ctx: &Renderer) -> bool {
+    ctx.debug.active()
+}
+
+/// Sets the space the following calls are given in, [`DebugSpace::Screen`] by default.
+pub fn set_space(ctx: &mut Renderer, space: DebugSpace) {
+    ctx.debug.space = space;
+}
+
+/// Sets the font used by [`text`], which draws with built-in line glyphs until one is set.
+pub fn set_font(ctx: &mut Renderer, font: Arc<Font>) {
+    ctx.debug.font = Some(font);
+}
+
+pub fn line(ctx: &mut Renderer, a: (f32, f32), b: (f32, f32), color: Color) {
+    ctx.debug.line(a, b, color);
+}
+
+/// Outlines `rect`.
+pub fn rect(ctx: &mut Renderer, rect: Rect, color: Color) {
+    ctx.debug.rect(rect, color);
+}
+
+/// Outlines the bounds of `drawable`, if it has any, see [`Bounded`].
+pub fn bounds(ctx: &mut Renderer, drawable: &dyn Bounded, color: Color) {
+    if let Some(bounds) = drawable.bounds() {
+        ctx.debug.rect(bounds, color);
+    }
+}
+
+/// Outlines a circle.
+pub fn circle(ctx: &mut Renderer, center: (f32, f32), radius: f32, color: Color) {
+    ctx.debug.circle(center, radius, color);
+}
+
+/// A line from `from` to `to` with a head at `to`.
+pub fn arrow(ctx: &mut Renderer, from: (f32, f32), to: (f32, f32), color: Color) {
+    ctx.debug.arrow(from, to, color);
+}
+
+/// Text in the font given to [`set_font`], with its top left corner at `pos`.
+pub fn text(ctx: &mut Renderer, pos: (f32, f32), text: &str) {
+    if !ctx.debug.active() {
+        return;
+    }
+
+    let debug = &mut ctx.debug;
+    let font = match &debug.font {
+        Some(font) => font.clone(),
+        None => {
+            let (scale, width) = (debug.text_scale, debug.line_width);
+            let color = ctx.palette.get(palette::names::TEXT);
+            line_text(debug.batch(), pos, text, scale, width, color);
+            return;
+        }
+    };
+
+    let batch = match debug.space {
+        DebugSpace::Screen => &mut debug.screen_text,
+        DebugSpace::World => &mut debug.world_text,
+    };
+    batch.draw_text(&font, text, pos, debug.text_scale, ctx.palette.get(palette::names::TEXT));
+}
+
+/// Segments of the built-in line glyph for `c` on a grid 2 units wide and 4 tall, as
+/// `[x0, y0, x1, y1]` with y pointing down. Lowercase letters use their uppercase glyph, and
+/// anything without a glyph is drawn as a space.
+fn line_glyph(c: char) -> &'static [[f32; 4]] {
+    match c.to_ascii_uppercase() {
+        '0' => &[[0.0, 0.0, 2.0, 0.0], [2.0, 0.0, 2.0, 4.0], [2.0, 4.0, 0.0, 4.0], [0.0, 4.0, 0.0, 0.0], [0.0, 4.0, 2.0, 0.0]],
+        '1' => &[[1.0, 0.0, 1.0, 4.0], [0.0, 1.0, 1.0, 0.0], [0.0, 4.0, 2.0, 4.0]],
+        '2' => &[[0.0, 0.0, 2.0, 0.0], [2.0, 0.0, 2.0, 2.0], [2.0, 2.0, 0.0, 2.0], [0.0, 2.0, 0.0, 4.0], [0.0, 4.0, 2.0, 4.0]],
+        '3' => &[[0.0, 0.0, 2.0, 0.0], [2.0, 0.0, 2.0, 4.0], [2.0, 4.0, 0.0, 4.0], [0.0, 2.0, 2.0, 2.0]],
+        '4' => &[[0.0, 0.0, 0.0, 2.0], [0.0, 2.0, 2.0, 2.0], [2.0, 0.0, 2.0, 4.0]],
+        '5' | 'S' => &[[2.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 2.0], [0.0, 2.0, 2.0, 2.0], [2.0, 2.0, 2.0, 4.0], [2.0, 4.0, 0.0, 4.0]],
+        '6' => &[[2.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 4.0], [0.0, 4.0, 2.0, 4.0], [2.0, 4.0, 2.0, 2.0], [2.0, 2.0, 0.0, 2.0]],
+        '7' => &[[0.0, 0.0, 2.0, 0.0], [2.0, 0.0, 1.0, 4.0]],
+        '8' | 'B' => &[[0.0, 0.0, 2.0, 0.0], [2.0, 0.0, 2.0, 4.0], [2.0, 4.0, 0.0, 4.0], [0.0, 4.0, 0.0, 0.0], [0.0, 2.0, 2.0, 2.0]],
+        '9' => &[[2.0, 2.0, 0.0, 2.0], [0.0, 2.0, 0.0, 0.0], [0.0, 0.0, 2.0, 0.0], [2.0, 0.0, 2.0, 4.0], [2.0, 4.0, 0.0, 4.0]],
+        'A' => &[[0.0, 4.0, 0.0, 0.0], [0.0, 0.0, 2.0, 0.0], [2.0, 0.0, 2.0, 4.0], [0.0, 2.0, 2.0, 2.0]],
+        'C' => &[[2.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 4.0], [0.0, 4.0, 2.0, 4.0]],
+        'D' => &[[0.0, 0.0, 0.0, 4.0], [0.0, 0.0, 1.0, 0.0], [1.0, 0.0, 2.0, 1.0], [2.0, 1.0, 2.0, 3.0], [2.0, 3.0, 1.0, 4.0], [1.0, 4.0, 0.0, 4.0]],
+        'E' => &[[2.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 4.0], [0.0, 4.0, 2.0, 4.0], [0.0, 2.0, 1.5, 2.0]],
+        'F' => &[[2.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 4.0], [0.0, 2.0, 1.5, 2.0]],
+        'G' => &[[2.0, 0.0, 0.0, 0.0], [0.0, 0.0, 0.0, 4.0], [0.0, 4.0, 2.0, 4.0], [2.0, 4.0, 2.0, 2.0], [2.0, 2.0, 1.0, 2.0]],
+        'H' => &[[0.0, 0.0, 0.0, 4.0], [2.0, 0.0, 2.0, 4.0], [0.0, 2.0, 2.0, 2.0]],
+        'I' => &[[1.0, 0.0, 1.0, 4.0], [0.0, 0.0, 2.0, 0.0], [0.0, 4.0, 2.0, 4.0]],
+        'J' => &[[2.0, 0.0, 2.0, 4.0], [2.0, 4.0, 0.0, 4.0], [0.0, 4.0, 0.0, 3.0]],
+        'K' => &[[0.0, 0.0, 0.0, 4.0], [2.0, 0.0, 0.0, 2.0], [0.0, 2.0, 2.0, 4.0]],
+        'L' => &[[0.0, 0.0, 0.0, 4.0], [0.0, 4.0, 2.0, 4.0]],
+        'M' => &[[0.0, 4.0, 0.0, 0.0], [0.0, 0.0, 1.0, 2.0], [1.0, 2.0, 2.0, 0.0], [2.0, 0.0, 2.0, 4.0]],
+        'N' => &[[0.0, 4.0, 0.0, 0.0], [0.0, 0.0, 2.0, 4.0], [2.0, 4.0, 2.0, 0.0]],
+        'O' => &[[0.0, 0.0, 2.0, 0.0], [2.0, 0.0, 2.0, 4.0], [2.0, 4.0, 0.0, 4.0], [0.0, 4.0, 0.0, 0.0]],
+        'P' => &[[0.0, 4.0, 0.0, 0.0], [0.0, 0.0, 2.0, 0.0], [2.0, 0.0, 2.0, 2.0], [2.0, 2.0, 0.0, 2.0]],
+        'Q' => &[[0.0, 0.0, 2.0, 0.0], [2.0, 0.0, 2.0, 4.0], [2.0, 4.0, 0.0, 4.0], [0.0, 4.0, 0.0, 0.0], [1.0, 3.0, 2.0, 4.0]],
+        'R' => &[[0.0, 4.0, 0.0, 0.0], [0.0, 0.0, 2.0, 0.0], [2.0, 0.0, 2.0, 2.0], [2.0, 2.0, 0.0, 2.0], [0.0, 2.0, 2.0, 4.0]],
+        'T' => &[[0.0, 0.0, 2.0, 0.0], [1.0, 0.0, 1.0, 4.0]],
+        'U' => &[[0.0, 0.0, 0.0, 4.0], [0.0, 4.0, 2.0, 4.0], [2.0, 4.0, 2.0, 0.0]],
+        'V' => &[[0.0, 0.0, 1.0, 4.0], [1.0, 4.0, 2.0, 0.0]],
+        'W' => &[[0.0, 0.0, 0.0, 4.0], [0.0, 4.0, 1.0, 2.0], [1.0, 2.0, 2.0, 4.0], [2.0, 4.0, 2.0, 0.0]],
+        'X' => &[[0.0, 0.0, 2.0, 4.0], [2.0, 0.0, 0.0, 4.0]],
+        'Y' => &[[0.0, 0.0, 1.0, 2.0], [2.0, 0.0, 1.0, 2.0], [1.0, 2.0, 1.0, 4.0]],
+        'Z' => &[[0.0, 0.0, 2.0, 0.0], [2.0, 0.0, 0.0, 4.0], [0.0, 4.0, 2.0, 4.0]],
+        '.' => &[[1.0, 3.5, 1.0, 4.0]],
+        ':' => &[[1.0, 1.0, 1.0, 1.5], [1.0, 2.5, 1.0, 3.0]],
+        '-' => &[[0.0, 2.0, 2.0, 2.0]],
+        '/' => &[[0.0, 4.0, 2.0, 0.0]],
+        '%' => &[[0.0, 4.0, 2.0, 0.0], [0.0, 0.0, 0.0, 0.5], [2.0, 3.5, 2.0, 4.0]],
+        _ => &[],
+    }
+}
+
+/// Width of `text` drawn by [`line_text`] at `scale`.
+fn line_text_width(text: &str, scale: f32) -> f32 {
+    text.chars().count() as f32 * scale * 0.75
+}
+
+/// Draws `text` with the built-in line glyphs, `scale` tall with its top left corner at `pos`.
+fn line_text(batch: &mut ImmediateBatch, pos: (f32, f32), text: &str, scale: f32, width: f32, color: Color) {
+    let unit = scale / 4.0;
+
+    for (i, c) in text.chars().enumerate() {
+        let x = pos.0 + i as f32 * unit * 3.0;
+        for [x0, y0, x1, y1] in line_glyph(c) {
+            batch.line(
+                (x + x0 * unit, pos.1 + y0 * unit),
+                (x + x1 * unit, pos.1 + y1 * unit),
+                width,
+                color,
+            );
+        }
+    }
+}
+
+/// Queues the diagnostics overlay into the screen space batch.
+fn push_diagnostics(debug: &mut DebugDraw, viewport: &Rect) {
+    let lines = debug.diagnostics.lines();
+    let line_height = DIAGNOSTICS_TEXT_SCALE * 1.5;
+    let padding = DIAGNOSTICS_TEXT_SCALE * 0.5;
+
+    let w = lines
+        .iter()
+        .map(|line| line_text_width(line, DIAGNOSTICS_TEXT_SCALE))
+        .fold(0.0, f32::max)
+        + padding * 2.0;
+    let h = lines.len() as f32 * line_height - (line_height - DIAGNOSTICS_TEXT_SCALE) + padding * 2.0;
+
+    let x = match debug.diagnostics.corner {
+        Corner::TopLeft | Corner::BottomLeft => DIAGNOSTICS_MARGIN,
+        Corner::TopRight | Corner::BottomRight => viewport.w - w - DIAGNOSTICS_MARGIN,
+    };
+    let y = match debug.diagnostics.corner {
+        Corner::TopLeft | Corner::TopRight => DIAGNOSTICS_MARGIN,
+        Corner::BottomLeft | Corner::BottomRight => viewport.h - h - DIAGNOSTICS_MARGIN,
+    };
+
+    debug.screen.rectangle(Rect { x, y, w, h }, debug.diagnostics.panel_color);
+    for (i, line) in lines.iter().enumerate() {
+        let pos = (x + padding, y + padding + i as f32 * line_height);
+        line_text(&mut debug.screen, pos, line, DIAGNOSTICS_TEXT_SCALE, 1.0, debug.diagnostics.text_color);
+    }
+}
+
+/// Lines every `spacing` units across everything visible.
+pub fn grid(ctx: &mut Renderer, spacing: f32) {
+    if !ctx.debug.active() || spacing <= 0.0 {
+        return;
+    }
+
+    let space = ctx.debug.space;
+    let color = ctx.palette.get(palette::names::GRID);
+    ctx.debug.grids.push((space, spacing, color));
+}
+
+/// The region of the world `camera` shows, as `(min, max)` corners.
+fn visible_region(camera: &[[f32; 4]; 4]) -> Option<((f32, f32), (f32, f32))> {
+    let inverse = Matrix4::from(*camera).invert()?;
+
+    let corners = [(-1.0, -1.0), (1.0, -1.0), (-1.0, 1.0), (1.0, 1.0)]
+        .iter()
+        .map(|(x, y)| inverse * Vector4::new(*x, *y, 0.0, 1.0))
+        .collect::<Vec<_>>();
+
+    let min = corners.iter().fold((f32::MAX, f32::MAX), |m, c| (m.0.min(c.x), m.1.min(c.y)));
+    let max = corners.iter().fold((f32::MIN, f32::MIN), |m, c| (m.0.max(c.x), m.1.max(c.y)));
+    Some((min, max))
+}
+
+fn push_grid(debug: &mut DebugDraw, space: DebugSpace, spacing: f32, color: Color, min: (f32, f32), max: (f32, f32)) {
+    let previous = debug.space;
+    debug.space = space;
+
+    let mut x = (min.0 / spacing).floor() * spacing;
+    while x <= max.0 {
+        debug.push_line((x, min.1), (x, max.1), color);
+        x += spacing;
+    }
+
+    let mut y = (min.1 / spacing).floor() * spacing;
+    while y <= max.1 {
+        debug.push_line((min.0, y), (max.0, y), color);
+        y += spacing;
+    }
+
+    debug.space = previous;
+}
+
+/// Draws everything queued this frame with `shader` and clears it.
+///
+/// World space shapes are drawn with the pass's current camera, screen space ones on top of
+/// them with a pixel camera over the pass's viewport, and the diagnostics overlay on top of
+/// everything if it's shown. The pass's draw state is left unchanged.
+pub fn present(pass: &mut Pass, debug: &mut DebugDraw, shader: ShaderId) -> Result<()> {
+    if !debug.active() && !debug.diagnostics.enabled() {
+        debug.clear();
+        return Ok(());
+    }
+
+    let viewport = pass.state().viewport;
+    if debug.diagnostics.enabled() {
+        push_diagnostics(debug, &viewport);
+    }
+
+    let screen = CoordinateSystem::PixelsTopLeft.projection(&viewport);
+
+    for (space, spacing, color) in std::mem::take(&mut debug.grids) {
+        let region = match space {
+            DebugSpace::Screen => Some(((0.0, 0.0), (viewport.w, viewport.h))),
+            DebugSpace::World => visible_region(&pass.state().camera),
+        };
+
+        if let Some((min, max)) = region {
+            push_grid(debug, space, spacing, color, min, max);
+        }
+    }
+
+    pass.push_state();
+    let result = draw_overlay(pass, debug, shader, screen);
+    pass.pop_state();
+
+    debug.clear();
+    result
+}
+
+fn draw_overlay(pass: &mut Pass, debug: &mut DebugDraw, shader: ShaderId, screen: [[f32; 4]; 4]) -> Result<()> {
+    pass.draw_immediate(&mut debug.world, shader)?;
+    debug.world_text.flush(pass, shader)?;
+
+    pass.set_camera(screen)?;
+    pass.draw_immediate(&mut debug.screen, shader)?;
+    debug.screen_text.flush(pass, shader)
+}
+
+#[cfg(all(test, feature = "debug-draw"))]
+mod tests {
+    use super::*;
+    use crate::graphics::{render_offscreen, test_device, test_pass};
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn circles_draw_in_one_batch() {
+        let (queue, sampler) = test_device();
+        let mut debug = DebugDraw::new(queue.clone(), sampler);
+
+        for i in 0..50 {
+            debug.circle((i as f32 * 2.0, 16.0), 4.0, Color::white());
+        }
+        assert_eq!(debug.batch_count(), 1);
+
+        let mut pass = test_pass(queue.clone());
+        render_offscreen(&queue, &mut pass, [100, 32], Color::black(), |pass| {
+            present(pass, &mut debug, 0)?;
+            assert_eq!(pass.stats().draw_calls, 1);
+            Ok(())
+        });
+        assert_eq!(debug.batch_count(), 0);
+    }
+}
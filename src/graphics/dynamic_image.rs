@@ -0,0 +1,172 @@
+//! Images drawn into as well as drawn, for marks that build up over time such as paint
+//! splats, decals, or terrain damage.
+//!
+//! A [`DynamicImage`] has its own render pass, which loads the image's contents instead of
+//! clearing them, so every [`render_to_image`] draws on top of the previous ones. Shaders
+//! used to draw into it are registered with [`DynamicImage::register_shader`], and the image
+//! itself is drawn like any other through [`DynamicImage::image`].
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryCommandBuffer};
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::{view::ImageView, AttachmentImage, ImageUsage};
+use vulkano::pipeline::graphics::vertex_input::VertexDefinition;
+use vulkano::sync::{self, GpuFuture};
+
+use crate::graphics::camera::OrthographicCamera;
+use crate::graphics::image::Image;
+use crate::graphics::render_pass::frame::{Pass, PassState};
+use crate::graphics::renderer::Renderer;
+use crate::graphics::shader::{Shader, ShaderId};
+use crate::graphics::*;
+
+/// Format of every [`DynamicImage`], matching images loaded from files.
+pub const DYNAMIC_IMAGE_FORMAT: Format = Format::R8G8B8A8_UNORM;
+
+/// An RGBA image that can be the target of draws, see the [module docs](self).
+pub struct DynamicImage {
+    view: Arc<ImageView<AttachmentImage>>,
+    image: Image,
+    pass: render_pass::RenderPass,
+}
+
+impl DynamicImage {
+    /// A `width` by `height` image cleared to transparent, sampled with `sampler` when drawn.
+    pub fn new(queue: Arc<Queue>, sampler: Arc<Sampler>, width: u32, height: u32) -> Result<Self> {
+        let device = queue.device().clone();
+        let usage = ImageUsage {
+            sampled: true,
+            color_attachment: true,
+            transfer_destination: true,
+            ..ImageUsage::none()
+        };
+        let view = ImageView::new_default(AttachmentImage::with_usage(
+            device.clone(),
+            [width, height],
+            DYNAMIC_IMAGE_FORMAT,
+            usage,
+        )?)?;
+
+        let render_pass = vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Load,
+                    store: Store,
+                    format: DYNAMIC_IMAGE_FORMAT,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )?;
+
+        // Attachment images start out undefined, give the first splat something to land on.
+        let mut builder = AutoCommandBufferBuilder::primary(
+            device,
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        builder.clear_color_image(view.image().clone(), ClearValue::Float([0.0; 4]))?;
+        builder
+            .build()?
+            .execute(queue.clone())?
+            .then_signal_fence_and_flush()?
+            .wait(None)?;
+
+        Ok(Self {
+            image: Image::from_view(view.clone(), sampler, width, height),
+            view,
+            pass: render_pass::RenderPass::new(queue, render_pass)?,
+        })
+    }
+
+    /// Registers a shader for drawing into this image, the ids of shaders registered with the
+    /// renderer's passes can't be used here.
+    pub fn register_shader<Vd: VertexDefinition + Clone + 'static + Sync + Send>(&mut self, shader: Arc<Shader>, v_type: Vd) -> Result<ShaderId> {
+        self.pass.register_shader(shader, v_type)
+    }
+
+    /// The image to draw, which shows everything rendered into it so far.
+    pub fn image(&self) -> &Image {
+        &self.image
+    }
+
+    pub fn width(&self) -> u32 {
+        self.image.width()
+    }
+
+    pub fn height(&self) -> u32 {
+        self.image.height()
+    }
+
+    /// Records `draw` into this image after `before`, returning the future of the finished pass.
+    pub(crate) fn record<F>(&mut self, before: Box<dyn GpuFuture>, draw: F) -> Result<Box<dyn GpuFuture>>
+    where
+        F: FnOnce(&mut Pass) -> Result<()>,
+    {
+        let rect = Rect {
+            x: 0.0,
+            y: 0.0,
+            w: self.width() as f32,
+            h: self.height() as f32,
+        };
+        let camera = Arc::new(OrthographicCamera::with_projection(
+            CoordinateSystem::PixelsTopLeft.projection(&rect),
+        ));
+
+        let mut frame = self.pass.frame([0.0; 4], before, self.view.clone(), camera)?;
+
+        let mut draw = Some(draw);
+        let mut after_future = None;
+        while let Some(pass) = frame.next_pass()? {
+            match pass {
+                PassState::DrawPass(mut pass) => {
+                    if let Some(draw) = draw.take() {
+                        draw(&mut pass)?;
+                    }
+                }
+                PassState::Finished(future) => {
+                    after_future = Some(future);
+                }
+            }
+        }
+
+        Ok(after_future.unwrap())
+    }
+}
+
+/// Runs `draw` with a pass drawing into `target`, on top of what it already holds.
+///
+/// The pass's camera maps pixels from the image's top left corner, like
+/// [`CoordinateSystem::PixelsTopLeft`]. Called before [`Renderer::begin_frame`], e.g. from
+/// `update`, the draws are submitted ahead of the next frame without waiting. Called during a
+/// frame they're submitted and waited on immediately, so the frame can sample the result.
+pub fn render_to_image<F>(ctx: &mut Renderer, target: &mut DynamicImage, draw: F) -> Result<()>
+where
+    F: FnOnce(&mut Pass) -> Result<()>,
+{
+    let in_frame = ctx.previous_frame_end.is_none();
+    let before = ctx
+        .previous_frame_end
+        .take()
+        .unwrap_or_else(|| sync::now(ctx.device.clone()).boxed());
+
+    let result = target.record(before, draw).and_then(|future| {
+        if in_frame {
+            future.then_signal_fence_and_flush()?.wait(None)?;
+        } else {
+            ctx.previous_frame_end = Some(future);
+        }
+        Ok(())
+    });
+
+    if result.is_err() && !in_frame {
+        ctx.previous_frame_end = Some(sync::now(ctx.device.clone()).boxed());
+    }
+
+    result
+}
@@ -0,0 +1,631 @@
+//! Ready-made full screen post-process effects, applied in order by an [`EffectChain`].
+//!
+//! The scene is rendered into [`EffectChain::scene_target`] instead of the swapchain image, then
+//! [`EffectChain::apply`] runs every enabled effect over it and writes the result to the final
+//! image:
+//!
+//! ```ignore
+//! let scene = effects.scene_target(renderer.viewport())?;
+//! let mut frame = renderer.render_passes[0].frame(clear, before_future, scene, camera)?;
+//! // ... draw the frame as usual, then:
+//! let after_future = effects.apply(scene_future, renderer.final_image())?;
+//! ```
+//!
+//! Each effect's parameters are plain structs pushed as push constants, so changing them costs
+//! nothing beyond the next frame's draw. With the `serde` feature they can be saved to and
+//! loaded from settings files.
+
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, SubpassContents};
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::{
+    view::ImageView, AttachmentImage, ImageAccess, ImageDimensions, ImageUsage, ImmutableImage,
+    MipmapsCount,
+};
+use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
+use vulkano::sampler::{Filter, SamplerAddressMode, SamplerCreateInfo};
+use vulkano::shader::ShaderModule;
+
+use std::path;
+
+use crate::graphics::image::Image;
+use crate::graphics::*;
+
+mod fullscreen_vs {
+    vulkano_shaders::shader! { ty: "vertex", path: "src/graphics/shaders/fullscreen.vert", }
+}
+
+mod grayscale_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/grayscale.frag", }
+}
+
+mod vignette_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/vignette.frag", }
+}
+
+mod pixelate_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/pixelate.frag", }
+}
+
+mod crt_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/crt.frag", }
+}
+
+mod color_lut_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/color_lut.frag", }
+}
+
+mod color_grade_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/color_grade.frag", }
+}
+
+/// Blends towards the image's luminance, `0.0` leaves it unchanged and `1.0` is fully gray.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Zeroable, Pod)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Grayscale {
+    pub amount: f32,
+}
+
+/// Darkens towards the edges. Both values are fractions of the distance from the center to
+/// the edge, shading starts at `radius - softness` and is complete at `radius`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Zeroable, Pod)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Vignette {
+    pub radius: f32,
+    pub softness: f32,
+}
+
+/// Draws the image as blocks of `block_size` pixels.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Zeroable, Pod)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Pixelate {
+    pub block_size: f32,
+}
+
+/// Bends the image like a curved screen and darkens every other pixel row.
+///
+/// `curvature` around `0.1` is subtle, `scanline_intensity` is how dark the darkest part of a
+/// scanline gets, from `0.0` to `1.0`.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Zeroable, Pod)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Crt {
+    pub curvature: f32,
+    pub scanline_intensity: f32,
+}
+
+/// How strongly a color lookup table is applied, `1.0` uses its colors as they are.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Zeroable, Pod)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColorLut {
+    pub intensity: f32,
+}
+
+/// How strongly a [`Lut3d`] grades the image, `1.0` uses its colors as they are.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, PartialEq, Zeroable, Pod)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct ColorGrade {
+    pub intensity: f32,
+}
+
+impl ColorGrade {
+    /// A LUT mapping every color to itself, for checking the grading pipeline or as a starting
+    /// point for generated grades.
+    pub fn identity_lut(queue: Arc<Queue>, size: u32) -> Result<Lut3d> {
+        if size < 2 {
+            return Err(anyhow::anyhow!("a color LUT needs at least 2 entries per channel, got {}", size));
+        }
+
+        let step = |i: u32| (i as f32 / (size - 1) as f32 * 65535.0).round() as u16;
+        let mut texels = Vec::with_capacity((size * size * size * 4) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    texels.extend_from_slice(&[step(r), step(g), step(b), u16::MAX]);
+                }
+            }
+        }
+
+        Lut3d::from_texels(queue, size, texels)
+    }
+}
+
+/// A color lookup table stored as an N by N by N 3D image, sampled with trilinear filtering by
+/// [`Effect::ColorGrade`].
+///
+/// Grading tools such as Photoshop export these as strips of N slices of N by N pixels, N*N
+/// wide and N tall: 256 by 16 or 1024 by 32 are the usual sizes. Blue increases from slice to
+/// slice, red across each slice, and green down it. Entries are stored with 16 bits per channel
+/// so interpolating between them doesn't band.
+#[derive(Clone)]
+pub struct Lut3d {
+    view: Arc<ImageView<ImmutableImage>>,
+    size: u32,
+    _memory: Arc<memory::TrackedAllocation>,
+}
+
+impl Lut3d {
+    /// Loads a LUT strip from the PNG at `path`.
+    pub fn load<P: AsRef<path::Path>>(queue: Arc<Queue>, path: P) -> Result<Self> {
+        let (width, height, pixels) = Image::decode(&path)?;
+        Self::from_strip(queue, width, height, &pixels).map_err(|e| e.context(format!("loading color LUT {:?}", path.as_ref())))
+    }
+
+    /// Builds a LUT from the RGBA8 pixels of a strip `width` by `height` pixels in size.
+    pub fn from_strip(queue: Arc<Queue>, width: u32, height: u32, pixels: &[u8]) -> Result<Self> {
+        let size = height;
+        if size < 2 || width != size * size {
+            return Err(anyhow::anyhow!(
+                "color LUT strip is {}x{}, expected N slices of NxN side by side, N*N wide and N tall (e.g. 256x16 or 1024x32)",
+                width,
+                height,
+            ));
+        }
+        if pixels.len() < (width * height * 4) as usize {
+            return Err(anyhow::anyhow!(
+                "color LUT strip has {} bytes of pixels, expected {} for {}x{} RGBA8",
+                pixels.len(),
+                width * height * 4,
+                width,
+                height,
+            ));
+        }
+
+        let mut texels = Vec::with_capacity((size * size * size * 4) as usize);
+        for b in 0..size {
+            for g in 0..size {
+                for r in 0..size {
+                    let i = ((g * width + b * size + r) * 4) as usize;
+                    texels.extend(pixels[i..i + 4].iter().map(|c| *c as u16 * 257));
+                }
+            }
+        }
+
+        Self::from_texels(queue, size, texels)
+    }
+
+    /// Number of entries along each axis.
+    pub fn size(&self) -> u32 {
+        self.size
+    }
+
+    fn from_texels(queue: Arc<Queue>, size: u32, texels: Vec<u16>) -> Result<Self> {
+        let (image, _) = ImmutableImage::from_iter(
+            texels,
+            ImageDimensions::Dim3d {
+                width: size,
+                height: size,
+                depth: size,
+            },
+            MipmapsCount::One,
+            Format::R16G16B16A16_UNORM,
+            queue,
+        )?;
+
+        Ok(Self {
+            view: ImageView::new_default(image)?,
+            size,
+            _memory: Arc::new(memory::TrackedAllocation::new(
+                memory::MemoryCategory::Textures,
+                size as u64 * size as u64 * size as u64 * 8,
+            )),
+        })
+    }
+}
+
+/// A post-process effect and its parameters, which can be changed between frames.
+#[derive(Clone)]
+pub enum Effect {
+    Grayscale(Grayscale),
+    Vignette(Vignette),
+    Pixelate(Pixelate),
+    Crt(Crt),
+    /// Remaps colors through `lut`, a strip of N slices of N by N pixels (N*N wide, N tall) with
+    /// blue increasing from slice to slice, red across each slice, and green down it.
+    ColorLut { lut: Image, params: ColorLut },
+    /// Remaps colors through a 3D lookup table, see [`Lut3d`].
+    ColorGrade { lut: Lut3d, params: ColorGrade },
+}
+
+impl Effect {
+    pub fn grayscale(amount: f32) -> Self {
+        Effect::Grayscale(Grayscale { amount })
+    }
+
+    pub fn vignette(radius: f32, softness: f32) -> Self {
+        Effect::Vignette(Vignette { radius, softness })
+    }
+
+    pub fn pixelate(block_size: f32) -> Self {
+        Effect::Pixelate(Pixelate { block_size })
+    }
+
+    pub fn crt(curvature: f32, scanline_intensity: f32) -> Self {
+        Effect::Crt(Crt {
+            curvature,
+            scanline_intensity,
+        })
+    }
+
+    pub fn color_lut(lut: Image) -> Self {
+        Effect::ColorLut {
+            lut,
+            params: ColorLut { intensity: 1.0 },
+        }
+    }
+
+    pub fn color_grade(lut: Lut3d, intensity: f32) -> Self {
+        Effect::ColorGrade {
+            lut,
+            params: ColorGrade { intensity },
+        }
+    }
+
+    /// Index of the effect's pipeline in the chain's pipelines.
+    fn kind(&self) -> usize {
+        match self {
+            Effect::Grayscale(_) => 0,
+            Effect::Vignette(_) => 1,
+            Effect::Pixelate(_) => 2,
+            Effect::Crt(_) => 3,
+            Effect::ColorLut { .. } => 4,
+            Effect::ColorGrade { .. } => 5,
+        }
+    }
+}
+
+/// An effect in an [`EffectChain`] and whether it's applied.
+#[derive(Clone)]
+pub struct EffectSlot {
+    pub effect: Effect,
+    pub enabled: bool,
+}
+
+/// The intermediate images effects are rendered between, all the size of the final image.
+struct Targets {
+    extent: [u32; 2],
+    scene: Arc<ImageView<AttachmentImage>>,
+    ping_pong: [Arc<ImageView<AttachmentImage>>; 2],
+}
+
+/// An ordered list of post-process effects, applied from first to last.
+///
+/// Effects can be toggled, edited, and reordered between any two frames.
+pub struct EffectChain {
+    queue: Arc<Queue>,
+    format: Format,
+    render_pass: Arc<RenderPass>,
+    sampler: Arc<Sampler>,
+    /// One pipeline per kind of effect, in the order of [`Effect::kind`].
+    pipelines: Vec<Arc<GraphicsPipeline>>,
+    effects: Vec<EffectSlot>,
+    targets: Option<Targets>,
+}
+
+impl EffectChain {
+    /// Builds the effect pipelines for writing to images of `format`, usually
+    /// [`renderer::Renderer::output_format`].
+    pub fn new(queue: Arc<Queue>, format: Format) -> Result<Self> {
+        let device = queue.device().clone();
+
+        let render_pass = vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: DontCare,
+                    store: Store,
+                    format: format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )?;
+
+        let sampler = Sampler::new(
+            device.clone(),
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )?;
+
+        let vertex = fullscreen_vs::load(device.clone())?;
+        let fragments = [
+            grayscale_fs::load(device.clone())?,
+            vignette_fs::load(device.clone())?,
+            pixelate_fs::load(device.clone())?,
+            crt_fs::load(device.clone())?,
+            color_lut_fs::load(device.clone())?,
+            color_grade_fs::load(device.clone())?,
+        ];
+
+        let pipelines = fragments
+            .iter()
+            .map(|fragment| effect_pipeline(device.clone(), render_pass.clone(), &vertex, fragment))
+            .collect::<Result<Vec<_>>>()?;
+
+        Ok(Self {
+            queue,
+            format,
+            render_pass,
+            sampler,
+            pipelines,
+            effects: Vec::new(),
+            targets: None,
+        })
+    }
+
+    /// Appends an enabled effect and returns its index.
+    pub fn push(&mut self, effect: Effect) -> usize {
+        self.effects.push(EffectSlot {
+            effect,
+            enabled: true,
+        });
+        self.effects.len() - 1
+    }
+
+    pub fn remove(&mut self, index: usize) -> Option<Effect> {
+        if index < self.effects.len() {
+            Some(self.effects.remove(index).effect)
+        } else {
+            None
+        }
+    }
+
+    pub fn get(&self, index: usize) -> Option<&EffectSlot> {
+        self.effects.get(index)
+    }
+
+    /// The effect at `index`, for changing its parameters.
+    pub fn get_mut(&mut self, index: usize) -> Option<&mut EffectSlot> {
+        self.effects.get_mut(index)
+    }
+
+    pub fn set_enabled(&mut self, index: usize, enabled: bool) {
+        if let Some(slot) = self.effects.get_mut(index) {
+            slot.enabled = enabled;
+        }
+    }
+
+    /// Moves the effect at `from` so it's applied at position `to`, shifting the ones between.
+    pub fn move_effect(&mut self, from: usize, to: usize) {
+        if from < self.effects.len() && to < self.effects.len() {
+            let slot = self.effects.remove(from);
+            self.effects.insert(to, slot);
+        }
+    }
+
+    pub fn effects(&self) -> &[EffectSlot] {
+        &self.effects
+    }
+
+    pub fn len(&self) -> usize {
+        self.effects.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.effects.is_empty()
+    }
+
+    /// The image to render the scene into this frame, reallocated when `viewport` changes size.
+    pub fn scene_target(&mut self, viewport: Rect) -> Result<Arc<ImageView<AttachmentImage>>> {
+        let extent = [viewport.w as u32, viewport.h as u32];
+
+        match &self.targets {
+            Some(targets) if targets.extent == extent => {}
+            _ => {
+                let usage = ImageUsage {
+                    sampled: true,
+                    color_attachment: true,
+                    ..ImageUsage::none()
+                };
+                let target = || -> Result<Arc<ImageView<AttachmentImage>>> {
+                    let image = AttachmentImage::with_usage(self.queue.device().clone(), extent, self.format, usage)?;
+                    Ok(ImageView::new_default(image)?)
+                };
+
+                self.targets = Some(Targets {
+                    extent,
+                    scene: target()?,
+                    ping_pong: [target()?, target()?],
+                });
+            }
+        }
+
+        Ok(self.targets.as_ref().unwrap().scene.clone())
+    }
+
+    /// Runs every enabled effect over the scene target in order, writing the last one's output
+    /// to `output`. With no effects enabled the scene is copied as is.
+    ///
+    /// `before_future` must include the frame that rendered into [`EffectChain::scene_target`].
+    pub fn apply(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        output: Arc<dyn ImageViewAbstract>,
+    ) -> Result<Box<dyn GpuFuture>> {
+        let targets = self
+            .targets
+            .as_ref()
+            .ok_or_else(|| anyhow::anyhow!("no scene target, call EffectChain::scene_target first"))?;
+
+        let passthrough = Effect::grayscale(0.0);
+        let mut steps = self
+            .effects
+            .iter()
+            .filter(|slot| slot.enabled)
+            .map(|slot| &slot.effect)
+            .collect::<Vec<_>>();
+        if steps.is_empty() {
+            steps.push(&passthrough);
+        }
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.queue.device().clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        let mut input: Arc<dyn ImageViewAbstract> = targets.scene.clone();
+        for (i, effect) in steps.iter().enumerate() {
+            let target: Arc<dyn ImageViewAbstract> = if i + 1 == steps.len() {
+                output.clone()
+            } else {
+                targets.ping_pong[i % 2].clone()
+            };
+
+            self.record(&mut builder, effect, input, target.clone())?;
+            input = target;
+        }
+
+        let commands = builder.build()?;
+        Ok(before_future.then_execute(self.queue.clone(), commands)?.boxed())
+    }
+
+    fn record(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<vulkano::command_buffer::PrimaryAutoCommandBuffer>,
+        effect: &Effect,
+        input: Arc<dyn ImageViewAbstract>,
+        output: Arc<dyn ImageViewAbstract>,
+    ) -> Result<()> {
+        let [w, h] = output.image().dimensions().width_height();
+        let framebuffer = Framebuffer::new(
+            self.render_pass.clone(),
+            FramebufferCreateInfo {
+                attachments: vec![output],
+                ..Default::default()
+            },
+        )?;
+
+        let pipeline = &self.pipelines[effect.kind()];
+        let layout = pipeline.layout().set_layouts()[0].clone();
+
+        let mut writes = vec![WriteDescriptorSet::image_view_sampler(0, input, self.sampler.clone())];
+        match effect {
+            Effect::ColorLut { lut, .. } => {
+                writes.push(WriteDescriptorSet::image_view_sampler(1, lut.view(), self.sampler.clone()));
+            }
+            Effect::ColorGrade { lut, .. } => {
+                writes.push(WriteDescriptorSet::image_view_sampler(1, lut.view.clone(), self.sampler.clone()));
+            }
+            _ => {}
+        }
+        let set = PersistentDescriptorSet::new(layout, writes)?;
+
+        builder
+            .begin_render_pass(framebuffer, SubpassContents::Inline, vec![ClearValue::None])?
+            .set_viewport(
+                0,
+                vec![Viewport {
+                    origin: [0.0, 0.0],
+                    dimensions: [w as f32, h as f32],
+                    depth_range: 0.0..1.0,
+                }],
+            )
+            .bind_pipeline_graphics(pipeline.clone())
+            .bind_descriptor_sets(PipelineBindPoint::Graphics, pipeline.layout().clone(), 0, set);
+
+        match effect {
+            Effect::Grayscale(params) => builder.push_constants(pipeline.layout().clone(), 0, *params),
+            Effect::Vignette(params) => builder.push_constants(pipeline.layout().clone(), 0, *params),
+            Effect::Pixelate(params) => builder.push_constants(pipeline.layout().clone(), 0, *params),
+            Effect::Crt(params) => builder.push_constants(pipeline.layout().clone(), 0, *params),
+            Effect::ColorLut { params, .. } => builder.push_constants(pipeline.layout().clone(), 0, *params),
+            Effect::ColorGrade { params, .. } => builder.push_constants(pipeline.layout().clone(), 0, *params),
+        };
+
+        builder.draw(3, 1, 0, 0)?.end_render_pass()?;
+
+        Ok(())
+    }
+}
+
+fn effect_pipeline(
+    device: Arc<Device>,
+    render_pass: Arc<RenderPass>,
+    vertex: &Arc<ShaderModule>,
+    fragment: &Arc<ShaderModule>,
+) -> Result<Arc<GraphicsPipeline>> {
+    Ok(GraphicsPipeline::start()
+        .vertex_input_state(BuffersDefinition::new())
+        .vertex_shader(vertex.entry_point("main").unwrap(), ())
+        .input_assembly_state(InputAssemblyState::new())
+        .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+        .fragment_shader(fragment.entry_point("main").unwrap(), ())
+        .render_pass(Subpass::from(render_pass, 0).unwrap())
+        .build(device)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{draw_quad, read_target, render_into, test_device, test_pass, test_target, TEST_TARGET_FORMAT};
+
+    /// Runs `chain` over a 16x16 scene of differently colored squares and reads back the result.
+    fn apply_to_squares(queue: &Arc<Queue>, chain: &mut EffectChain) -> Vec<u8> {
+        let scene = chain.scene_target(Rect { x: 0.0, y: 0.0, w: 16.0, h: 16.0 }).unwrap();
+        render_into(&mut test_pass(queue.clone()), scene, Color::black(), |pass| {
+            for i in 0..4u8 {
+                for j in 0..4u8 {
+                    let rect = Rect { x: i as f32 * 4.0, y: j as f32 * 4.0, w: 4.0, h: 4.0 };
+                    draw_quad(pass, rect, Color::rgba(i * 85, j * 60 + 7, i * j * 13 + 100, 255))?;
+                }
+            }
+            Ok(())
+        });
+
+        let output = test_target(queue, [16, 16]);
+        chain
+            .apply(vulkano::sync::now(queue.device().clone()).boxed(), ImageView::new_default(output.clone()).unwrap())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        read_target(queue, output)
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn identity_grades_leave_every_pixel_unchanged() {
+        let (queue, _) = test_device();
+        let mut chain = EffectChain::new(queue.clone(), TEST_TARGET_FORMAT).unwrap();
+        let original = apply_to_squares(&queue, &mut chain);
+
+        chain.push(Effect::color_grade(ColorGrade::identity_lut(queue.clone(), 33).unwrap(), 1.0));
+        let graded = apply_to_squares(&queue, &mut chain);
+
+        assert_eq!(graded, original);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn lut_strips_of_the_wrong_shape_are_rejected() {
+        let (queue, _) = test_device();
+        let pixels = vec![0u8; 1024 * 32 * 4];
+
+        for (width, height) in [(256, 17), (255, 16), (1, 1), (32, 1024)] {
+            let error = Lut3d::from_strip(queue.clone(), width, height, &pixels).err().unwrap().to_string();
+            assert!(error.contains("expected N slices"), "{}x{}: {}", width, height, error);
+        }
+
+        let error = Lut3d::from_strip(queue.clone(), 256, 16, &pixels[..100]).err().unwrap().to_string();
+        assert!(error.contains("bytes of pixels"), "{}", error);
+        assert!(ColorGrade::identity_lut(queue.clone(), 1).is_err());
+        assert_eq!(Lut3d::from_strip(queue, 256, 16, &pixels).unwrap().size(), 16);
+    }
+}
@@ -0,0 +1,39 @@
+use std::fmt;
+
+use vulkano::format::Format;
+
+/// Why reading a frame back from the GPU failed, see [`crate::graphics::capture_thumbnail`].
+#[derive(Debug)]
+pub enum GraphicsError {
+    /// The frame couldn't be copied off the GPU.
+    Capture(String),
+    /// The frame is in a format readback can't convert to RGBA.
+    UnsupportedFormat(Format),
+    /// The pixels couldn't be encoded as a PNG.
+    Encode(png::EncodingError),
+}
+
+impl fmt::Display for GraphicsError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            GraphicsError::Capture(e) => write!(f, "failed to capture frame: {}", e),
+            GraphicsError::UnsupportedFormat(format) => write!(f, "can't read back frames in format {:?}", format),
+            GraphicsError::Encode(e) => write!(f, "failed to encode png: {}", e),
+        }
+    }
+}
+
+impl std::error::Error for GraphicsError {
+    fn source(&self) -> Option<&(dyn std::error::Error + 'static)> {
+        match self {
+            GraphicsError::Encode(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl From<png::EncodingError> for GraphicsError {
+    fn from(e: png::EncodingError) -> Self {
+        GraphicsError::Encode(e)
+    }
+}
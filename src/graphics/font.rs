@@ -0,0 +1,494 @@
+use crate::graphics::image::Image;
+use crate::graphics::render_pass::frame::Pass;
+use crate::graphics::shader::ShaderId;
+use crate::graphics::sprite::{self, SpriteBatch};
+use crate::graphics::*;
+
+use cgmath::{Matrix4, Rad};
+use std::ops::Range;
+
+/// A bitmap font laid out as a grid of glyphs on a single image, starting at 'A'.
+#[derive(Clone)]
+pub struct Font {
+    image: Image,
+    width: f32,
+    height: f32,
+}
+
+impl Font {
+    /// `w` and `h` are the number of glyph columns and rows in the sheet.
+    pub fn new(i: Image, w: u32, h: u32) -> Self {
+        Self {
+            image: i,
+            width: w as f32,
+            height: h as f32,
+        }
+    }
+
+    pub fn map(&self, r: &char) -> (f32, f32) {
+        let i: u32 = (*r).into();
+        let x = (i - 64 - 1) % self.width as u32;
+        let y = (i - 64 - 1) / self.width as u32;
+        (x as f32, y as f32)
+    }
+
+    /// The glyph's region of the sheet in uv space, `None` if the sheet doesn't contain it.
+    pub fn glyph(&self, r: &char) -> Option<Rect> {
+        let i: u32 = (*r).into();
+        if i <= 64 || i - 64 > (self.width * self.height) as u32 {
+            return None;
+        }
+
+        let coords = self.map(r);
+        Some(Rect {
+            x: coords.0 / self.width,
+            y: coords.1 / self.height,
+            w: 1. / self.width,
+            h: 1. / self.height,
+        })
+    }
+
+    pub fn sheet(&self) -> &Image {
+        &self.image
+    }
+}
+
+/// Text made of differently colored runs, drawn as one instanced draw of the font sheet.
+pub struct RichText {
+    font: Arc<Font>,
+    glyphs: Vec<InstanceData>,
+}
+
+impl RichText {
+    /// Lays out `spans` left to right starting at `position`, each glyph `scale` units square.
+    ///
+    /// The pen carries on from one span to the next, a `'\n'` in any span starts a new line
+    /// back at `position.0`, and characters missing from the font still advance the pen.
+    pub fn new(font: Arc<Font>, spans: &[(&str, Color)], position: (f32, f32), scale: f32) -> Self {
+        let mut glyphs = Vec::new();
+        layout(&font, spans, position, scale, |info| glyphs.push(info.into()));
+
+        Self { font, glyphs }
+    }
+
+    /// Like [`RichText::new`] with the baseline turned by `rotation` about `position`, the top
+    /// left of the first glyph. Glyphs turn with it, and each line starts `scale` further
+    /// along the perpendicular, so multi-line text stays a rotated block. Positive angles turn
+    /// clockwise in y-down coordinate systems.
+    pub fn rotated(font: Arc<Font>, spans: &[(&str, Color)], position: (f32, f32), scale: f32, rotation: Rad<f32>) -> Self {
+        let mut glyphs = Vec::new();
+        layout_rotated(&font, spans, position, scale, rotation, |info| glyphs.push(info.into()));
+
+        Self { font, glyphs }
+    }
+
+    /// Lays out `text` in `base`, recoloring the byte `ranges` given, see [`color_ranges`].
+    pub fn with_ranges(
+        font: Arc<Font>,
+        text: &str,
+        base: Color,
+        ranges: &[(Range<usize>, Color)],
+        position: (f32, f32),
+        scale: f32,
+    ) -> Self {
+        Self::new(font, &color_ranges(text, base, ranges), position, scale)
+    }
+
+    /// Lays out `text` in `base`, recolored by its `[color=...]` tags, see [`parse_markup`].
+    pub fn from_markup(font: Arc<Font>, text: &str, base: Color, position: (f32, f32), scale: f32) -> Self {
+        let spans = parse_markup(text, base);
+        let spans: Vec<(&str, Color)> = spans.iter().map(|(text, color)| (text.as_str(), *color)).collect();
+        Self::new(font, &spans, position, scale)
+    }
+}
+
+/// Splits `text` into spans colored by `ranges`, with anything they don't cover in `base`.
+///
+/// Ranges are byte offsets into `text`. They're applied in order of their start, clamped to the
+/// text, and an earlier range wins where two overlap. Ranges that don't fall on a char boundary
+/// are ignored.
+pub fn color_ranges<'a>(text: &'a str, base: Color, ranges: &[(Range<usize>, Color)]) -> Vec<(&'a str, Color)> {
+    let mut ranges: Vec<&(Range<usize>, Color)> = ranges.iter().collect();
+    ranges.sort_by_key(|(range, _)| range.start);
+
+    let mut spans = Vec::new();
+    let mut cursor = 0;
+    for (range, color) in ranges {
+        let start = range.start.max(cursor);
+        let end = range.end.min(text.len());
+        if start >= end || !text.is_char_boundary(start) || !text.is_char_boundary(end) {
+            continue;
+        }
+
+        if start > cursor {
+            spans.push((&text[cursor..start], base));
+        }
+        spans.push((&text[start..end], *color));
+        cursor = end;
+    }
+
+    if cursor < text.len() {
+        spans.push((&text[cursor..], base));
+    }
+
+    spans
+}
+
+/// Splits `text` into spans by its `[color=...]` and `[/color]` tags, untagged text in `base`.
+///
+/// Colors are `#rrggbb`, `#rrggbbaa`, or one of `black`, `grey`, `white`, `red`, and tags nest,
+/// a `[/color]` going back to whichever color was in effect before its opening tag. A tag that
+/// doesn't parse is kept as plain text.
+pub fn parse_markup(text: &str, base: Color) -> Vec<(String, Color)> {
+    let mut spans: Vec<(String, Color)> = Vec::new();
+    let mut stack = vec![base];
+    let mut rest = text;
+
+    let push = |spans: &mut Vec<(String, Color)>, text: &str, color: Color| {
+        if text.is_empty() {
+            return;
+        }
+        match spans.last_mut() {
+            Some((last, last_color)) if *last_color == color => last.push_str(text),
+            _ => spans.push((text.to_string(), color)),
+        }
+    };
+
+    while let Some(open) = rest.find('[') {
+        let color = *stack.last().unwrap();
+        push(&mut spans, &rest[..open], color);
+        rest = &rest[open..];
+
+        let tag = rest.find(']').map(|close| (&rest[1..close], close));
+        match tag {
+            Some(("/color", close)) if stack.len() > 1 => {
+                stack.pop();
+                rest = &rest[close + 1..];
+            }
+            Some((tag, close)) if tag.starts_with("color=") => match parse_color(&tag["color=".len()..]) {
+                Some(tagged) => {
+                    stack.push(tagged);
+                    rest = &rest[close + 1..];
+                }
+                None => {
+                    push(&mut spans, &rest[..1], color);
+                    rest = &rest[1..];
+                }
+            },
+            _ => {
+                push(&mut spans, &rest[..1], color);
+                rest = &rest[1..];
+            }
+        }
+    }
+
+    push(&mut spans, rest, *stack.last().unwrap());
+    spans
+}
+
+fn parse_color(value: &str) -> Option<Color> {
+    match value {
+        "black" => return Some(Color::black()),
+        "grey" | "gray" => return Some(Color::grey()),
+        "white" => return Some(Color::white()),
+        "red" => return Some(Color::red()),
+        _ => {}
+    }
+
+    let hex = value.strip_prefix('#')?;
+    if !(hex.len() == 6 || hex.len() == 8) || !hex.is_ascii() {
+        return None;
+    }
+
+    let channel = |i: usize| u8::from_str_radix(&hex[i..i + 2], 16).ok();
+    let alpha = if hex.len() == 8 { channel(6)? } else { 255 };
+    Some(Color::rgba(channel(0)?, channel(2)?, channel(4)?, alpha))
+}
+
+/// Emits the `DrawInfo` of every glyph in `spans`, see [`RichText::new`] for the layout rules.
+fn layout<F: FnMut(DrawInfo)>(font: &Font, spans: &[(&str, Color)], position: (f32, f32), scale: f32, emit: F) {
+    layout_rotated(font, spans, position, scale, Rad(0.0), emit)
+}
+
+/// [`layout`] along a baseline turned by `rotation` about `position`, see [`RichText::rotated`].
+fn layout_rotated<F: FnMut(DrawInfo)>(
+    font: &Font,
+    spans: &[(&str, Color)],
+    position: (f32, f32),
+    scale: f32,
+    rotation: Rad<f32>,
+    mut emit: F,
+) {
+    let (sin, cos) = rotation.0.sin_cos();
+    // The pen in the text's own unrotated space, relative to `position`.
+    let mut pen = (0.0, 0.0);
+
+    for (text, color) in spans {
+        for r in text.chars() {
+            if r == '\n' {
+                pen = (0.0, pen.1 + scale);
+                continue;
+            }
+
+            if let Some(rect) = font.glyph(&r) {
+                let mut info = DrawInfo::with_rect(rect);
+                info.color(*color);
+                if rotation.0 == 0.0 {
+                    info.dest(position.0 + pen.0, position.1 + pen.1, 0.0);
+                    info.scale(scale);
+                } else {
+                    let x = position.0 + pen.0 * cos - pen.1 * sin;
+                    let y = position.1 + pen.0 * sin + pen.1 * cos;
+                    info.transform = Transform::Matrix(
+                        Matrix4::from_translation(Vector3::new(x, y, 0.0))
+                            * Matrix4::from_angle_z(rotation)
+                            * Matrix4::from_scale(scale),
+                    );
+                }
+                emit(info);
+            }
+
+            pen.0 += scale;
+        }
+    }
+}
+
+impl Bounded for RichText {
+    fn bounds(&self) -> Option<Rect> {
+        self.glyphs
+            .iter()
+            .map(|glyph| sprite::quad_bounds(glyph.transform.into()))
+            .reduce(|a, b| a.union(&b))
+    }
+}
+
+impl Drawable for RichText {
+    fn stats(&self) -> DrawStats {
+        DrawStats {
+            draw_calls: 1,
+            instances: self.glyphs.len() as u32,
+        }
+    }
+
+    fn draw(&self, queue: Arc<Queue>, shader_handle: &dyn ShaderHandle, state: &DrawState, _info: DrawInfo) -> Result<SecondaryAutoCommandBuffer> {
+        let instance_count = self.glyphs.len() as u32;
+        let instance_buffer = CpuAccessibleBuffer::from_iter(
+            queue.device().clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            self.glyphs.iter().cloned(),
+        )?;
+
+        self.font.sheet().draw_instances(queue, shader_handle, state, instance_buffer, instance_count, instance_count)
+    }
+}
+
+/// Draws runs of text with their own colors, see [`RichText::new`] for how they're laid out.
+pub fn draw_rich_text(
+    pass: &mut Pass,
+    font: &Arc<Font>,
+    shader: ShaderId,
+    spans: &[(&str, Color)],
+    position: (f32, f32),
+    scale: f32,
+) -> Result<()> {
+    pass.draw_with(
+        Arc::new(RichText::new(font.clone(), spans, position, scale)),
+        shader,
+        DrawInfo::default(),
+    )
+}
+
+/// Draws `text` along a baseline turned by `rotation`, see [`RichText::rotated`].
+#[allow(clippy::too_many_arguments)]
+pub fn draw_text_rotated(
+    pass: &mut Pass,
+    font: &Arc<Font>,
+    shader: ShaderId,
+    text: &str,
+    position: (f32, f32),
+    scale: f32,
+    rotation: Rad<f32>,
+    color: Color,
+) -> Result<()> {
+    pass.draw_with(
+        Arc::new(RichText::rotated(font.clone(), &[(text, color)], position, scale, rotation)),
+        shader,
+        DrawInfo::default(),
+    )
+}
+
+/// Collects text from many [`TextBatch::draw_text`] calls into one [`SpriteBatch`] per font,
+/// so a whole page of text costs one instanced draw per font when flushed.
+#[derive(Default)]
+pub struct TextBatch {
+    batches: Vec<(Arc<Font>, SpriteBatch)>,
+}
+
+impl TextBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues `text` in a single color, laid out like [`RichText::new`].
+    pub fn draw_text(&mut self, font: &Arc<Font>, text: &str, position: (f32, f32), scale: f32, color: Color) {
+        self.draw_rich_text(font, &[(text, color)], position, scale);
+    }
+
+    /// Queues runs of text with their own colors, laid out like [`RichText::new`].
+    pub fn draw_rich_text(&mut self, font: &Arc<Font>, spans: &[(&str, Color)], position: (f32, f32), scale: f32) {
+        let batch = self.batch(font);
+        layout(font, spans, position, scale, |info| {
+            batch.insert(info);
+        });
+    }
+
+    /// Queues `text` along a baseline turned by `rotation`, laid out like [`RichText::rotated`].
+    pub fn draw_text_rotated(
+        &mut self,
+        font: &Arc<Font>,
+        text: &str,
+        position: (f32, f32),
+        scale: f32,
+        rotation: Rad<f32>,
+        color: Color,
+    ) {
+        let batch = self.batch(font);
+        layout_rotated(font, &[(text, color)], position, scale, rotation, |info| {
+            batch.insert(info);
+        });
+    }
+
+    /// Queues `text` with its `[color=...]` tags applied, see [`parse_markup`].
+    pub fn draw_markup(&mut self, font: &Arc<Font>, text: &str, base: Color, position: (f32, f32), scale: f32) {
+        let spans = parse_markup(text, base);
+        let spans: Vec<(&str, Color)> = spans.iter().map(|(text, color)| (text.as_str(), *color)).collect();
+        self.draw_rich_text(font, &spans, position, scale);
+    }
+
+    fn batch(&mut self, font: &Arc<Font>) -> &mut SpriteBatch {
+        let idx = match self.batches.iter().position(|(f, _)| Arc::ptr_eq(f, font)) {
+            Some(idx) => idx,
+            None => {
+                self.batches.push((font.clone(), SpriteBatch::new(font.sheet().clone())));
+                self.batches.len() - 1
+            }
+        };
+
+        &mut self.batches[idx].1
+    }
+
+    /// Number of batches, and so draw calls, a flush will make: one per distinct font.
+    pub fn batch_count(&self) -> usize {
+        self.batches.len()
+    }
+
+    pub fn glyph_count(&self) -> usize {
+        self.batches.iter().map(|(_, batch)| batch.count()).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.batches.is_empty()
+    }
+
+    /// Drops everything queued without drawing it.
+    pub fn clear(&mut self) {
+        self.batches.clear();
+    }
+
+    /// Draws everything queued since the last flush with `shader` and empties the batch.
+    pub fn flush(&mut self, pass: &mut Pass, shader: ShaderId) -> Result<()> {
+        for (_, batch) in self.batches.drain(..) {
+            pass.draw_with(Arc::new(batch), shader, DrawInfo::default())?;
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn owned(spans: &[(&str, Color)]) -> Vec<(String, Color)> {
+        spans.iter().map(|(text, color)| (text.to_string(), *color)).collect()
+    }
+
+    #[test]
+    fn markup_colors_tagged_text_and_leaves_the_rest_in_base() {
+        let spans = parse_markup("a[color=red]b[/color]c", Color::white());
+        assert_eq!(spans, owned(&[("a", Color::white()), ("b", Color::red()), ("c", Color::white())]));
+    }
+
+    #[test]
+    fn markup_tags_nest() {
+        let spans = parse_markup("[color=red]a[color=#00ff00]b[/color]c[/color]d", Color::white());
+        assert_eq!(
+            spans,
+            owned(&[
+                ("a", Color::red()),
+                ("b", Color::rgba(0, 255, 0, 255)),
+                ("c", Color::red()),
+                ("d", Color::white()),
+            ])
+        );
+    }
+
+    #[test]
+    fn unterminated_markup_tag_stays_text() {
+        let spans = parse_markup("a[color=red", Color::white());
+        assert_eq!(spans, owned(&[("a[color=red", Color::white())]));
+    }
+
+    #[test]
+    fn unknown_colors_and_stray_closing_tags_stay_text() {
+        let spans = parse_markup("[color=teal]a[/color]", Color::white());
+        assert_eq!(spans, owned(&[("[color=teal]a[/color]", Color::white())]));
+    }
+
+    #[test]
+    fn ranges_color_their_bytes_and_the_earlier_range_wins() {
+        let spans = color_ranges("abcdef", Color::white(), &[(3..5, Color::black()), (1..4, Color::red())]);
+        assert_eq!(
+            spans,
+            vec![("a", Color::white()), ("bcd", Color::red()), ("e", Color::black()), ("f", Color::white())]
+        );
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn glyphs_carry_their_span_color() {
+        let (queue, sampler) = test_device();
+        let font = Arc::new(Font::new(Image::placeholder(queue, sampler), 8, 8));
+
+        let text = RichText::from_markup(font, "A[color=red]B[/color]C", Color::white(), (0.0, 0.0), 8.0);
+        let colors = text.glyphs.iter().map(|glyph| glyph.color).collect::<Vec<_>>();
+        let [white, red]: [[f32; 4]; 2] = [Color::white().into(), Color::red().into()];
+        assert_eq!(colors, vec![white, red, white]);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn strings_in_one_font_share_a_batch() {
+        let (queue, sampler) = test_device();
+        let font = Arc::new(Font::new(Image::placeholder(queue.clone(), sampler.clone()), 8, 8));
+        let other = Arc::new(Font::new(Image::placeholder(queue.clone(), sampler), 8, 8));
+
+        let mut text = TextBatch::new();
+        text.draw_text(&font, "ABC", (0.0, 0.0), 4.0, Color::white());
+        text.draw_text(&font, "DE", (0.0, 8.0), 4.0, Color::red());
+        assert_eq!(text.batch_count(), 1);
+        assert_eq!(text.glyph_count(), 5);
+
+        text.draw_text(&other, "F", (0.0, 16.0), 4.0, Color::white());
+        assert_eq!(text.batch_count(), 2);
+
+        let mut pass = test_pass(queue.clone());
+        render_offscreen(&queue, &mut pass, [32, 32], Color::black(), |pass| {
+            text.flush(pass, 0)?;
+            assert_eq!(pass.stats(), DrawStats { draw_calls: 2, instances: 6 });
+            Ok(())
+        });
+        assert!(text.is_empty());
+    }
+}
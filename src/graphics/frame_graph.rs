@@ -0,0 +1,151 @@
+//! Ordering for frames that draw into offscreen targets before sampling them.
+//!
+//! A [`FrameGraph`] records passes in the order they're declared, each one chained onto the
+//! future of the pass before it. Vulkano tracks every image used along a chain of futures, so
+//! a target written by one pass and sampled by a later one gets the layout transition and
+//! barrier between the two inserted for it: the later pass always sees the finished contents.
+//!
+//! The ordering semantics are simple on purpose:
+//!
+//! - Passes run in declaration order, never reordered or culled.
+//! - A pass sees every write made by the passes declared before it, whether it samples a
+//!   target or draws into it again.
+//! - A target sampled before the pass that draws into it sees last frame's contents, or
+//!   whatever it was created with. Declare the pass that writes a target first.
+//!
+//! ```ignore
+//! let mut graph = FrameGraph::new(before_future);
+//! graph.render_to_image(&mut self.scene, |pass| draw_scene(pass))?;
+//! graph.render_to_screen(renderer, 0, Color::black().into(), |pass| {
+//!     pass.draw_with(Arc::new(self.scene.image()), self.post_shader, info)
+//! })?;
+//! graph.finish()
+//! ```
+
+use std::sync::Arc;
+
+use anyhow::{anyhow, Result};
+use vulkano::image::ImageViewAbstract;
+use vulkano::sync::GpuFuture;
+
+use crate::graphics::camera::Camera;
+use crate::graphics::dynamic_image::DynamicImage;
+use crate::graphics::render_pass::frame::{Pass, PassState};
+use crate::graphics::render_pass::RenderPass;
+use crate::graphics::renderer::Renderer;
+
+/// Passes recorded one after another within a frame, see the [module docs](self).
+pub struct FrameGraph {
+    future: Option<Box<dyn GpuFuture>>,
+    passes: usize,
+}
+
+impl FrameGraph {
+    /// Starts a graph whose first pass runs after `before_future`, usually the future handed
+    /// to [`crate::event::EventHandler::draw`].
+    pub fn new(before_future: Box<dyn GpuFuture>) -> Self {
+        Self {
+            future: Some(before_future),
+            passes: 0,
+        }
+    }
+
+    /// Number of passes recorded so far.
+    pub fn len(&self) -> usize {
+        self.passes
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.passes == 0
+    }
+
+    /// Draws into `target` after every pass declared so far. Later passes can sample
+    /// [`DynamicImage::image`] and see these draws.
+    pub fn render_to_image<F>(&mut self, target: &mut DynamicImage, draw: F) -> Result<()>
+    where
+        F: FnOnce(&mut Pass) -> Result<()>,
+    {
+        self.then(|future| target.record(future, draw))
+    }
+
+    /// Draws into `target` with `render_pass` after every pass declared so far, for targets
+    /// such as [`Renderer::scene_image`] or an effect chain's images.
+    pub fn render_to<F>(
+        &mut self,
+        render_pass: &mut RenderPass,
+        target: Arc<dyn ImageViewAbstract>,
+        camera: Arc<dyn Camera>,
+        clear_color: [f32; 4],
+        draw: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(&mut Pass) -> Result<()>,
+    {
+        self.then(|future| {
+            let mut frame = render_pass.frame(clear_color, future, target, camera)?;
+
+            let mut draw = Some(draw);
+            let mut after_future = None;
+            while let Some(pass) = frame.next_pass()? {
+                match pass {
+                    PassState::DrawPass(mut pass) => {
+                        if let Some(draw) = draw.take() {
+                            draw(&mut pass)?;
+                        }
+                    }
+                    PassState::Finished(future) => {
+                        after_future = Some(future);
+                    }
+                }
+            }
+
+            Ok(after_future.unwrap())
+        })
+    }
+
+    /// Draws into [`Renderer::final_image`] with the renderer's render pass at
+    /// `render_pass_index` and its [`Renderer::default_camera`], after every pass declared so far.
+    pub fn render_to_screen<F>(
+        &mut self,
+        ctx: &mut Renderer,
+        render_pass_index: usize,
+        clear_color: [f32; 4],
+        draw: F,
+    ) -> Result<()>
+    where
+        F: FnOnce(&mut Pass) -> Result<()>,
+    {
+        let target = ctx.final_image();
+        let camera = ctx.default_camera();
+        let render_pass = ctx
+            .render_passes
+            .get_mut(render_pass_index)
+            .ok_or_else(|| anyhow!("no render pass at index {}", render_pass_index))?;
+
+        self.render_to(render_pass, target, camera, clear_color, draw)
+    }
+
+    /// Records work that isn't a render pass, such as [`Renderer::resolve_scene`] or
+    /// [`crate::graphics::effects::EffectChain::apply`], in order with the other passes. `record`
+    /// is given the future of everything declared so far and returns the one that follows it.
+    pub fn then<F>(&mut self, record: F) -> Result<()>
+    where
+        F: FnOnce(Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>>,
+    {
+        let future = self
+            .future
+            .take()
+            .ok_or_else(|| anyhow!("frame graph pass {} declared after an earlier pass failed", self.passes))?;
+
+        self.future = Some(record(future)?);
+        self.passes += 1;
+
+        Ok(())
+    }
+
+    /// The future of the last pass, to return from [`crate::event::EventHandler::draw`].
+    pub fn finish(self) -> Result<Box<dyn GpuFuture>> {
+        self.future
+            .ok_or_else(|| anyhow!("frame graph finished after a pass failed"))
+    }
+}
@@ -0,0 +1,14 @@
+//! What's left of [`crate::graphics`] without the `graphics` feature: the math types shared
+//! with game logic, and with the `mint` feature their conversions, so servers and tools can
+//! use them without linking Vulkan or a windowing library.
+
+#[path = "math.rs"]
+#[allow(dead_code)]
+mod math;
+
+pub use math::{Bounded, Color, Rect, Transform};
+
+/// Conversions to and from `mint` and `glam` math types, enabled by the features of the same name.
+#[cfg(feature = "mint")]
+#[path = "interop.rs"]
+pub mod interop;
@@ -4,66 +4,482 @@
 // use crate::graphics::GraphicsContext;
 // use crate::graphics::Drawable;
 // use crate::graphics::DrawInfo;
+use crate::asset;
 use crate::graphics::*;
 use std::fs;
-use std::io::Cursor;
-use std::io::Read;
 use std::path;
 use std::sync::Arc;
 use vulkano::descriptor_set::PersistentDescriptorSet;
 use vulkano::format::Format;
-use vulkano::image::{view::ImageView, ImageDimensions, ImmutableImage, MipmapsCount};
-use vulkano::command_buffer::CommandBufferUsage;
-use vulkano::pipeline::graphics::viewport::Viewport;
+use vulkano::image::{
+    view::ImageView, ImageCreateFlags, ImageDimensions, ImageLayout, ImageUsage, ImmutableImage,
+    MipmapsCount,
+};
+use vulkano::command_buffer::{CommandBufferUsage, PrimaryAutoCommandBuffer};
 use vulkano::pipeline::{Pipeline, PipelineBindPoint};
 
+/// Side length in pixels of the [`Image::placeholder`] texture.
+pub const PLACEHOLDER_SIZE: u32 = 64;
+/// Number of checkerboard cells along each side of a placeholder.
+const PLACEHOLDER_CELLS: u32 = 8;
+
+pub use crate::asset::ImageError;
+
+/// How [`Image::with_spec`] and [`Image::load_many`] load images.
+///
+/// The renderer keeps one built from its [`crate::conf::Conf`] for
+/// [`crate::graphics::renderer::Renderer::load_image`], see
+/// [`crate::graphics::renderer::Renderer::image_spec`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub struct ImageSpec {
+    premultiply: bool,
+    placeholder_on_error: bool,
+}
+
+impl Default for ImageSpec {
+    fn default() -> Self {
+        Self {
+            premultiply: true,
+            placeholder_on_error: false,
+        }
+    }
+}
+
+impl ImageSpec {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Multiplies PNG colors by their alpha as they're loaded, so they filter without dark
+    /// edges and draw with [`BlendMode::PremultipliedAlpha`]. `true` by default, turn it off
+    /// for textures whose channels aren't colors to blend, such as lookup tables, or for custom
+    /// shaders that expect straight alpha. See [`Image::is_premultiplied`].
+    pub fn premultiply(mut self, enabled: bool) -> Self {
+        self.premultiply = enabled;
+        self
+    }
+
+    pub fn premultiplies(&self) -> bool {
+        self.premultiply
+    }
+
+    /// Logs a warning and loads [`Image::placeholder`] in place of images that fail to load,
+    /// instead of returning an error. `false` by default.
+    pub fn placeholder_on_error(mut self, enabled: bool) -> Self {
+        self.placeholder_on_error = enabled;
+        self
+    }
+
+    pub fn uses_placeholder_on_error(&self) -> bool {
+        self.placeholder_on_error
+    }
+}
+
 #[derive(Clone)]
 #[allow(unused)]
 pub struct Image {
-    inner: Arc<ImageView<ImmutableImage>>,
+    inner: Arc<dyn ImageViewAbstract>,
     width: u32,
     height: u32,
     sampler: Arc<Sampler>,
+    _memory: Arc<memory::TrackedAllocation>,
+    upload: Option<(Arc<upload::UploadState>, Arc<dyn ImageViewAbstract>)>,
+    premultiplied: bool,
 }
 
 impl Image {
-    pub fn new<P: AsRef<path::Path>>(queue: Arc<Queue>, sampler: Arc<Sampler>, path: P) -> Self {
-        let mut png_bytes = Vec::new();
+    /// Loads the PNG, or the DDS if `path` ends in `.dds`, at `path` and uploads it to the GPU.
+    ///
+    /// Use [`Image::load_or_default`] to draw a placeholder instead when a missing asset
+    /// shouldn't stop the app, or load with [`Image::with_spec`] and
+    /// [`ImageSpec::placeholder_on_error`] to do the same and never fail.
+    ///
+    /// PNGs are premultiplied by their alpha, see [`ImageSpec::premultiply`].
+    pub fn new<P: AsRef<path::Path>>(queue: Arc<Queue>, sampler: Arc<Sampler>, path: P) -> Result<Self, ImageError> {
+        Self::with_spec(queue, sampler, path, ImageSpec::default())
+    }
 
-        fs::File::open(path)
-            .unwrap()
-            .read_to_end(&mut png_bytes)
-            .unwrap();
+    /// [`Image::new`] with the options in `spec`.
+    pub fn with_spec<P: AsRef<path::Path>>(queue: Arc<Queue>, sampler: Arc<Sampler>, path: P, spec: ImageSpec) -> Result<Self, ImageError> {
+        if spec.placeholder_on_error {
+            return Ok(Self::load_or_placeholder(queue, sampler, path, spec.premultiply));
+        }
+
+        Self::load(queue, sampler, path, spec.premultiply)
+    }
+
+    /// [`Image::new`] without premultiplying, shorthand for [`Image::with_spec`] with
+    /// [`ImageSpec::premultiply`] off.
+    pub fn new_straight<P: AsRef<path::Path>>(queue: Arc<Queue>, sampler: Arc<Sampler>, path: P) -> Result<Self, ImageError> {
+        Self::load(queue, sampler, path, false)
+    }
+
+    /// Loads the image at `path`, falling back to [`Image::placeholder`] if the file is
+    /// missing or can't be decoded.
+    pub fn load_or_default<P: AsRef<path::Path>>(queue: Arc<Queue>, sampler: Arc<Sampler>, path: P) -> Self {
+        Self::load_or_placeholder(queue, sampler, path, ImageSpec::default().premultiply)
+    }
+
+    fn load_or_placeholder<P: AsRef<path::Path>>(queue: Arc<Queue>, sampler: Arc<Sampler>, path: P, premultiply: bool) -> Self {
+        match Self::load(queue.clone(), sampler.clone(), &path, premultiply) {
+            Ok(image) => image,
+            Err(e) => {
+                log::warn!(
+                    target: "ledge::asset",
+                    "failed to load image {:?}, using placeholder: {:?}",
+                    path.as_ref(),
+                    e
+                );
+                Self::placeholder(queue, sampler)
+            }
+        }
+    }
+
+    /// A magenta and black checkerboard, used in place of textures that failed to load.
+    pub fn placeholder(queue: Arc<Queue>, sampler: Arc<Sampler>) -> Self {
+        Self::placeholder_sized(queue, sampler, PLACEHOLDER_SIZE)
+    }
+
+    /// [`Image::placeholder`] `size` pixels across, still eight cells to a side.
+    pub fn placeholder_sized(queue: Arc<Queue>, sampler: Arc<Sampler>, size: u32) -> Self {
+        let magenta = [255, 0, 255, 255];
+        let black = [0, 0, 0, 255];
+
+        let size = size.max(1);
+        let cell = (size / PLACEHOLDER_CELLS).max(1);
+
+        let mut v: Vec<u8> = Vec::with_capacity((size * size * 4) as usize);
+        for y in 0..size {
+            for x in 0..size {
+                let parity = (x / cell + y / cell) % 2;
+                v.extend_from_slice(if parity == 0 { &magenta } else { &black });
+            }
+        }
+
+        Self::from_u8(queue, sampler, size, size, v).unwrap()
+    }
+
+    /// A 1x1 image of a single color.
+    pub fn from_color(queue: Arc<Queue>, sampler: Arc<Sampler>, color: Color) -> Self {
+        Self::from_u8(queue, sampler, 1, 1, color.as_u8_vec()).unwrap()
+    }
+
+    /// Loads a DDS file of BC1 (DXT1) or BC3 (DXT5) blocks, keeping them compressed on the GPU
+    /// if the device can sample them and decompressing them first otherwise. Only the top mip
+    /// level is loaded, check [`Image::format`] for which way it went.
+    pub fn from_dds<P: AsRef<path::Path>>(queue: Arc<Queue>, sampler: Arc<Sampler>, path: P) -> Result<Self, ImageError> {
+        let bytes = fs::read(path)?;
+        let dds = compressed::Dds::parse(&bytes).map_err(ImageError::Format)?;
+
+        if dds.format.is_supported(queue.device()) {
+            let bytes = dds.blocks.len() as u64;
+            return Self::upload(queue, sampler, dds.width, dds.height, dds.format.format(), dds.blocks, bytes)
+                .map_err(ImageError::Upload);
+        }
+
+        log::info!(target: "ledge::asset", "{:?} textures aren't supported by the device, decompressing", dds.format);
+        let pixels = dds.decompress();
+        Self::from_u8(queue, sampler, dds.width, dds.height, pixels).map_err(ImageError::Upload)
+    }
+
+    fn load<P: AsRef<path::Path>>(queue: Arc<Queue>, sampler: Arc<Sampler>, path: P, premultiply: bool) -> Result<Self, ImageError> {
+        let is_dds = path
+            .as_ref()
+            .extension()
+            .is_some_and(|extension| extension.eq_ignore_ascii_case("dds"));
+        if is_dds {
+            return Self::from_dds(queue, sampler, path);
+        }
+
+        let (width, height, image_data) = Self::decode_for_drawing(path, premultiply)?;
+
+        let mut image = Self::from_u8(queue, sampler, width, height, image_data).map_err(ImageError::Upload)?;
+        image.premultiplied = premultiply;
+        Ok(image)
+    }
+
+    pub(crate) fn decode<P: AsRef<path::Path>>(path: P) -> Result<(u32, u32, Vec<u8>), ImageError> {
+        let image = asset::load_png(path)?;
+        Ok((image.width, image.height, image.pixels))
+    }
+
+    /// [`Image::decode`], multiplying the colors by their alpha if `premultiply` is set.
+    fn decode_for_drawing<P: AsRef<path::Path>>(path: P, premultiply: bool) -> Result<(u32, u32, Vec<u8>), ImageError> {
+        let (width, height, mut pixels) = Self::decode(path)?;
+        if premultiply {
+            let len = (width as usize * height as usize * 4).min(pixels.len());
+            asset::premultiply_alpha(&mut pixels[..len]);
+        }
+        Ok((width, height, pixels))
+    }
+
+    /// Loads the image at `path` but leaves copying it to the GPU to `uploads`, which submits
+    /// all pending uploads together at the start of the next frame.
+    ///
+    /// Until the copy has finished the image draws as the upload queue's placeholder, see
+    /// [`Image::is_ready`]. PNGs are premultiplied, as by [`Image::new`].
+    pub fn new_deferred<P: AsRef<path::Path>>(uploads: &mut upload::UploadQueue, sampler: Arc<Sampler>, path: P) -> Result<Self> {
+        let premultiplied = ImageSpec::default().premultiply;
+        let (width, height, image_data) = Self::decode_for_drawing(path, premultiplied)?;
+        let queue = uploads.queue().clone();
+
+        let (image, _init) = ImmutableImage::uninitialized(
+            queue.device().clone(),
+            ImageDimensions::Dim2d {
+                width,
+                height,
+                array_layers: 1,
+            },
+            Format::R8G8B8A8_UNORM,
+            MipmapsCount::One,
+            ImageUsage {
+                transfer_destination: true,
+                sampled: true,
+                ..ImageUsage::none()
+            },
+            ImageCreateFlags::none(),
+            ImageLayout::ShaderReadOnlyOptimal,
+            queue.device().active_queue_families(),
+        )?;
+
+        let state = uploads.push(image_data, image.clone())?;
+
+        Ok(Self {
+            inner: ImageView::new_default(image)?,
+            width,
+            height,
+            sampler,
+            _memory: Arc::new(memory::TrackedAllocation::new(
+                memory::MemoryCategory::Textures,
+                width as u64 * height as u64 * 4,
+            )),
+            upload: Some((state, uploads.placeholder().inner.clone())),
+            premultiplied,
+        })
+    }
+
+    /// Loads every PNG in `paths` and uploads them all in one command buffer, waiting once for
+    /// the lot instead of once per image, e.g. when loading a level. Images come back in the
+    /// order of `paths`.
+    ///
+    /// Every image is decoded before anything is submitted, so the decoded pixels and a staging
+    /// buffer of the same size for each image, 4 bytes a pixel, are held at once until the
+    /// copy finishes. Split very large sets into several calls to bound that.
+    ///
+    /// DDS files are loaded one at a time through [`Image::from_dds`]. Fails on the first image
+    /// that can't be loaded, unless `spec` has [`ImageSpec::placeholder_on_error`] on, in which
+    /// case it's replaced by a placeholder.
+    pub fn load_many<P: AsRef<path::Path>>(queue: Arc<Queue>, sampler: Arc<Sampler>, paths: &[P], spec: ImageSpec) -> Result<Vec<Self>, ImageError> {
+        let placeholder_on_error = spec.placeholder_on_error;
+        let premultiplied = spec.premultiply;
+        let mut builder = AutoCommandBufferBuilder::primary(
+            queue.device().clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .map_err(|e| ImageError::Upload(e.into()))?;
+
+        let mut images = Vec::with_capacity(paths.len());
+        let mut uploads = 0;
+        for path in paths {
+            let path = path.as_ref();
+            let is_dds = path
+                .extension()
+                .is_some_and(|extension| extension.eq_ignore_ascii_case("dds"));
+
+            let loaded = if is_dds {
+                Self::from_dds(queue.clone(), sampler.clone(), path)
+            } else {
+                Self::decode_for_drawing(path, premultiplied).and_then(|(width, height, mut pixels)| {
+                    pixels.truncate(width as usize * height as usize * 4);
+                    uploads += 1;
+                    let mut image = Self::record_upload(&mut builder, &queue, sampler.clone(), width, height, pixels)
+                        .map_err(ImageError::Upload)?;
+                    image.premultiplied = premultiplied;
+                    Ok(image)
+                })
+            };
+
+            match loaded {
+                Ok(image) => images.push(image),
+                Err(e) if placeholder_on_error => {
+                    log::warn!(target: "ledge::asset", "failed to load image {:?}, using placeholder: {:?}", path, e);
+                    images.push(Self::placeholder(queue.clone(), sampler.clone()));
+                }
+                Err(e) => return Err(e),
+            }
+        }
+
+        if uploads > 0 {
+            let commands = builder.build().map_err(|e| ImageError::Upload(e.into()))?;
+            vulkano::sync::now(queue.device().clone())
+                .then_execute(queue.clone(), commands)
+                .map_err(|e| ImageError::Upload(e.into()))?
+                .then_signal_fence_and_flush()
+                .map_err(|e| ImageError::Upload(e.into()))?
+                .wait(None)
+                .map_err(|e| ImageError::Upload(e.into()))?;
+            log::debug!(target: "ledge::asset", "uploaded {} images in one submission", uploads);
+        }
+
+        Ok(images)
+    }
+
+    /// Creates an RGBA image and records copying `pixels` into it with `builder`, for
+    /// [`Image::load_many`]. The image can't be sampled until the commands have run.
+    fn record_upload(
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        queue: &Arc<Queue>,
+        sampler: Arc<Sampler>,
+        width: u32,
+        height: u32,
+        pixels: Vec<u8>,
+    ) -> Result<Self> {
+        let (image, _init) = ImmutableImage::uninitialized(
+            queue.device().clone(),
+            ImageDimensions::Dim2d {
+                width,
+                height,
+                array_layers: 1,
+            },
+            Format::R8G8B8A8_UNORM,
+            MipmapsCount::One,
+            ImageUsage {
+                transfer_destination: true,
+                sampled: true,
+                ..ImageUsage::none()
+            },
+            ImageCreateFlags::none(),
+            ImageLayout::ShaderReadOnlyOptimal,
+            queue.device().active_queue_families(),
+        )?;
+
+        let staging = CpuAccessibleBuffer::from_iter(
+            queue.device().clone(),
+            BufferUsage::transfer_source(),
+            false,
+            pixels,
+        )?;
+        builder.copy_buffer_to_image(staging, image.clone())?;
+
+        Ok(Self {
+            inner: ImageView::new_default(image)?,
+            width,
+            height,
+            sampler,
+            _memory: Arc::new(memory::TrackedAllocation::new(
+                memory::MemoryCategory::Textures,
+                width as u64 * height as u64 * 4,
+            )),
+            upload: None,
+            premultiplied: false,
+        })
+    }
+
+    /// `false` while a deferred upload of the image hasn't finished on the GPU.
+    pub fn is_ready(&self) -> bool {
+        self.upload.as_ref().is_none_or(|(state, _)| state.is_ready())
+    }
+
+    /// The view to sample when drawing, the placeholder while an upload is pending.
+    pub(crate) fn view(&self) -> Arc<dyn ImageViewAbstract> {
+        match &self.upload {
+            Some((state, placeholder)) if !state.is_ready() => placeholder.clone(),
+            _ => self.inner.clone(),
+        }
+    }
+
+    /// Draws an existing view, e.g. one also rendered into, as an RGBA image.
+    pub(crate) fn from_view(view: Arc<dyn ImageViewAbstract>, sampler: Arc<Sampler>, width: u32, height: u32) -> Self {
+        Self {
+            inner: view,
+            width,
+            height,
+            sampler,
+            _memory: Arc::new(memory::TrackedAllocation::new(
+                memory::MemoryCategory::Textures,
+                width as u64 * height as u64 * 4,
+            )),
+            upload: None,
+            premultiplied: false,
+        }
+    }
 
-        let cursor = Cursor::new(png_bytes);
-        let decoder = png::Decoder::new(cursor);
-        let mut reader = decoder.read_info().unwrap();
-        let width = reader.info().width;
-        let height = reader.info().height;
+    pub(crate) fn from_u8(queue: Arc<Queue>, sampler: Arc<Sampler>, width: u32, height: u32, v: Vec<u8>) -> Result<Self> {
+        let bytes = width as u64 * height as u64 * 4;
+        Self::upload(queue, sampler, width, height, Format::R8G8B8A8_UNORM, v, bytes)
+    }
+
+    /// Copies `v`, in `format`, into a new GPU image, tracking `bytes` of texture memory.
+    fn upload(
+        queue: Arc<Queue>,
+        sampler: Arc<Sampler>,
+        width: u32,
+        height: u32,
+        format: Format,
+        v: Vec<u8>,
+        bytes: u64,
+    ) -> Result<Self> {
         let dimensions = ImageDimensions::Dim2d {
-            width: width,
-            height: height,
+            width,
+            height,
             array_layers: 1,
         };
-        let mut image_data = Vec::new();
-        image_data.resize((width * height * 8) as usize, 0);
-        reader.next_frame(&mut image_data).unwrap();
 
         let (image, _) = ImmutableImage::from_iter(
-            image_data.iter().cloned(),
+            v.iter().cloned(),
             dimensions,
             MipmapsCount::One,
-            Format::R8G8B8A8_UNORM,
+            format,
             queue.clone(),
-        )
-        .unwrap();
-        let image_view = ImageView::new_default(image).unwrap();
+        )?;
+        let image_view = ImageView::new_default(image)?;
 
-        Self {
+        Ok(Self {
             inner: image_view,
             width,
             height,
             sampler,
+            _memory: Arc::new(memory::TrackedAllocation::new(
+                memory::MemoryCategory::Textures,
+                bytes,
+            )),
+            upload: None,
+            premultiplied: false,
+        })
+    }
+
+    pub fn width(&self) -> u32 {
+        self.width
+    }
+
+    pub fn height(&self) -> u32 {
+        self.height
+    }
+
+    /// The GPU format of the image, `R8G8B8A8_UNORM` unless it was loaded compressed.
+    pub fn format(&self) -> Format {
+        self.inner.format().unwrap()
+    }
+
+    /// Whether the image's colors were multiplied by their alpha when it was loaded.
+    ///
+    /// Linearly filtering straight alpha blends the color of transparent texels into the
+    /// edges of opaque ones, usually black, leaving dark halos around sprites that are
+    /// scaled, rotated, or drawn at fractional positions. Premultiplied texels filter cleanly.
+    /// Premultiplied images drawn with [`BlendMode::Alpha`] are drawn with
+    /// [`BlendMode::PremultipliedAlpha`] instead, with the tint's alpha applied to its
+    /// color too, so they need no changes to draw code.
+    pub fn is_premultiplied(&self) -> bool {
+        self.premultiplied
+    }
+
+    /// The blend mode to draw the image with when `mode` is asked for, see
+    /// [`Image::is_premultiplied`].
+    pub(crate) fn blend_mode_for(&self, mode: BlendMode) -> BlendMode {
+        match mode {
+            BlendMode::Alpha if self.premultiplied => BlendMode::PremultipliedAlpha,
+            mode => mode,
         }
     }
 
@@ -133,13 +549,83 @@ impl Image {
     //     }
     // }
 
-    pub fn inner(&self) -> &Arc<ImageView<ImmutableImage>> {
+    pub fn inner(&self) -> &Arc<dyn ImageViewAbstract> {
         &self.inner
     }
+
+    /// Identifies the GPU image behind this image, shared by its clones.
+    pub(crate) fn texture_key(&self) -> usize {
+        Arc::as_ptr(&self.inner) as *const () as usize
+    }
+
+    /// Whether both images draw the same GPU image, e.g. because one is a clone of the other.
+    pub fn same_texture(&self, other: &Image) -> bool {
+        self.texture_key() == other.texture_key()
+    }
+
+    pub fn sampler(&self) -> &Arc<Sampler> {
+        &self.sampler
+    }
 }
 
 impl Drawable for Image {
-    fn draw(&self, queue: Arc<Queue>, shader_handle: &Box<dyn ShaderHandle>, info: DrawInfo) -> Result<SecondaryAutoCommandBuffer> {
+    fn draw(&self, queue: Arc<Queue>, shader_handle: &dyn ShaderHandle, state: &DrawState, info: DrawInfo) -> Result<SecondaryAutoCommandBuffer> {
+        let mut instance: InstanceData = info.into();
+        if self.premultiplied {
+            instance.flags |= INSTANCE_FLAG_PREMULTIPLIED_TEXTURE;
+        }
+        let instances = vec![instance];
+        let instance_count = instances.len() as u32;
+        let instance_buffer = CpuAccessibleBuffer::from_iter(
+            queue.device().clone(),
+            BufferUsage::all(),
+            false,
+            instances,
+        ).unwrap();
+
+        self.draw_instances(queue, shader_handle, state, instance_buffer, instance_count, instance_count)
+    }
+}
+
+impl Image {
+    /// Records a draw of the unit quad textured with this image for every instance in `instance_buffer`.
+    ///
+    /// Instances are split into draw calls of at most `chunk_size` each, all reading from sub-ranges
+    /// of the same instance buffer.
+    pub(crate) fn draw_instances(
+        &self,
+        queue: Arc<Queue>,
+        shader_handle: &dyn ShaderHandle,
+        state: &DrawState,
+        instance_buffer: Arc<dyn BufferAccess>,
+        instance_count: u32,
+        chunk_size: u32,
+    ) -> Result<SecondaryAutoCommandBuffer> {
+        self.draw_instances_with(queue, shader_handle, state, instance_buffer, 0..instance_count, chunk_size, Vec::new())
+    }
+
+    /// [`Image::draw_instances`] for only the `instances` range of the buffer, with `extra`
+    /// written to the per-draw set after the image at binding 0 for shaders that take more
+    /// than a texture.
+    #[allow(clippy::too_many_arguments)]
+    pub(crate) fn draw_instances_with(
+        &self,
+        queue: Arc<Queue>,
+        shader_handle: &dyn ShaderHandle,
+        state: &DrawState,
+        instance_buffer: Arc<dyn BufferAccess>,
+        instances: std::ops::Range<u32>,
+        chunk_size: u32,
+        extra: Vec<WriteDescriptorSet>,
+    ) -> Result<SecondaryAutoCommandBuffer> {
+        // Masked draws swap in the masked sprite program, which takes the mask after the image.
+        // Draws already binding more than the image, such as lit batches, are left unmasked.
+        if let (Some(mask), true) = (&state.mask, extra.is_empty()) {
+            let mut unmasked = state.clone();
+            unmasked.mask = None;
+            return self.draw_instances_with(queue, mask.program.as_ref(), &unmasked, instance_buffer, instances, chunk_size, vec![mask.write()]);
+        }
+
         let mut builder = AutoCommandBufferBuilder::secondary_graphics(
             queue.device().clone(),
             queue.family(),
@@ -155,72 +641,205 @@ impl Drawable for Image {
             QUAD_VERTICES.to_vec(),
         ).unwrap();
 
-        let instances: Vec<InstanceData> = vec![info.into()];
-        let instance_count = instances.len() as u32;
-        let instance_buffer = CpuAccessibleBuffer::from_iter(
-            queue.device().clone(),
-            BufferUsage::all(),
-            false,
-            instances,
-        ).unwrap();
+        let set_index = shader_handle.descriptor_set_index();
+        let layout = shader_handle.layout()[set_index].clone();
 
-        let layout = shader_handle.layout()[1].clone();
+        let mut writes = vec![WriteDescriptorSet::image_view_sampler(
+            0,
+            self.view(),
+            self.sampler.clone(),
+        )];
+        writes.extend(extra);
 
-        let set = PersistentDescriptorSet::new(
-            layout.clone(),
-            [WriteDescriptorSet::image_view_sampler(
-                0,
-                self.inner.clone(),
-                self.sampler.clone(),
-            )],
-        ).unwrap();
+        let set = PersistentDescriptorSet::new(layout.clone(), writes).unwrap();
 
-        let layout = shader_handle.layout()[0].clone();
+        let cam_set = state.camera_set(shader_handle, queue.device().clone())?;
 
-        let mvp_buffer = CpuAccessibleBuffer::from_iter(
-            queue.device().clone(),
-            BufferUsage::all(),
-            false,
-            [
-                    [1.0,0.0,0.0,0.0],
-                    [0.0,1.0,0.0,0.0],
-                    [0.0,0.0,1.0,0.0],
-                    [0.0,0.0,0.0,1.0],
-                ],
-        ).unwrap();
+        let buffers: Vec<Arc<dyn BufferAccess>> = vec![vertex_buffer, instance_buffer];
 
-        let cam_set = PersistentDescriptorSet::new(
-            layout.clone(),
-            [WriteDescriptorSet::buffer(
-                0,
-                mvp_buffer,
-            )],
-        ).unwrap();
+        let pipeline = state.pipeline_for(shader_handle, self.blend_mode_for(state.blend_mode));
+
+        builder.bind_pipeline_graphics(pipeline.clone());
+        state.bind_viewport(&mut builder, &pipeline);
+        builder.bind_vertex_buffers(0, buffers);
 
-        builder
-            .bind_pipeline_graphics(shader_handle.pipeline().clone())
-            .set_viewport(0, vec![Viewport {
-                origin: [0.0, 0.0],
-                dimensions: [800 as f32, 600 as f32],
-                depth_range: 0.0..1.0,
-            }])
-            .bind_vertex_buffers(0, (vertex_buffer, instance_buffer))
-            .bind_descriptor_sets(
+        if let Some(cam_set) = cam_set {
+            builder.bind_descriptor_sets(
                 PipelineBindPoint::Graphics,
-                shader_handle.pipeline().layout().clone(),
-                0,
-                (cam_set, set),
-            )
-            .draw(
+                pipeline.layout().clone(),
+                camera::CAMERA_UNIFORM_SET as u32,
+                cam_set,
+            );
+        }
+
+        state.bind_params(&mut builder, &pipeline, queue.device().clone())?;
+
+        builder.bind_descriptor_sets(
+            PipelineBindPoint::Graphics,
+            pipeline.layout().clone(),
+            set_index as u32,
+            set,
+        );
+
+        let chunk_size = chunk_size.max(1);
+        let mut first_instance = instances.start;
+        while first_instance < instances.end {
+            let count = chunk_size.min(instances.end - first_instance);
+            builder.draw(
                 vertex_count, 
-                instance_count, 
-                0, 
+                count, 
                 0, 
-                )
-            .unwrap();
+                first_instance, 
+                )?;
+            first_instance += count;
+        }
         
         let commands = builder.build()?;
 
         Ok(commands)
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_files_are_io_errors() {
+        assert!(matches!(Image::decode("does/not/exist.png"), Err(ImageError::Io(_))));
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn new_returns_an_io_error_for_missing_files() {
+        let (queue, sampler) = crate::graphics::test_device();
+        assert!(matches!(Image::new(queue, sampler, "does/not/exist.png"), Err(ImageError::Io(_))));
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn bad_paths_load_the_placeholder() {
+        let (queue, sampler) = crate::graphics::test_device();
+        let image = Image::load_or_default(queue, sampler, "does/not/exist.png");
+        assert_eq!((image.width(), image.height()), (PLACEHOLDER_SIZE, PLACEHOLDER_SIZE));
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn failed_loads_return_the_placeholder_when_enabled() {
+        let (queue, sampler) = crate::graphics::test_device();
+        let spec = ImageSpec::new().placeholder_on_error(true);
+
+        let image = Image::with_spec(queue.clone(), sampler.clone(), "does/not/exist.png", spec).unwrap();
+        assert_eq!((image.width(), image.height()), (PLACEHOLDER_SIZE, PLACEHOLDER_SIZE));
+
+        let images = Image::load_many(queue.clone(), sampler.clone(), &["does/not/exist.png"], spec).unwrap();
+        assert_eq!((images[0].width(), images[0].height()), (PLACEHOLDER_SIZE, PLACEHOLDER_SIZE));
+
+        // The setting belongs to the spec, loads without it still fail.
+        assert!(Image::with_spec(queue, sampler, "does/not/exist.png", ImageSpec::new()).is_err());
+    }
+
+    /// Writes an opaque `width` x `height` PNG filled with `rgb` to the temp dir.
+    fn write_png(name: &str, width: u32, height: u32, rgb: [u8; 3]) -> path::PathBuf {
+        write_rgba_png(name, width, height, &[rgb[0], rgb[1], rgb[2], 255].repeat((width * height) as usize))
+    }
+
+    /// Writes `pixels`, RGBA8 rows `width` pixels long, as a PNG in the temp dir.
+    fn write_rgba_png(name: &str, width: u32, height: u32, pixels: &[u8]) -> path::PathBuf {
+        let path = std::env::temp_dir().join(format!("ledge-{}-{}.png", std::process::id(), name));
+        let mut encoder = png::Encoder::new(fs::File::create(&path).unwrap(), width, height);
+        encoder.set_color(png::ColorType::Rgba);
+        encoder.set_depth(png::BitDepth::Eight);
+        encoder.write_header().unwrap().write_image_data(pixels).unwrap();
+        path
+    }
+
+    /// Copies `image` back from the GPU.
+    fn read_back(queue: &Arc<Queue>, image: &Image) -> Vec<u8> {
+        let buffer = CpuAccessibleBuffer::from_iter(
+            queue.device().clone(),
+            BufferUsage::transfer_destination(),
+            false,
+            (0..image.width() * image.height() * 4).map(|_| 0u8),
+        )
+        .unwrap();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            queue.device().clone(),
+            queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder.copy_image_to_buffer(image.view().image(), buffer.clone()).unwrap();
+        vulkano::sync::now(queue.device().clone())
+            .then_execute(queue.clone(), builder.build().unwrap())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        let pixels = buffer.read().unwrap().to_vec();
+        pixels
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn load_many_uploads_every_image_in_order() {
+        let (queue, sampler) = crate::graphics::test_device();
+        let files = [("red", 4, 2, [255, 0, 0]), ("green", 3, 3, [0, 255, 0]), ("blue", 1, 5, [0, 0, 255])];
+        let paths = files
+            .iter()
+            .map(|(name, width, height, rgb)| write_png(name, *width, *height, *rgb))
+            .collect::<Vec<_>>();
+
+        let images = Image::load_many(queue.clone(), sampler, &paths, ImageSpec::new()).unwrap();
+        for path in &paths {
+            fs::remove_file(path).unwrap();
+        }
+
+        assert_eq!(images.len(), files.len());
+        for (image, (_, width, height, rgb)) in images.iter().zip(files) {
+            assert_eq!((image.width(), image.height()), (width, height));
+            assert_eq!(read_back(&queue, image), [rgb[0], rgb[1], rgb[2], 255].repeat((width * height) as usize));
+        }
+    }
+
+    #[test]
+    fn specs_premultiply_unless_told_not_to() {
+        assert!(ImageSpec::default().premultiplies());
+        assert!(!ImageSpec::new().premultiply(false).premultiplies());
+        assert!(!ImageSpec::default().uses_placeholder_on_error());
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn premultiplied_edges_filter_without_a_dark_fringe() {
+        let (queue, sampler) = crate::graphics::test_device();
+        // An opaque white texel beside a transparent black one, the way exporters store the
+        // edge of anti-aliased art.
+        let path = write_rgba_png("edge", 2, 1, &[255, 255, 255, 255, 0, 0, 0, 0]);
+        let mut pass = crate::graphics::test_pass(queue.clone());
+
+        // Magnified over white, so filtering blends the two texels across the middle.
+        let mut edge_row = |spec: ImageSpec| {
+            let image = Image::with_spec(queue.clone(), sampler.clone(), &path, spec).unwrap();
+            assert_eq!(image.is_premultiplied(), spec.premultiplies());
+            let pixels = crate::graphics::render_offscreen(&queue, &mut pass, [64, 16], Color::white(), |pass| {
+                pass.draw(Arc::new(image), DrawInfo::centered((32.0, 8.0), (64.0, 16.0), 0.0, Color::white()))
+            });
+            (0..64)
+                .map(|x| crate::graphics::pixel_at(&pixels, 64, (x, 8)))
+                .map(|[r, g, b, _]| r.min(g).min(b))
+                .min()
+                .unwrap()
+        };
+
+        let straight = edge_row(ImageSpec::new().premultiply(false));
+        let premultiplied = edge_row(ImageSpec::new());
+        fs::remove_file(&path).unwrap();
+
+        assert!(straight < 230, "straight alpha should darken the edge, darkest channel {}", straight);
+        assert!(premultiplied >= 250, "premultiplied edge darkened to {}", premultiplied);
+    }
+}
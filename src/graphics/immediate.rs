@@ -0,0 +1,357 @@
+use crate::graphics::image::Image;
+use crate::graphics::mesh::Mesh;
+use crate::graphics::*;
+use vulkano::command_buffer::CommandBufferUsage;
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::pipeline::{Pipeline, PipelineBindPoint};
+
+/// Counters for the geometry recorded into an [`ImmediateBatch`] during the last flush.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct ImmediateStats {
+    /// Number of `circle`, `line`, and `rectangle` calls recorded.
+    pub shapes: u32,
+    /// Number of draw calls actually issued to draw those shapes.
+    pub draw_calls: u32,
+    /// Shapes that shared a draw call with an earlier shape, `shapes - draw_calls`.
+    pub immediate_draws_merged: u32,
+}
+
+struct Bucket {
+    /// `None` for untextured shapes.
+    image: Option<Image>,
+    blend_mode: BlendMode,
+    vertices: Vec<Vertex>,
+    indices: Vec<u32>,
+}
+
+impl Bucket {
+    fn matches(&self, image: Option<&Image>, blend_mode: BlendMode) -> bool {
+        self.blend_mode == blend_mode
+            && match (&self.image, image) {
+                (Some(a), Some(b)) => a.same_texture(b),
+                (None, None) => true,
+                _ => false,
+            }
+    }
+}
+
+/// Accumulates immediate-mode shapes for a frame so that they can be drawn with as few
+/// draw calls as possible.
+///
+/// Shapes drawn without a texture use the built-in [`Shader::colored`](crate::graphics::shader::Shader::colored)
+/// program rather than the shader the batch is drawn with, so they bind no texture at all.
+/// Geometry is grouped into one vertex/index buffer per (texture, blend mode) bucket and
+/// uploaded once when the batch is flushed with [`Pass::draw_immediate`](crate::graphics::render_pass::frame::Pass::draw_immediate).
+///
+/// Buckets are drawn in the order they were first used. Shapes within a bucket keep their
+/// submission order, but interleaving between buckets is not preserved: if a shape in bucket A
+/// is submitted after a shape in bucket B that it overlaps, and A was first used before B,
+/// the A shape will be drawn underneath. Switch textures or blend modes sparingly, or flush
+/// between overlapping layers, when ordering matters.
+pub struct ImmediateBatch {
+    white: Image,
+    texture: Option<Image>,
+    blend_mode: BlendMode,
+    buckets: Vec<Bucket>,
+    shapes: u32,
+    stats: ImmediateStats,
+    untextured_pipeline: bool,
+}
+
+impl ImmediateBatch {
+    pub fn new(queue: Arc<Queue>, sampler: Arc<Sampler>) -> Self {
+        Self::with_white(Image::from_color(queue, sampler, Color::white()))
+    }
+
+    /// A batch sampling `white` for untextured shapes drawn without the colored program.
+    pub(crate) fn with_white(white: Image) -> Self {
+        Self {
+            white,
+            texture: None,
+            blend_mode: BlendMode::Alpha,
+            buckets: Vec::new(),
+            shapes: 0,
+            stats: ImmediateStats::default(),
+            untextured_pipeline: true,
+        }
+    }
+
+    /// Whether untextured shapes use the built-in colored program, `true` by default. Turn it
+    /// off to draw them with the batch's own shader, sampling a white texture.
+    pub fn set_untextured_pipeline(&mut self, enabled: bool) {
+        self.untextured_pipeline = enabled;
+    }
+
+    /// Whether flushing now needs the colored program for untextured shapes.
+    pub(crate) fn wants_untextured_pipeline(&self) -> bool {
+        self.untextured_pipeline && self.buckets.iter().any(|b| b.image.is_none())
+    }
+
+    /// Sets the texture used by the following shapes, `None` draws them untextured.
+    pub fn set_texture(&mut self, image: Option<Image>) {
+        self.texture = image;
+    }
+
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.blend_mode = mode;
+    }
+
+    /// Statistics from the most recent flush.
+    pub fn stats(&self) -> ImmediateStats {
+        self.stats
+    }
+
+    /// Number of shapes recorded since the last flush.
+    pub fn len(&self) -> usize {
+        self.shapes as usize
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.shapes == 0
+    }
+
+    /// Draw calls the shapes recorded since the last flush will take, one per texture and
+    /// blend mode combination.
+    pub fn batch_count(&self) -> usize {
+        self.buckets.len()
+    }
+
+    pub fn clear(&mut self) {
+        self.buckets.clear();
+        self.shapes = 0;
+    }
+
+    pub fn line(&mut self, from: (f32, f32), to: (f32, f32), width: f32, color: Color) {
+        let (dx, dy) = (to.0 - from.0, to.1 - from.1);
+        let len = (dx * dx + dy * dy).sqrt();
+        if len == 0.0 {
+            return;
+        }
+
+        let (nx, ny) = (-dy / len * width * 0.5, dx / len * width * 0.5);
+
+        self.push(
+            &[
+                (from.0 + nx, from.1 + ny),
+                (from.0 - nx, from.1 - ny),
+                (to.0 + nx, to.1 + ny),
+                (to.0 - nx, to.1 - ny),
+            ],
+            &QUAD_INDICES,
+            color,
+        );
+    }
+
+    pub fn rectangle(&mut self, rect: Rect, color: Color) {
+        self.quad(
+            [
+                (rect.x, rect.y),
+                (rect.x, rect.y + rect.h),
+                (rect.x + rect.w, rect.y),
+                (rect.x + rect.w, rect.y + rect.h),
+            ],
+            color,
+        );
+    }
+
+    /// Records a quad from its corners in [`QUAD_VERTICES`](crate::graphics::QUAD_VERTICES)
+    /// order: top left, bottom left, top right, bottom right.
+    pub(crate) fn quad(&mut self, corners: [(f32, f32); 4], color: Color) {
+        self.push(&corners, &QUAD_INDICES, color);
+    }
+
+    pub fn circle(&mut self, center: (f32, f32), radius: f32, segments: u32, color: Color) {
+        let segments = segments.max(3);
+
+        let mut points = Vec::with_capacity(segments as usize + 1);
+        points.push(center);
+        for i in 0..segments {
+            let theta = i as f32 / segments as f32 * std::f32::consts::PI * 2.0;
+            points.push((center.0 + radius * theta.cos(), center.1 + radius * theta.sin()));
+        }
+
+        let mut indices = Vec::with_capacity(segments as usize * 3);
+        for i in 0..segments {
+            indices.extend_from_slice(&[0, i + 1, (i + 1) % segments + 1]);
+        }
+
+        self.push(&points, &indices, color);
+    }
+
+    /// Records `mesh` as one shape, keeping its vertices' own colors and uvs.
+    pub fn mesh(&mut self, mesh: &Mesh) {
+        let bucket = self.bucket();
+        let base = bucket.vertices.len() as u32;
+        bucket.vertices.extend_from_slice(&mesh.vertices);
+        bucket.indices.extend(mesh.indices.iter().map(|i| base + i));
+
+        self.shapes += 1;
+    }
+
+    fn push(&mut self, points: &[(f32, f32)], indices: &[u32], color: Color) {
+        let bucket = self.bucket();
+
+        let base = bucket.vertices.len() as u32;
+        bucket.vertices.extend(points.iter().map(|p| Vertex {
+            pos: [p.0, p.1, 0.0],
+            uv: [0.0, 0.0],
+            vert_color: color.into(),
+        }));
+        bucket.indices.extend(indices.iter().map(|i| base + i));
+
+        self.shapes += 1;
+    }
+
+    /// The bucket for the current texture and blend mode, added if it's the first use.
+    fn bucket(&mut self) -> &mut Bucket {
+        let image = self.texture.clone();
+        let blend_mode = self.blend_mode;
+
+        match self.buckets.iter().position(|b| b.matches(image.as_ref(), blend_mode)) {
+            Some(i) => &mut self.buckets[i],
+            None => {
+                self.buckets.push(Bucket {
+                    image,
+                    blend_mode,
+                    vertices: Vec::new(),
+                    indices: Vec::new(),
+                });
+                self.buckets.last_mut().unwrap()
+            }
+        }
+    }
+
+    /// Records every bucket into a secondary command buffer and resets the batch.
+    ///
+    /// The shader must use a triangle list topology with [`Vertex`] and [`InstanceData`] inputs.
+    /// Untextured shapes are drawn with `colored` when given, see [`Shader::colored`](crate::graphics::shader::Shader::colored).
+    pub(crate) fn flush(
+        &mut self,
+        queue: Arc<Queue>,
+        shader_handle: &dyn ShaderHandle,
+        colored: Option<&dyn ShaderHandle>,
+        state: &DrawState,
+    ) -> Result<Option<SecondaryAutoCommandBuffer>> {
+        if self.buckets.is_empty() {
+            self.stats = ImmediateStats::default();
+            return Ok(None);
+        }
+
+        let mut builder = AutoCommandBufferBuilder::secondary_graphics(
+            queue.device().clone(),
+            queue.family(),
+            CommandBufferUsage::MultipleSubmit,
+            shader_handle.pipeline().subpass().clone(),
+        )?;
+
+        let instance_buffer = CpuAccessibleBuffer::from_iter(
+            queue.device().clone(),
+            BufferUsage::all(),
+            false,
+            [InstanceData::from(DrawInfo::default())],
+        )?;
+
+        let colored = colored.filter(|_| self.untextured_pipeline);
+        let cam_set = state.camera_set(shader_handle, queue.device().clone())?;
+        let colored_cam_set = match colored {
+            Some(colored) => state.camera_set(colored, queue.device().clone())?,
+            None => None,
+        };
+
+
+        let mut draw_calls = 0;
+        for bucket in self.buckets.drain(..) {
+            // Untextured shapes skip the texture set, and the batch shader's params with it.
+            let (handle, cam_set, image) = match (bucket.image, colored) {
+                (None, Some(colored)) => (colored, &colored_cam_set, None),
+                (image, _) => (shader_handle, &cam_set, Some(image.unwrap_or_else(|| self.white.clone()))),
+            };
+
+            let pipeline = state.pipeline_for(handle, bucket.blend_mode);
+
+            let index_count = bucket.indices.len() as u32;
+            let vertex_buffer = CpuAccessibleBuffer::from_iter(
+                queue.device().clone(),
+                BufferUsage::all(),
+                false,
+                bucket.vertices,
+            )?;
+            let index_buffer = CpuAccessibleBuffer::from_iter(
+                queue.device().clone(),
+                BufferUsage::all(),
+                false,
+                bucket.indices,
+            )?;
+
+            builder.bind_pipeline_graphics(pipeline.clone());
+            state.bind_viewport(&mut builder, &pipeline);
+            builder
+                .bind_vertex_buffers(0, (vertex_buffer, instance_buffer.clone()))
+                .bind_index_buffer(index_buffer);
+
+            if let Some(cam_set) = &cam_set {
+                builder.bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    pipeline.layout().clone(),
+                    camera::CAMERA_UNIFORM_SET as u32,
+                    cam_set.clone(),
+                );
+            }
+
+            if let Some(image) = image {
+                let set_index = handle.descriptor_set_index();
+                let set = PersistentDescriptorSet::new(
+                    handle.layout()[set_index].clone(),
+                    [WriteDescriptorSet::image_view_sampler(
+                        0,
+                        image.inner().clone(),
+                        image.sampler().clone(),
+                    )],
+                )?;
+
+                state.bind_params(&mut builder, &pipeline, queue.device().clone())?;
+
+                builder.bind_descriptor_sets(
+                    PipelineBindPoint::Graphics,
+                    pipeline.layout().clone(),
+                    set_index as u32,
+                    set,
+                );
+            }
+
+            builder.draw_indexed(index_count, 1, 0, 0, 0)?;
+
+            draw_calls += 1;
+        }
+
+        self.stats = ImmediateStats {
+            shapes: self.shapes,
+            draw_calls,
+            immediate_draws_merged: self.shapes - draw_calls,
+        };
+        self.shapes = 0;
+
+        Ok(Some(builder.build()?))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn thousand_debug_lines_take_at_most_four_draws() {
+        let (queue, sampler) = test_device();
+        let mut batch = ImmediateBatch::new(queue, sampler);
+
+        for i in 0..1000 {
+            let y = i as f32;
+            let color = if i % 2 == 0 { Color::red() } else { Color::white() };
+            batch.line((0.0, y), (100.0, y), 1.0, color);
+        }
+
+        assert_eq!(batch.len(), 1000);
+        assert!(batch.batch_count() <= 4, "{} draws", batch.batch_count());
+    }
+}
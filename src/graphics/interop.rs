@@ -0,0 +1,188 @@
+//! Boundary conversions for apps whose own math isn't cgmath.
+//!
+//! With the `mint` feature the crate's `Transform`, `Rect`, and `DrawInfo` convert to and from
+//! `mint` types, and positions can be handed to [`DrawInfo::dest_point`] and
+//! [`Camera2D::follow_point`] as anything that converts into a `mint` point. The `glam` feature
+//! builds on this, so `glam` vectors can be passed straight to those methods, and adds direct
+//! `From` impls for the common `glam` types. Internally everything stays cgmath. Without the
+//! `graphics` feature only the `Transform` and `Rect` conversions are built.
+
+use cgmath::Matrix4;
+
+#[cfg(feature = "graphics")]
+use crate::graphics::camera::Camera2D;
+#[cfg(feature = "graphics")]
+use crate::graphics::DrawInfo;
+use crate::graphics::{Rect, Transform};
+
+#[cfg(feature = "graphics")]
+impl From<mint::Point2<f32>> for DrawInfo {
+    fn from(pos: mint::Point2<f32>) -> DrawInfo {
+        DrawInfo::from((pos.x, pos.y, 0.0))
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl From<mint::Vector3<f32>> for DrawInfo {
+    fn from(pos: mint::Vector3<f32>) -> DrawInfo {
+        DrawInfo::from((pos.x, pos.y, pos.z))
+    }
+}
+
+impl From<mint::ColumnMatrix4<f32>> for Transform {
+    fn from(matrix: mint::ColumnMatrix4<f32>) -> Transform {
+        Transform::Matrix(Matrix4::from(matrix))
+    }
+}
+
+impl From<Transform> for mint::ColumnMatrix4<f32> {
+    fn from(transform: Transform) -> mint::ColumnMatrix4<f32> {
+        transform.as_mat4().into()
+    }
+}
+
+/// A rect from its top left corner and its size.
+impl From<(mint::Point2<f32>, mint::Vector2<f32>)> for Rect {
+    fn from((pos, size): (mint::Point2<f32>, mint::Vector2<f32>)) -> Rect {
+        Rect {
+            x: pos.x,
+            y: pos.y,
+            w: size.x,
+            h: size.y,
+        }
+    }
+}
+
+impl From<Rect> for (mint::Point2<f32>, mint::Vector2<f32>) {
+    fn from(rect: Rect) -> Self {
+        (
+            mint::Point2 { x: rect.x, y: rect.y },
+            mint::Vector2 { x: rect.w, y: rect.h },
+        )
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl DrawInfo {
+    /// Same as [`DrawInfo::dest`] with a 2D point, leaving z at `0.0`.
+    pub fn dest_point(&mut self, pos: impl Into<mint::Point2<f32>>) {
+        let pos = pos.into();
+        self.dest(pos.x, pos.y, 0.0);
+    }
+}
+
+#[cfg(feature = "graphics")]
+impl Camera2D {
+    /// Same as [`Camera2D::follow`].
+    pub fn follow_point(&mut self, target: impl Into<mint::Point2<f32>>) {
+        let target = target.into();
+        self.follow((target.x, target.y));
+    }
+
+    pub fn position_point(&self) -> mint::Point2<f32> {
+        let (x, y) = self.position();
+        mint::Point2 { x, y }
+    }
+}
+
+#[cfg(feature = "glam")]
+mod glam_impls {
+    use super::*;
+
+    #[cfg(feature = "graphics")]
+    impl From<glam::Vec2> for DrawInfo {
+        fn from(pos: glam::Vec2) -> DrawInfo {
+            DrawInfo::from((pos.x, pos.y, 0.0))
+        }
+    }
+
+    #[cfg(feature = "graphics")]
+    impl From<glam::Vec3> for DrawInfo {
+        fn from(pos: glam::Vec3) -> DrawInfo {
+            DrawInfo::from((pos.x, pos.y, pos.z))
+        }
+    }
+
+    impl From<glam::Mat4> for Transform {
+        fn from(matrix: glam::Mat4) -> Transform {
+            Transform::Matrix(Matrix4::from(matrix.to_cols_array_2d()))
+        }
+    }
+
+    impl From<Transform> for glam::Mat4 {
+        fn from(transform: Transform) -> glam::Mat4 {
+            glam::Mat4::from_cols_array_2d(&transform.as_mat4().into())
+        }
+    }
+
+    /// A rect from its top left corner and its size.
+    impl From<(glam::Vec2, glam::Vec2)> for Rect {
+        fn from((pos, size): (glam::Vec2, glam::Vec2)) -> Rect {
+            Rect {
+                x: pos.x,
+                y: pos.y,
+                w: size.x,
+                h: size.y,
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use cgmath::{Rad, Vector2};
+
+    fn transform() -> Transform {
+        Transform::from_trs(Vector2::new(10.0, 20.0), Rad(0.5), Vector2::new(2.0, 3.0))
+    }
+
+    #[test]
+    fn transforms_round_trip_through_mint() {
+        let matrix = mint::ColumnMatrix4::from(transform());
+        assert_eq!(Transform::from(matrix).as_mat4(), transform().as_mat4());
+    }
+
+    #[test]
+    fn rects_round_trip_through_mint() {
+        let rect = Rect { x: 1.0, y: 2.0, w: 3.0, h: 4.0 };
+        let (pos, size): (mint::Point2<f32>, mint::Vector2<f32>) = rect.into();
+        assert_eq!(Rect::from((pos, size)), rect);
+    }
+
+    #[cfg(feature = "graphics")]
+    #[test]
+    fn mint_positions_match_tuples() {
+        assert_eq!(DrawInfo::from(mint::Point2 { x: 1.0, y: 2.0 }), DrawInfo::from((1.0, 2.0)));
+        assert_eq!(DrawInfo::from(mint::Vector3 { x: 1.0, y: 2.0, z: 3.0 }), DrawInfo::from((1.0, 2.0, 3.0)));
+
+        let mut info = DrawInfo::default();
+        info.dest_point(mint::Point2 { x: 1.0, y: 2.0 });
+        assert_eq!(info, DrawInfo::from((1.0, 2.0)));
+
+        let mut camera = Camera2D::default();
+        camera.follow_point(mint::Point2 { x: 1.0, y: 2.0 });
+        assert_eq!(camera.position_point(), mint::Point2 { x: 1.0, y: 2.0 });
+    }
+
+    #[cfg(feature = "glam")]
+    #[test]
+    fn transforms_and_positions_round_trip_through_glam() {
+        let matrix = glam::Mat4::from(transform());
+        assert_eq!(Transform::from(matrix).as_mat4(), transform().as_mat4());
+
+        let rect = Rect::from((glam::Vec2::new(1.0, 2.0), glam::Vec2::new(3.0, 4.0)));
+        assert_eq!(rect, Rect { x: 1.0, y: 2.0, w: 3.0, h: 4.0 });
+    }
+
+    #[cfg(all(feature = "glam", feature = "graphics"))]
+    #[test]
+    fn glam_positions_match_tuples() {
+        assert_eq!(DrawInfo::from(glam::Vec2::new(1.0, 2.0)), DrawInfo::from((1.0, 2.0)));
+        assert_eq!(DrawInfo::from(glam::Vec3::new(1.0, 2.0, 3.0)), DrawInfo::from((1.0, 2.0, 3.0)));
+
+        let mut camera = Camera2D::default();
+        camera.follow_point(glam::Vec2::new(1.0, 2.0));
+        assert_eq!(camera.position(), (1.0, 2.0));
+    }
+}
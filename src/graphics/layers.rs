@@ -0,0 +1,98 @@
+//! Named sorting layers, the way artists usually think about draw order.
+//!
+//! Every [`DrawInfo`](crate::graphics::DrawInfo) has a layer and an order within it. Queued
+//! draws such as [`SpriteRenderer`](crate::graphics::sprite::SpriteRenderer) draw lower layers
+//! first, then lower orders within a layer, whatever order they were submitted in, so the UI
+//! layer always ends up over the world. Draws recorded directly with
+//! [`Pass::draw`](crate::graphics::render_pass::frame::Pass::draw) go down in the order
+//! they're made and ignore their layer.
+//!
+//! Layers are plain numbers, registering a name just saves repeating them:
+//!
+//! ```ignore
+//! graphics::register_layer("background", -10);
+//! info.set_layer_named("background");
+//! ```
+//!
+//! The registry is shared by the whole process.
+
+use std::sync::RwLock;
+
+/// Layer of draws that don't set one, registered as `"default"`.
+pub const DEFAULT_LAYER: i16 = 0;
+/// Layer for interface elements drawn over the world, registered as `"ui"`.
+pub const UI_LAYER: i16 = 1000;
+
+static LAYERS: RwLock<Vec<(String, i16)>> = RwLock::new(Vec::new());
+
+/// Names `layer` as `name`, replacing any layer previously registered under the same name,
+/// including the built-in `"default"` and `"ui"`.
+pub fn register_layer(name: &str, layer: i16) {
+    let mut layers = LAYERS.write().unwrap();
+    match layers.iter_mut().find(|(n, _)| n == name) {
+        Some(entry) => entry.1 = layer,
+        None => layers.push((name.to_string(), layer)),
+    }
+}
+
+/// The layer registered as `name`, if any.
+pub fn layer(name: &str) -> Option<i16> {
+    let registered = LAYERS
+        .read()
+        .unwrap()
+        .iter()
+        .find(|(n, _)| n == name)
+        .map(|(_, layer)| *layer);
+
+    registered.or(match name {
+        "default" => Some(DEFAULT_LAYER),
+        "ui" => Some(UI_LAYER),
+        _ => None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::image::Image;
+    use crate::graphics::sprite::SpriteRenderer;
+    use crate::graphics::{pixel_at, render_offscreen, test_device, test_pass, Color, DrawInfo};
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn queued_sprites_draw_by_layer_then_order_whatever_the_submission_order() {
+        let (queue, sampler) = test_device();
+        let [red, green, blue] = [Color::red(), Color::rgba(0, 255, 0, 255), Color::rgba(0, 0, 255, 255)]
+            .map(|color| Image::from_color(queue.clone(), sampler.clone(), color));
+        register_layer("layers test background", -10);
+
+        // Each sprite covers one pixel of a 3x1 target, and `(layer, order)` decides which of
+        // the sprites sharing a pixel ends up on top.
+        let sprite = |x: f32, layer: &str, order: i16| {
+            let mut info = DrawInfo::centered((x + 0.5, 0.5), (1.0, 1.0), 0.0, Color::white());
+            assert!(info.set_layer_named(layer));
+            info.set_order(order);
+            info
+        };
+        let submissions = [
+            (&blue, sprite(0.0, "ui", 0)),
+            (&green, sprite(1.0, "default", 0)),
+            (&green, sprite(2.0, "default", 1)),
+            (&red, sprite(0.0, "layers test background", 5)),
+            (&red, sprite(2.0, "default", 0)),
+            (&red, sprite(1.0, "layers test background", 0)),
+        ];
+
+        let mut sprites = SpriteRenderer::new();
+        for (image, info) in submissions {
+            sprites.draw(image, info);
+        }
+
+        let mut pass = test_pass(queue.clone());
+        let pixels = render_offscreen(&queue, &mut pass, [3, 1], Color::black(), |pass| sprites.flush(pass, 0));
+
+        assert_eq!(pixel_at(&pixels, 3, (0, 0)), [0, 0, 255, 255], "ui over the background");
+        assert_eq!(pixel_at(&pixels, 3, (1, 0)), [0, 255, 0, 255], "default over the background");
+        assert_eq!(pixel_at(&pixels, 3, (2, 0)), [0, 255, 0, 255], "higher order within a layer");
+    }
+}
@@ -0,0 +1,674 @@
+//! Simple 2D lighting: lights are drawn additively into an offscreen light map, which the
+//! finished scene is then multiplied by.
+//!
+//! A frame with lighting looks like:
+//!
+//! ```ignore
+//! lights.begin();
+//! lights.add_light((x, y), 0.5, Color::rgba(255, 200, 120, 255), 1.5);
+//! let lit = lights.end(scene_future, camera.as_mvp(), renderer.viewport())?;
+//! let after_future = lights.composite(lit, renderer.final_image())?;
+//! ```
+//!
+//! Lights are given in world units and drawn with the same camera as the scene, so they stay
+//! attached to the world as the camera moves. Anything no light reaches is lit by the ambient
+//! color.
+//!
+//! Occluders added with [`LightMap::add_occluder`] cast hard shadows: for every light, each
+//! occluder segment within its radius is extruded away from it into a [`shadow_mesh`], and the
+//! light isn't added where that mesh covers. Occluders stay until
+//! [`LightMap::clear_occluders`], so static level geometry only needs adding once.
+
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{
+    AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer, SubpassContents,
+};
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::{view::ImageView, AttachmentImage, ImageAccess, ImageUsage};
+use vulkano::pipeline::graphics::color_blend::{
+    AttachmentBlend, BlendFactor, BlendOp, ColorBlendState, ColorComponents,
+};
+use vulkano::pipeline::graphics::depth_stencil::{
+    CompareOp, DepthStencilState, StencilOp, StencilOpState, StencilOps, StencilState,
+};
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint, StateMode};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
+use vulkano::sampler::{Filter, SamplerAddressMode, SamplerCreateInfo};
+
+use crate::graphics::mesh::Mesh;
+use crate::graphics::renderer::Renderer;
+use crate::graphics::*;
+
+mod light_vs {
+    vulkano_shaders::shader! { ty: "vertex", path: "src/graphics/shaders/light.vert", }
+}
+
+mod light_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/light.frag", }
+}
+
+mod shadow_vs {
+    vulkano_shaders::shader! { ty: "vertex", path: "src/graphics/shaders/shadow.vert", }
+}
+
+mod shadow_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/shadow.frag", }
+}
+
+mod composite_vs {
+    vulkano_shaders::shader! { ty: "vertex", path: "src/graphics/shaders/fullscreen.vert", }
+}
+
+mod composite_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/light_composite.frag", }
+}
+
+/// Format of the light map, which only needs color and holds values above 1 where lights overlap.
+const LIGHT_MAP_FORMAT: Format = Format::R16G16B16A16_SFLOAT;
+
+/// Stencil formats tried in order for the shadow stencil, every device supports at least one.
+const STENCIL_FORMATS: [Format; 3] = [
+    Format::D24_UNORM_S8_UINT,
+    Format::D32_SFLOAT_S8_UINT,
+    Format::D16_UNORM_S8_UINT,
+];
+
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+struct LightInstance {
+    center: [f32; 2],
+    radius: f32,
+    color: [f32; 4],
+}
+
+vulkano::impl_vertex!(LightInstance, center, radius, color);
+
+/// An offscreen light map the scene is multiplied by, see the [module docs](self).
+pub struct LightMap {
+    queue: Arc<Queue>,
+    resolution_scale: f32,
+    ambient: Color,
+    lights: Vec<LightInstance>,
+    occluders: Vec<[(f32, f32); 2]>,
+    light_pass: Arc<RenderPass>,
+    light_pipeline: Arc<GraphicsPipeline>,
+    /// Marks a light's shadows in the stencil.
+    shadow_pipeline: Arc<GraphicsPipeline>,
+    /// Clears the marks made by `shadow_pipeline` once the light is drawn.
+    unshadow_pipeline: Arc<GraphicsPipeline>,
+    stencil_format: Format,
+    composite_pass: Arc<RenderPass>,
+    composite_pipeline: Arc<GraphicsPipeline>,
+    sampler: Arc<Sampler>,
+    image: Option<LightMapImages>,
+}
+
+struct LightMapImages {
+    extent: [u32; 2],
+    light: Arc<ImageView<AttachmentImage>>,
+    stencil: Arc<ImageView<AttachmentImage>>,
+}
+
+impl LightMap {
+    /// `resolution_scale` is the size of the light map relative to the viewport. Values below
+    /// 1 save fill rate and soften the lights, since the light map is stretched over the scene
+    /// with linear filtering.
+    pub fn new(ctx: &Renderer, resolution_scale: f32) -> Result<Self> {
+        let device = ctx.device.clone();
+
+        let stencil_format = STENCIL_FORMATS
+            .into_iter()
+            .find(|format| {
+                device
+                    .physical_device()
+                    .format_properties(*format)
+                    .optimal_tiling_features
+                    .depth_stencil_attachment
+            })
+            .ok_or_else(|| anyhow::anyhow!("no supported stencil format for light map shadows"))?;
+
+        let light_pass = vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                light: {
+                    load: Clear,
+                    store: Store,
+                    format: LIGHT_MAP_FORMAT,
+                    samples: 1,
+                },
+                stencil: {
+                    load: Clear,
+                    store: DontCare,
+                    format: stencil_format,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [light],
+                depth_stencil: {stencil}
+            }
+        )?;
+
+        let composite_pass = vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                scene: {
+                    load: Load,
+                    store: Store,
+                    format: ctx.output_format(),
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [scene],
+                depth_stencil: {}
+            }
+        )?;
+
+        let light_vs = light_vs::load(device.clone())?;
+        let light_fs = light_fs::load(device.clone())?;
+        let light_pipeline = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new().instance::<LightInstance>())
+            .vertex_shader(light_vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new().topology(PrimitiveTopology::TriangleStrip))
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(light_fs.entry_point("main").unwrap(), ())
+            .color_blend_state(BlendMode::Add.into())
+            .depth_stencil_state(stencil_test(CompareOp::NotEqual, StencilOp::Keep))
+            .render_pass(Subpass::from(light_pass.clone(), 0).unwrap())
+            .build(device.clone())?;
+
+        let mut no_color = ColorBlendState::new(1);
+        no_color.attachments[0].color_write_mask = ColorComponents::none();
+
+        let shadow_vs = shadow_vs::load(device.clone())?;
+        let shadow_fs = shadow_fs::load(device.clone())?;
+        let shadow_pipeline = |pass_op| {
+            GraphicsPipeline::start()
+                .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>())
+                .vertex_shader(shadow_vs.entry_point("main").unwrap(), ())
+                .input_assembly_state(InputAssemblyState::new())
+                .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+                .fragment_shader(shadow_fs.entry_point("main").unwrap(), ())
+                .color_blend_state(no_color.clone())
+                .depth_stencil_state(stencil_test(CompareOp::Always, pass_op))
+                .render_pass(Subpass::from(light_pass.clone(), 0).unwrap())
+                .build(device.clone())
+        };
+        let unshadow_pipeline = shadow_pipeline(StencilOp::Zero)?;
+        let shadow_pipeline = shadow_pipeline(StencilOp::Replace)?;
+
+        let multiply = AttachmentBlend {
+            color_op: BlendOp::Add,
+            color_source: BlendFactor::DstColor,
+            color_destination: BlendFactor::Zero,
+            alpha_op: BlendOp::Add,
+            alpha_source: BlendFactor::Zero,
+            alpha_destination: BlendFactor::One,
+        };
+
+        let composite_vs = composite_vs::load(device.clone())?;
+        let composite_fs = composite_fs::load(device.clone())?;
+        let composite_pipeline = GraphicsPipeline::start()
+            .vertex_input_state(BuffersDefinition::new())
+            .vertex_shader(composite_vs.entry_point("main").unwrap(), ())
+            .input_assembly_state(InputAssemblyState::new())
+            .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+            .fragment_shader(composite_fs.entry_point("main").unwrap(), ())
+            .color_blend_state(ColorBlendState::new(1).blend(multiply))
+            .render_pass(Subpass::from(composite_pass.clone(), 0).unwrap())
+            .build(device.clone())?;
+
+        let sampler = Sampler::new(
+            device,
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )?;
+
+        Ok(Self {
+            queue: ctx.queue.clone(),
+            resolution_scale: resolution_scale.max(f32::EPSILON),
+            ambient: Color::black(),
+            lights: Vec::new(),
+            occluders: Vec::new(),
+            light_pass,
+            light_pipeline,
+            shadow_pipeline,
+            unshadow_pipeline,
+            stencil_format,
+            composite_pass,
+            composite_pipeline,
+            sampler,
+            image: None,
+        })
+    }
+
+    /// The light everything receives, black by default so only lit areas are visible.
+    pub fn set_ambient(&mut self, color: Color) {
+        self.ambient = color;
+    }
+
+    pub fn ambient(&self) -> Color {
+        self.ambient
+    }
+
+    /// Starts a new light map, dropping the previous frame's lights.
+    pub fn begin(&mut self) {
+        self.lights.clear();
+    }
+
+    /// A light at `pos` in world units, fading from `color * intensity` at its center to nothing
+    /// at `radius`.
+    pub fn add_light(&mut self, pos: (f32, f32), radius: f32, color: Color, intensity: f32) {
+        let [r, g, b, a]: [f32; 4] = color.into();
+        self.lights.push(LightInstance {
+            center: [pos.0, pos.1],
+            radius,
+            color: [r * intensity, g * intensity, b * intensity, a],
+        });
+    }
+
+    /// Adds the line strip through `points` as shadow casting segments, in world units.
+    ///
+    /// Repeat the first point at the end to close a shape. Occluders are kept across frames,
+    /// unlike lights.
+    pub fn add_occluder(&mut self, points: &[(f32, f32)]) {
+        self.occluders.extend(points.windows(2).map(|pair| [pair[0], pair[1]]));
+    }
+
+    pub fn clear_occluders(&mut self) {
+        self.occluders.clear();
+    }
+
+    /// Number of occluder segments added since the last [`LightMap::clear_occluders`].
+    pub fn occluder_count(&self) -> usize {
+        self.occluders.len()
+    }
+
+    /// Number of lights added since [`LightMap::begin`].
+    pub fn len(&self) -> usize {
+        self.lights.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.lights.is_empty()
+    }
+
+    /// Renders the lights added since [`LightMap::begin`] through `camera`, the scene's
+    /// view-projection matrix, into a light map sized to `viewport` times the resolution scale.
+    pub fn end(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        camera: [[f32; 4]; 4],
+        viewport: Rect,
+    ) -> Result<Box<dyn GpuFuture>> {
+        let (image, stencil) = self.light_map(viewport)?;
+        let [w, h] = image.image().dimensions().width_height();
+
+        let framebuffer = Framebuffer::new(
+            self.light_pass.clone(),
+            FramebufferCreateInfo {
+                attachments: vec![image, stencil],
+                ..Default::default()
+            },
+        )?;
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.queue.device().clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        builder.begin_render_pass(
+            framebuffer,
+            SubpassContents::Inline,
+            vec![ClearValue::Float(self.ambient.into()), ClearValue::DepthStencil((1.0, 0))],
+        )?;
+
+        if !self.lights.is_empty() {
+            let instances = CpuAccessibleBuffer::from_iter(
+                self.queue.device().clone(),
+                BufferUsage::vertex_buffer(),
+                false,
+                self.lights.iter().copied(),
+            )?;
+
+            builder.set_viewport(0, vec![full_viewport(w, h)]);
+
+            let shadows = self.shadow_meshes();
+            if shadows.iter().all(|(_, mesh)| mesh.indices.is_empty()) {
+                builder
+                    .bind_pipeline_graphics(self.light_pipeline.clone())
+                    .push_constants(self.light_pipeline.layout().clone(), 0, camera)
+                    .bind_vertex_buffers(0, instances)
+                    .draw(4, self.lights.len() as u32, 0, 0)?;
+            } else {
+                self.record_shadowed(&mut builder, camera, instances, shadows)?;
+            }
+        }
+
+        builder.end_render_pass()?;
+
+        let commands = builder.build()?;
+        Ok(before_future.then_execute(self.queue.clone(), commands)?.boxed())
+    }
+
+    /// Each light's shadow mesh, with the offset of its first index and vertex once every mesh
+    /// is packed into one buffer.
+    fn shadow_meshes(&self) -> Vec<((u32, i32), Mesh)> {
+        let mut first = (0, 0);
+        self.lights
+            .iter()
+            .map(|light| {
+                let mesh = shadow_mesh((light.center[0], light.center[1]), light.radius, &self.occluders);
+                let offsets = first;
+                first = (first.0 + mesh.index_count(), first.1 + mesh.vertex_count() as i32);
+                (offsets, mesh)
+            })
+            .collect()
+    }
+
+    /// Draws the lights one at a time, each masked by its shadows in the stencil, which is
+    /// reset to zero again before the next light.
+    fn record_shadowed(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+        camera: [[f32; 4]; 4],
+        instances: Arc<CpuAccessibleBuffer<[LightInstance]>>,
+        shadows: Vec<((u32, i32), Mesh)>,
+    ) -> Result<()> {
+        let device = self.queue.device().clone();
+        let vertices = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            shadows.iter().flat_map(|(_, mesh)| mesh.vertices.iter().copied()).collect::<Vec<_>>(),
+        )?;
+        let indices = CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage::index_buffer(),
+            false,
+            shadows.iter().flat_map(|(_, mesh)| mesh.indices.iter().copied()).collect::<Vec<_>>(),
+        )?;
+
+        builder.bind_index_buffer(indices);
+
+        for (i, ((first_index, vertex_offset), mesh)) in shadows.iter().enumerate() {
+            let index_count = mesh.index_count();
+
+            if index_count > 0 {
+                builder
+                    .bind_pipeline_graphics(self.shadow_pipeline.clone())
+                    .push_constants(self.shadow_pipeline.layout().clone(), 0, camera)
+                    .bind_vertex_buffers(0, vertices.clone())
+                    .draw_indexed(index_count, 1, *first_index, *vertex_offset, 0)?;
+            }
+
+            builder
+                .bind_pipeline_graphics(self.light_pipeline.clone())
+                .push_constants(self.light_pipeline.layout().clone(), 0, camera)
+                .bind_vertex_buffers(0, instances.clone())
+                .draw(4, 1, 0, i as u32)?;
+
+            if index_count > 0 {
+                builder
+                    .bind_pipeline_graphics(self.unshadow_pipeline.clone())
+                    .push_constants(self.unshadow_pipeline.layout().clone(), 0, camera)
+                    .bind_vertex_buffers(0, vertices.clone())
+                    .draw_indexed(index_count, 1, *first_index, *vertex_offset, 0)?;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Multiplies `target`, which already holds the rendered scene, by the light map rendered
+    /// by [`LightMap::end`].
+    pub fn composite(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        target: Arc<dyn ImageViewAbstract>,
+    ) -> Result<Box<dyn GpuFuture>> {
+        let light_map = match &self.image {
+            Some(images) => images.light.clone(),
+            None => return Err(anyhow::anyhow!("no light map rendered, call LightMap::end first")),
+        };
+        let [w, h] = target.image().dimensions().width_height();
+
+        let framebuffer = Framebuffer::new(
+            self.composite_pass.clone(),
+            FramebufferCreateInfo {
+                attachments: vec![target],
+                ..Default::default()
+            },
+        )?;
+
+        let set = PersistentDescriptorSet::new(
+            self.composite_pipeline.layout().set_layouts()[0].clone(),
+            [WriteDescriptorSet::image_view_sampler(0, light_map, self.sampler.clone())],
+        )?;
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.queue.device().clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        builder
+            .begin_render_pass(framebuffer, SubpassContents::Inline, vec![ClearValue::None])?
+            .set_viewport(0, vec![full_viewport(w, h)])
+            .bind_pipeline_graphics(self.composite_pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                self.composite_pipeline.layout().clone(),
+                0,
+                set,
+            )
+            .draw(3, 1, 0, 0)?
+            .end_render_pass()?;
+
+        let commands = builder.build()?;
+        Ok(before_future.then_execute(self.queue.clone(), commands)?.boxed())
+    }
+
+    /// The light map and its shadow stencil for `viewport`, reallocated when its scaled size
+    /// changes.
+    #[allow(clippy::type_complexity)]
+    fn light_map(&mut self, viewport: Rect) -> Result<(Arc<ImageView<AttachmentImage>>, Arc<ImageView<AttachmentImage>>)> {
+        let extent = [
+            ((viewport.w * self.resolution_scale) as u32).max(1),
+            ((viewport.h * self.resolution_scale) as u32).max(1),
+        ];
+
+        match &self.image {
+            Some(images) if images.extent == extent => Ok((images.light.clone(), images.stencil.clone())),
+            _ => {
+                let image = AttachmentImage::with_usage(
+                    self.queue.device().clone(),
+                    extent,
+                    LIGHT_MAP_FORMAT,
+                    ImageUsage {
+                        sampled: true,
+                        color_attachment: true,
+                        ..ImageUsage::none()
+                    },
+                )?;
+                let light = ImageView::new_default(image)?;
+
+                let stencil = AttachmentImage::transient(self.queue.device().clone(), extent, self.stencil_format)?;
+                let stencil = ImageView::new_default(stencil)?;
+
+                self.image = Some(LightMapImages {
+                    extent,
+                    light: light.clone(),
+                    stencil: stencil.clone(),
+                });
+                Ok((light, stencil))
+            }
+        }
+    }
+}
+
+/// The shadows `segments` cast from a light at `light` reaching `radius`, as a triangle list in
+/// the same units.
+///
+/// Each segment is extruded away from the light far enough to cover everything behind it
+/// within the radius. Segments out of reach, of zero length, or in line with the light cast
+/// nothing, so a light sitting exactly on an occluder isn't blocked by it.
+pub fn shadow_mesh(light: (f32, f32), radius: f32, segments: &[[(f32, f32); 2]]) -> Mesh {
+    let mut mesh = Mesh::default();
+
+    for [a, b] in segments.iter().copied() {
+        let da = (a.0 - light.0, a.1 - light.1);
+        let db = (b.0 - light.0, b.1 - light.1);
+        let (ab_x, ab_y) = (b.0 - a.0, b.1 - a.1);
+        let len_sq = ab_x * ab_x + ab_y * ab_y;
+        if len_sq <= f32::EPSILON {
+            continue;
+        }
+
+        // Closest point on the segment to the light, to skip segments out of reach.
+        let t = (-(da.0 * ab_x + da.1 * ab_y) / len_sq).clamp(0.0, 1.0);
+        let closest = (da.0 + ab_x * t, da.1 + ab_y * t);
+        if closest.0 * closest.0 + closest.1 * closest.1 >= radius * radius {
+            continue;
+        }
+
+        let (len_a, len_b) = (length(da), length(db));
+        let cross = da.0 * db.1 - da.1 * db.0;
+        if cross.abs() <= 1e-6 * len_a * len_b {
+            continue;
+        }
+
+        // The shadow is the wedge behind the segment. It's cut off by two chords through a far
+        // point on each edge and one on the bisector, which stay outside the radius as long as
+        // the far points are at least radius / cos(45 degrees) away.
+        let far = 2.0 * radius.max(len_a).max(len_b);
+        let dir_a = (da.0 / len_a, da.1 / len_a);
+        let dir_b = (db.0 / len_b, db.1 / len_b);
+        let mid = (dir_a.0 + dir_b.0, dir_a.1 + dir_b.1);
+        let dir_m = (mid.0 / length(mid), mid.1 / length(mid));
+
+        let base = mesh.vertex_count();
+        for (x, y) in [
+            a,
+            b,
+            (light.0 + dir_b.0 * far, light.1 + dir_b.1 * far),
+            (light.0 + dir_m.0 * far, light.1 + dir_m.1 * far),
+            (light.0 + dir_a.0 * far, light.1 + dir_a.1 * far),
+        ] {
+            mesh.vertices.push(Vertex {
+                pos: [x, y, 0.0],
+                uv: [0.0, 0.0],
+                vert_color: [1.0; 4],
+            });
+        }
+        mesh.indices.extend_from_slice(&[base, base + 1, base + 2, base, base + 2, base + 3, base, base + 3, base + 4]);
+    }
+
+    mesh
+}
+
+fn length(v: (f32, f32)) -> f32 {
+    (v.0 * v.0 + v.1 * v.1).sqrt()
+}
+
+/// A stencil that passes where `compare` holds against a reference of 1 and applies `pass_op`.
+fn stencil_test(compare: CompareOp, pass_op: StencilOp) -> DepthStencilState {
+    let face = StencilOpState {
+        ops: StateMode::Fixed(StencilOps {
+            fail_op: StencilOp::Keep,
+            pass_op,
+            depth_fail_op: StencilOp::Keep,
+            compare_op: compare,
+        }),
+        compare_mask: StateMode::Fixed(0xff),
+        write_mask: StateMode::Fixed(0xff),
+        reference: StateMode::Fixed(1),
+    };
+
+    DepthStencilState {
+        depth: None,
+        depth_bounds: None,
+        stencil: Some(StencilState {
+            enable_dynamic: false,
+            front: face,
+            back: face,
+        }),
+    }
+}
+
+fn full_viewport(w: u32, h: u32) -> Viewport {
+    Viewport {
+        origin: [0.0, 0.0],
+        dimensions: [w as f32, h as f32],
+        depth_range: 0.0..1.0,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Whether `p` is inside any triangle of `mesh`.
+    fn shadowed(mesh: &Mesh, p: (f32, f32)) -> bool {
+        mesh.indices.chunks(3).any(|triangle| {
+            let [a, b, c] = [0, 1, 2].map(|i| mesh.vertices[triangle[i] as usize].pos);
+            let side = |u: [f32; 3], v: [f32; 3]| (v[0] - u[0]) * (p.1 - u[1]) - (v[1] - u[1]) * (p.0 - u[0]);
+            let (ab, bc, ca) = (side(a, b), side(b, c), side(c, a));
+            (ab >= 0.0 && bc >= 0.0 && ca >= 0.0) || (ab <= 0.0 && bc <= 0.0 && ca <= 0.0)
+        })
+    }
+
+    #[test]
+    fn segment_shadows_what_is_behind_it() {
+        let mesh = shadow_mesh((0.0, 0.0), 10.0, &[[(2.0, -1.0), (2.0, 1.0)]]);
+        assert_eq!((mesh.vertex_count(), mesh.index_count()), (5, 9));
+
+        assert!(shadowed(&mesh, (5.0, 0.0)));
+        assert!(shadowed(&mesh, (8.0, 3.0)));
+        assert!(!shadowed(&mesh, (1.0, 0.0)));
+        assert!(!shadowed(&mesh, (5.0, 5.0)));
+        assert!(!shadowed(&mesh, (-5.0, 0.0)));
+    }
+
+    #[test]
+    fn segment_behind_the_light_shadows_away_from_it() {
+        let mesh = shadow_mesh((0.0, 0.0), 10.0, &[[(-2.0, 1.0), (-2.0, -1.0)]]);
+
+        assert!(shadowed(&mesh, (-5.0, 0.0)));
+        assert!(!shadowed(&mesh, (5.0, 0.0)));
+    }
+
+    #[test]
+    fn unreachable_and_degenerate_segments_cast_nothing() {
+        let segments = [
+            // Out of reach.
+            [(20.0, -1.0), (20.0, 1.0)],
+            // Zero length.
+            [(2.0, 2.0), (2.0, 2.0)],
+            // In line with the light.
+            [(1.0, 1.0), (3.0, 3.0)],
+            // Through the light.
+            [(-1.0, 0.0), (1.0, 0.0)],
+        ];
+
+        assert_eq!(shadow_mesh((0.0, 0.0), 10.0, &segments).vertex_count(), 0);
+    }
+
+    #[test]
+    fn each_segment_adds_its_own_wedge() {
+        let mesh = shadow_mesh((0.0, 0.0), 10.0, &[[(2.0, -1.0), (2.0, 1.0)], [(-1.0, 3.0), (1.0, 3.0)]]);
+        assert_eq!((mesh.vertex_count(), mesh.index_count()), (10, 18));
+
+        assert!(shadowed(&mesh, (5.0, 0.0)));
+        assert!(shadowed(&mesh, (0.0, 6.0)));
+        assert!(mesh.indices[9..].iter().all(|i| *i >= 5));
+    }
+}
@@ -0,0 +1,401 @@
+//! Masks that limit where sprites show to the alpha of another sprite, e.g. a spotlight or
+//! keyhole reveal.
+//!
+//! A [`SpriteMask`] is rendered into its own single channel target before the pass that uses
+//! it, then switched on for the draws it should cover:
+//!
+//! ```ignore
+//! let mut graph = FrameGraph::new(before_future);
+//! graph.then(|future| keyhole.render(future, &keyhole_image, info, camera, renderer.viewport()))?;
+//! graph.render_to_screen(renderer, 0, clear, |pass| {
+//!     keyhole.begin(pass)?;
+//!     pass.draw_with(batch.clone(), shader, DrawInfo::default())?;
+//!     keyhole.end(pass);
+//!     Ok(())
+//! })?;
+//! ```
+//!
+//! While a mask is on, images and sprite batches are drawn with a built-in variant of the
+//! sprite shader that multiplies their alpha by the mask, whatever shader the draw asked for.
+//! Batches with a normal map keep their lit shader and aren't masked. An inverted mask shows
+//! everything except the sprite's shape.
+//!
+//! The mask is sampled by the draws recorded with it, so a frame that needs several masks
+//! uses one [`SpriteMask`] for each, turning them on one after another.
+
+use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+use vulkano::command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, SubpassContents};
+use vulkano::descriptor_set::PersistentDescriptorSet;
+use vulkano::format::{ClearValue, Format};
+use vulkano::image::{view::ImageView, AttachmentImage, ImageAccess, ImageUsage};
+use vulkano::pipeline::graphics::color_blend::{AttachmentBlend, BlendFactor, BlendOp, ColorBlendState};
+use vulkano::pipeline::graphics::input_assembly::{InputAssemblyState, PrimitiveTopology};
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+use vulkano::pipeline::graphics::viewport::{Viewport, ViewportState};
+use vulkano::pipeline::{GraphicsPipeline, Pipeline, PipelineBindPoint};
+use vulkano::render_pass::{Framebuffer, FramebufferCreateInfo, RenderPass, Subpass};
+use vulkano::sampler::{Filter, SamplerAddressMode, SamplerCreateInfo};
+
+use crate::graphics::render_pass::frame::Pass;
+use crate::graphics::renderer::Renderer;
+use crate::graphics::shader::{ShaderProgram, VertexTopology};
+use crate::graphics::*;
+
+mod mask_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/mask.frag", }
+}
+
+mod masked_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/masked.frag", }
+}
+
+/// Format of the mask, which only needs one channel of coverage.
+const MASK_FORMAT: Format = Format::R8_UNORM;
+
+/// A sprite's alpha rendered into a target that later draws are clipped by, see the
+/// [module docs](self).
+pub struct SpriteMask {
+    queue: Arc<Queue>,
+    inverted: bool,
+    mask_pass: Arc<RenderPass>,
+    /// Keeps the most covered value where mask sprites overlap.
+    pipeline: Arc<GraphicsPipeline>,
+    /// Keeps the least covered value, for inverted masks.
+    inverted_pipeline: Arc<GraphicsPipeline>,
+    sampler: Arc<Sampler>,
+    image: Option<([u32; 2], Arc<ImageView<AttachmentImage>>)>,
+    /// The masked sprite program for each render pass the mask has been used in.
+    programs: Vec<(Arc<RenderPass>, Arc<dyn ShaderHandle>)>,
+}
+
+/// The mask bound while a [`SpriteMask`] is on, carried by the [`DrawState`].
+#[derive(Clone)]
+pub(crate) struct ActiveMask {
+    pub(crate) view: Arc<ImageView<AttachmentImage>>,
+    pub(crate) sampler: Arc<Sampler>,
+    pub(crate) program: Arc<dyn ShaderHandle>,
+}
+
+impl ActiveMask {
+    /// The write binding the mask in the masked program's per-draw set.
+    pub(crate) fn write(&self) -> WriteDescriptorSet {
+        WriteDescriptorSet::image_view_sampler(1, self.view.clone(), self.sampler.clone())
+    }
+}
+
+impl SpriteMask {
+    pub fn new(ctx: &Renderer) -> Result<Self> {
+        Self::with_queue(ctx.queue.clone())
+    }
+
+    pub(crate) fn with_queue(queue: Arc<Queue>) -> Result<Self> {
+        let device = queue.device().clone();
+
+        let mask_pass = vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                mask: {
+                    load: Clear,
+                    store: Store,
+                    format: MASK_FORMAT,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [mask],
+                depth_stencil: {}
+            }
+        )?;
+
+        let vs = crate::graphics::vs::load(device.clone())?;
+        let fs = mask_fs::load(device.clone())?;
+        let mask_pipeline = |op| {
+            let blend = AttachmentBlend {
+                color_op: op,
+                color_source: BlendFactor::One,
+                color_destination: BlendFactor::One,
+                alpha_op: op,
+                alpha_source: BlendFactor::One,
+                alpha_destination: BlendFactor::One,
+            };
+
+            GraphicsPipeline::start()
+                .vertex_input_state(BuffersDefinition::new().vertex::<Vertex>().instance::<InstanceData>())
+                .vertex_shader(vs.entry_point("main").unwrap(), ())
+                .input_assembly_state(InputAssemblyState::new().topology(PrimitiveTopology::TriangleStrip))
+                .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+                .fragment_shader(fs.entry_point("main").unwrap(), ())
+                .color_blend_state(ColorBlendState::new(1).blend(blend))
+                .render_pass(Subpass::from(mask_pass.clone(), 0).unwrap())
+                .build(device.clone())
+        };
+        let pipeline = mask_pipeline(BlendOp::Max)?;
+        let inverted_pipeline = mask_pipeline(BlendOp::Min)?;
+
+        let sampler = Sampler::new(
+            device,
+            SamplerCreateInfo {
+                mag_filter: Filter::Linear,
+                min_filter: Filter::Linear,
+                address_mode: [SamplerAddressMode::ClampToEdge; 3],
+                ..Default::default()
+            },
+        )?;
+
+        Ok(Self {
+            queue,
+            inverted: false,
+            mask_pass,
+            pipeline,
+            inverted_pipeline,
+            sampler,
+            image: None,
+            programs: Vec::new(),
+        })
+    }
+
+    /// Whether the mask shows everything outside the sprite's shape instead of inside it, off
+    /// by default. Takes effect on the next [`SpriteMask::render`].
+    pub fn set_inverted(&mut self, inverted: bool) {
+        self.inverted = inverted;
+    }
+
+    pub fn inverted(&self) -> bool {
+        self.inverted
+    }
+
+    /// Renders the alpha of `image` drawn with `info` through `camera`, the scene's
+    /// view-projection matrix, into a mask sized to `viewport`. The viewport should cover the
+    /// whole target the masked draws go to, e.g. [`Renderer::viewport`].
+    pub fn render(
+        &mut self,
+        before_future: Box<dyn GpuFuture>,
+        image: &image::Image,
+        info: DrawInfo,
+        camera: [[f32; 4]; 4],
+        viewport: Rect,
+    ) -> Result<Box<dyn GpuFuture>> {
+        let target = self.mask(viewport)?;
+        let [w, h] = target.image().dimensions().width_height();
+        let device = self.queue.device().clone();
+
+        let framebuffer = Framebuffer::new(
+            self.mask_pass.clone(),
+            FramebufferCreateInfo {
+                attachments: vec![target],
+                ..Default::default()
+            },
+        )?;
+
+        let pipeline = if self.inverted { &self.inverted_pipeline } else { &self.pipeline };
+        let clear = if self.inverted { 1.0 } else { 0.0 };
+
+        let camera_buffer = CpuAccessibleBuffer::from_data(
+            device.clone(),
+            BufferUsage::uniform_buffer(),
+            false,
+            camera::CameraUniform::from(camera),
+        )?;
+        let camera_set = PersistentDescriptorSet::new(
+            pipeline.layout().set_layouts()[camera::CAMERA_UNIFORM_SET].clone(),
+            [WriteDescriptorSet::buffer(camera::CAMERA_UNIFORM_BINDING, camera_buffer)],
+        )?;
+        let texture_set = PersistentDescriptorSet::new(
+            pipeline.layout().set_layouts()[1].clone(),
+            [WriteDescriptorSet::image_view_sampler(0, image.view(), image.sampler().clone())],
+        )?;
+
+        let vertices = CpuAccessibleBuffer::from_iter(
+            device.clone(),
+            BufferUsage::vertex_buffer(),
+            false,
+            QUAD_VERTICES.to_vec(),
+        )?;
+        let instances = CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage::vertex_buffer(),
+            false,
+            [InstanceData::from(info)],
+        )?;
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.queue.device().clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        builder
+            .begin_render_pass(framebuffer, SubpassContents::Inline, vec![ClearValue::Float([clear; 4])])?
+            .set_viewport(0, vec![Viewport {
+                origin: [0.0, 0.0],
+                dimensions: [w as f32, h as f32],
+                depth_range: 0.0..1.0,
+            }])
+            .bind_pipeline_graphics(pipeline.clone())
+            .bind_descriptor_sets(
+                PipelineBindPoint::Graphics,
+                pipeline.layout().clone(),
+                0,
+                vec![camera_set, texture_set],
+            )
+            .push_constants(pipeline.layout().clone(), 0, mask_fs::ty::MaskParams {
+                inverted: self.inverted as u32,
+            })
+            .bind_vertex_buffers(0, vec![vertices as Arc<dyn BufferAccess>, instances])
+            .draw(QUAD_VERTICES.len() as u32, 1, 0, 0)?
+            .end_render_pass()?;
+
+        let commands = builder.build()?;
+        Ok(before_future.then_execute(self.queue.clone(), commands)?.boxed())
+    }
+
+    /// Masks the images and sprite batches drawn in `pass` from here until [`SpriteMask::end`]
+    /// by the mask last rendered, replacing any mask already on.
+    pub fn begin(&mut self, pass: &mut Pass) -> Result<()> {
+        let view = match &self.image {
+            Some((_, view)) => view.clone(),
+            None => return Err(anyhow::anyhow!("no mask rendered, call SpriteMask::render first")),
+        };
+
+        let program = self.program(pass.vk_render_pass())?;
+        pass.set_mask(Some(ActiveMask {
+            view,
+            sampler: self.sampler.clone(),
+            program,
+        }));
+
+        Ok(())
+    }
+
+    /// Stops masking the draws that follow in `pass`.
+    pub fn end(&self, pass: &mut Pass) {
+        pass.set_mask(None);
+    }
+
+    /// The masked sprite program for `render_pass`, built the first time it's used there.
+    fn program(&mut self, render_pass: Arc<RenderPass>) -> Result<Arc<dyn ShaderHandle>> {
+        if let Some((_, program)) = self.programs.iter().find(|(rp, _)| Arc::ptr_eq(rp, &render_pass)) {
+            return Ok(program.clone());
+        }
+
+        let device = self.queue.device().clone();
+        let program: Arc<dyn ShaderHandle> = Arc::new(ShaderProgram::new(
+            device.clone(),
+            render_pass.clone(),
+            BuffersDefinition::new().vertex::<Vertex>().instance::<InstanceData>(),
+            VertexTopology::TriangleStrip,
+            crate::graphics::vs::load(device.clone())?,
+            masked_fs::load(device)?,
+            BlendMode::Alpha,
+        )?);
+
+        self.programs.push((render_pass, program.clone()));
+        Ok(program)
+    }
+
+    /// The mask target for `viewport`, reallocated when its size changes.
+    fn mask(&mut self, viewport: Rect) -> Result<Arc<ImageView<AttachmentImage>>> {
+        let extent = [(viewport.w as u32).max(1), (viewport.h as u32).max(1)];
+
+        match &self.image {
+            Some((size, view)) if *size == extent => Ok(view.clone()),
+            _ => {
+                let image = AttachmentImage::with_usage(
+                    self.queue.device().clone(),
+                    extent,
+                    MASK_FORMAT,
+                    ImageUsage {
+                        sampled: true,
+                        color_attachment: true,
+                        ..ImageUsage::none()
+                    },
+                )?;
+                let view = ImageView::new_default(image)?;
+
+                self.image = Some((extent, view.clone()));
+                Ok(view)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::coordinates::CoordinateSystem;
+    use crate::graphics::{pixel_at, render_offscreen, test_device, test_pass};
+
+    const VIEWPORT: Rect = Rect { x: 0.0, y: 0.0, w: 8.0, h: 1.0 };
+
+    /// A mask of an opaque sprite over columns `columns` of an 8x1 target.
+    fn mask_over(queue: &Arc<Queue>, sampler: &Arc<Sampler>, columns: std::ops::Range<u32>, inverted: bool) -> SpriteMask {
+        let mut mask = SpriteMask::with_queue(queue.clone()).unwrap();
+        mask.set_inverted(inverted);
+
+        let shape = image::Image::from_color(queue.clone(), sampler.clone(), Color::white());
+        let width = (columns.end - columns.start) as f32;
+        let info = DrawInfo::centered((columns.start as f32 + width / 2.0, 0.5), (width, 1.0), 0.0, Color::white());
+        let camera = CoordinateSystem::PixelsTopLeft.projection(&VIEWPORT);
+        mask.render(vulkano::sync::now(queue.device().clone()).boxed(), &shape, info, camera, VIEWPORT)
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+        mask
+    }
+
+    /// A sprite of `color` covering the whole 8x1 target.
+    fn full_width(queue: &Arc<Queue>, sampler: &Arc<Sampler>, color: Color) -> (Arc<image::Image>, DrawInfo) {
+        let image = Arc::new(image::Image::from_color(queue.clone(), sampler.clone(), color));
+        (image, DrawInfo::centered((4.0, 0.5), (8.0, 1.0), 0.0, Color::white()))
+    }
+
+    fn columns(pixels: &[u8]) -> Vec<[u8; 4]> {
+        (0..8).map(|x| pixel_at(pixels, 8, (x, 0))).collect()
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn masks_show_draws_inside_the_shape_and_inverted_ones_outside() {
+        let (queue, sampler) = test_device();
+        let (red, info) = full_width(&queue, &sampler, Color::red());
+        let [r, k] = [[255, 0, 0, 255], [0, 0, 0, 255]];
+
+        for (inverted, expected) in [(false, [r, r, r, k, k, k, k, k]), (true, [k, k, k, r, r, r, r, r])] {
+            let mut mask = mask_over(&queue, &sampler, 0..3, inverted);
+            let mut pass = test_pass(queue.clone());
+            let pixels = render_offscreen(&queue, &mut pass, [8, 1], Color::black(), |pass| {
+                mask.begin(pass)?;
+                pass.draw(red.clone(), info)?;
+                mask.end(pass);
+                Ok(())
+            });
+            assert_eq!(columns(&pixels), expected, "inverted: {}", inverted);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn masks_turned_on_one_after_another_each_clip_their_own_draws() {
+        let (queue, sampler) = test_device();
+        let mut left = mask_over(&queue, &sampler, 0..4, false);
+        let mut right = mask_over(&queue, &sampler, 4..8, false);
+        let (red, info) = full_width(&queue, &sampler, Color::red());
+        let (white, _) = full_width(&queue, &sampler, Color::white());
+        let dot = DrawInfo::centered((6.5, 0.5), (1.0, 1.0), 0.0, Color::rgba(0, 0, 255, 255));
+
+        let mut pass = test_pass(queue.clone());
+        let pixels = render_offscreen(&queue, &mut pass, [8, 1], Color::black(), |pass| {
+            left.begin(pass)?;
+            pass.draw(red, info)?;
+            right.begin(pass)?;
+            pass.draw(white, info)?;
+            right.end(pass);
+            // Unmasked again, so drawn over the white half.
+            pass.draw(Arc::new(image::Image::from_color(queue.clone(), sampler.clone(), Color::white())), dot)
+        });
+
+        let [r, w, b] = [[255, 0, 0, 255], [255; 4], [0, 0, 255, 255]];
+        assert_eq!(columns(&pixels), [r, r, r, r, w, w, b, w]);
+    }
+}
@@ -0,0 +1,400 @@
+//! The math types shared by drawing and game logic, built without the `graphics` feature.
+
+use cgmath::{prelude::Angle, Deg, Matrix, Matrix4, Rad, Vector2, Vector3, Vector4};
+
+/// Anything that can report the region it covers, for culling, framing it with
+/// [`Camera2D::fit_bounds`](crate::graphics::camera::Camera2D::fit_bounds), or outlining it
+/// with [`debug::bounds`](crate::graphics::debug::bounds).
+pub trait Bounded {
+    /// The axis-aligned box around everything drawn, `None` when there's nothing to draw.
+    fn bounds(&self) -> Option<Rect>;
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum Transform {
+    Components {
+        pos: Vector3<f32>,
+        rotation: Rad<f32>,
+        scale: Vector3<f32>,
+        offset: Vector3<f32>,
+    },
+    Matrix(Matrix4<f32>),
+}
+
+impl Default for Transform {
+    fn default() -> Self {
+        Transform::identity()
+    }
+}
+
+impl Transform {
+    pub(crate) fn identity() -> Self {
+        // Self::Matrix(Matrix4::identity())
+        Self::Components {
+            pos: Vector3::from((0.0, 0.0, 0.0)),
+            rotation: Rad(0.0),
+            scale: Vector3::from((1.0, 1.0, 1.0)),
+            offset: Vector3::from((0.0, 0.0, 0.0)),
+        }
+    }
+
+    /// Scales by `scale`, rotates by `rotation` and moves to `pos` in one step, the same as
+    /// setting each with [`DrawInfo::dest`](crate::graphics::DrawInfo::dest),
+    /// [`DrawInfo::rotate_value`](crate::graphics::DrawInfo::rotate_value) and
+    /// [`DrawInfo::nonuniform_scale`](crate::graphics::DrawInfo::nonuniform_scale) on a default transform.
+    pub fn from_trs(pos: Vector2<f32>, rotation: Rad<f32>, scale: Vector2<f32>) -> Transform {
+        Transform::from_trs_3d(pos.extend(0.0), rotation, scale.extend(1.0))
+    }
+
+    /// [`Transform::from_trs`] with a z position, used for depth ordering, and z scale.
+    pub fn from_trs_3d(pos: Vector3<f32>, rotation: Rad<f32>, scale: Vector3<f32>) -> Transform {
+        Transform::Components {
+            pos,
+            rotation,
+            scale,
+            offset: Vector3::from((0.0, 0.0, 0.0)),
+        }
+    }
+
+    /// The transform that applies `self` first and `other` after it.
+    pub fn then(&self, other: &Transform) -> Transform {
+        Transform::Matrix(other.as_mat4() * self.as_mat4())
+    }
+
+    pub fn as_mat4(&self) -> Matrix4<f32> {
+        match self {
+            Transform::Matrix(mat) => *mat,
+            Transform::Components {
+                pos,
+                rotation,
+                scale,
+                offset,
+            } => {
+                let (sinr, cosr) = rotation.sin_cos();
+                let cr00 = cosr * scale.x;
+                let cr01 = -sinr * scale.y;
+                let cr10 = sinr * scale.x;
+                let cr11 = cosr * scale.y;
+                let cr03 = offset.x * (1.0 - cr00) - offset.y * cr01 + pos.x;
+                let cr13 = offset.y * (1.0 - cr11) - offset.x * cr10 + pos.y;
+
+                Matrix4::from_cols(
+                    Vector4::new(cr00, cr01, 0.0, cr03),
+                    Vector4::new(cr10, cr11, 0.0, cr13),
+                    Vector4::new(0.0, 0.0, 1.0, 0.0),
+                    Vector4::new(0.0, 0.0, 0.0, 1.0),
+                )
+                .transpose()
+            }
+        }
+    }
+
+    /// The z of the transform's position, used to order sprites by depth.
+    pub fn z(&self) -> f32 {
+        match self {
+            Transform::Matrix(mat) => mat.w.z,
+            Transform::Components { pos, .. } => pos.z,
+        }
+    }
+
+    pub(crate) fn dest(&mut self, x: f32, y: f32, z: f32) {
+        match self {
+            Transform::Matrix(_mat) => {
+                // *mat = Matrix4::from_translation(Vector3::new(x, y, z)) * *mat;
+            }
+            Transform::Components { pos, .. } => {
+                *pos = Vector3::from((x, y, z));
+            }
+        }
+    }
+
+    pub(crate) fn translate(&mut self, x: f32, y: f32, z: f32) {
+        match self {
+            Transform::Matrix(mat) => {
+                *mat = Matrix4::from_translation(Vector3::new(x, y, z)) * *mat;
+            }
+            Transform::Components { pos, .. } => {
+                *pos += Vector3::from((x, y, z));
+            }
+        }
+    }
+
+    pub(crate) fn rotate(&mut self, x: f32, y: f32, z: f32) {
+        let rotation = Matrix4::from_angle_x(Deg(x))
+            + Matrix4::from_angle_y(Deg(y))
+            + Matrix4::from_angle_z(Deg(z));
+        match self {
+            Transform::Matrix(mat) => {
+                *mat = rotation * *mat;
+            }
+            Transform::Components {
+                // rotation,
+                ..
+            } => {
+                // *rotation += Rad(3.14);
+            }
+        }
+    }
+
+    pub(crate) fn rotate_value(&mut self, r: Rad<f32>) {
+        match self {
+            Transform::Matrix(_) => {}
+            Transform::Components { rotation, .. } => {
+                *rotation = r;
+            }
+        }
+    }
+
+    pub(crate) fn nonuniform_scale(&mut self, x: f32, y: f32, z: f32) {
+        match self {
+            Transform::Matrix(mat) => {
+                *mat = Matrix4::from_nonuniform_scale(x, y, z) * *mat;
+            }
+            Transform::Components { scale, .. } => {
+                *scale = Vector3::from((x, y, z));
+            }
+        }
+    }
+}
+
+impl From<Color> for [f32; 4] {
+    fn from(color: Color) -> [f32; 4] {
+        color.0
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct Color(pub(crate) [f32; 4]);
+
+impl From<[f32; 4]> for Color {
+    fn from(a: [f32; 4]) -> Color {
+        Color(a)
+    }
+}
+
+impl Color {
+    /// Blends linearly from this color at `t = 0.0` to `other` at `t = 1.0`.
+    pub fn lerp(&self, other: Color, t: f32) -> Color {
+        let mut blended = self.0;
+        for (channel, target) in blended.iter_mut().zip(other.0) {
+            *channel += (target - *channel) * t;
+        }
+        Color(blended)
+    }
+
+    /// The color as 8 bit red, green, blue, and alpha channels, rounded and clamped.
+    pub fn to_rgba8(&self) -> [u8; 4] {
+        self.0.map(|channel| (channel.clamp(0.0, 1.0) * 255.0).round() as u8)
+    }
+
+    pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
+        Color([
+            r as f32 / 255.,
+            g as f32 / 255.,
+            b as f32 / 255.,
+            a as f32 / 255.,
+        ])
+    }
+
+    pub fn black() -> Color {
+        Color([0.0, 0.0, 0.0, 1.0])
+    }
+
+    pub fn grey() -> Color {
+        Color([0.25, 0.25, 0.25, 1.0])
+    }
+
+    pub fn white() -> Color {
+        Color([1.0, 1.0, 1.0, 1.0])
+    }
+
+    pub fn red() -> Color {
+        Color([1.0, 0.05, 0.05, 1.0])
+    }
+
+    pub fn transparent() -> Color {
+        Color([0.0, 0.0, 0.0, 0.0])
+    }
+
+    pub fn as_u8_arr(&self) -> [u8; 4] {
+        let mut arr = [0u8; 4];
+        arr[0] = (self.0[0] * 255.) as u8;
+        arr[1] = (self.0[1] * 255.) as u8;
+        arr[2] = (self.0[2] * 255.) as u8;
+        arr[3] = (self.0[3] * 255.) as u8;
+        arr
+    }
+
+    pub fn as_u8_vec(&self) -> Vec<u8> {
+        vec![
+            (self.0[0] * 255.) as u8,
+            (self.0[1] * 255.) as u8,
+            (self.0[2] * 255.) as u8,
+            (self.0[3] * 255.) as u8,
+        ]
+    }
+}
+
+impl Default for Color {
+    fn default() -> Color {
+        Color::black()
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Rect {
+    pub x: f32,
+    pub y: f32,
+    pub w: f32,
+    pub h: f32,
+}
+
+impl Rect {
+    pub fn as_vec(&self) -> [f32; 4] {
+        [self.x, self.y, self.w, self.h]
+    }
+
+    /// The rect shrunk by `dx` on its left and right and `dy` on its top and bottom, never
+    /// past its own center.
+    pub fn inset(&self, dx: f32, dy: f32) -> Rect {
+        let dx = dx.min(self.w / 2.0);
+        let dy = dy.min(self.h / 2.0);
+        Rect {
+            x: self.x + dx,
+            y: self.y + dy,
+            w: self.w - dx * 2.0,
+            h: self.h - dy * 2.0,
+        }
+    }
+
+    /// The smallest rect covering both `self` and `other`.
+    pub fn union(&self, other: &Rect) -> Rect {
+        let x = self.x.min(other.x);
+        let y = self.y.min(other.y);
+        Rect {
+            x,
+            y,
+            w: (self.x + self.w).max(other.x + other.w) - x,
+            h: (self.y + self.h).max(other.y + other.h) - y,
+        }
+    }
+
+    /// The smallest rect covering every point, `None` if there are none.
+    pub fn from_points<I: IntoIterator<Item = (f32, f32)>>(points: I) -> Option<Rect> {
+        let mut points = points.into_iter();
+        let first = points.next()?;
+        let (min, max) = points.fold((first, first), |(min, max), p| {
+            ((min.0.min(p.0), min.1.min(p.1)), (max.0.max(p.0), max.1.max(p.1)))
+        });
+
+        Some(Rect {
+            x: min.0,
+            y: min.1,
+            w: max.0 - min.0,
+            h: max.1 - min.1,
+        })
+    }
+}
+
+impl Default for Rect {
+    fn default() -> Self {
+        Rect {
+            x: 0.0,
+            y: 0.0,
+            w: 1.0,
+            h: 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn assert_close(a: Vector4<f32>, b: Vector4<f32>) {
+        let (lhs, rhs): ([f32; 4], [f32; 4]) = (a.into(), b.into());
+        assert!(lhs.iter().zip(rhs).all(|(l, r)| (l - r).abs() < 1e-5), "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn union_covers_both_rects() {
+        let a = Rect { x: 0.0, y: 0.0, w: 10.0, h: 10.0 };
+        let overlapping = Rect { x: 5.0, y: -5.0, w: 10.0, h: 10.0 };
+        let disjoint = Rect { x: 20.0, y: 30.0, w: 5.0, h: 5.0 };
+
+        assert_eq!(a.union(&overlapping), Rect { x: 0.0, y: -5.0, w: 15.0, h: 15.0 });
+        assert_eq!(a.union(&disjoint), Rect { x: 0.0, y: 0.0, w: 25.0, h: 35.0 });
+        assert_eq!(a.union(&a), a);
+    }
+
+    #[test]
+    fn inset_stops_at_center() {
+        let rect = Rect { x: 0.0, y: 0.0, w: 10.0, h: 4.0 };
+
+        assert_eq!(rect.inset(1.0, 1.0), Rect { x: 1.0, y: 1.0, w: 8.0, h: 2.0 });
+        assert_eq!(rect.inset(1.0, 5.0), Rect { x: 1.0, y: 2.0, w: 8.0, h: 0.0 });
+    }
+
+    #[test]
+    fn from_points_bounds_every_point() {
+        let rect = Rect::from_points([(3.0, -1.0), (-2.0, 4.0), (1.0, 1.0)]);
+
+        assert_eq!(rect, Some(Rect { x: -2.0, y: -1.0, w: 5.0, h: 5.0 }));
+        assert_eq!(Rect::from_points(std::iter::empty()), None);
+    }
+
+    #[test]
+    fn from_trs_matches_the_mutator_sequence() {
+        let mut chained = Transform::identity();
+        chained.dest(10.0, 20.0, 0.0);
+        chained.rotate_value(Rad(0.5));
+        chained.nonuniform_scale(2.0, 3.0, 1.0);
+        assert_eq!(Transform::from_trs(Vector2::new(10.0, 20.0), Rad(0.5), Vector2::new(2.0, 3.0)), chained);
+
+        chained.dest(10.0, 20.0, 0.25);
+        chained.nonuniform_scale(2.0, 3.0, 4.0);
+        let trs = Transform::from_trs_3d(Vector3::new(10.0, 20.0, 0.25), Rad(0.5), Vector3::new(2.0, 3.0, 4.0));
+        assert_eq!(trs, chained);
+    }
+
+    #[test]
+    fn then_applies_self_first() {
+        let scale = Transform::from_trs(Vector2::new(0.0, 0.0), Rad(0.0), Vector2::new(2.0, 2.0));
+        let shift = Transform::from_trs(Vector2::new(10.0, 0.0), Rad(0.0), Vector2::new(1.0, 1.0));
+        let point = Vector4::new(1.0, 1.0, 0.0, 1.0);
+
+        assert_close(scale.then(&shift).as_mat4() * point, Vector4::new(12.0, 2.0, 0.0, 1.0));
+        assert_close(shift.then(&scale).as_mat4() * point, Vector4::new(22.0, 2.0, 0.0, 1.0));
+    }
+
+    #[test]
+    fn components_rotate_before_translating() {
+        let quarter_turn = Transform::from_trs(
+            Vector2::new(5.0, 0.0),
+            Rad(std::f32::consts::FRAC_PI_2),
+            Vector2::new(1.0, 1.0),
+        );
+
+        assert_close(
+            quarter_turn.as_mat4() * Vector4::new(1.0, 0.0, 0.0, 1.0),
+            Vector4::new(5.0, 1.0, 0.0, 1.0),
+        );
+    }
+
+    #[test]
+    fn rgba8_rounds_and_clamps() {
+        assert_eq!(Color([0.5, 1.5, -0.25, 1.0]).to_rgba8(), [128, 255, 0, 255]);
+        assert_eq!(Color::rgba(12, 34, 56, 78).to_rgba8(), [12, 34, 56, 78]);
+    }
+
+    #[test]
+    fn lerp_hits_both_ends() {
+        let from = Color::black();
+        let to = Color::white();
+
+        assert_eq!(from.lerp(to, 0.0), from);
+        assert_eq!(from.lerp(to, 1.0), to);
+        assert_eq!(from.lerp(to, 0.5), Color([0.5, 0.5, 0.5, 1.0]));
+    }
+}
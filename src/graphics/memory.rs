@@ -0,0 +1,240 @@
+use std::sync::atomic::{AtomicU64, Ordering};
+use vulkano::device::physical::PhysicalDevice;
+use vulkano::{Version, VulkanObject};
+
+static TEXTURE_BYTES: AtomicU64 = AtomicU64::new(0);
+static BUFFER_BYTES: AtomicU64 = AtomicU64::new(0);
+
+/// What a tracked allocation is used for.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum MemoryCategory {
+    Textures,
+    Buffers,
+}
+
+impl MemoryCategory {
+    fn counter(&self) -> &'static AtomicU64 {
+        match self {
+            MemoryCategory::Textures => &TEXTURE_BYTES,
+            MemoryCategory::Buffers => &BUFFER_BYTES,
+        }
+    }
+}
+
+/// Counts `bytes` towards a category for as long as it's alive.
+///
+/// Held by long lived resources such as images so [`MemoryInfo::usage`] can be broken down
+/// per category without asking the driver.
+#[derive(Debug)]
+pub struct TrackedAllocation {
+    category: MemoryCategory,
+    bytes: u64,
+}
+
+impl TrackedAllocation {
+    pub fn new(category: MemoryCategory, bytes: u64) -> Self {
+        category.counter().fetch_add(bytes, Ordering::Relaxed);
+        Self { category, bytes }
+    }
+}
+
+impl Drop for TrackedAllocation {
+    fn drop(&mut self) {
+        self.category.counter().fetch_sub(self.bytes, Ordering::Relaxed);
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct HeapInfo {
+    pub size: u64,
+    pub device_local: bool,
+    /// How much of the heap the driver is willing to give this process, `None` without
+    /// `VK_EXT_memory_budget`.
+    pub budget: Option<u64>,
+}
+
+/// A snapshot of device memory.
+///
+/// `budget` is what the driver is willing to give this process across the device local heaps
+/// when it supports `VK_EXT_memory_budget`. Without it, it falls back to the total size of
+/// those heaps, an upper bound that thresholds should leave some headroom under.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct MemoryInfo {
+    pub budget: u64,
+    /// Bytes held by tracked textures and buffers.
+    pub usage: u64,
+    pub textures: u64,
+    pub buffers: u64,
+    pub heaps: Vec<HeapInfo>,
+}
+
+impl MemoryInfo {
+    /// Builds the snapshot from the device's heaps, their budgets if the driver reports them,
+    /// and the tracked usage.
+    pub fn query(physical: PhysicalDevice) -> Self {
+        let budgets = heap_budgets(physical);
+        let heaps = physical
+            .memory_heaps()
+            .map(|heap| HeapInfo {
+                size: heap.size(),
+                device_local: heap.is_device_local(),
+                budget: budgets.as_ref().and_then(|budgets| budgets.get(heap.id() as usize).copied()),
+            })
+            .collect::<Vec<_>>();
+
+        Self::from_heaps(
+            heaps,
+            TEXTURE_BYTES.load(Ordering::Relaxed),
+            BUFFER_BYTES.load(Ordering::Relaxed),
+        )
+    }
+
+    /// Builds the snapshot from `heaps`, taking each device local heap's budget where it has
+    /// one and its size where it doesn't.
+    pub fn from_heaps(heaps: Vec<HeapInfo>, textures: u64, buffers: u64) -> Self {
+        let budget = heaps
+            .iter()
+            .filter(|heap| heap.device_local)
+            .map(|heap| heap.budget.unwrap_or(heap.size))
+            .sum();
+
+        Self {
+            budget,
+            usage: textures + buffers,
+            textures,
+            buffers,
+            heaps,
+        }
+    }
+
+    /// Fraction of the budget in use, `0.0` if the budget is unknown.
+    pub fn pressure(&self) -> f32 {
+        if self.budget == 0 {
+            0.0
+        } else {
+            self.usage as f32 / self.budget as f32
+        }
+    }
+}
+
+/// Each heap's budget from `VK_EXT_memory_budget`, by heap index. `None` if the device doesn't
+/// support the extension or the instance can't query extended memory properties.
+fn heap_budgets(physical: PhysicalDevice) -> Option<Vec<u64>> {
+    if !physical.supported_extensions().ext_memory_budget {
+        return None;
+    }
+
+    let instance = physical.instance();
+    let fns = instance.fns();
+    let mut budget = ash::vk::PhysicalDeviceMemoryBudgetPropertiesEXT::default();
+    let mut output = ash::vk::PhysicalDeviceMemoryProperties2 {
+        p_next: &mut budget as *mut _ as *mut std::ffi::c_void,
+        ..Default::default()
+    };
+
+    unsafe {
+        if instance.api_version() >= Version::V1_1 {
+            fns.v1_1.get_physical_device_memory_properties2(physical.internal_object(), &mut output);
+        } else if instance.enabled_extensions().khr_get_physical_device_properties2 {
+            fns.khr_get_physical_device_properties2
+                .get_physical_device_memory_properties2_khr(physical.internal_object(), &mut output);
+        } else {
+            return None;
+        }
+    }
+
+    let count = output.memory_properties.memory_heap_count as usize;
+    Some(budget.heap_budget[..count].to_vec())
+}
+
+/// Fires a callback the first time memory pressure crosses a threshold, and again only once
+/// it has dropped back below it.
+pub struct MemoryWarning {
+    threshold: f32,
+    exceeded: bool,
+    callback: Box<dyn FnMut(&MemoryInfo)>,
+}
+
+impl MemoryWarning {
+    /// `threshold` is a fraction of the budget, e.g. `0.8` warns at 80% usage.
+    pub fn new<F: FnMut(&MemoryInfo) + 'static>(threshold: f32, callback: F) -> Self {
+        Self {
+            threshold,
+            exceeded: false,
+            callback: Box::new(callback),
+        }
+    }
+
+    /// Returns `true` if the callback fired.
+    pub fn check(&mut self, info: &MemoryInfo) -> bool {
+        let over = info.pressure() > self.threshold;
+        let fire = over && !self.exceeded;
+        self.exceeded = over;
+
+        if fire {
+            log::warn!(
+                target: "ledge::graphics::memory",
+                "memory usage {} of {} bytes is over the {:.0}% threshold",
+                info.usage,
+                info.budget,
+                self.threshold * 100.0
+            );
+            (self.callback)(info);
+        }
+
+        fire
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::cell::Cell;
+    use std::rc::Rc;
+
+    const GIB: u64 = 1 << 30;
+
+    fn heaps(budget: Option<u64>) -> Vec<HeapInfo> {
+        vec![
+            HeapInfo { size: 8 * GIB, device_local: true, budget },
+            HeapInfo { size: 16 * GIB, device_local: false, budget: budget.map(|_| 12 * GIB) },
+        ]
+    }
+
+    #[test]
+    fn budget_falls_back_to_the_device_local_heap_sizes() {
+        let info = MemoryInfo::from_heaps(heaps(None), GIB, GIB);
+        assert_eq!(info.budget, 8 * GIB);
+        assert_eq!(info.usage, 2 * GIB);
+        assert_eq!(info.pressure(), 0.25);
+    }
+
+    #[test]
+    fn reported_budgets_replace_the_heap_sizes() {
+        let info = MemoryInfo::from_heaps(heaps(Some(4 * GIB)), GIB, GIB);
+        assert_eq!(info.budget, 4 * GIB);
+        assert_eq!(info.pressure(), 0.5);
+    }
+
+    #[test]
+    fn an_unknown_budget_reports_no_pressure() {
+        assert_eq!(MemoryInfo::from_heaps(Vec::new(), GIB, 0).pressure(), 0.0);
+    }
+
+    #[test]
+    fn warnings_fire_once_per_crossing() {
+        let fired = Rc::new(Cell::new(0));
+        let mut warning = MemoryWarning::new(0.8, {
+            let fired = fired.clone();
+            move |_| fired.set(fired.get() + 1)
+        });
+        let at = |usage: u64| MemoryInfo::from_heaps(heaps(None), usage, 0);
+
+        assert!(!warning.check(&at(6 * GIB)));
+        assert!(warning.check(&at(7 * GIB)));
+        assert!(!warning.check(&at(7 * GIB)));
+        assert!(!warning.check(&at(5 * GIB)));
+        assert!(warning.check(&at(8 * GIB)));
+        assert_eq!(fired.get(), 2);
+    }
+}
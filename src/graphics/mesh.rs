@@ -0,0 +1,40 @@
+use crate::graphics::{Bounded, Rect, Vertex};
+
+/// CPU side vertex and index data, drawn as an indexed triangle list.
+#[derive(Clone, Debug, Default)]
+pub struct Mesh {
+    pub vertices: Vec<Vertex>,
+    pub indices: Vec<u32>,
+}
+
+impl Mesh {
+    pub fn new(vertices: Vec<Vertex>, indices: Vec<u32>) -> Self {
+        Self { vertices, indices }
+    }
+
+    pub fn vertex_count(&self) -> u32 {
+        self.vertices.len() as u32
+    }
+
+    pub fn index_count(&self) -> u32 {
+        self.indices.len() as u32
+    }
+}
+
+impl Bounded for Mesh {
+    /// Bounds of the vertex positions in the mesh's own space, ignoring z.
+    fn bounds(&self) -> Option<Rect> {
+        Rect::from_points(self.vertices.iter().map(|v| (v.pos[0], v.pos[1])))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounds_cover_every_vertex_and_empty_meshes_have_none() {
+        assert_eq!(Mesh::default().bounds(), None);
+        assert_eq!(crate::graphics::quad_mesh().bounds(), Some(Rect { x: 0.0, y: 0.0, w: 1.0, h: 1.0 }));
+    }
+}
@@ -1,29 +1,84 @@
+/// Sprite sheets with trimmed and rotated frames, as packed by TexturePacker.
+pub mod atlas;
+/// Frames read back from the GPU in the background, for save slot thumbnails.
+pub mod capture;
 /// The camera module holds the different camera options and helper functions for creating and
 /// manipulating views.
 pub mod camera;
+/// The coordinate systems positions can be given in and conversions between them.
+pub mod coordinates;
 /// The main Vulkan interface, holds backend components and
 /// contextual information such as device, queue, and swapchain information.
 // pub mod context;
 /// Holds all graphics error enums.
-// pub mod error;
+pub mod error;
+/// Debug shapes and text drawn as an overlay, enabled by the `debug-draw` feature.
+pub mod debug;
+/// Images that can be drawn into, for splats and decals that build up over time.
+pub mod dynamic_image;
+/// Built-in full screen post-process effects such as grayscale, vignette, and CRT.
+pub mod effects;
+/// Bitmap fonts and text drawing.
+pub mod font;
+/// Declaration-ordered passes, so offscreen targets are finished before they're sampled.
+pub mod frame_graph;
+/// Conversions to and from `mint` and `glam` math types, enabled by the features of the same name.
+#[cfg(feature = "mint")]
+pub mod interop;
 /// TODO: A module dedicated to images, used for textures and other image related things.
 pub mod image;
+/// Batched immediate-mode shape drawing for debug overlays and simple geometry.
+pub mod immediate;
+/// 2D lights composited over the scene through an offscreen light map.
+pub mod lighting;
+/// Named sorting layers for queued draws, see [`DrawInfo::set_layer`].
+pub mod layers;
+/// Device memory queries and usage tracking.
+pub mod memory;
+/// Clipping draws to the alpha of a sprite, for spotlight and keyhole reveals.
+pub mod mask;
+/// Named colors for a consistent style, read by the debug overlay.
+pub mod palette;
+/// Plain vertex and index data for building custom geometry.
+pub mod mesh;
+/// BC1 and BC3 compressed textures loaded from DDS files.
+pub mod compressed;
+/// Tapering trails through the recent positions of moving objects.
+pub mod ribbon;
 /// The shader module defines types, traits, and structs to abstract complex operations that involve shaders.
 /// This module has a lot of intense types from Vulkano wrapped in less scary interfaces that are not as troublesome to deal with
 pub mod shader;
+/// Filtering, addressing, and border colors for the samplers images are drawn with.
+pub mod sampler;
+/// The types shared with game logic, which also build without the `graphics` feature.
+mod math;
 
-// pub mod sprite;
+pub mod sprite;
 
 pub mod renderer;
+/// Rendering the scene at a fraction of the window's resolution.
+pub mod render_scale;
 
 pub mod render_pass;
 
+/// Batched uploads of image data to the GPU.
+pub mod upload;
+
 // pub mod text;
 
+pub use math::{Bounded, Color, Rect, Transform};
+pub use coordinates::{coordinate_system, set_coordinate_system, CoordinateSystem};
+pub use layers::register_layer;
+pub use palette::{palette, set_palette, Palette};
+pub use render_scale::{render_scale, set_render_scale};
+pub use dynamic_image::{render_to_image, DynamicImage};
+pub use frame_graph::FrameGraph;
+
 // use crate::graphics::context::GraphicsContext;
 use vulkano::buffer::BufferAccess;
+use vulkano::pipeline::graphics::rasterization::FrontFace;
 
-use cgmath::{prelude::Angle, Deg, Matrix, Matrix4, Rad, Vector3, Vector4};
+use cgmath::{Matrix4, Rad, SquareMatrix, Vector3, Vector4};
 
 use bytemuck::{Pod, Zeroable};
 use std::sync::Arc;
@@ -34,16 +89,27 @@ use vulkano::device::{Device, Queue};
 use vulkano::image::view::ImageViewAbstract;
 use vulkano::sampler::Sampler;
 use vulkano::command_buffer::{AutoCommandBufferBuilder, SecondaryAutoCommandBuffer};
+use vulkano::sync::GpuFuture;
 use crate::graphics::shader::ShaderHandle;
 
 use anyhow::Result;
 
-#[derive(Clone, Copy, PartialEq, Hash, Eq)]
+/// How a draw's color combines with what's already in the target.
+///
+/// [`BlendMode::Alpha`] expects straight colors and multiplies them by alpha as it blends.
+/// [`BlendMode::PremultipliedAlpha`] expects colors already multiplied by alpha: from
+/// premultiplied textures, or with [`DrawInfo::set_premultiply`] for straight ones. Images are
+/// loaded premultiplied by default and draw with the latter when asked for the former, see
+/// [`image::Image::is_premultiplied`].
+#[derive(Clone, Copy, Debug, PartialEq, Hash, Eq)]
 pub enum BlendMode {
     Add,
     Subtract,
     Alpha,
     Invert,
+    /// Source over for colors already multiplied by their alpha, which filters and
+    /// composites without the dark fringes straight alpha gets.
+    PremultipliedAlpha,
     // Multiply,
     // Replace,
     // Lighten,
@@ -52,7 +118,335 @@ pub enum BlendMode {
 
 pub trait Drawable {
     // fn draw(&self, context: &mut Renderer, info: DrawInfo);
-    fn draw(&self, queue: Arc<Queue>, shader_handle: &Box<dyn ShaderHandle>, info: DrawInfo) -> Result<SecondaryAutoCommandBuffer>;
+    fn draw(&self, queue: Arc<Queue>, shader_handle: &dyn ShaderHandle, state: &DrawState, info: DrawInfo) -> Result<SecondaryAutoCommandBuffer>;
+
+    /// The draw calls and instances one `draw` records, counted into [`DrawStats`].
+    fn stats(&self) -> DrawStats {
+        DrawStats {
+            draw_calls: 1,
+            instances: 1,
+        }
+    }
+}
+
+/// Draw calls and instances recorded during a frame, see [`render_pass::RenderPass::frame_stats`].
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct DrawStats {
+    pub draw_calls: u32,
+    pub instances: u32,
+}
+
+impl std::ops::Add for DrawStats {
+    type Output = DrawStats;
+
+    fn add(self, other: DrawStats) -> DrawStats {
+        DrawStats {
+            draw_calls: self.draw_calls + other.draw_calls,
+            instances: self.instances + other.instances,
+        }
+    }
+}
+
+/// The state shared by every draw in a frame until it's changed, see
+/// [`render_pass::frame::Pass::push_state`] for saving and restoring it.
+#[derive(Clone)]
+pub struct DrawState {
+    /// Shader used by [`render_pass::frame::Pass::draw`].
+    pub shader: Option<shader::ShaderId>,
+    pub blend_mode: BlendMode,
+    /// The combined model-view-projection matrix bound for each draw.
+    pub camera: [[f32; 4]; 4],
+    /// Region of the framebuffer drawn to, in pixels.
+    pub viewport: Rect,
+    /// How triangles are rasterized, falling back to filled where the shader or device can't
+    /// do otherwise.
+    pub polygon_mode: shader::PolygonMode,
+    camera_buffer: Option<Arc<CpuAccessibleBuffer<camera::CameraUniform>>>,
+    pub(crate) params: Option<shader::ShaderParams>,
+    /// The mask image draws are clipped by, see [`mask::SpriteMask::begin`].
+    pub(crate) mask: Option<mask::ActiveMask>,
+}
+
+impl DrawState {
+    pub fn new(width: f32, height: f32) -> Self {
+        Self {
+            shader: None,
+            blend_mode: BlendMode::Alpha,
+            camera: Matrix4::identity().into(),
+            viewport: Rect {
+                x: 0.0,
+                y: 0.0,
+                w: width,
+                h: height,
+            },
+            polygon_mode: shader::PolygonMode::Fill,
+            camera_buffer: None,
+            params: None,
+            mask: None,
+        }
+    }
+
+    /// Sets the camera matrix and uploads it, so draws share one uniform buffer until it changes.
+    pub(crate) fn upload_camera(&mut self, device: Arc<Device>, camera: [[f32; 4]; 4]) -> Result<()> {
+        self.camera = camera;
+        self.camera_buffer = Some(CpuAccessibleBuffer::from_data(
+            device,
+            BufferUsage::uniform_buffer(),
+            false,
+            camera::CameraUniform::from(camera),
+        )?);
+        Ok(())
+    }
+
+    /// Sets the camera matrix along with a buffer it's already been written to.
+    pub(crate) fn use_camera_buffer(&mut self, camera: [[f32; 4]; 4], buffer: Arc<CpuAccessibleBuffer<camera::CameraUniform>>) {
+        self.camera = camera;
+        self.camera_buffer = Some(buffer);
+    }
+
+    /// The uploaded camera uniform, uploading it now if this state was never given one.
+    pub(crate) fn camera_buffer(&self, device: Arc<Device>) -> Result<Arc<CpuAccessibleBuffer<camera::CameraUniform>>> {
+        match &self.camera_buffer {
+            Some(buffer) => Ok(buffer.clone()),
+            None => Ok(CpuAccessibleBuffer::from_data(
+                device,
+                BufferUsage::uniform_buffer(),
+                false,
+                camera::CameraUniform::from(self.camera),
+            )?),
+        }
+    }
+
+    pub(crate) fn vk_viewport(&self) -> vulkano::pipeline::graphics::viewport::Viewport {
+        vulkano::pipeline::graphics::viewport::Viewport {
+            origin: [self.viewport.x, self.viewport.y],
+            dimensions: [self.viewport.w, self.viewport.h],
+            depth_range: 0.0..1.0,
+        }
+    }
+
+    /// The viewport as a scissor, clamped to start at the framebuffer's origin.
+    pub(crate) fn vk_scissor(&self) -> vulkano::pipeline::graphics::viewport::Scissor {
+        let x = self.viewport.x.max(0.0);
+        let y = self.viewport.y.max(0.0);
+        vulkano::pipeline::graphics::viewport::Scissor {
+            origin: [x as u32, y as u32],
+            dimensions: [
+                (self.viewport.x + self.viewport.w - x).max(0.0) as u32,
+                (self.viewport.y + self.viewport.h - y).max(0.0) as u32,
+            ],
+        }
+    }
+
+    /// Sets the viewport for `pipeline`, and the scissor to match it if the pipeline takes a
+    /// dynamic one. Call after binding the pipeline.
+    pub(crate) fn bind_viewport<L>(&self, builder: &mut AutoCommandBufferBuilder<L>, pipeline: &vulkano::pipeline::GraphicsPipeline) {
+        builder.set_viewport(0, vec![self.vk_viewport()]);
+        if pipeline.dynamic_state(vulkano::pipeline::DynamicState::Scissor) == Some(true) {
+            builder.set_scissor(0, vec![self.vk_scissor()]);
+        }
+    }
+
+    /// The descriptor set holding the camera uniform, `None` if the shader binds its per-draw
+    /// resources to the camera's set instead.
+    pub(crate) fn camera_set(
+        &self,
+        shader_handle: &dyn ShaderHandle,
+        device: Arc<Device>,
+    ) -> Result<Option<Arc<vulkano::descriptor_set::PersistentDescriptorSet>>> {
+        if shader_handle.descriptor_set_index() == camera::CAMERA_UNIFORM_SET {
+            return Ok(None);
+        }
+
+        Ok(Some(vulkano::descriptor_set::PersistentDescriptorSet::new(
+            shader_handle.layout()[camera::CAMERA_UNIFORM_SET].clone(),
+            [WriteDescriptorSet::buffer(
+                camera::CAMERA_UNIFORM_BINDING,
+                self.camera_buffer(device)?,
+            )],
+        )?))
+    }
+
+    /// Binds the params set by [`render_pass::frame::Pass::set_shader_params`], if any.
+    pub(crate) fn bind_params(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
+        pipeline: &Arc<vulkano::pipeline::GraphicsPipeline>,
+        device: Arc<Device>,
+    ) -> Result<()> {
+        match &self.params {
+            Some(params) => params.bind(builder, pipeline, device),
+            None => Ok(()),
+        }
+    }
+
+    /// The pipeline for `blend_mode` and this state's polygon mode, falling back to filled and
+    /// then to the shader's current pipeline.
+    pub(crate) fn pipeline_for(&self, shader_handle: &dyn ShaderHandle, blend_mode: BlendMode) -> Arc<vulkano::pipeline::GraphicsPipeline> {
+        shader_handle
+            .pipeline_for_polygon(blend_mode, self.polygon_mode)
+            .or_else(|| shader_handle.pipeline_for(blend_mode))
+            .unwrap_or_else(|| shader_handle.pipeline())
+    }
+}
+
+/// Draws with the shader `id`, passing it `params` for this draw only.
+///
+/// The params are pushed as push constants if the shader has any, in which case their size
+/// must match or the draw fails, otherwise they're bound as a uniform buffer at
+/// [`shader::PARAMS_DESCRIPTOR_SET_INDEX`], binding [`shader::PARAMS_BINDING`].
+pub fn draw_with_params<P: Pod + Send + Sync>(
+    pass: &mut render_pass::frame::Pass,
+    drawable: Arc<dyn Drawable>,
+    shader: shader::ShaderId,
+    info: DrawInfo,
+    params: P,
+) -> Result<()> {
+    pass.push_state();
+    pass.set_shader(shader);
+    pass.set_shader_params(params);
+    let result = pass.draw(drawable, info);
+    pass.pop_state();
+
+    result
+}
+
+/// Draws a drawable using a model matrix computed elsewhere (e.g. by a physics engine),
+/// skipping the `Transform::Components` path entirely.
+pub fn draw_matrix(
+    pass: &mut render_pass::frame::Pass,
+    drawable: Arc<dyn Drawable>,
+    shader: shader::ShaderId,
+    matrix: Matrix4<f32>,
+    color: Color,
+    tex_rect: Rect,
+) -> Result<()> {
+    pass.draw_with(drawable, shader, DrawInfo::with_matrix(matrix, color, tex_rect))
+}
+
+/// Draws `image` centered on `center` at `size`, rotated `rotation` radians about its center.
+///
+/// Both are in the units of the current camera, so with a camera from
+/// [`CoordinateSystem::PixelsTopLeft`] they're pixels, independent of the image's own dimensions.
+pub fn draw_sprite(
+    pass: &mut render_pass::frame::Pass,
+    image: &image::Image,
+    shader: shader::ShaderId,
+    center: (f32, f32),
+    size: (f32, f32),
+    rotation: f32,
+    color: Color,
+) -> Result<()> {
+    pass.draw_with(
+        Arc::new(image.clone()),
+        shader,
+        DrawInfo::centered(center, size, rotation, color),
+    )
+}
+
+/// Fills `dest` with `color`, using the pass's current blend mode and transform.
+///
+/// No image is needed, the fill is drawn with the built-in [`shader::Shader::colored`] program
+/// like the untextured shapes of [`render_pass::frame::Pass::draw_immediate`], so no texture
+/// is bound. A shader must still be set on the pass for the subpass it's drawn in. `dest` is
+/// in the units of the current camera, `(dest.x, dest.y)` being its top left corner under
+/// [`CoordinateSystem::PixelsTopLeft`].
+pub fn draw_quad(pass: &mut render_pass::frame::Pass, dest: Rect, color: Color) -> Result<()> {
+    let shader = pass
+        .state()
+        .shader
+        .ok_or_else(|| anyhow::anyhow!("no shader set for the current draw state"))?;
+
+    let transform = pass.transform().as_mat4();
+    let corner = |x: f32, y: f32| {
+        let p = transform * Vector4::new(x, y, 0.0, 1.0);
+        (p.x, p.y)
+    };
+
+    let mut batch = immediate::ImmediateBatch::with_white(pass.white_image()?);
+    batch.set_blend_mode(pass.state().blend_mode);
+    batch.quad(
+        [
+            corner(dest.x, dest.y),
+            corner(dest.x, dest.y + dest.h),
+            corner(dest.x + dest.w, dest.y),
+            corner(dest.x + dest.w, dest.y + dest.h),
+        ],
+        color,
+    );
+
+    pass.draw_immediate(&mut batch, shader)
+}
+
+/// Shows or hides the diagnostics overlay drawn by [`debug::present`], with the frame rate,
+/// frame time percentiles, draw counts, memory usage, and GPU utilization of recent frames.
+///
+/// Frame data is only collected while it's shown, and it doesn't need the `debug-draw` feature
+/// or a font.
+pub fn show_diagnostics(renderer: &mut renderer::Renderer, show: bool) {
+    renderer.debug.diagnostics.set_enabled(show);
+}
+
+/// Moves the diagnostics overlay to `corner` of the viewport, the top left by default.
+pub fn set_diagnostics_corner(renderer: &mut renderer::Renderer, corner: debug::Corner) {
+    renderer.debug.diagnostics.corner = corner;
+}
+
+/// Device heap sizes and tracked texture and buffer usage.
+pub fn memory_info(renderer: &renderer::Renderer) -> memory::MemoryInfo {
+    memory::MemoryInfo::query(renderer.device.physical_device())
+}
+
+/// Captures the frame being drawn as a PNG no larger than `max_size`, keeping its aspect
+/// ratio, without waiting on the GPU. Poll the handle in later frames until it's ready, see
+/// [`capture`].
+pub fn capture_thumbnail(renderer: &mut renderer::Renderer, max_size: (u32, u32)) -> capture::ThumbnailHandle {
+    renderer.captures.request(max_size)
+}
+
+/// Submits every deferred image upload now instead of waiting for the next frame.
+pub fn flush_uploads(renderer: &mut renderer::Renderer) -> Result<()> {
+    if let Some(future) = renderer.uploads.flush()? {
+        renderer.previous_frame_end = Some(match renderer.previous_frame_end.take() {
+            Some(previous) => previous.join(future).boxed(),
+            None => future,
+        });
+    }
+
+    Ok(())
+}
+
+/// A shader and blend mode combination to compile ahead of time with [`warm_up`].
+///
+/// The vertex layout is fixed per shader when it's registered, so every layout in use is
+/// covered by listing each shader that uses it.
+#[derive(Clone, Copy, PartialEq)]
+pub struct WarmUpRequest {
+    pub shader: shader::ShaderId,
+    pub blend_mode: BlendMode,
+}
+
+/// Compiles the pipelines for every request, typically behind a loading screen, so the first
+/// draw using a new blend mode doesn't stall on pipeline creation.
+///
+/// `progress` is called after each request with the number completed and the total. Use
+/// [`render_pass::RenderPass::pipeline_stats`] afterwards to confirm nothing is compiled at runtime.
+pub fn warm_up<F: FnMut(usize, usize)>(
+    render_pass: &render_pass::RenderPass,
+    requests: &[WarmUpRequest],
+    mut progress: F,
+) -> Result<()> {
+    for (i, request) in requests.iter().enumerate() {
+        let shader = render_pass
+            .shader(request.shader)
+            .ok_or_else(|| anyhow::anyhow!("no shader registered with id {}", request.shader))?;
+
+        shader.warm_up(request.blend_mode)?;
+
+        progress(i + 1, requests.len());
+    }
+
+    Ok(())
 }
 
 pub struct PipelineData {
@@ -92,6 +486,21 @@ impl PipelineData {
         self
     }
 
+    /// Binds `image` with its own sampler, sampling the placeholder while a deferred upload is pending.
+    pub fn texture(mut self, binding: u32, image: &image::Image) -> Self {
+        self.descriptors = Vec::new();
+
+        self.descriptors
+            .push(WriteDescriptorSet::image_view_sampler(
+                binding,
+                image.view(),
+                image.sampler().clone(),
+            ));
+
+        self
+    }
+
+    #[cfg(feature = "unstable-raw")]
     pub fn sampled_image(
         mut self,
         binding: u32,
@@ -133,29 +542,6 @@ impl PipelineData {
 
         self
     }
-
-    fn new(device: Arc<vulkano::device::Device>) -> Self {
-        Self {
-            device: device.clone(),
-            vertex_buffer: CpuAccessibleBuffer::from_iter(
-                device.clone(),
-                vulkano::buffer::BufferUsage::vertex_buffer(),
-                true,
-                [Vertex::default()].iter().cloned(),
-            )
-            .unwrap(),
-            vertex_count: 0,
-            instance_buffer: CpuAccessibleBuffer::from_iter(
-                device.clone(),
-                vulkano::buffer::BufferUsage::vertex_buffer(),
-                true,
-                [InstanceData::default()].iter().cloned(),
-            )
-            .unwrap(),
-            instance_count: 0,
-            descriptors: Vec::new(),
-        }
-    }
 }
 
 #[repr(C)]
@@ -169,21 +555,42 @@ pub struct Vertex {
 vulkano::impl_vertex!(Vertex, pos, uv, vert_color);
 
 #[repr(C)]
-#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+#[derive(Clone, Copy, Debug, Zeroable, Pod)]
 pub struct InstanceData {
     src: [f32; 4],
     color: [f32; 4],
     transform: [[f32; 4]; 4],
+    flags: u32,
+    corner_radius: f32,
+    /// RGBA8 colors of the unit quad's corners, see [`DrawInfo::set_corner_colors`].
+    corner_colors: [u32; 4],
 }
 
-vulkano::impl_vertex!(InstanceData, src, color, transform);
+vulkano::impl_vertex!(InstanceData, src, color, transform, flags, corner_radius, corner_colors);
+
+/// Bit set in `InstanceData::flags` when the color should replace the texture color.
+const INSTANCE_FLAG_REPLACE_COLOR: u32 = 1;
+/// Bit set in `InstanceData::flags` when the shader should premultiply the color by its alpha.
+const INSTANCE_FLAG_PREMULTIPLY: u32 = 2;
+/// Bit set in `InstanceData::flags` when the texture is premultiplied, so the tint's alpha
+/// scales its color too, see [`image::Image::is_premultiplied`].
+const INSTANCE_FLAG_PREMULTIPLIED_TEXTURE: u32 = 4;
+
+impl Default for InstanceData {
+    fn default() -> Self {
+        DrawInfo::default().into()
+    }
+}
 
 impl From<DrawInfo> for InstanceData {
     fn from(info: DrawInfo) -> InstanceData {
         InstanceData {
-            src: info.tex_rect.as_vec(),
-            color: info.color.into(),
+            src: info.instance_src(),
+            color: info.instance_color(),
             transform: info.transform.as_mat4().into(),
+            flags: info.instance_flags(),
+            corner_radius: info.corner_radius,
+            corner_colors: info.packed_corner_colors(),
         }
     }
 }
@@ -191,14 +598,22 @@ impl From<DrawInfo> for InstanceData {
 impl From<&DrawInfo> for InstanceData {
     fn from(info: &DrawInfo) -> InstanceData {
         InstanceData {
-            src: info.tex_rect.as_vec(),
-            color: info.color.into(),
+            src: info.instance_src(),
+            color: info.instance_color(),
             transform: info.transform.as_mat4().into(),
+            flags: info.instance_flags(),
+            corner_radius: info.corner_radius,
+            corner_colors: info.packed_corner_colors(),
         }
     }
 }
 
-const QUAD_VERTICES: [Vertex; 4] = [
+/// The unit quad every sprite is drawn from, spanning (0, 0) to (1, 1) with matching uvs.
+///
+/// The vertex order forms a triangle strip whose two triangles share the same winding, the
+/// one [`QUAD_INDICES`] also uses: counter-clockwise in Vulkan's y-down framebuffer space,
+/// see [`QUAD_FRONT_FACE`]. Culling back faces with it keeps quads that aren't mirrored.
+pub const QUAD_VERTICES: [Vertex; 4] = [
     Vertex {
         pos: [0.0, 0.0, 0.0],
         uv: [0.0, 0.0],
@@ -221,6 +636,36 @@ const QUAD_VERTICES: [Vertex; 4] = [
     },
 ];
 
+/// Indices of the two triangles making up [`QUAD_VERTICES`] for indexed drawing.
+///
+/// Both triangles are wound clockwise when +y points up, which is counter-clockwise in
+/// Vulkan's y-down framebuffer space.
+pub const QUAD_INDICES: [u32; 6] = [0, 1, 2, 1, 3, 2];
+
+/// The front face of [`QUAD_VERTICES`] and [`QUAD_INDICES`] drawn through a transform and
+/// camera that don't mirror them.
+pub const QUAD_FRONT_FACE: FrontFace = FrontFace::CounterClockwise;
+
+/// The front face of quads drawn through `mvp`, the product of a camera and a draw's
+/// transform.
+///
+/// Mirroring one axis reverses the winding: a projection with +y pointing up does it, as does
+/// a negative scale on a flipped sprite. Pipelines culling back faces need the face to match,
+/// or the quads they mean to keep are the ones culled.
+pub fn quad_front_face(mvp: [[f32; 4]; 4]) -> FrontFace {
+    let determinant = mvp[0][0] * mvp[1][1] - mvp[1][0] * mvp[0][1];
+    if determinant < 0.0 {
+        FrontFace::Clockwise
+    } else {
+        QUAD_FRONT_FACE
+    }
+}
+
+/// A [`Mesh`] built from [`QUAD_VERTICES`] and [`QUAD_INDICES`].
+pub fn quad_mesh() -> mesh::Mesh {
+    mesh::Mesh::new(QUAD_VERTICES.to_vec(), QUAD_INDICES.to_vec())
+}
+
 pub mod vs {
     vulkano_shaders::shader! { ty: "vertex", path: "src/graphics/shaders/texture.vert", }
 }
@@ -229,11 +674,59 @@ pub mod fs {
     vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/texture.frag", }
 }
 
+/// The built-in fragment shader with a normal map, used with [`vs`] for sprites drawn by a
+/// [`sprite::SpriteBatch`] with [`sprite::SpriteBatch::set_normal_map`].
+pub mod lit_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/texture_lit.frag", }
+}
+
+/// The built-in fragment shader for untextured shapes, used with [`vs`] by
+/// [`immediate::ImmediateBatch`] for shapes drawn without a texture. It binds no sampler.
+pub mod colored_fs {
+    vulkano_shaders::shader! { ty: "fragment", path: "src/graphics/shaders/colored.frag", }
+}
+
+/// How the vertex and instance colors combine with the texture in the built-in fragment shader.
+#[derive(Debug, Default, Clone, Copy, PartialEq, Eq)]
+pub enum ColorMode {
+    /// The texture color is tinted by the draw color.
+    #[default]
+    Multiply,
+    /// The draw color is used as is and the texture is ignored, for solid shapes.
+    Replace,
+}
+
+impl ColorMode {
+    fn flags(&self) -> u32 {
+        match self {
+            ColorMode::Multiply => 0,
+            ColorMode::Replace => INSTANCE_FLAG_REPLACE_COLOR,
+        }
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub struct DrawInfo {
     pub tex_rect: Rect,
     pub color: Color,
     pub transform: Transform,
+    pub color_mode: ColorMode,
+    /// Multiplies the alpha of `color` when drawn, see [`DrawInfo::set_opacity`].
+    pub opacity: f32,
+    /// Rounds the corners of the drawn quad, see [`DrawInfo::set_corner_radius`].
+    pub corner_radius: f32,
+    /// Sorting layer, higher layers draw over lower ones, see [`layers`].
+    pub layer: i16,
+    /// Order within the layer, higher orders draw over lower ones.
+    pub order: i16,
+    /// Colors blended across the quad and multiplied with `color`, see [`DrawInfo::set_corner_colors`].
+    pub corner_colors: [Color; 4],
+    /// Shifts the texture within `tex_rect`, see [`DrawInfo::uv_offset`].
+    pub uv_offset: (f32, f32),
+    /// Repeats the texture within `tex_rect`, see [`DrawInfo::uv_scale`].
+    pub uv_scale: (f32, f32),
+    /// Multiplies the drawn color by its alpha in the shader, see [`DrawInfo::set_premultiply`].
+    pub premultiply: bool,
 }
 
 impl Default for DrawInfo {
@@ -242,297 +735,335 @@ impl Default for DrawInfo {
             tex_rect: Rect::default(),
             color: Color::white(),
             transform: Transform::identity(),
+            color_mode: ColorMode::Multiply,
+            opacity: 1.0,
+            corner_radius: 0.0,
+            layer: 0,
+            order: 0,
+            corner_colors: [Color::white(); 4],
+            uv_offset: (0.0, 0.0),
+            uv_scale: (1.0, 1.0),
+            premultiply: false,
         }
     }
 }
 
 impl DrawInfo {
     pub fn new() -> Self {
-        Self {
-            tex_rect: Rect::default(),
-            color: Color::white(),
-            transform: Transform::identity(),
-        }
+        Self::default()
     }
 
     pub fn reset(&mut self) {
-        self.tex_rect = Rect::default();
-        self.color = Color::white();
-        self.transform = Transform::identity();
+        *self = Self::default();
     }
 
     pub fn with_rect(rect: Rect) -> Self {
         Self {
             tex_rect: rect,
-            color: Color::white(),
-            transform: Transform::identity(),
+            ..Self::default()
         }
     }
 
     pub fn with_transform(transform: Transform) -> Self {
         Self {
-            tex_rect: Rect::default(),
-            color: Color::white(),
-            transform: transform,
+            transform,
+            ..Self::default()
         }
     }
 
     pub fn with_color(color: Color) -> Self {
         Self {
-            tex_rect: Rect::default(),
-            color: color,
-            transform: Transform::identity(),
+            color,
+            ..Self::default()
         }
     }
 
-    pub fn color(&mut self, color: Color) {
-        self.color = color;
+    pub fn with_matrix(matrix: Matrix4<f32>, color: Color, tex_rect: Rect) -> Self {
+        Self {
+            tex_rect,
+            color,
+            transform: Transform::Matrix(matrix),
+            ..Self::default()
+        }
     }
 
-    pub fn tex_offset(&mut self, offset: (f32, f32)) {
-        self.tex_rect.x = offset.0;
-        self.tex_rect.y = offset.1;
+    pub fn with_corner_radius(radius: f32) -> Self {
+        let mut info = Self::new();
+        info.set_corner_radius(radius);
+        info
     }
 
-    pub fn translate(&mut self, x: f32, y: f32, z: f32) {
-        self.transform.translate(x, y, z);
+    pub fn with_all(pos: (f32, f32, f32), scale: f32, rotation: f32, color: Color) -> Self {
+        let mut info = Self::with_color(color);
+        info.dest(pos.0, pos.1, pos.2);
+        info.scale(scale);
+        info.rotate_value(rotation);
+        info
     }
 
-    pub fn rotate(&mut self, x: f32, y: f32, z: f32) {
-        self.transform.rotate(x, y, z);
+    /// A draw of the unit quad stretched to `size`, rotated `rotation` radians about its center,
+    /// and centered on `center`.
+    pub fn centered(center: (f32, f32), size: (f32, f32), rotation: f32, color: Color) -> Self {
+        let matrix = Matrix4::from_translation(Vector3::new(center.0, center.1, 0.0))
+            * Matrix4::from_angle_z(Rad(rotation))
+            * Matrix4::from_nonuniform_scale(size.0, size.1, 1.0)
+            * Matrix4::from_translation(Vector3::new(-0.5, -0.5, 0.0));
+
+        Self::with_matrix(matrix, color, Rect::default())
     }
 
-    pub fn rotate_value(&mut self, r: f32) {
-        self.transform.rotate_value(Rad(r));
+    pub fn with_position_scale(x: f32, y: f32, s: f32) -> Self {
+        let mut info = Self::new();
+        info.dest(x, y, 0.0);
+        info.scale(s);
+        info
     }
 
-    pub fn nonuniform_scale(&mut self, x: f32, y: f32, z: f32) {
-        self.transform.nonuniform_scale(x, y, z);
+    pub fn with_position_color(x: f32, y: f32, z: f32, color: Color) -> Self {
+        let mut info = Self::with_color(color);
+        info.dest(x, y, z);
+        info
     }
 
-    pub fn scale(&mut self, s: f32) {
-        self.transform.nonuniform_scale(s, s, s);
+    pub fn color(&mut self, color: Color) {
+        self.color = color;
     }
 
-    pub fn dest(&mut self, x: f32, y: f32, z: f32) {
-        self.transform.dest(x, y, z);
+    /// Fades the draw without changing its tint, `0.5` halves the alpha of whatever color is set.
+    pub fn set_opacity(&mut self, opacity: f32) {
+        self.opacity = opacity;
     }
-}
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub enum Transform {
-    Components {
-        pos: Vector3<f32>,
-        rotation: Rad<f32>,
-        scale: Vector3<f32>,
-        offset: Vector3<f32>,
-    },
-    Matrix(Matrix4<f32>),
-}
+    pub fn opacity(&self) -> f32 {
+        self.opacity
+    }
 
-impl Default for Transform {
-    fn default() -> Self {
-        Transform::identity()
+    /// Clips the quad to a rounded rectangle, with `radius` in pixels measured against the
+    /// quad's drawn size, so a `4.0` radius rounds off four pixels of each corner whatever the
+    /// camera or the quad's scale.
+    ///
+    /// The radius is clamped to half the shorter side, anything beyond that is a pill or circle.
+    /// Only the built-in fragment shader applies it, `0.0` draws square corners.
+    pub fn set_corner_radius(&mut self, radius: f32) {
+        self.corner_radius = radius.max(0.0);
     }
-}
 
-impl Transform {
-    fn identity() -> Self {
-        // Self::Matrix(Matrix4::identity())
-        Self::Components {
-            pos: Vector3::from((0.0, 0.0, 0.0)),
-            rotation: Rad(0.0),
-            scale: Vector3::from((1.0, 1.0, 1.0)),
-            offset: Vector3::from((0.0, 0.0, 0.0)),
-        }
+    pub fn corner_radius(&self) -> f32 {
+        self.corner_radius
     }
 
-    pub fn as_mat4(&self) -> Matrix4<f32> {
-        match self {
-            Transform::Matrix(mat) => *mat,
-            Transform::Components {
-                pos,
-                rotation,
-                scale,
-                offset,
-            } => {
-                let (sinr, cosr) = rotation.sin_cos();
-                let cr00 = cosr * scale.x;
-                let cr01 = -sinr * scale.y;
-                let cr10 = sinr * scale.x;
-                let cr11 = cosr * scale.y;
-                let cr03 = offset.x * (1.0 - cr00) - offset.y * cr01 + pos.x;
-                let cr13 = offset.y * (1.0 - cr11) - offset.x * cr10 + pos.y;
-
-                Matrix4::from_cols(
-                    Vector4::new(cr00, cr01, 0.0, cr03),
-                    Vector4::new(cr10, cr11, 0.0, cr13),
-                    Vector4::new(0.0, 0.0, 1.0, 0.0),
-                    Vector4::new(0.0, 0.0, 0.0, 1.0),
-                )
-                .transpose()
-            }
-        }
+    /// Puts the draw on sorting layer `layer`, honoured by queued draws such as
+    /// [`sprite::SpriteRenderer`]. Draws recorded directly with
+    /// [`render_pass::frame::Pass::draw`] ignore it. Layer 0 is [`layers::DEFAULT_LAYER`].
+    pub fn set_layer(&mut self, layer: i16) {
+        self.layer = layer;
     }
 
-    fn dest(&mut self, x: f32, y: f32, z: f32) {
-        match self {
-            Transform::Matrix(_mat) => {
-                // *mat = Matrix4::from_translation(Vector3::new(x, y, z)) * *mat;
-            }
-            Transform::Components { pos, .. } => {
-                *pos = Vector3::from((x, y, z));
+    /// Puts the draw on the layer registered as `name` with [`register_layer`], returns `false`
+    /// and leaves the layer unchanged if there isn't one.
+    pub fn set_layer_named(&mut self, name: &str) -> bool {
+        match layers::layer(name) {
+            Some(layer) => {
+                self.layer = layer;
+                true
             }
+            None => false,
         }
     }
 
-    fn translate(&mut self, x: f32, y: f32, z: f32) {
-        match self {
-            Transform::Matrix(mat) => {
-                *mat = Matrix4::from_translation(Vector3::new(x, y, z)) * *mat;
-            }
-            Transform::Components { pos, .. } => {
-                *pos += Vector3::from((x, y, z));
-            }
-        }
+    pub fn layer(&self) -> i16 {
+        self.layer
     }
 
-    fn rotate(&mut self, x: f32, y: f32, z: f32) {
-        let rotation = Matrix4::from_angle_x(Deg(x))
-            + Matrix4::from_angle_y(Deg(y))
-            + Matrix4::from_angle_z(Deg(z));
-        match self {
-            Transform::Matrix(mat) => {
-                *mat = rotation * *mat;
-            }
-            Transform::Components {
-                // rotation,
-                ..
-            } => {
-                // *rotation += Rad(3.14);
-            }
-        }
+    /// Sets the draw's order within its layer.
+    pub fn set_order(&mut self, order: i16) {
+        self.order = order;
     }
 
-    fn rotate_value(&mut self, r: Rad<f32>) {
-        match self {
-            Transform::Matrix(_) => {}
-            Transform::Components { rotation, .. } => {
-                *rotation = r;
-            }
-        }
+    pub fn order(&self) -> i16 {
+        self.order
     }
 
-    fn nonuniform_scale(&mut self, x: f32, y: f32, z: f32) {
-        match self {
-            Transform::Matrix(mat) => {
-                println!("{:?}", Matrix4::from_nonuniform_scale(x, y, z));
-                *mat = Matrix4::from_nonuniform_scale(x, y, z) * *mat;
-            }
-            Transform::Components { scale, .. } => {
-                *scale = Vector3::from((x, y, z));
-            }
-        }
+    /// The layer and order combined so that comparing keys compares layers first, then orders.
+    pub fn sort_key(&self) -> u32 {
+        let layer = (self.layer as u16 ^ 0x8000) as u32;
+        let order = (self.order as u16 ^ 0x8000) as u32;
+        layer << 16 | order
     }
-}
 
-impl From<Color> for [f32; 4] {
-    fn from(color: Color) -> [f32; 4] {
-        color.0
+    /// Tints each corner of the quad, blending between them across it. The corners are in the
+    /// order of the unit quad's (0, 0), (1, 0), (0, 1), and (1, 1), which is the texture's top
+    /// left, top right, bottom left, and bottom right.
+    ///
+    /// The colors multiply [`DrawInfo::color`] and the texture, so white corners change nothing.
+    /// They're blended by the vertex's position within the unit quad, so meshes and shapes that
+    /// aren't drawn from the unit quad should leave them white.
+    pub fn set_corner_colors(&mut self, colors: [Color; 4]) {
+        self.corner_colors = colors;
     }
-}
 
-#[derive(Clone, Copy, Debug, PartialEq)]
-pub struct Color([f32; 4]);
+    /// A gradient from `left` on the quad's left edge to `right` on its right edge.
+    pub fn horizontal_gradient(&mut self, left: Color, right: Color) {
+        self.corner_colors = [left, right, left, right];
+    }
 
-impl From<[f32; 4]> for Color {
-    fn from(a: [f32; 4]) -> Color {
-        Color(a)
+    /// A gradient from `top` on the quad's top edge, where the texture's top row is, to
+    /// `bottom` on its bottom edge.
+    pub fn vertical_gradient(&mut self, top: Color, bottom: Color) {
+        self.corner_colors = [top, top, bottom, bottom];
+    }
+
+    /// A gradient from `from` in the top left corner to `to` in the bottom right.
+    pub fn diagonal_gradient(&mut self, from: Color, to: Color) {
+        let middle = from.lerp(to, 0.5);
+        self.corner_colors = [from, middle, middle, to];
+    }
+
+    /// Scrolls the texture by `(x, y)` widths and heights of `tex_rect`, e.g. increase `x` a
+    /// little every frame for flowing water or a conveyor belt.
+    ///
+    /// Offsets and [`DrawInfo::uv_scale`] apply in `tex_rect`'s own space, the quad samples
+    /// `tex_rect.xy + (uv * scale + offset) * tex_rect.wh`. Anything beyond the rect wraps
+    /// according to the sampler, so seamless scrolling and tiling need `tex_rect` to cover the
+    /// whole texture and a sampler with [`sampler::AddressMode::Repeat`], the default. With a
+    /// sub-rect of an atlas the neighbouring sprites show through instead.
+    pub fn uv_offset(&mut self, x: f32, y: f32) {
+        self.uv_offset = (x, y);
+    }
+
+    /// Tiles the texture `x` times across the quad and `y` times down it, see
+    /// [`DrawInfo::uv_offset`] for how this interacts with `tex_rect` and the sampler.
+    pub fn uv_scale(&mut self, x: f32, y: f32) {
+        self.uv_scale = (x, y);
+    }
+
+    /// Whether the built-in fragment shaders multiply the final color, texture times tint and
+    /// corner colors, by its alpha before blending.
+    ///
+    /// Turn it on for draws with [`BlendMode::PremultipliedAlpha`] from straight alpha
+    /// textures, so semi-transparent tints and gradients aren't brightened where they fade.
+    /// Leave it off for [`BlendMode::Alpha`], which multiplies by alpha itself, and for
+    /// textures whose colors are already premultiplied.
+    pub fn set_premultiply(&mut self, premultiply: bool) {
+        self.premultiply = premultiply;
+    }
+
+    /// The instance flags for the color mode and premultiplying.
+    fn instance_flags(&self) -> u32 {
+        let premultiply = if self.premultiply { INSTANCE_FLAG_PREMULTIPLY } else { 0 };
+        self.color_mode.flags() | premultiply
+    }
+
+    /// `tex_rect` with the uv offset and scale folded in, as the instance data's `src`.
+    fn instance_src(&self) -> [f32; 4] {
+        let rect = self.tex_rect;
+        [
+            rect.x + self.uv_offset.0 * rect.w,
+            rect.y + self.uv_offset.1 * rect.h,
+            rect.w * self.uv_scale.0,
+            rect.h * self.uv_scale.1,
+        ]
+    }
+
+    /// The corner colors packed as RGBA8 for the instance data.
+    fn packed_corner_colors(&self) -> [u32; 4] {
+        self.corner_colors.map(|color| u32::from_le_bytes(color.to_rgba8()))
+    }
+
+    /// The color written to the instance data, with the opacity applied to its alpha.
+    fn instance_color(&self) -> [f32; 4] {
+        let mut color: [f32; 4] = self.color.into();
+        color[3] *= self.opacity;
+        color
     }
-}
 
-impl Color {
-    pub fn rgba(r: u8, g: u8, b: u8, a: u8) -> Color {
-        Color([
-            r as f32 / 255.,
-            g as f32 / 255.,
-            b as f32 / 255.,
-            a as f32 / 255.,
-        ])
+    pub fn color_mode(&mut self, mode: ColorMode) {
+        self.color_mode = mode;
+    }
+
+    pub fn tex_offset(&mut self, offset: (f32, f32)) {
+        self.tex_rect.x = offset.0;
+        self.tex_rect.y = offset.1;
+    }
+
+    pub fn translate(&mut self, x: f32, y: f32, z: f32) {
+        self.transform.translate(x, y, z);
     }
 
-    pub fn black() -> Color {
-        Color([0.0, 0.0, 0.0, 1.0])
+    pub fn rotate(&mut self, x: f32, y: f32, z: f32) {
+        self.transform.rotate(x, y, z);
     }
 
-    pub fn grey() -> Color {
-        Color([0.25, 0.25, 0.25, 1.0])
+    pub fn rotate_value(&mut self, r: f32) {
+        self.transform.rotate_value(Rad(r));
     }
 
-    pub fn white() -> Color {
-        Color([1.0, 1.0, 1.0, 1.0])
+    pub fn nonuniform_scale(&mut self, x: f32, y: f32, z: f32) {
+        self.transform.nonuniform_scale(x, y, z);
     }
 
-    pub fn red() -> Color {
-        Color([1.0, 0.05, 0.05, 1.0])
+    pub fn scale(&mut self, s: f32) {
+        self.transform.nonuniform_scale(s, s, s);
     }
 
-    pub fn transparent() -> Color {
-        Color([0.0, 0.0, 0.0, 0.0])
+    pub fn dest(&mut self, x: f32, y: f32, z: f32) {
+        self.transform.dest(x, y, z);
     }
+}
 
-    pub fn as_u8_arr(&self) -> [u8; 4] {
-        let mut arr = [0u8; 4];
-        arr[0] = (self.0[0] * 255.) as u8;
-        arr[1] = (self.0[1] * 255.) as u8;
-        arr[2] = (self.0[2] * 255.) as u8;
-        arr[3] = (self.0[3] * 255.) as u8;
-        arr
+impl From<(f32, f32)> for DrawInfo {
+    fn from(pos: (f32, f32)) -> DrawInfo {
+        DrawInfo::from((pos.0, pos.1, 0.0))
     }
+}
 
-    pub fn as_u8_vec(&self) -> Vec<u8> {
-        let mut v = Vec::new();
-        v.push((self.0[0] * 255.) as u8);
-        v.push((self.0[1] * 255.) as u8);
-        v.push((self.0[2] * 255.) as u8);
-        v.push((self.0[3] * 255.) as u8);
-        v
+impl From<(f32, f32, f32)> for DrawInfo {
+    fn from(pos: (f32, f32, f32)) -> DrawInfo {
+        let mut info = DrawInfo::with_transform(Transform::identity());
+        info.dest(pos.0, pos.1, pos.2);
+        info
     }
 }
 
-impl Default for Color {
-    fn default() -> Color {
-        Color::black()
+impl From<[f32; 3]> for DrawInfo {
+    fn from(pos: [f32; 3]) -> DrawInfo {
+        DrawInfo::from((pos[0], pos[1], pos[2]))
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
-pub struct Rect {
-    pub x: f32,
-    pub y: f32,
-    pub w: f32,
-    pub h: f32,
+impl From<Vector3<f32>> for DrawInfo {
+    fn from(pos: Vector3<f32>) -> DrawInfo {
+        DrawInfo::from((pos.x, pos.y, pos.z))
+    }
 }
 
-impl Rect {
-    pub fn as_vec(&self) -> [f32; 4] {
-        [self.x, self.y, self.w, self.h]
+impl From<&Rect> for DrawInfo {
+    fn from(rect: &Rect) -> DrawInfo {
+        DrawInfo::with_rect(*rect)
     }
 }
 
-impl Default for Rect {
-    fn default() -> Self {
-        Rect {
-            x: 0.0,
-            y: 0.0,
-            w: 1.0,
-            h: 1.0,
-        }
+/// Uses the rect as the `tex_rect`, the region of the texture to draw.
+impl From<Rect> for DrawInfo {
+    fn from(rect: Rect) -> DrawInfo {
+        DrawInfo::with_rect(rect)
+    }
+}
+
+impl From<Transform> for DrawInfo {
+    fn from(transform: Transform) -> DrawInfo {
+        DrawInfo::with_transform(transform)
     }
 }
 
-impl Into<[Vertex; 4]> for Rect {
-    fn into(self) -> [Vertex; 4] {
+impl From<Color> for DrawInfo {
+    fn from(color: Color) -> DrawInfo {
+        DrawInfo::with_color(color)
+    }
+}
+
+impl From<Rect> for [Vertex; 4] {
+    fn from(rect: Rect) -> [Vertex; 4] {
         [
             Vertex {
                 pos: [0.0, 0.0, 0.0],
@@ -540,20 +1071,401 @@ impl Into<[Vertex; 4]> for Rect {
                 vert_color: [1.0, 1.0, 1.0, 1.0],
             },
             Vertex {
-                pos: [0.0, self.h * 1.0, 0.0],
+                pos: [0.0, rect.h * 1.0, 0.0],
                 uv: [0.0, 1.0],
                 vert_color: [1.0, 1.0, 1.0, 1.0],
             },
             Vertex {
-                pos: [self.w * 1.0, 0.0, 0.0],
+                pos: [rect.w * 1.0, 0.0, 0.0],
                 uv: [1.0, 0.0],
                 vert_color: [1.0, 1.0, 1.0, 1.0],
             },
             Vertex {
-                pos: [self.w * 1.0, self.h * 1.0, 0.0],
+                pos: [rect.w * 1.0, rect.h * 1.0, 0.0],
                 uv: [1.0, 1.0],
                 vert_color: [1.0, 1.0, 1.0, 1.0],
             },
         ]
     }
 }
+
+/// A queue and sampler on the first device with a graphics queue, without a window, for tests
+/// that record GPU work. Those tests are ignored by default since CI has no Vulkan driver.
+#[cfg(test)]
+pub(crate) fn test_device() -> (Arc<Queue>, Arc<Sampler>) {
+    use vulkano::device::physical::PhysicalDevice;
+    use vulkano::device::{DeviceCreateInfo, QueueCreateInfo};
+    use vulkano::instance::{Instance, InstanceCreateInfo};
+
+    // Validation errors are printed by the layer itself when it's installed.
+    let instance = Instance::new(InstanceCreateInfo {
+        enabled_layers: renderer::Renderer::validation_layers(true),
+        ..Default::default()
+    })
+    .expect("no Vulkan driver");
+    let (physical, family) = PhysicalDevice::enumerate(&instance)
+        .find_map(|p| p.queue_families().find(|q| q.supports_graphics()).map(|q| (p, q)))
+        .expect("no device with a graphics queue");
+    let (device, mut queues) = Device::new(
+        physical,
+        DeviceCreateInfo {
+            enabled_extensions: *physical.required_extensions(),
+            // The optional features the renderer turns on when the device has them.
+            enabled_features: vulkano::device::Features {
+                wide_lines: physical.supported_features().wide_lines,
+                fill_mode_non_solid: physical.supported_features().fill_mode_non_solid,
+                ..vulkano::device::Features::none()
+            },
+            queue_create_infos: vec![QueueCreateInfo::family(family)],
+            ..Default::default()
+        },
+    )
+    .unwrap();
+    let sampler = sampler::SamplerConfig::default().build(device).unwrap();
+    (queues.next().unwrap(), sampler)
+}
+
+/// Format of the targets [`render_offscreen`] draws into.
+#[cfg(test)]
+pub(crate) const TEST_TARGET_FORMAT: vulkano::format::Format = vulkano::format::Format::R8G8B8A8_UNORM;
+
+/// A render pass drawing to a single [`TEST_TARGET_FORMAT`] target, with the built-in sprite
+/// shaders registered as shader 0.
+#[cfg(test)]
+pub(crate) fn test_pass(queue: Arc<Queue>) -> render_pass::RenderPass {
+    test_pass_for(queue, TEST_TARGET_FORMAT)
+}
+
+/// [`test_pass`] drawing to a target of `format` instead, such as a swapchain image.
+#[cfg(test)]
+pub(crate) fn test_pass_for(queue: Arc<Queue>, format: vulkano::format::Format) -> render_pass::RenderPass {
+    use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+    let device = queue.device().clone();
+    let vk_pass = vulkano::single_pass_renderpass!(device.clone(),
+        attachments: {
+            color: {
+                load: Clear,
+                store: Store,
+                format: format,
+                samples: 1,
+            }
+        },
+        pass: {
+            color: [color],
+            depth_stencil: {}
+        }
+    )
+    .unwrap();
+
+    let mut pass = render_pass::RenderPass::new(queue, vk_pass).unwrap();
+    let shader = Arc::new(shader::Shader {
+        vertex: vs::load(device.clone()).unwrap(),
+        fragment: fs::load(device).unwrap(),
+        topology: shader::VertexTopology::TriangleStrip,
+    });
+    pass.register_shader(shader, BuffersDefinition::new().vertex::<Vertex>().instance::<InstanceData>())
+        .unwrap();
+    pass
+}
+
+/// Draws a frame of `pass`, made with [`test_pass`] or for a target of the same format, into a
+/// new `width` by `height` target cleared to `clear`, and reads the target back as RGBA8 rows.
+///
+/// The camera is [`CoordinateSystem::PixelsTopLeft`] and `draw` starts with shader 0 set.
+#[cfg(test)]
+pub(crate) fn render_offscreen<F>(
+    queue: &Arc<Queue>,
+    pass: &mut render_pass::RenderPass,
+    extent: [u32; 2],
+    clear: Color,
+    draw: F,
+) -> Vec<u8>
+where
+    F: FnOnce(&mut render_pass::frame::Pass) -> Result<()>,
+{
+    let target = test_target(queue, extent);
+    render_into(pass, vulkano::image::view::ImageView::new_default(target.clone()).unwrap(), clear, draw);
+    read_target(queue, target)
+}
+
+/// A [`TEST_TARGET_FORMAT`] color target that [`read_target`] can read back.
+#[cfg(test)]
+pub(crate) fn test_target(queue: &Arc<Queue>, extent: [u32; 2]) -> Arc<vulkano::image::AttachmentImage> {
+    use vulkano::image::{AttachmentImage, ImageUsage};
+
+    AttachmentImage::with_usage(
+        queue.device().clone(),
+        extent,
+        TEST_TARGET_FORMAT,
+        ImageUsage {
+            transfer_source: true,
+            sampled: true,
+            ..ImageUsage::color_attachment()
+        },
+    )
+    .unwrap()
+}
+
+/// Like [`render_offscreen`], drawing into `target` and waiting for the frame to finish
+/// instead of reading it back.
+#[cfg(test)]
+pub(crate) fn render_into<F>(
+    pass: &mut render_pass::RenderPass,
+    target: Arc<dyn vulkano::image::view::ImageViewAbstract>,
+    clear: Color,
+    draw: F,
+) where
+    F: FnOnce(&mut render_pass::frame::Pass) -> Result<()>,
+{
+    use vulkano::device::DeviceOwned;
+
+    let [width, height] = target.image().dimensions().width_height();
+    let device = target.device().clone();
+    let viewport = Rect { x: 0.0, y: 0.0, w: width as f32, h: height as f32 };
+    let camera = camera::OrthographicCamera::with_projection(CoordinateSystem::PixelsTopLeft.projection(&viewport));
+    let mut frame = pass
+        .frame(clear.into(), vulkano::sync::now(device).boxed(), target, Arc::new(camera))
+        .unwrap();
+
+    let mut draw = Some(draw);
+    while let Some(state) = frame.next_pass().unwrap() {
+        match state {
+            render_pass::frame::PassState::DrawPass(mut pass) => {
+                pass.set_shader(0);
+                if let Some(draw) = draw.take() {
+                    draw(&mut pass).unwrap();
+                }
+            }
+            render_pass::frame::PassState::Finished(future) => {
+                future.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+            }
+        }
+    }
+}
+
+/// Reads `target`, made with [`test_target`], back as RGBA8 rows once the GPU is idle with it.
+#[cfg(test)]
+pub(crate) fn read_target(queue: &Arc<Queue>, target: Arc<vulkano::image::AttachmentImage>) -> Vec<u8> {
+    use vulkano::command_buffer::CommandBufferUsage;
+    use vulkano::image::ImageAccess;
+
+    let device = queue.device().clone();
+    let [width, height] = target.dimensions().width_height();
+    let buffer = CpuAccessibleBuffer::from_iter(
+        device.clone(),
+        BufferUsage::transfer_destination(),
+        false,
+        (0..width * height * 4).map(|_| 0u8),
+    )
+    .unwrap();
+    let mut builder = AutoCommandBufferBuilder::primary(device.clone(), queue.family(), CommandBufferUsage::OneTimeSubmit).unwrap();
+    builder.copy_image_to_buffer(target, buffer.clone()).unwrap();
+    vulkano::sync::now(device)
+        .then_execute(queue.clone(), builder.build().unwrap())
+        .unwrap()
+        .then_signal_fence_and_flush()
+        .unwrap()
+        .wait(None)
+        .unwrap();
+
+    let pixels = buffer.read().unwrap().to_vec();
+    pixels
+}
+
+/// The RGBA8 pixel at `(x, y)` of `pixels`, rows `width` pixels long.
+#[cfg(test)]
+pub(crate) fn pixel_at(pixels: &[u8], width: u32, (x, y): (u32, u32)) -> [u8; 4] {
+    let i = ((y * width + x) * 4) as usize;
+    [pixels[i], pixels[i + 1], pixels[i + 2], pixels[i + 3]]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Twice the signed area of a triangle the way Vulkan measures it in framebuffer space,
+    /// positive for counter-clockwise.
+    fn framebuffer_area(a: &Vertex, b: &Vertex, c: &Vertex) -> f32 {
+        -((b.pos[0] - a.pos[0]) * (c.pos[1] - a.pos[1]) - (c.pos[0] - a.pos[0]) * (b.pos[1] - a.pos[1]))
+    }
+
+    #[test]
+    fn quad_indices_wind_counter_clockwise() {
+        for triangle in QUAD_INDICES.chunks(3) {
+            let [a, b, c] = [0, 1, 2].map(|i| &QUAD_VERTICES[triangle[i] as usize]);
+            assert!(framebuffer_area(a, b, c) > 0.0, "{:?}", triangle);
+        }
+    }
+
+    #[test]
+    fn quad_strip_winds_counter_clockwise() {
+        // Odd triangles in a strip swap their first two vertices to keep the winding.
+        for i in 0..QUAD_VERTICES.len() - 2 {
+            let (a, b) = if i % 2 == 0 { (i, i + 1) } else { (i + 1, i) };
+            let area = framebuffer_area(&QUAD_VERTICES[a], &QUAD_VERTICES[b], &QUAD_VERTICES[i + 2]);
+            assert!(area > 0.0, "triangle {}", i);
+        }
+    }
+
+    #[test]
+    fn rect_converts_to_a_tex_rect() {
+        let rect = Rect { x: 0.25, y: 0.5, w: 0.25, h: 0.5 };
+        assert_eq!(DrawInfo::from(rect), DrawInfo { tex_rect: rect, ..DrawInfo::default() });
+    }
+
+    #[test]
+    fn transform_converts_to_a_transform() {
+        let transform = Transform::from_trs(cgmath::Vector2::new(10.0, 20.0), Rad(1.0), cgmath::Vector2::new(2.0, 3.0));
+        assert_eq!(DrawInfo::from(transform), DrawInfo { transform, ..DrawInfo::default() });
+    }
+
+    #[test]
+    fn color_converts_to_a_color() {
+        let color = Color::red();
+        assert_eq!(DrawInfo::from(color), DrawInfo { color, ..DrawInfo::default() });
+    }
+
+    #[test]
+    fn mirrored_transforms_flip_the_front_face() {
+        let mirrored = Matrix4::from_nonuniform_scale(-1.0, 1.0, 1.0);
+        assert!(matches!(quad_front_face(Matrix4::identity().into()), FrontFace::CounterClockwise));
+        assert!(matches!(quad_front_face(mirrored.into()), FrontFace::Clockwise));
+    }
+
+    #[test]
+    fn with_all_matches_the_chained_setters() {
+        let mut chained = DrawInfo::new();
+        chained.color(Color::red());
+        chained.dest(10.0, 20.0, 0.5);
+        chained.scale(2.0);
+        chained.rotate_value(1.0);
+        assert_eq!(DrawInfo::with_all((10.0, 20.0, 0.5), 2.0, 1.0, Color::red()), chained);
+    }
+
+    #[test]
+    fn partial_constructors_match_the_chained_setters() {
+        let mut chained = DrawInfo::new();
+        chained.dest(10.0, 20.0, 0.0);
+        chained.scale(2.0);
+        assert_eq!(DrawInfo::with_position_scale(10.0, 20.0, 2.0), chained);
+
+        let mut chained = DrawInfo::new();
+        chained.color(Color::red());
+        chained.dest(10.0, 20.0, 0.5);
+        assert_eq!(DrawInfo::with_position_color(10.0, 20.0, 0.5, Color::red()), chained);
+    }
+
+    #[test]
+    fn matrix_draws_match_the_equivalent_components() {
+        let matrix = Matrix4::from_translation(Vector3::new(10.0, 20.0, 0.0))
+            * Matrix4::from_angle_z(Rad(0.5))
+            * Matrix4::from_nonuniform_scale(2.0, 2.0, 1.0);
+        let tex_rect = Rect { x: 0.0, y: 0.5, w: 0.5, h: 0.5 };
+        let raw = InstanceData::from(DrawInfo::with_matrix(matrix, Color::red(), tex_rect));
+
+        let mut info = DrawInfo::with_all((10.0, 20.0, 0.0), 2.0, 0.5, Color::red());
+        info.tex_rect = tex_rect;
+        let components = InstanceData::from(info);
+
+        assert_eq!(raw.src, components.src);
+        assert_eq!(raw.color, components.color);
+        let (raw, components): ([f32; 16], [f32; 16]) =
+            (bytemuck::cast(raw.transform), bytemuck::cast(components.transform));
+        for (a, b) in raw.iter().zip(components.iter()) {
+            assert!((a - b).abs() < 1e-5, "{:?} != {:?}", raw, components);
+        }
+    }
+
+    #[test]
+    fn half_opacity_halves_alpha_and_keeps_the_tint() {
+        let mut info = DrawInfo::with_color(Color::rgba(255, 128, 0, 200));
+        let opaque = InstanceData::from(&info);
+        info.set_opacity(0.5);
+        let faded = InstanceData::from(&info);
+
+        assert_eq!(faded.color[..3], opaque.color[..3]);
+        assert_eq!(faded.color[3], opaque.color[3] * 0.5);
+        assert_eq!(info.color, Color::rgba(255, 128, 0, 200));
+    }
+
+    #[test]
+    fn positions_convert_to_a_destination() {
+        let mut expected = DrawInfo::with_transform(Transform::identity());
+        expected.dest(10.0, 20.0, 0.5);
+        assert_eq!(DrawInfo::from((10.0, 20.0, 0.5)), expected);
+        assert_eq!(DrawInfo::from([10.0, 20.0, 0.5]), expected);
+        assert_eq!(DrawInfo::from(Vector3::new(10.0, 20.0, 0.5)), expected);
+
+        let mut expected = DrawInfo::with_transform(Transform::identity());
+        expected.dest(10.0, 20.0, 0.0);
+        assert_eq!(DrawInfo::from((10.0, 20.0)), expected);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn quads_fill_through_the_colored_program_and_the_pushed_transform() {
+        let (queue, _) = test_device();
+        let mut pass = test_pass(queue.clone());
+
+        let pixels = render_offscreen(&queue, &mut pass, [16, 16], Color::black(), |pass| {
+            pass.push_transform(Transform::from_trs(
+                cgmath::Vector2::new(4.0, 0.0),
+                Rad(0.0),
+                cgmath::Vector2::new(1.0, 1.0),
+            ));
+            draw_quad(pass, Rect { x: 0.0, y: 4.0, w: 8.0, h: 8.0 }, Color::red())?;
+            assert_eq!(pass.stats().draw_calls, 1);
+            Ok(())
+        });
+
+        assert_eq!(pixel_at(&pixels, 16, (8, 8)), [255, 0, 0, 255]);
+        assert_eq!(pixel_at(&pixels, 16, (2, 8)), [0, 0, 0, 255]);
+        assert_eq!(pixel_at(&pixels, 16, (8, 2)), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn corner_radius_is_in_pixels_of_the_drawn_quad() {
+        let (queue, sampler) = test_device();
+        let mut pass = test_pass(queue.clone());
+        let white = Arc::new(image::Image::from_color(queue.clone(), sampler, Color::white()));
+
+        // Under an identity camera the unit quad covers the bottom right 16x16 pixels, so a
+        // radius taken in its own units would clamp to a circle.
+        let pixels = render_offscreen(&queue, &mut pass, [32, 32], Color::rgba(0, 0, 0, 0), |pass| {
+            pass.set_camera(Matrix4::identity().into())?;
+            pass.draw(white, DrawInfo::with_corner_radius(3.0))
+        });
+
+        assert_eq!(pixel_at(&pixels, 32, (16, 16))[3], 0);
+        assert_eq!(pixel_at(&pixels, 32, (24, 24))[3], 255);
+        assert_eq!(pixel_at(&pixels, 32, (16, 20))[3], 255);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn replace_mode_ignores_the_texture() {
+        let (queue, sampler) = test_device();
+        let mut pass = test_pass(queue.clone());
+        let red = Arc::new(image::Image::from_color(queue.clone(), sampler, Color::red()));
+
+        let mut draw = |mode: ColorMode| {
+            let pixels = render_offscreen(&queue, &mut pass, [4, 4], Color::white(), |pass| {
+                let mut info = DrawInfo::centered((2.0, 2.0), (4.0, 4.0), 0.0, Color::rgba(0, 0, 255, 255));
+                info.color_mode(mode);
+                pass.draw(red.clone(), info)
+            });
+            pixel_at(&pixels, 4, (2, 2))
+        };
+
+        assert_eq!(draw(ColorMode::Replace), [0, 0, 255, 255]);
+        assert_eq!(draw(ColorMode::Multiply), [0, 0, 0, 255]);
+    }
+
+    #[test]
+    fn rect_reference_converts_to_a_tex_rect() {
+        let rect = Rect { x: 0.25, y: 0.5, w: 0.25, h: 0.5 };
+        assert_eq!(DrawInfo::from(&rect), DrawInfo { tex_rect: rect, ..DrawInfo::default() });
+    }
+}
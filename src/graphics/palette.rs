@@ -0,0 +1,146 @@
+//! Named colors shared by everything drawn in a consistent style.
+//!
+//! The renderer holds one [`Palette`], the [`Palette::default`] theme until it's replaced with
+//! [`set_palette`]. The debug overlay reads its colors from it, under the names in
+//! [`names`], and games can look up their own names alongside those:
+//!
+//! ```ignore
+//! let mut palette = Palette::default();
+//! palette.set("accent", Color::rgba(255, 120, 40, 255));
+//! graphics::set_palette(renderer, palette);
+//! let accent = graphics::palette(renderer).get("accent");
+//! ```
+//!
+//! With the `serde` feature a palette serializes as a map from names to `[r, g, b, a]` colors,
+//! so themes can be kept in whichever settings format the game already uses.
+
+use std::collections::HashMap;
+
+use crate::graphics::renderer::Renderer;
+use crate::graphics::Color;
+
+/// Names the built-in helpers look colors up by.
+pub mod names {
+    /// Text drawn by [`crate::graphics::debug::text`] and the diagnostics overlay.
+    pub const TEXT: &str = "text";
+    /// Background of the diagnostics overlay.
+    pub const PANEL: &str = "panel";
+    /// Lines drawn by [`crate::graphics::debug::grid`].
+    pub const GRID: &str = "grid";
+    /// Highlights and selections.
+    pub const ACCENT: &str = "accent";
+    pub const BACKGROUND: &str = "background";
+    pub const ERROR: &str = "error";
+    pub const WARNING: &str = "warning";
+}
+
+/// Returned by [`Palette::get`] for names neither the palette nor the default theme has, a
+/// loud magenta so missing entries are easy to spot.
+pub const MISSING_COLOR: Color = Color([1.0, 0.0, 1.0, 1.0]);
+
+/// A set of colors looked up by name.
+#[derive(Clone, Debug, PartialEq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+#[cfg_attr(feature = "serde", serde(transparent))]
+pub struct Palette {
+    colors: HashMap<String, Color>,
+}
+
+impl Default for Palette {
+    /// The default theme, which defines every name in [`names`].
+    fn default() -> Self {
+        let mut palette = Self::empty();
+        palette.set(names::TEXT, Color::white());
+        palette.set(names::PANEL, Color::rgba(0, 0, 0, 160));
+        palette.set(names::GRID, Color::rgba(255, 255, 255, 48));
+        palette.set(names::ACCENT, Color::rgba(80, 160, 255, 255));
+        palette.set(names::BACKGROUND, Color::grey());
+        palette.set(names::ERROR, Color::red());
+        palette.set(names::WARNING, Color::rgba(255, 190, 40, 255));
+        palette
+    }
+}
+
+impl Palette {
+    /// A palette with no colors, every lookup falls back to the default theme.
+    pub fn empty() -> Self {
+        Self {
+            colors: HashMap::new(),
+        }
+    }
+
+    /// The color named `name`, falling back to the default theme's color of that name, then
+    /// to [`MISSING_COLOR`].
+    pub fn get(&self, name: &str) -> Color {
+        self.try_get(name)
+            .or_else(|| Self::default().try_get(name))
+            .unwrap_or(MISSING_COLOR)
+    }
+
+    /// The color named `name` in this palette, without any fallback.
+    pub fn try_get(&self, name: &str) -> Option<Color> {
+        self.colors.get(name).copied()
+    }
+
+    pub fn set(&mut self, name: &str, color: Color) {
+        self.colors.insert(name.to_string(), color);
+    }
+
+    pub fn remove(&mut self, name: &str) -> Option<Color> {
+        self.colors.remove(name)
+    }
+
+    /// Every name this palette defines, in no particular order.
+    pub fn names(&self) -> impl Iterator<Item = &str> {
+        self.colors.keys().map(|name| name.as_str())
+    }
+}
+
+/// Replaces the renderer's palette, used by the following draws.
+pub fn set_palette(ctx: &mut Renderer, palette: Palette) {
+    ctx.palette = palette;
+}
+
+pub fn palette(ctx: &Renderer) -> &Palette {
+    &ctx.palette
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_resolve_then_fall_back_to_the_default_theme() {
+        let mut palette = Palette::empty();
+        palette.set(names::ACCENT, Color::red());
+        palette.set("player", Color::black());
+
+        assert_eq!(palette.get(names::ACCENT), Color::red());
+        assert_eq!(palette.get("player"), Color::black());
+        assert_eq!(palette.get(names::TEXT), Palette::default().get(names::TEXT));
+        assert_eq!(palette.try_get(names::TEXT), None);
+        assert_eq!(palette.get("missing"), MISSING_COLOR);
+    }
+
+    #[test]
+    fn default_theme_defines_every_built_in_name() {
+        let palette = Palette::default();
+        for name in [names::TEXT, names::PANEL, names::GRID, names::ACCENT, names::BACKGROUND, names::ERROR, names::WARNING] {
+            assert!(palette.try_get(name).is_some(), "{}", name);
+        }
+    }
+
+    #[cfg(feature = "serde")]
+    #[test]
+    fn palettes_load_from_a_map_of_names() {
+        let palette: Palette = serde_json::from_str(r#"{ "accent": [1.0, 0.0, 0.0, 1.0], "player": [0.0, 0.0, 0.0, 1.0] }"#).unwrap();
+
+        assert_eq!(palette.get(names::ACCENT), Color([1.0, 0.0, 0.0, 1.0]));
+        assert_eq!(palette.get("player"), Color([0.0, 0.0, 0.0, 1.0]));
+        assert_eq!(palette.get(names::PANEL), Palette::default().get(names::PANEL));
+        assert_eq!(palette.get("missing"), MISSING_COLOR);
+
+        let json = serde_json::to_string(&palette).unwrap();
+        assert_eq!(serde_json::from_str::<Palette>(&json).unwrap(), palette);
+    }
+}
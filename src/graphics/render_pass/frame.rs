@@ -1,28 +1,52 @@
 use vulkano::{
     command_buffer::{
-        AutoCommandBufferBuilder, PrimaryAutoCommandBuffer,
+        AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer,
     },
     // render_pass::{Framebuffer},
-    sync::{GpuFuture},
+    render_pass::{RenderPass, Subpass},
+    sync::{GpuFuture, PipelineStage},
     device::Queue,
 };
 
-use anyhow::Result;
+use anyhow::{anyhow, Result};
+use std::cell::{Cell, RefCell};
 use std::sync::Arc;
 
 use crate::graphics::shader::*;
-use crate::graphics::{Drawable, DrawInfo};
+use crate::graphics::sampler::SamplerConfig;
+use crate::graphics::{BlendMode, Color, Drawable, DrawInfo, DrawState, DrawStats, InstanceData, Rect, Transform, Vertex};
+use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+use crate::graphics::image::Image;
+use crate::graphics::immediate::ImmediateBatch;
+use crate::graphics::mask::ActiveMask;
+use crate::graphics::render_pass::timing::GpuTimer;
+use crate::graphics::camera::{CameraUniform, CAMERA_UNIFORM_BINDING, CAMERA_UNIFORM_SET};
 // use crate::graphics::camera::Camera2D;
 
 pub struct Frame<'p> {
     pub(crate) pipelines: &'p Vec<Box<dyn ShaderHandle>>,
+    /// See [`super::RenderPass`]'s field of the same name.
+    pub(crate) colored: &'p RefCell<Option<ShaderProgram>>,
+    /// See [`super::RenderPass`]'s field of the same name.
+    pub(crate) white: &'p RefCell<Option<Image>>,
     pub(crate) num_pass: u8,
     pub(crate) cur_pass: u8,
     pub(crate) queue: Arc<Queue>,
     pub(crate) before_main_cb_future: Option<Box<dyn GpuFuture>>,
     // pub(crate) framebuffer: Arc<Framebuffer>,
     pub(crate) command_buffer: Option<AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>>,
+    pub(crate) timer: Option<&'p GpuTimer>,
+    pub(crate) render_pass: Arc<RenderPass>,
+    /// Start queries of the gpu timer scopes not yet ended, innermost last.
+    pub(crate) gpu_scopes: Vec<Option<u32>>,
     // pub(crate) camera: Camera2D,
+    pub(crate) state: DrawState,
+    pub(crate) state_stack: Vec<DrawState>,
+    /// Each entry is the product of every transform pushed up to it.
+    pub(crate) transforms: Vec<Transform>,
+    pub(crate) stats: DrawStats,
+    /// Where `stats` is saved once the frame is finished.
+    pub(crate) last_stats: &'p Cell<DrawStats>,
 }
 
 impl<'p> Frame<'p> {
@@ -34,13 +58,22 @@ impl<'p> Frame<'p> {
                     frame: self,
                 }))
             } else if self.cur_pass == self.num_pass {
+                while !self.gpu_scopes.is_empty() {
+                    self.end_gpu_timer()?;
+                }
+
                 self.cur_pass += 1;
                 self.command_buffer
                 .as_mut()
                 .unwrap()
                 .end_render_pass()?;
 
+                if let Some(timer) = self.timer {
+                    timer.end(self.command_buffer.as_mut().unwrap())?;
+                }
+
                 let command_buffer = self.command_buffer.take().unwrap().build()?;
+                self.last_stats.set(self.stats);
 
                 let after_main_cb = self
                     .before_main_cb_future
@@ -57,6 +90,38 @@ impl<'p> Frame<'p> {
     }
 }
 
+impl<'p> Frame<'p> {
+    fn end_gpu_timer(&mut self) -> Result<()> {
+        if let Some(Some(start)) = self.gpu_scopes.pop() {
+            self.write_timestamp(start + 1, PipelineStage::BottomOfPipe)?;
+        }
+
+        Ok(())
+    }
+
+    /// Timestamps can't be written by the primary command buffer inside a render pass
+    /// recorded with secondary command buffers, so each one gets a secondary of its own.
+    fn write_timestamp(&mut self, query: u32, stage: PipelineStage) -> Result<()> {
+        let timer = match self.timer {
+            Some(timer) => timer,
+            None => return Ok(()),
+        };
+
+        let subpass = Subpass::from(self.render_pass.clone(), self.cur_pass as u32 - 1).unwrap();
+        let mut builder = AutoCommandBufferBuilder::secondary_graphics(
+            self.queue.device().clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+            subpass,
+        )?;
+        timer.write(&mut builder, query, stage)?;
+
+        self.command_buffer.as_mut().unwrap().execute_commands(builder.build()?)?;
+
+        Ok(())
+    }
+}
+
 pub enum PassState<'f, 'p: 'f> {
     DrawPass(Pass<'f, 'p>),
     Finished(Box<dyn GpuFuture>),
@@ -67,17 +132,318 @@ pub struct Pass<'f, 'p> {
 }
 
 impl<'f, 'p> Pass<'f, 'p> {
-    pub fn draw_with(&mut self, d: Arc<dyn Drawable>, id: ShaderId, draw_info: DrawInfo) -> Result<()> {
+    pub fn state(&self) -> &DrawState {
+        &self.frame.state
+    }
+
+    /// Saves the current draw state so it can be restored with [`Pass::pop_state`].
+    ///
+    /// The stack lives for the whole frame, so state pushed in one pass can be popped in a later one.
+    pub fn push_state(&mut self) {
+        self.frame.state_stack.push(self.frame.state.clone());
+    }
+
+    /// Restores the most recently pushed draw state, does nothing if the stack is empty.
+    pub fn pop_state(&mut self) {
+        if let Some(state) = self.frame.state_stack.pop() {
+            self.frame.state = state;
+        }
+    }
+
+    pub fn set_shader(&mut self, id: ShaderId) {
+        self.frame.state.shader = Some(id);
+    }
+
+    /// Parameters bound for the following draws' shaders, see [`crate::graphics::draw_with_params`].
+    pub fn set_shader_params<P: bytemuck::Pod + Send + Sync>(&mut self, params: P) {
+        self.frame.state.params = Some(ShaderParams::new(params));
+    }
+
+    pub fn clear_shader_params(&mut self) {
+        self.frame.state.params = None;
+    }
+
+    pub fn set_blend_mode(&mut self, mode: BlendMode) {
+        self.frame.state.blend_mode = mode;
+    }
+
+    /// Rasterizes the following draws as `mode`, e.g. [`PolygonMode::Line`] to outline a single
+    /// mesh. Draws stay filled without the device's `fill_mode_non_solid` feature.
+    pub fn set_polygon_mode(&mut self, mode: PolygonMode) {
+        self.frame.state.polygon_mode = mode;
+    }
+
+    /// Replaces the camera matrix, uploading it for the draws that follow.
+    pub fn set_camera(&mut self, mvp: [[f32; 4]; 4]) -> Result<()> {
+        self.frame.state.upload_camera(self.frame.queue.device().clone(), mvp)
+    }
+
+    /// The camera uniform bound for the draws that follow.
+    pub fn camera_uniform(&self) -> CameraUniform {
+        self.frame.state.camera.into()
+    }
+
+    /// The descriptor set and binding the camera uniform is bound to, for custom shaders.
+    pub fn camera_uniform_binding(&self) -> (usize, u32) {
+        (CAMERA_UNIFORM_SET, CAMERA_UNIFORM_BINDING)
+    }
+
+    /// Clips the image draws that follow by `mask`, or stops clipping them.
+    pub(crate) fn set_mask(&mut self, mask: Option<ActiveMask>) {
+        self.frame.state.mask = mask;
+    }
+
+    /// A 1x1 white image shared by the render pass, for [`ImmediateBatch`]es of solid fills
+    /// that shouldn't upload one of their own.
+    pub(crate) fn white_image(&self) -> Result<Image> {
+        if let Some(white) = self.frame.white.borrow().as_ref() {
+            return Ok(white.clone());
+        }
+
+        let sampler = SamplerConfig::default().build(self.frame.queue.device().clone())?;
+        let white = Image::from_color(self.frame.queue.clone(), sampler, Color::white());
+        self.frame.white.replace(Some(white.clone()));
+        Ok(white)
+    }
+
+    /// The Vulkan render pass being recorded, for building pipelines to draw within it.
+    pub(crate) fn vk_render_pass(&self) -> Arc<RenderPass> {
+        self.frame.render_pass.clone()
+    }
+
+    pub fn set_viewport(&mut self, viewport: Rect) {
+        self.frame.state.viewport = viewport;
+    }
+
+    /// Nests `transform` inside the current one, so later draws are moved by `transform` and
+    /// then by everything pushed before it, until the matching [`Pass::pop_transform`].
+    pub fn push_transform(&mut self, transform: Transform) {
+        let nested = match self.frame.transforms.last() {
+            Some(parent) => transform.then(parent),
+            None => transform,
+        };
+        self.frame.transforms.push(nested);
+    }
+
+    /// Undoes the last [`Pass::push_transform`], does nothing if the stack is empty.
+    pub fn pop_transform(&mut self) {
+        self.frame.transforms.pop();
+    }
+
+    /// Starts timing the GPU work recorded from here to the matching [`Pass::end_gpu_timer`]
+    /// under `label`. Scopes can nest and span passes, any still open are ended with the frame.
+    ///
+    /// Results appear in [`super::RenderPass::gpu_timings`] a few frames later, once the GPU
+    /// has finished with them. Does nothing if the device doesn't support timestamps.
+    pub fn begin_gpu_timer(&mut self, label: &str) -> Result<()> {
+        let start = self.frame.timer.and_then(|timer| timer.begin_scope(label));
+        self.frame.gpu_scopes.push(start);
+
+        match start {
+            Some(start) => self.frame.write_timestamp(start, PipelineStage::TopOfPipe),
+            None => Ok(()),
+        }
+    }
+
+    /// Ends the most recently started gpu timer scope, does nothing if none are open.
+    pub fn end_gpu_timer(&mut self) -> Result<()> {
+        self.frame.end_gpu_timer()
+    }
+
+    /// Draw calls and instances recorded so far this frame.
+    pub fn stats(&self) -> DrawStats {
+        self.frame.stats
+    }
+
+    /// The product of the pushed transforms, applied after each draw's own transform.
+    pub fn transform(&self) -> Transform {
+        self.frame.transforms.last().copied().unwrap_or_default()
+    }
+
+    /// Draws with the current shader set by [`Pass::set_shader`].
+    ///
+    /// The draw is recorded right away, so it lands over everything drawn before it whatever
+    /// its [`DrawInfo::layer`]. Queue sprites in a [`crate::graphics::sprite::SpriteRenderer`]
+    /// to have them sorted by layer.
+    pub fn draw(&mut self, d: Arc<dyn Drawable>, mut draw_info: DrawInfo) -> Result<()> {
+        if let Some(parent) = self.frame.transforms.last() {
+            draw_info.transform = draw_info.transform.then(parent);
+        }
+
+        let id = self
+            .frame
+            .state
+            .shader
+            .ok_or_else(|| anyhow!("no shader set for the current draw state"))?;
         let shader_handle = self.frame.pipelines.get(id).unwrap();
 
         let commands = d.draw(
             self.frame.queue.clone(),
-            shader_handle,
+            shader_handle.as_ref(),
+            &self.frame.state,
             draw_info,
         )?;
 
         self.frame.command_buffer.as_mut().unwrap().execute_commands(commands)?;
+        self.frame.stats = self.frame.stats + d.stats();
 
         Ok(())
     }
-}
\ No newline at end of file
+
+    /// Draws with the shader `id` without changing the current draw state.
+    pub fn draw_with(&mut self, d: Arc<dyn Drawable>, id: ShaderId, draw_info: DrawInfo) -> Result<()> {
+        self.push_state();
+        self.set_shader(id);
+        let result = self.draw(d, draw_info);
+        self.pop_state();
+
+        result
+    }
+
+    /// Draws every shape recorded in `batch` since its last flush, then empties it.
+    ///
+    /// Shapes recorded without a texture are drawn with the built-in [`Shader::colored`]
+    /// program instead of `id`, unless turned off with
+    /// [`ImmediateBatch::set_untextured_pipeline`].
+    pub fn draw_immediate(&mut self, batch: &mut ImmediateBatch, id: ShaderId) -> Result<()> {
+        let shader_handle = self.frame.pipelines.get(id).unwrap();
+
+        if batch.wants_untextured_pipeline() && self.frame.colored.borrow().is_none() {
+            let device = self.frame.queue.device().clone();
+            let shader = Shader::colored(device.clone())?;
+            let program = ShaderProgram::new(
+                device,
+                self.frame.render_pass.clone(),
+                BuffersDefinition::new().vertex::<Vertex>().instance::<InstanceData>(),
+                shader.topology,
+                shader.vertex.clone(),
+                shader.fragment.clone(),
+                BlendMode::Alpha,
+            )?;
+            self.frame.colored.replace(Some(program));
+        }
+
+        let colored = self.frame.colored.borrow();
+        let colored = colored.as_ref().map(|program| program as &dyn ShaderHandle);
+
+        if let Some(commands) = batch.flush(self.frame.queue.clone(), shader_handle.as_ref(), colored, &self.frame.state)? {
+            self.frame.command_buffer.as_mut().unwrap().execute_commands(commands)?;
+
+            let stats = batch.stats();
+            self.frame.stats = self.frame.stats + DrawStats {
+                draw_calls: stats.draw_calls,
+                instances: stats.shapes,
+            };
+        }
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::camera::{Camera, OrthographicCamera};
+    use crate::graphics::test_device;
+    use cgmath::{Rad, Vector2};
+    use vulkano::format::Format;
+    use vulkano::image::view::ImageView;
+    use vulkano::image::AttachmentImage;
+
+    /// Runs `test` on the first pass of a frame drawing to a 64x64 target.
+    fn with_pass<F: FnOnce(&mut Pass)>(test: F) {
+        with_camera_pass(Arc::new(OrthographicCamera::new(0.0, 1.0)), test)
+    }
+
+    /// Like [`with_pass`], viewing the frame through `camera`.
+    fn with_camera_pass<F: FnOnce(&mut Pass)>(camera: Arc<dyn Camera>, test: F) {
+        let (queue, _) = test_device();
+        let device = queue.device().clone();
+        let render_pass = vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::B8G8R8A8_SRGB,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+        .unwrap();
+        let mut pass = crate::graphics::render_pass::RenderPass::new(queue, render_pass).unwrap();
+        let target = ImageView::new_default(AttachmentImage::new(device.clone(), [64, 64], Format::B8G8R8A8_SRGB).unwrap()).unwrap();
+        let mut frame = pass
+            .frame([0.0; 4], vulkano::sync::now(device).boxed(), target, camera)
+            .unwrap();
+        match frame.next_pass().unwrap() {
+            Some(PassState::DrawPass(mut pass)) => test(&mut pass),
+            _ => unreachable!(),
+        };
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn camera_uniform_matches_the_uploaded_view_projection() {
+        let mut camera = OrthographicCamera::new(0.0, 1.0);
+        camera.translate_x(3.0);
+        camera.translate_y(-2.0);
+        let expected = CameraUniform::from(camera.as_mvp());
+
+        with_camera_pass(Arc::new(camera), |pass| {
+            assert_eq!(pass.camera_uniform(), expected);
+            let uploaded = pass.frame.state.camera_buffer(pass.frame.queue.device().clone()).unwrap();
+            assert_eq!(*uploaded.read().unwrap(), expected);
+
+            let moved = cgmath::Matrix4::from_scale(2.0f32).into();
+            pass.set_camera(moved).unwrap();
+            assert_eq!(pass.camera_uniform(), CameraUniform::from(moved));
+            let uploaded = pass.frame.state.camera_buffer(pass.frame.queue.device().clone()).unwrap();
+            assert_eq!(*uploaded.read().unwrap(), CameraUniform::from(moved));
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn popping_a_transform_restores_the_one_before_it() {
+        with_pass(|pass| {
+            let parent = Transform::from_trs(Vector2::new(10.0, 20.0), Rad(0.5), Vector2::new(2.0, 2.0));
+            let child = Transform::from_trs(Vector2::new(5.0, 0.0), Rad(0.0), Vector2::new(1.0, 1.0));
+
+            assert_eq!(pass.transform(), Transform::default());
+            pass.push_transform(parent);
+            let before = pass.transform();
+            pass.push_transform(child);
+            assert_eq!(pass.transform(), child.then(&parent));
+
+            pass.pop_transform();
+            assert_eq!(pass.transform(), before);
+            pass.pop_transform();
+            assert_eq!(pass.transform(), Transform::default());
+        });
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn popping_state_restores_the_shader_and_camera() {
+        with_pass(|pass| {
+            pass.set_shader(1);
+            let camera = pass.camera_uniform();
+
+            pass.push_state();
+            pass.set_shader(2);
+            pass.set_camera(cgmath::Matrix4::from_scale(2.0).into()).unwrap();
+            pass.set_blend_mode(BlendMode::Add);
+            assert_eq!(pass.state().shader, Some(2));
+            assert_ne!(pass.camera_uniform(), camera);
+
+            pass.pop_state();
+            assert_eq!(pass.state().shader, Some(1));
+            assert_eq!(pass.camera_uniform(), camera);
+            assert_eq!(pass.state().blend_mode, BlendMode::Alpha);
+        });
+    }
+}
@@ -1,28 +1,45 @@
 use vulkano::image::ImageViewAbstract;
 use vulkano::device::Queue;
 use vulkano::sync::GpuFuture;
-use crate::graphics::camera::Camera;
-use crate::graphics::shader::{Shader, ShaderId, ShaderHandle, ShaderProgram};
+use crate::graphics::camera::{self, Camera};
+use crate::graphics::shader::{PipelineStats, PolygonMode, Shader, ShaderId, ShaderHandle, ShaderInfo, ShaderProgram};
 
 use vulkano::{
-    render_pass::{Framebuffer, FramebufferCreateInfo},
+    format::ClearValue,
+    render_pass::{Framebuffer, FramebufferCreateInfo, LoadOp, Subpass},
     command_buffer::{
         AutoCommandBufferBuilder, CommandBufferUsage, SubpassContents,
     },
 };
 
+use std::cell::{Cell, RefCell};
 use std::sync::Arc;
 
 use anyhow::*;
 
 use vulkano::pipeline::graphics::vertex_input::VertexDefinition;
-use crate::graphics::BlendMode;
+use crate::graphics::{BlendMode, DrawState, DrawStats, Rect};
+use crate::graphics::image::Image;
 
 pub mod frame;
+pub mod timing;
 
 pub struct RenderPass {
     queue: Arc<Queue>,
     shaders: Vec<Box<dyn ShaderHandle>>,
+    timer: Option<timing::GpuTimer>,
+    stats: Cell<DrawStats>,
+    /// The program [`frame::Pass::draw_immediate`] draws untextured shapes with, built the first
+    /// time it's needed.
+    colored: RefCell<Option<ShaderProgram>>,
+    /// The 1x1 white image [`crate::graphics::draw_quad`] hands its batch, made the first time
+    /// it's needed.
+    white: RefCell<Option<Image>>,
+    polygon_mode: PolygonMode,
+    /// The part of the target frames are drawn to, `None` for all of it.
+    render_region: Option<Rect>,
+    /// The camera uniform each frame starts with, see [`camera::CameraRing`].
+    cameras: camera::CameraRing,
     pub render_pass: Arc<vulkano::render_pass::RenderPass>,
 }
 
@@ -31,11 +48,92 @@ impl RenderPass {
         Ok(Self {
             queue: queue.clone(),
             shaders: Vec::new(),
+            timer: timing::GpuTimer::new(&queue),
+            stats: Cell::new(DrawStats::default()),
+            colored: RefCell::new(None),
+            white: RefCell::new(None),
+            polygon_mode: PolygonMode::Fill,
+            render_region: None,
+            cameras: camera::CameraRing::new(queue.device().clone())?,
             render_pass,
         })
     }
 
-    pub fn register_shader<Vd: VertexDefinition + 'static + Sync + Send>(&mut self, shader: Arc<Shader>, v_type: Vd) -> Result<ShaderId> {
+    pub fn shader(&self, id: ShaderId) -> Option<&dyn ShaderHandle> {
+        self.shaders.get(id).map(|s| s.as_ref())
+    }
+
+    /// Subpass `index` of the Vulkan render pass, for building pipelines by hand. `None` if
+    /// the render pass has no such subpass.
+    pub fn subpass(&self, index: u32) -> Option<Subpass> {
+        Subpass::from(self.render_pass.clone(), index)
+    }
+
+    /// How triangles are rasterized at the start of each frame, see
+    /// [`crate::graphics::renderer::Renderer::set_wireframe`]. Draws can change it mid-frame
+    /// with [`frame::Pass::set_polygon_mode`].
+    pub fn set_polygon_mode(&mut self, mode: PolygonMode) {
+        self.polygon_mode = mode;
+    }
+
+    pub fn polygon_mode(&self) -> PolygonMode {
+        self.polygon_mode
+    }
+
+    /// Limits each frame's viewport and scissor to `region` of the target in pixels, see
+    /// [`crate::graphics::renderer::Renderer::set_render_region`]. The region is clamped to
+    /// the target when a frame begins.
+    pub fn set_render_region(&mut self, region: Option<Rect>) {
+        self.render_region = region;
+    }
+
+    pub fn render_region(&self) -> Option<Rect> {
+        self.render_region
+    }
+
+    /// Every shader registered with this pass in id order, with its blend mode and compiled
+    /// pipelines, to check warm up covered the modes drawn with.
+    pub fn shaders(&self) -> impl Iterator<Item = ShaderInfo> + '_ {
+        self.shaders.iter().enumerate().map(|(id, s)| ShaderInfo {
+            id,
+            blend_mode: s.blend_mode(),
+            cached_modes: s.cached_modes(),
+            stats: s.stats(),
+        })
+    }
+
+    /// Pipeline compile counts summed over every shader registered with this pass.
+    pub fn pipeline_stats(&self) -> PipelineStats {
+        self.shaders
+            .iter()
+            .fold(PipelineStats::default(), |total, s| total + s.stats())
+    }
+
+    /// GPU time spent executing this pass's most recently completed frame.
+    ///
+    /// `None` until a timestamp query has resolved, or if the device doesn't support timestamps.
+    pub fn gpu_frame_time(&self) -> Option<std::time::Duration> {
+        self.timer.as_ref().and_then(|t| t.last())
+    }
+
+    /// GPU time of each scope timed with [`frame::Pass::begin_gpu_timer`], from the most
+    /// recent frame whose timestamps have resolved.
+    ///
+    /// Timestamps are read without waiting, so these trail the frame being recorded by a few
+    /// frames. Empty if no scopes were timed or the device doesn't support timestamps.
+    pub fn gpu_timings(&self) -> Vec<(String, std::time::Duration)> {
+        self.timer
+            .as_ref()
+            .map(|t| t.timings().to_vec())
+            .unwrap_or_default()
+    }
+
+    /// Draw calls and instances recorded by this pass's most recently finished frame.
+    pub fn frame_stats(&self) -> DrawStats {
+        self.stats.get()
+    }
+
+    pub fn register_shader<Vd: VertexDefinition + Clone + 'static + Sync + Send>(&mut self, shader: Arc<Shader>, v_type: Vd) -> Result<ShaderId> {
         self.shaders.push(
             Box::new(
                 ShaderProgram::new(
@@ -46,21 +144,62 @@ impl RenderPass {
                     shader.vertex.clone(),
                     shader.fragment.clone(),
                     BlendMode::Alpha,
-                )
+                )?
             )
         );
 
         Ok(self.shaders.len()-1)
     }
 
+    /// Replaces the Vulkan render pass, e.g. one made for a new swapchain format, and rebuilds
+    /// every registered shader's pipelines against it. Shader ids stay valid.
+    pub fn set_render_pass(&mut self, render_pass: Arc<vulkano::render_pass::RenderPass>) -> Result<()> {
+        self.render_pass = render_pass;
+        self.rebuild_pipelines()
+    }
+
+    /// Rebuilds every registered shader's pipelines against the current render pass.
+    ///
+    /// Needed after assigning [`RenderPass::render_pass`] directly. Shaders registered with
+    /// [`RenderPass::register_program`] from a fixed pipeline can't be rebuilt and make this
+    /// return an error, after the others have been rebuilt.
+    pub fn rebuild_pipelines(&mut self) -> Result<()> {
+        self.colored.replace(None);
+
+        let mut result = Ok(());
+        for (id, shader) in self.shaders.iter_mut().enumerate() {
+            if let Err(e) = shader.rebuild(self.render_pass.clone()) {
+                log::error!(target: "ledge::shader", "failed to rebuild pipelines of shader {}: {:?}", id, e);
+                result = Err(e);
+            }
+        }
+
+        result
+    }
+
+    /// Registers an already built program, e.g. one given its own descriptor set index with
+    /// [`ShaderProgram::set_descriptor_set_index`].
+    pub fn register_program(&mut self, program: ShaderProgram) -> ShaderId {
+        self.shaders.push(Box::new(program));
+        self.shaders.len() - 1
+    }
+
     pub fn frame(&mut self,
         clear_color: [f32; 4],
         before_future: Box<dyn GpuFuture + 'static>,
         final_image: Arc<dyn ImageViewAbstract + 'static>,
-        _camera: Arc<dyn Camera>,
-    ) -> Result<frame::Frame>
+        camera: Arc<dyn Camera>,
+    ) -> Result<frame::Frame<'_>>
     {
-        let _img_dims = final_image.image().dimensions().width_height();
+        let img_dims = final_image.image().dimensions().width_height();
+
+        let mut state = DrawState::new(img_dims[0] as f32, img_dims[1] as f32);
+        state.polygon_mode = self.polygon_mode;
+        if let Some(region) = self.render_region {
+            state.viewport = clamp_region(region, img_dims[0] as f32, img_dims[1] as f32);
+        }
+        let mvp = camera.as_mvp();
+        state.use_camera_buffer(mvp, self.cameras.write(self.queue.device().clone(), mvp)?);
 
         let framebuffer = Framebuffer::new(
            self.render_pass.clone(),
@@ -76,10 +215,25 @@ impl RenderPass {
             CommandBufferUsage::OneTimeSubmit,
         )?;
 
+        if let Some(timer) = self.timer.as_mut() {
+            timer.begin(&mut command_buffer)?;
+        }
+
+        // Attachments that load their contents instead of clearing them take no clear value.
+        let clear_values = self
+            .render_pass
+            .attachments()
+            .iter()
+            .map(|attachment| match attachment.load_op {
+                LoadOp::Clear => clear_color.into(),
+                _ => ClearValue::None,
+            })
+            .collect::<Vec<_>>();
+
         command_buffer.begin_render_pass(
             framebuffer.clone(),
             SubpassContents::SecondaryCommandBuffers,
-            vec![clear_color.into()],
+            clear_values,
         )?;
 
         // if render_pass.subpasses().len() > 16 {
@@ -90,13 +244,166 @@ impl RenderPass {
 
         Ok(frame::Frame {
             pipelines: &self.shaders,
+            colored: &self.colored,
+            white: &self.white,
             before_main_cb_future: Some(before_future),
             // framebuffer,
             queue: self.queue.clone(),
             num_pass,
             cur_pass: 0,
             command_buffer: Some(command_buffer),
+            timer: self.timer.as_ref(),
+            render_pass: self.render_pass.clone(),
+            gpu_scopes: Vec::new(),
             // camera,
+            state,
+            state_stack: Vec::new(),
+            transforms: Vec::new(),
+            stats: DrawStats::default(),
+            last_stats: &self.stats,
         })
     }
-}
\ No newline at end of file
+}
+/// `region` cut down to the part inside a `width` by `height` target.
+pub(crate) fn clamp_region(region: Rect, width: f32, height: f32) -> Rect {
+    let x = region.x.clamp(0.0, width);
+    let y = region.y.clamp(0.0, height);
+    Rect {
+        x,
+        y,
+        w: (region.x + region.w).clamp(x, width) - x,
+        h: (region.y + region.h).clamp(y, height) - y,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{test_device, InstanceData, Vertex};
+    use crate::graphics::camera::OrthographicCamera;
+    use vulkano::format::Format;
+    use vulkano::image::view::ImageView;
+    use vulkano::image::AttachmentImage;
+    use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+    /// A render pass drawing to a single color attachment.
+    fn color_pass(queue: Arc<Queue>) -> RenderPass {
+        let render_pass = vulkano::single_pass_renderpass!(queue.device().clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: Format::B8G8R8A8_SRGB,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+        .unwrap();
+        RenderPass::new(queue, render_pass).unwrap()
+    }
+
+    #[test]
+    fn regions_are_clamped_to_the_target() {
+        let inside = Rect { x: 100.0, y: 50.0, w: 300.0, h: 200.0 };
+        assert_eq!(clamp_region(inside, 800.0, 600.0), inside);
+
+        let overhanging = Rect { x: -50.0, y: 500.0, w: 300.0, h: 200.0 };
+        assert_eq!(clamp_region(overhanging, 800.0, 600.0), Rect { x: 0.0, y: 500.0, w: 250.0, h: 100.0 });
+
+        let outside = Rect { x: 900.0, y: 0.0, w: 100.0, h: 100.0 };
+        assert_eq!(clamp_region(outside, 800.0, 600.0), Rect { x: 800.0, y: 0.0, w: 0.0, h: 100.0 });
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn frames_draw_into_the_render_region() {
+        let (queue, _) = test_device();
+        let device = queue.device().clone();
+        let mut pass = color_pass(queue);
+        let region = Rect { x: 100.0, y: 50.0, w: 300.0, h: 200.0 };
+        pass.set_render_region(Some(region));
+
+        let target = ImageView::new_default(AttachmentImage::new(device.clone(), [800, 600], Format::B8G8R8A8_SRGB).unwrap()).unwrap();
+        let mut frame = pass
+            .frame([0.0; 4], vulkano::sync::now(device).boxed(), target, Arc::new(OrthographicCamera::new(0.0, 1.0)))
+            .unwrap();
+        match frame.next_pass().unwrap() {
+            Some(frame::PassState::DrawPass(pass)) => {
+                assert_eq!(pass.state().viewport, region);
+                let scissor = pass.state().vk_scissor();
+                assert_eq!((scissor.origin, scissor.dimensions), ([100, 50], [300, 200]));
+            }
+            _ => unreachable!(),
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn shaders_lists_every_registered_shader() {
+        let (queue, _) = test_device();
+        let device = queue.device().clone();
+        let mut pass = color_pass(queue);
+
+        let v_type = BuffersDefinition::new().vertex::<Vertex>().instance::<InstanceData>();
+        let lit = pass.register_shader(Shader::lit_sprite(device.clone()).unwrap(), v_type.clone()).unwrap();
+        let colored = pass.register_shader(Shader::colored(device).unwrap(), v_type).unwrap();
+
+        let shaders = pass.shaders().collect::<Vec<_>>();
+        assert_eq!(shaders.iter().map(|s| s.id).collect::<Vec<_>>(), vec![lit, colored]);
+        for shader in shaders {
+            assert_eq!(shader.blend_mode, BlendMode::Alpha);
+            assert!(shader.cached_modes.contains(&BlendMode::Alpha));
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn warmed_up_scenes_compile_nothing_while_drawing() {
+        use crate::graphics::shader::VertexTopology;
+        use crate::graphics::{warm_up, DrawInfo, WarmUpRequest};
+
+        let (queue, sampler) = test_device();
+        let device = queue.device().clone();
+        let mut pass = color_pass(queue.clone());
+
+        // The scene from examples/premultiplied.rs: one sprite shader drawing a straight alpha
+        // and a premultiplied image.
+        let shader = Arc::new(Shader {
+            vertex: crate::graphics::vs::load(device.clone()).unwrap(),
+            fragment: crate::graphics::fs::load(device.clone()).unwrap(),
+            topology: VertexTopology::TriangleStrip,
+        });
+        let v_type = BuffersDefinition::new().vertex::<Vertex>().instance::<InstanceData>();
+        let shader = pass.register_shader(shader, v_type).unwrap();
+        let straight = Image::new_straight(queue.clone(), sampler.clone(), "examples/images/pokeball.png").unwrap();
+        let premultiplied = Image::new(queue, sampler, "examples/images/pokeball.png").unwrap();
+
+        let requests = [BlendMode::Alpha, BlendMode::PremultipliedAlpha].map(|blend_mode| WarmUpRequest { shader, blend_mode });
+        let mut progress = Vec::new();
+        warm_up(&pass, &requests, |done, total| progress.push((done, total))).unwrap();
+        assert_eq!(progress, vec![(1, 2), (2, 2)]);
+
+        let target = ImageView::new_default(AttachmentImage::new(device.clone(), [64, 64], Format::B8G8R8A8_SRGB).unwrap()).unwrap();
+        let mut frame = pass
+            .frame([0.0; 4], vulkano::sync::now(device).boxed(), target, Arc::new(OrthographicCamera::new(0.0, 1.0)))
+            .unwrap();
+        while let Some(state) = frame.next_pass().unwrap() {
+            match state {
+                frame::PassState::DrawPass(mut pass) => {
+                    pass.set_shader(shader);
+                    pass.draw(Arc::new(straight.clone()), DrawInfo::default()).unwrap();
+                    pass.draw(Arc::new(premultiplied.clone()), DrawInfo::default()).unwrap();
+                }
+                frame::PassState::Finished(future) => {
+                    future.then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+                }
+            }
+        }
+
+        assert_eq!(pass.pipeline_stats().runtime_compiles, 0);
+    }
+}
@@ -0,0 +1,167 @@
+use vulkano::{
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    device::Queue,
+    query::{QueryPool, QueryPoolCreateInfo, QueryResultFlags, QueryType},
+    sync::PipelineStage,
+};
+
+use anyhow::Result;
+use std::cell::RefCell;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Number of query pools cycled through, so a pool is only reset once the GPU has had
+/// a few frames to finish with it.
+const TIMER_SLOTS: usize = 3;
+
+/// Labelled scopes that can be timed in one frame, each using a pair of queries after the
+/// pair timing the whole pass.
+pub const MAX_GPU_SCOPES: u32 = 32;
+
+/// Measures the GPU time taken by a render pass's command buffer using timestamp queries,
+/// along with any labelled scopes recorded inside it.
+///
+/// Results are read back when a pool comes around again, so they trail the frame being
+/// recorded by [`TIMER_SLOTS`] frames and are never waited on.
+pub struct GpuTimer {
+    pools: Vec<Arc<QueryPool>>,
+    written: [bool; TIMER_SLOTS],
+    /// The labels of the scopes started in each pool, in query order.
+    scopes: RefCell<Vec<Vec<String>>>,
+    slot: usize,
+    period: f32,
+    last: Option<Duration>,
+    timings: Vec<(String, Duration)>,
+}
+
+impl GpuTimer {
+    /// Returns `None` if the queue doesn't support timestamps.
+    pub fn new(queue: &Arc<Queue>) -> Option<Self> {
+        queue.family().timestamp_valid_bits()?;
+
+        let device = queue.device();
+        let period = device.physical_device().properties().timestamp_period;
+
+        let pools = (0..TIMER_SLOTS)
+            .map(|_| {
+                QueryPool::new(
+                    device.clone(),
+                    QueryPoolCreateInfo {
+                        query_count: 2 + MAX_GPU_SCOPES * 2,
+                        ..QueryPoolCreateInfo::query_type(QueryType::Timestamp)
+                    },
+                )
+                .ok()
+            })
+            .collect::<Option<Vec<_>>>()?;
+
+        Some(Self {
+            pools,
+            written: [false; TIMER_SLOTS],
+            scopes: RefCell::new(vec![Vec::new(); TIMER_SLOTS]),
+            slot: 0,
+            period,
+            last: None,
+            timings: Vec::new(),
+        })
+    }
+
+    /// The most recently resolved GPU time, `None` until the first query has finished.
+    pub fn last(&self) -> Option<Duration> {
+        self.last
+    }
+
+    /// The most recently resolved time of every scope, in the order they were started.
+    pub fn timings(&self) -> &[(String, Duration)] {
+        &self.timings
+    }
+
+    fn elapsed(&self, start: u64, end: u64) -> Duration {
+        let nanos = end.saturating_sub(start) as f64 * self.period as f64;
+        Duration::from_nanos(nanos as u64)
+    }
+
+    /// Moves on to the next pool, resolving its previous result, and writes the start
+    /// timestamp. Must be called outside of a render pass.
+    pub(crate) fn begin(
+        &mut self,
+        command_buffer: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> Result<()> {
+        self.slot = (self.slot + 1) % TIMER_SLOTS;
+        let pool = self.pools[self.slot].clone();
+
+        let labels = std::mem::take(&mut self.scopes.get_mut()[self.slot]);
+        if self.written[self.slot] {
+            let query_count = 2 + labels.len() as u32 * 2;
+            let mut ticks = vec![0u64; query_count as usize];
+            let available = pool.queries_range(0..query_count).unwrap().get_results(
+                &mut ticks,
+                QueryResultFlags {
+                    wait: false,
+                    with_availability: false,
+                    partial: false,
+                },
+            )?;
+
+            if available {
+                self.last = Some(self.elapsed(ticks[0], ticks[1]));
+                self.timings = labels
+                    .into_iter()
+                    .zip(ticks[2..].chunks(2))
+                    .map(|(label, pair)| (label, self.elapsed(pair[0], pair[1])))
+                    .collect();
+            }
+        }
+
+        unsafe {
+            command_buffer.reset_query_pool(pool.clone(), 0..2 + MAX_GPU_SCOPES * 2)?;
+            command_buffer.write_timestamp(pool, 0, PipelineStage::TopOfPipe)?;
+        }
+        self.written[self.slot] = true;
+
+        Ok(())
+    }
+
+    pub(crate) fn end(
+        &self,
+        command_buffer: &mut AutoCommandBufferBuilder<PrimaryAutoCommandBuffer>,
+    ) -> Result<()> {
+        unsafe {
+            command_buffer.write_timestamp(
+                self.pools[self.slot].clone(),
+                1,
+                PipelineStage::BottomOfPipe,
+            )?;
+        }
+
+        Ok(())
+    }
+
+    /// Reserves the pair of queries timing a scope called `label` in the current frame,
+    /// returning the index of its start query. `None` once [`MAX_GPU_SCOPES`] are in use.
+    pub(crate) fn begin_scope(&self, label: &str) -> Option<u32> {
+        let mut scopes = self.scopes.borrow_mut();
+        let labels = &mut scopes[self.slot];
+        if labels.len() as u32 >= MAX_GPU_SCOPES {
+            log::warn!(target: "ledge::graphics", "more than {} gpu timer scopes in one frame, ignoring '{}'", MAX_GPU_SCOPES, label);
+            return None;
+        }
+
+        labels.push(label.to_string());
+        Some(2 + (labels.len() as u32 - 1) * 2)
+    }
+
+    /// Writes timestamp `query` of the current frame's pool into `command_buffer`.
+    pub(crate) fn write<L, P>(
+        &self,
+        command_buffer: &mut AutoCommandBufferBuilder<L, P>,
+        query: u32,
+        stage: PipelineStage,
+    ) -> Result<()> {
+        unsafe {
+            command_buffer.write_timestamp(self.pools[self.slot].clone(), query, stage)?;
+        }
+
+        Ok(())
+    }
+}
@@ -0,0 +1,120 @@
+//! Rendering the scene below (or above) the window's resolution.
+//!
+//! With a scale other than 1 set by [`set_render_scale`], [`Renderer::scene_image`] is an
+//! internal image of `scale` times the swapchain extent instead of the swapchain image. The
+//! scene is drawn into it as usual, then [`Renderer::resolve_scene`] stretches it over the
+//! swapchain image with linear filtering. Anything drawn into [`Renderer::final_image`] after
+//! that, such as UI, is at native resolution, using a render pass that loads the attachment
+//! rather than clearing it. If the scene isn't resolved explicitly, [`Renderer::end_frame`]
+//! does it before presenting.
+//!
+//! Cameras and [`Renderer::viewport`] stay in native window coordinates, the internal image
+//! covers the same area at a lower density.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use vulkano::device::Queue;
+use vulkano::format::Format;
+use vulkano::image::view::{ImageView, ImageViewAbstract};
+use vulkano::image::{AttachmentImage, ImageAccess, ImageUsage};
+
+use crate::graphics::renderer::Renderer;
+
+/// The scale and the internal target the scene is drawn into when it isn't 1.
+pub(crate) struct RenderScale {
+    scale: f32,
+    target: Option<Arc<ImageView<AttachmentImage>>>,
+    /// Set when the target is handed out for a frame, until it's resolved.
+    pending: bool,
+}
+
+impl RenderScale {
+    pub(crate) fn new() -> Self {
+        Self {
+            scale: 1.0,
+            target: None,
+            pending: false,
+        }
+    }
+
+    pub(crate) fn scale(&self) -> f32 {
+        self.scale
+    }
+
+    pub(crate) fn is_native(&self) -> bool {
+        self.scale == 1.0
+    }
+
+    /// The size of the internal target for a swapchain of size `native`.
+    pub(crate) fn extent(&self, native: [u32; 2]) -> [u32; 2] {
+        let scaled = |size: u32| ((size as f32 * self.scale).round() as u32).max(1);
+        [scaled(native[0]), scaled(native[1])]
+    }
+
+    /// The internal target for this frame, reallocated if the scale, swapchain extent, or
+    /// format changed since the last one.
+    pub(crate) fn target(&mut self, queue: Arc<Queue>, format: Format, native: [u32; 2]) -> Result<Arc<ImageView<AttachmentImage>>> {
+        let extent = self.extent(native);
+
+        let current = self.target.as_ref().filter(|target| {
+            target.image().dimensions().width_height() == extent && target.format() == Some(format)
+        });
+
+        let target = match current {
+            Some(target) => target.clone(),
+            None => {
+                let usage = ImageUsage {
+                    sampled: true,
+                    color_attachment: true,
+                    transfer_source: true,
+                    ..ImageUsage::none()
+                };
+                let image = AttachmentImage::with_usage(queue.device().clone(), extent, format, usage)?;
+                let target = ImageView::new_default(image)?;
+
+                log::debug!(target: "ledge::graphics", "allocated {}x{} scene target for render scale {}", extent[0], extent[1], self.scale);
+                self.target = Some(target.clone());
+                target
+            }
+        };
+
+        self.pending = true;
+        Ok(target)
+    }
+
+    /// The target if the scene was drawn into it this frame and hasn't been resolved yet.
+    pub(crate) fn take_pending(&mut self) -> Option<Arc<ImageView<AttachmentImage>>> {
+        if !std::mem::take(&mut self.pending) {
+            return None;
+        }
+
+        self.target.clone()
+    }
+}
+
+/// Renders the scene at `scale` times the window's resolution from the next frame, 1 by
+/// default. Changing it only reallocates the internal target.
+///
+/// Ignored, with a warning, if `scale` isn't positive or the swapchain images can't be
+/// blitted to.
+pub fn set_render_scale(ctx: &mut Renderer, scale: f32) {
+    if scale.is_nan() || scale <= 0.0 {
+        log::warn!(target: "ledge::graphics", "ignoring render scale {}, it must be positive", scale);
+        return;
+    }
+
+    if scale != 1.0 && !ctx.swapchain.create_info().image_usage.transfer_destination {
+        log::warn!(target: "ledge::graphics", "swapchain images can't be blitted to, keeping native render scale");
+        return;
+    }
+
+    ctx.render_scale.scale = scale;
+    if scale == 1.0 {
+        ctx.render_scale.target = None;
+    }
+}
+
+pub fn render_scale(ctx: &Renderer) -> f32 {
+    ctx.render_scale.scale()
+}
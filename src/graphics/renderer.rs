@@ -1,19 +1,21 @@
 use vulkano::{
-    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage, PrimaryAutoCommandBuffer},
     device::physical::{PhysicalDevice, PhysicalDeviceType},
-    device::{Device, DeviceCreateInfo, DeviceExtensions, QueueCreateInfo},
-    image::{view::{ImageView, ImageViewCreateInfo}, ImageUsage, SwapchainImage},
-    instance::{Instance, InstanceCreateInfo},
-    sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo},
-    swapchain::{self, Surface, PresentMode, AcquireError, Swapchain, SwapchainCreateInfo, SwapchainCreationError},
+    device::{Device, DeviceCreateInfo, DeviceExtensions, Features, QueueCreateInfo},
+    image::{view::{ImageView, ImageViewCreateInfo, ImageViewAbstract}, ImageAccess, ImageCreateFlags, ImageDimensions, ImageUsage, StorageImage, SwapchainImage},
+    instance::debug::{DebugCallback, Message, MessageSeverity, MessageType},
+    instance::{Instance, InstanceCreateInfo, InstanceExtensions},
+    sampler::{Filter, Sampler},
+    swapchain::{self, ColorSpace, Surface, PresentMode, AcquireError, Swapchain, SwapchainCreateInfo, SwapchainCreationError},
     sync::{self, FlushError, GpuFuture},
     Version,
-    format::Format,
+    format::{ClearValue, Format},
 };
 
 use vulkano_win::VkSurfaceBuild;
-use winit::event_loop::EventLoop;
-use winit::window::{Window, WindowBuilder};
+use winit::event_loop::{EventLoop, EventLoopWindowTarget};
+use winit::dpi::LogicalSize;
+use winit::window::{Fullscreen, Window, WindowBuilder};
 
 use std::sync::Arc;
 
@@ -27,6 +29,72 @@ use crate::{
 
 pub type FinalImageView = Arc<ImageView<SwapchainImage<Window>>>;
 
+/// The layer [`Conf::validation`] enables.
+const VALIDATION_LAYER: &str = "VK_LAYER_KHRONOS_validation";
+
+/// Settings applied to the swapchain by [`Renderer::reconfigure`].
+///
+/// Every field is optional, `None` keeps the current value. Changing any of them recreates
+/// the swapchain and its image views, but the device, queues, pipelines, samplers, and loaded
+/// images are untouched.
+#[derive(Clone, Copy, Debug, Default)]
+pub struct SurfaceConfig {
+    /// Swapchain image size in pixels, defaults to the window's inner size on recreation.
+    pub extent: Option<[u32; 2]>,
+    pub present_mode: Option<PresentMode>,
+    /// Must be one of the formats reported by the surface.
+    pub format: Option<Format>,
+}
+
+/// The swapchain settings actually in use, returned by [`Renderer::swapchain_info`].
+///
+/// These can differ from what was asked for, the driver may hand out more images than the
+/// minimum requested and the format is picked from those the surface supports.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct SwapchainInfo {
+    pub image_count: u32,
+    pub format: Format,
+    /// How the presentation engine interprets `format`, sRGB nonlinear on nearly every surface.
+    pub color_space: ColorSpace,
+    /// Image size in pixels.
+    pub extent: [u32; 2],
+    pub present_mode: PresentMode,
+}
+
+impl SwapchainInfo {
+    fn of(swapchain: &Swapchain<Window>) -> Self {
+        Self {
+            image_count: swapchain.image_count(),
+            format: swapchain.image_format(),
+            color_space: swapchain.image_color_space(),
+            extent: swapchain.image_extent(),
+            present_mode: swapchain.present_mode(),
+        }
+    }
+}
+
+/// What a swapchain image holds before its first frame after the swapchain is recreated, set
+/// with [`Renderer::set_swapchain_content`].
+///
+/// Without this the new images start out undefined, which shows as a flash of garbage when a
+/// render pass loads rather than clears its attachment.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum SwapchainContent {
+    /// Cleared to the color.
+    Clear(Color),
+    /// The last presented frame, stretched to the new size. Every frame is copied aside
+    /// before it's presented while this is set, which costs a full-screen copy per frame.
+    /// Falls back to clearing to black if the surface doesn't support copying from swapchain
+    /// images.
+    Preserve,
+}
+
+impl Default for SwapchainContent {
+    fn default() -> Self {
+        SwapchainContent::Clear(Color::black())
+    }
+}
+
 pub struct Renderer {
     pub queue: Arc<vulkano::device::Queue>,
     pub(crate) surface: Arc<vulkano::swapchain::Surface<winit::window::Window>>,
@@ -41,22 +109,83 @@ pub struct Renderer {
     pub default_shader: ShaderId,
     pub render_passes: Vec<render_pass::RenderPass>,
     pub samplers: Vec<Arc<Sampler>>,
+    /// Image uploads waiting to be submitted at the start of the next frame.
+    pub uploads: upload::UploadQueue,
+    /// Frames asked for with [`crate::graphics::capture_thumbnail`].
+    pub(crate) captures: capture::Captures,
+    memory_warning: Option<memory::MemoryWarning>,
+    /// Shapes queued with the functions in [`crate::graphics::debug`].
+    pub debug: debug::DebugDraw,
+    pub(crate) coordinate_system: CoordinateSystem,
+    pub(crate) palette: Palette,
+    pub(crate) render_scale: render_scale::RenderScale,
+    swapchain_content: SwapchainContent,
+    wireframe: bool,
+    render_region: Option<Rect>,
+    /// The window's title, kept since winit can't read it back.
+    title: String,
+    /// How [`Renderer::load_image`] loads images, from the [`Conf`].
+    image_spec: image::ImageSpec,
+    /// A copy of the last presented frame, kept while [`SwapchainContent::Preserve`] is set.
+    /// The swapchain it came from may have been replaced since, so recreated images are filled
+    /// from this copy rather than from an old swapchain image that was never acquired.
+    last_frame: Option<Arc<StorageImage>>,
+    /// Images that haven't been initialized since the swapchain was recreated.
+    uninitialized: Vec<bool>,
+    /// Forwards validation layer messages to the log while [`Conf::validation`] is on.
+    _validation_messages: Option<DebugCallback>,
+    /// When the last frame began, and the wall time between it and the one before.
+    frame_started: Option<std::time::Instant>,
+    frame_interval: Option<std::time::Duration>,
 }
 
 impl Renderer {
-    pub fn new(_conf: Conf) -> (Self, winit::event_loop::EventLoop<()>) {
+    pub(crate) fn new<E: 'static>(conf: Conf) -> (Self, winit::event_loop::EventLoop<E>) {
+        let event_loop = EventLoop::with_user_event();
+        let renderer = Self::create(conf, &event_loop);
+
+        (renderer, event_loop)
+    }
+
+    /// Creates the window and renderer on an event loop owned by the caller, who is then
+    /// responsible for running it and forwarding window events.
+    #[cfg(feature = "unstable-raw")]
+    pub fn from_event_loop<E: 'static>(conf: Conf, event_loop: &EventLoopWindowTarget<E>) -> Self {
+        Self::create(conf, event_loop)
+    }
+
+    fn create<E: 'static>(conf: Conf, event_loop: &EventLoopWindowTarget<E>) -> Self {
         let required_extensions = vulkano_win::required_extensions();
+        let validation_layers = Self::validation_layers(conf.validation);
+        let debug_utils = !validation_layers.is_empty()
+            && InstanceExtensions::supported_by_core().is_ok_and(|supported| supported.ext_debug_utils);
         let instance = Instance::new(InstanceCreateInfo {
             application_name: None,
             application_version: Version::V1_1,
-            enabled_extensions: required_extensions,
+            enabled_extensions: InstanceExtensions {
+                ext_debug_utils: debug_utils,
+                ..required_extensions
+            },
+            enabled_layers: validation_layers,
             ..Default::default()
         })
         .unwrap();
+        let validation_messages = if debug_utils { Self::log_validation_messages(&instance) } else { None };
+
+        let title = conf.window_setup.title.clone();
+
+        let fullscreen = match conf.window_mode.fullscreen_type {
+            FullscreenType::Windowed => None,
+            _ => Some(Fullscreen::Borderless(None)),
+        };
 
-        let event_loop = EventLoop::new();
         let surface = WindowBuilder::new()
-            .build_vk_surface(&event_loop, instance.clone())
+            .with_title(&title)
+            .with_inner_size(LogicalSize::new(conf.window_mode.width, conf.window_mode.height))
+            .with_fullscreen(fullscreen)
+            .with_decorations(conf.window_mode.decorations)
+            .with_always_on_top(conf.window_mode.always_on_top)
+            .build_vk_surface(event_loop, instance.clone())
             .unwrap();
 
         let device_extensions = DeviceExtensions {
@@ -81,8 +210,9 @@ impl Renderer {
             })
             .unwrap();
 
-        println!(
-            "Using device: {} (type: {:?})",
+        log::info!(
+            target: "ledge::graphics",
+            "using device: {} (type: {:?})",
             physical_device.properties().device_name,
             physical_device.properties().device_type,
         );
@@ -93,6 +223,12 @@ impl Renderer {
                 enabled_extensions: physical_device
                     .required_extensions()
                     .union(&device_extensions),
+                enabled_features: Features {
+                    wide_lines: physical_device.supported_features().wide_lines,
+                    fill_mode_non_solid: physical_device.supported_features().fill_mode_non_solid,
+                    texture_compression_bc: physical_device.supported_features().texture_compression_bc,
+                    ..Features::none()
+                },
                 queue_create_infos: vec![QueueCreateInfo::family(queue_family)],
                 ..Default::default()
             },
@@ -105,27 +241,25 @@ impl Renderer {
             surface.clone(),
             physical_device,
             device.clone(),
-            PresentMode::Immediate
+            if conf.window_setup.vsync { PresentMode::Fifo } else { PresentMode::Immediate },
         ).unwrap();
 
+        log::info!(target: "ledge::graphics::swapchain", "created swapchain: {:?}", SwapchainInfo::of(&swapchain));
+
         let default_future = Some(sync::now(device.clone()).boxed());
 
         let mut samplers = Vec::new();
 
-        let default_sampler = Sampler::new(
-            device.clone(),
-            SamplerCreateInfo {
-                mag_filter: Filter::Linear,
-                min_filter: Filter::Linear,
-                address_mode: [SamplerAddressMode::Repeat; 3],
-                ..Default::default()
-            },
-        )
-        .unwrap();
+        let default_sampler = sampler::SamplerConfig::default()
+            .build(device.clone())
+            .unwrap();
+
+        let uploads = upload::UploadQueue::new(queue.clone(), default_sampler.clone());
+        let debug = debug::DebugDraw::new(queue.clone(), default_sampler.clone());
 
         samplers.push(default_sampler);
 
-        return (Renderer {
+        Renderer {
             queue,
             surface,
             device,
@@ -138,10 +272,79 @@ impl Renderer {
             command_buffer: None,
             default_shader: 0,
             samplers,
+            uploads,
+            captures: capture::Captures::default(),
             render_passes: Vec::new(),
-        }, event_loop);
+            memory_warning: None,
+            debug,
+            coordinate_system: CoordinateSystem::default(),
+            palette: Palette::default(),
+            render_scale: render_scale::RenderScale::new(),
+            swapchain_content: SwapchainContent::default(),
+            wireframe: false,
+            render_region: None,
+            title,
+            image_spec: image::ImageSpec::new()
+                .premultiply(conf.premultiply_images)
+                .placeholder_on_error(conf.placeholder_on_error),
+            last_frame: None,
+            uninitialized: Vec::new(),
+            _validation_messages: validation_messages,
+            frame_started: None,
+            frame_interval: None,
+        }
     }
     
+    /// The Khronos validation layer if `enabled` and it's installed.
+    pub(crate) fn validation_layers(enabled: bool) -> Vec<String> {
+        if !enabled {
+            return Vec::new();
+        }
+
+        let installed = vulkano::instance::layers_list()
+            .map(|mut layers| layers.any(|layer| layer.name() == VALIDATION_LAYER))
+            .unwrap_or(false);
+        if !installed {
+            log::warn!(target: "ledge::graphics", "{} isn't installed, running without validation", VALIDATION_LAYER);
+            return Vec::new();
+        }
+
+        log::info!(target: "ledge::graphics", "enabling {}", VALIDATION_LAYER);
+        vec![VALIDATION_LAYER.to_string()]
+    }
+
+    /// Sends every message from the validation layer to the log under
+    /// `ledge::graphics::validation`, at the level matching its severity.
+    fn log_validation_messages(instance: &Arc<Instance>) -> Option<DebugCallback> {
+        let callback = DebugCallback::new(instance, MessageSeverity::all(), MessageType::all(), |message: &Message| {
+            let level = if message.severity.error {
+                log::Level::Error
+            } else if message.severity.warning {
+                log::Level::Warn
+            } else if message.severity.information {
+                log::Level::Info
+            } else {
+                log::Level::Debug
+            };
+
+            log::log!(
+                target: "ledge::graphics::validation",
+                level,
+                "{}: {}",
+                message.layer_prefix.unwrap_or("validation"),
+                message.description,
+            );
+        });
+
+        match callback {
+            Ok(callback) => Some(callback),
+            Err(e) => {
+                log::warn!(target: "ledge::graphics", "couldn't forward validation messages to the log: {}", e);
+                None
+            }
+        }
+    }
+
     fn create_swap_chain(
         surface: Arc<Surface<Window>>,
         physical: PhysicalDevice,
@@ -166,10 +369,14 @@ impl Renderer {
                 surface.clone(),
                 SwapchainCreateInfo {
                     min_image_count: caps.min_image_count,
-                    image_format: image_format,
+                    image_format,
                     present_mode,
                     image_extent: surface.window().inner_size().into(),
-                    image_usage: ImageUsage::color_attachment(),
+                    image_usage: ImageUsage {
+                        transfer_source: caps.supported_usage_flags.transfer_source,
+                        transfer_destination: caps.supported_usage_flags.transfer_destination,
+                        ..ImageUsage::color_attachment()
+                    },
                     composite_alpha: caps
                         .supported_composite_alpha
                         .iter()
@@ -197,9 +404,21 @@ impl Renderer {
     /// This is necessary because the swapchain could be out of date,
     /// as well as updating the image_num, optimality, and the swapcahin future.
     pub fn begin_frame(&mut self) -> Result<Box<dyn GpuFuture>> {
+        let now = std::time::Instant::now();
+        if let Some(started) = self.frame_started.replace(now) {
+            self.frame_interval = Some(now - started);
+        }
+
         self.previous_frame_end.as_mut().unwrap().cleanup_finished();
+        self.captures.poll();
 
         if self.recreate_swapchain {
+            log::info!(
+                target: "ledge::graphics::swapchain",
+                "recreating swapchain at {:?}",
+                self.surface.window().inner_size(),
+            );
+
             let (new_swapchain, new_images) = match self.swapchain.recreate(SwapchainCreateInfo {
                 image_extent: self.surface.window().inner_size().into(),
                 ..self.swapchain.create_info()
@@ -209,17 +428,7 @@ impl Renderer {
                 Err(e) => panic!("Failed to recreate swapchain: {:?}", e),
             };
 
-            let new_images = new_images
-            .into_iter()
-            .map(|image| {
-                let info = ImageViewCreateInfo::from_image(&image);
-                ImageView::new(image, info).unwrap()
-            })
-            .collect::<Vec<_>>();
-
-            self.image_views = new_images;
-            self.swapchain = new_swapchain;
-            self.recreate_swapchain = false;
+            self.replace_swapchain(new_swapchain, new_images);
         }
 
         let (image_num, suboptimal, acquire_future) =
@@ -233,12 +442,23 @@ impl Renderer {
             };
 
         if suboptimal {
+            log::debug!(target: "ledge::graphics::swapchain", "swapchain is suboptimal, recreating next frame");
             self.recreate_swapchain = true;
         }
 
         self.image_num = image_num;
 
-        Ok(self.previous_frame_end.take().unwrap().join(acquire_future).boxed())
+        let mut future = self.previous_frame_end.take().unwrap().join(acquire_future).boxed();
+
+        if let Some(commands) = self.initialize_image(image_num)? {
+            future = future.then_execute(self.queue.clone(), commands)?.boxed();
+        }
+
+        if let Some(uploads) = self.uploads.flush()? {
+            future = future.join(uploads).boxed();
+        }
+
+        Ok(future)
     }
 
     /// This function submits the command buffer to the queue and fences the operation,
@@ -246,6 +466,35 @@ impl Renderer {
     ///
     /// This function must be run once at the end of all updates and draw calls in order for the frame to be sumbitted.
     pub fn end_frame(&mut self, after_future: Box<dyn GpuFuture>) {
+        let after_future = match self.resolve_scene(after_future) {
+            Ok(future) => future,
+            Err(e) => {
+                log::error!(target: "ledge::graphics", "failed to resolve the scene target: {:?}", e);
+                self.previous_frame_end = Some(sync::now(self.device.clone()).boxed());
+                return;
+            }
+        };
+
+        let image = self.image_views[self.image_num].image().clone();
+        let readable = self.swapchain.create_info().image_usage.transfer_source;
+        let after_future = match self.captures.record(&self.queue, image, readable, after_future) {
+            Ok(future) => future,
+            Err(e) => {
+                log::error!(target: "ledge::graphics", "failed to copy the frame for capture: {:?}", e);
+                self.previous_frame_end = Some(sync::now(self.device.clone()).boxed());
+                return;
+            }
+        };
+
+        let after_future = match self.keep_frame(after_future) {
+            Ok(future) => future,
+            Err(e) => {
+                log::error!(target: "ledge::graphics::swapchain", "failed to copy the frame for preserving: {:?}", e);
+                self.previous_frame_end = Some(sync::now(self.device.clone()).boxed());
+                return;
+            }
+        };
+
         let future = after_future
             .then_swapchain_present(
                 self.queue.clone(),
@@ -263,17 +512,750 @@ impl Renderer {
                 self.previous_frame_end = Some(sync::now(self.device.clone()).boxed());
             }
             Err(e) => {
-                println!("Failed to flush future: {:?}", e);
+                log::error!(target: "ledge::graphics::swapchain", "failed to flush frame: {:?}", e);
                 self.previous_frame_end = Some(sync::now(self.device.clone()).boxed());
             }
         };
+
+        self.debug.clear();
+
+        if self.debug.diagnostics.enabled() {
+            let stats = self.frame_stats();
+            let memory = memory_info(self);
+            let extent = self.render_extent();
+            let utilization = self.gpu_utilization();
+            self.debug.diagnostics.record(stats, &memory, &self.palette, extent, self.render_scale.scale(), utilization);
+        }
+
+        if let Some(mut warning) = self.memory_warning.take() {
+            warning.check(&memory_info(self));
+            self.memory_warning = Some(warning);
+        }
+    }
+
+    /// Recreates only the swapchain and its image views with the given settings.
+    ///
+    /// Unlike rebuilding the renderer this keeps every resource created from the device alive,
+    /// so it's the way to apply graphics settings changes at runtime. If `format` changes, give
+    /// each render pass a new Vulkan render pass using it with
+    /// [`render_pass::RenderPass::set_render_pass`], or assign them and call
+    /// [`Renderer::rebuild_pipelines`].
+    pub fn reconfigure(&mut self, config: SurfaceConfig) -> Result<()> {
+        let current = self.swapchain.create_info();
+
+        let (new_swapchain, new_images) = self.swapchain.recreate(SwapchainCreateInfo {
+            image_extent: config
+                .extent
+                .unwrap_or_else(|| self.surface.window().inner_size().into()),
+            present_mode: config.present_mode.unwrap_or(current.present_mode),
+            image_format: config.format.or(current.image_format),
+            ..current
+        })?;
+
+        self.replace_swapchain(new_swapchain, new_images);
+
+        Ok(())
+    }
+
+    /// Rebuilds the pipelines of every shader in every render pass against that pass's current
+    /// Vulkan render pass, see [`render_pass::RenderPass::rebuild_pipelines`].
+    pub fn rebuild_pipelines(&mut self) -> Result<()> {
+        let mut result = Ok(());
+        for pass in self.render_passes.iter_mut() {
+            if let Err(e) = pass.rebuild_pipelines() {
+                result = Err(e);
+            }
+        }
+
+        result
+    }
+
+    /// Sets what swapchain images hold before their first frame after the swapchain is
+    /// recreated, [`SwapchainContent::Clear`] to black by default.
+    pub fn set_swapchain_content(&mut self, content: SwapchainContent) {
+        self.swapchain_content = content;
+    }
+
+    pub fn swapchain_content(&self) -> SwapchainContent {
+        self.swapchain_content
+    }
+
+    fn replace_swapchain(&mut self, swapchain: Arc<Swapchain<Window>>, images: Vec<Arc<SwapchainImage<Window>>>) {
+        self.image_views = images
+            .into_iter()
+            .map(|image| {
+                let info = ImageViewCreateInfo::from_image(&image);
+                ImageView::new(image, info).unwrap()
+            })
+            .collect::<Vec<_>>();
+        self.uninitialized = vec![true; self.image_views.len()];
+        self.swapchain = swapchain;
+        self.recreate_swapchain = false;
+
+        log::debug!(target: "ledge::graphics::swapchain", "replaced swapchain: {:?}", self.swapchain_info());
+    }
+
+    /// The image count, format, color space, extent, and present mode of the current swapchain.
+    pub fn swapchain_info(&self) -> SwapchainInfo {
+        SwapchainInfo::of(&self.swapchain)
+    }
+
+    /// Records the clear or copy giving a newly recreated swapchain image its
+    /// [`SwapchainContent`], or `None` if the image was already initialized.
+    fn initialize_image(&mut self, image_num: usize) -> Result<Option<PrimaryAutoCommandBuffer>> {
+        if !self.uninitialized.get(image_num).copied().unwrap_or(false) {
+            return Ok(None);
+        }
+        self.uninitialized[image_num] = false;
+
+        let usage = self.swapchain.create_info().image_usage;
+        if !usage.transfer_destination {
+            log::debug!(target: "ledge::graphics::swapchain", "swapchain images can't be cleared, leaving them undefined");
+            return Ok(None);
+        }
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.device.clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        let target = self.image_views[image_num].image().clone();
+        let source = match self.swapchain_content {
+            SwapchainContent::Preserve => self.last_frame.clone(),
+            _ => None,
+        };
+
+        match (self.swapchain_content, source) {
+            (SwapchainContent::Preserve, Some(source)) => {
+                let [sw, sh] = source.dimensions().width_height();
+                let [tw, th] = target.dimensions().width_height();
+                builder.blit_image(
+                    source,
+                    [0, 0, 0],
+                    [sw as i32, sh as i32, 1],
+                    0,
+                    0,
+                    target,
+                    [0, 0, 0],
+                    [tw as i32, th as i32, 1],
+                    0,
+                    0,
+                    1,
+                    Filter::Linear,
+                )?;
+            }
+            (SwapchainContent::Clear(color), _) => {
+                builder.clear_color_image(target, ClearValue::Float(color.into()))?;
+            }
+            (SwapchainContent::Preserve, None) => {
+                builder.clear_color_image(target, ClearValue::Float(Color::black().into()))?;
+            }
+        }
+
+        Ok(Some(builder.build()?))
+    }
+
+    /// Copies the frame about to be presented into `last_frame` after `before_future` while
+    /// [`SwapchainContent::Preserve`] is set, and drops the copy otherwise.
+    fn keep_frame(&mut self, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let readable = self.swapchain.create_info().image_usage.transfer_source;
+        if self.swapchain_content != SwapchainContent::Preserve || !readable {
+            self.last_frame = None;
+            return Ok(before_future);
+        }
+
+        let source = self.image_views[self.image_num].image().clone();
+        let [width, height] = source.dimensions().width_height();
+        let reusable = self.last_frame.as_ref().is_some_and(|image| {
+            image.dimensions().width_height() == [width, height] && image.format() == source.format()
+        });
+        if !reusable {
+            self.last_frame = Some(StorageImage::with_usage(
+                self.device.clone(),
+                ImageDimensions::Dim2d {
+                    width,
+                    height,
+                    array_layers: 1,
+                },
+                source.format(),
+                ImageUsage {
+                    transfer_source: true,
+                    transfer_destination: true,
+                    ..ImageUsage::none()
+                },
+                ImageCreateFlags::none(),
+                [self.queue.family()],
+            )?);
+        }
+        let destination = self.last_frame.clone().unwrap();
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.device.clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+        builder.copy_image(source, [0, 0, 0], 0, 0, destination, [0, 0, 0], 0, 0, [width, height, 1], 1)?;
+        let commands = builder.build()?;
+
+        Ok(before_future.then_execute(self.queue.clone(), commands)?.boxed())
+    }
+
+    /// The debug overlay queued for this frame, see [`crate::graphics::debug`] for the free function versions.
+    pub fn debug(&mut self) -> &mut debug::DebugDraw {
+        &mut self.debug
+    }
+
+    /// Total GPU time of the most recently resolved frame across all render passes.
+    ///
+    /// `None` until at least one pass has a resolved timestamp query.
+    pub fn gpu_frame_time(&self) -> Option<std::time::Duration> {
+        self.render_passes
+            .iter()
+            .filter_map(|pass| pass.gpu_frame_time())
+            .fold(None, |total, t| Some(total.unwrap_or_default() + t))
+    }
+
+    /// An estimate of the fraction of a frame the GPU spent working, from 0 when it idled
+    /// throughout to 1 when it was busy the whole frame.
+    ///
+    /// This is [`Renderer::gpu_frame_time`] divided by the wall time between the last two
+    /// frames. The GPU time is from a frame a few behind, and only covers work inside the
+    /// render passes' timestamp queries, so treat it as a guide for capping the frame rate
+    /// or saving power rather than a measurement. `None` until both are known.
+    pub fn gpu_utilization(&self) -> Option<f32> {
+        let busy = self.gpu_frame_time()?;
+        let interval = self.frame_interval.filter(|interval| !interval.is_zero())?;
+        Some((busy.as_secs_f32() / interval.as_secs_f32()).min(1.0))
+    }
+
+    /// GPU time of every scope timed with [`Pass::begin_gpu_timer`](crate::graphics::render_pass::frame::Pass::begin_gpu_timer),
+    /// across all render passes in registration order.
+    ///
+    /// Like [`Renderer::gpu_frame_time`] these come from the most recently resolved frame,
+    /// a few frames behind the one being recorded.
+    pub fn gpu_timings(&self) -> Vec<(String, std::time::Duration)> {
+        self.render_passes
+            .iter()
+            .flat_map(|pass| pass.gpu_timings())
+            .collect()
+    }
+
+    /// Every shader registered with every render pass, paired with the index of its pass in
+    /// [`Renderer::render_passes`], see [`render_pass::RenderPass::shaders`].
+    pub fn shaders(&self) -> impl Iterator<Item = (usize, shader::ShaderInfo)> + '_ {
+        self.render_passes
+            .iter()
+            .enumerate()
+            .flat_map(|(i, pass)| pass.shaders().map(move |info| (i, info)))
+    }
+
+    /// Calls `callback` at the end of a frame once tracked memory usage goes over `threshold`,
+    /// a fraction of the budget reported by [`memory_info`]. Fires again only after usage
+    /// has dropped back under the threshold.
+    pub fn set_memory_warning<F: FnMut(&memory::MemoryInfo) + 'static>(&mut self, threshold: f32, callback: F) {
+        self.memory_warning = Some(memory::MemoryWarning::new(threshold, callback));
+    }
+
+    /// The Vulkan render pass of [`Renderer::render_passes`]`[index]`, for building a
+    /// [`shader::ShaderProgram`] against it. `None` if there's no pass at `index`.
+    ///
+    /// Programs built this way are registered with the same pass through
+    /// [`render_pass::RenderPass::register_program`].
+    ///
+    /// ```no_run
+    /// use ledge::graphics::shader::{ShaderId, ShaderProgram, VertexTopology};
+    /// use ledge::graphics::{BlendMode, InstanceData, Vertex};
+    /// use ledge::prelude::*;
+    /// use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+    ///
+    /// mod vs {
+    ///     vulkano_shaders::shader! { ty: "vertex", path: "examples/shaders/basic.vert" }
+    /// }
+    ///
+    /// mod fs {
+    ///     vulkano_shaders::shader! { ty: "fragment", path: "examples/shaders/basic.frag" }
+    /// }
+    ///
+    /// fn register_custom(renderer: &mut Renderer) -> ShaderId {
+    ///     let program = ShaderProgram::new(
+    ///         renderer.device.clone(),
+    ///         renderer.render_pass(0).expect("no render pass registered"),
+    ///         BuffersDefinition::new().vertex::<Vertex>().instance::<InstanceData>(),
+    ///         VertexTopology::TriangleStrip,
+    ///         vs::load(renderer.device.clone()).unwrap(),
+    ///         fs::load(renderer.device.clone()).unwrap(),
+    ///         BlendMode::Alpha,
+    ///     )
+    ///     .expect("failed to build the custom pipeline");
+    ///     renderer.render_passes[0].register_program(program)
+    /// }
+    /// ```
+    pub fn render_pass(&self, index: usize) -> Option<Arc<vulkano::render_pass::RenderPass>> {
+        self.render_passes.get(index).map(|pass| pass.render_pass.clone())
+    }
+
+    /// Subpass `subpass` of the Vulkan render pass of [`Renderer::render_passes`]`[index]`,
+    /// see [`render_pass::RenderPass::subpass`].
+    pub fn subpass(&self, index: usize, subpass: u32) -> Option<vulkano::render_pass::Subpass> {
+        self.render_passes.get(index)?.subpass(subpass)
+    }
+
+    /// Draw calls and instances recorded by every render pass's most recently finished frame.
+    pub fn frame_stats(&self) -> DrawStats {
+        self.render_passes
+            .iter()
+            .fold(DrawStats::default(), |total, pass| total + pass.frame_stats())
     }
 
     pub fn final_image(&self) -> FinalImageView {
         self.image_views[self.image_num].clone()
     }
 
+    /// Creates a sampler from `config` and adds it to [`Renderer::samplers`], returning its
+    /// index there.
+    pub fn add_sampler(&mut self, config: sampler::SamplerConfig) -> Result<usize> {
+        let sampler = config.build(self.device.clone())?;
+        self.samplers.push(sampler);
+        Ok(self.samplers.len() - 1)
+    }
+
+    /// The image to draw the scene into this frame: [`Renderer::final_image`] at the native
+    /// render scale, otherwise an internal image sized by [`crate::graphics::set_render_scale`]
+    /// that [`Renderer::resolve_scene`] stretches over it.
+    pub fn scene_image(&mut self) -> Result<Arc<dyn ImageViewAbstract>> {
+        if self.render_scale.is_native() {
+            return Ok(self.final_image());
+        }
+
+        let native = self.swapchain.image_extent();
+        let format = self.output_format();
+        Ok(self.render_scale.target(self.queue.clone(), format, native)?)
+    }
+
+    /// Stretches the scene drawn into [`Renderer::scene_image`] over the swapchain image, after
+    /// which draws into [`Renderer::final_image`] are at native resolution. Returns
+    /// `before_future` as is when there's nothing to resolve.
+    pub fn resolve_scene(&mut self, before_future: Box<dyn GpuFuture>) -> Result<Box<dyn GpuFuture>> {
+        let scene = match self.render_scale.take_pending() {
+            Some(scene) => scene,
+            None => return Ok(before_future),
+        };
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.device.clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        let source = scene.image().clone();
+        let target = self.image_views[self.image_num].image().clone();
+        let [sw, sh] = source.dimensions().width_height();
+        let [tw, th] = target.dimensions().width_height();
+        builder.blit_image(
+            source,
+            [0, 0, 0],
+            [sw as i32, sh as i32, 1],
+            0,
+            0,
+            target,
+            [0, 0, 0],
+            [tw as i32, th as i32, 1],
+            0,
+            0,
+            1,
+            Filter::Linear,
+        )?;
+
+        let commands = builder.build()?;
+        Ok(before_future.then_execute(self.queue.clone(), commands)?.boxed())
+    }
+
+    /// The size in pixels the scene is rendered at, the swapchain extent scaled by the
+    /// render scale.
+    pub fn render_extent(&self) -> [u32; 2] {
+        self.render_scale.extent(self.swapchain.image_extent())
+    }
+
     pub fn output_format(&self) -> Format {
         self.image_views[self.image_num].format().unwrap()
     }
+
+    /// Shows or hides the window's title bar and borders, see [`Conf::decorations`] for
+    /// platform support.
+    pub fn set_decorations(&mut self, decorations: bool) {
+        self.surface.window().set_decorations(decorations);
+    }
+
+    /// Draws only the edges of triangles in every render pass in [`Renderer::render_passes`],
+    /// for checking the triangles generated meshes, ribbons, and tilemaps are made of. Passes
+    /// added afterwards take it from [`render_pass::RenderPass::set_polygon_mode`].
+    ///
+    /// Needs the device's `fill_mode_non_solid` feature, without it this warns and keeps
+    /// drawing filled.
+    pub fn set_wireframe(&mut self, wireframe: bool) {
+        if wireframe && !self.device.enabled_features().fill_mode_non_solid {
+            log::warn!(target: "ledge::graphics", "wireframe needs the fill_mode_non_solid feature, which this device lacks, drawing filled");
+            return;
+        }
+
+        self.wireframe = wireframe;
+        let mode = if wireframe { shader::PolygonMode::Line } else { shader::PolygonMode::Fill };
+        for pass in self.render_passes.iter_mut() {
+            pass.set_polygon_mode(mode);
+        }
+    }
+
+    pub fn wireframe(&self) -> bool {
+        self.wireframe
+    }
+
+    /// Confines everything drawn by the passes in [`Renderer::render_passes`] to `region` of
+    /// the swapchain image in pixels, such as an editor's game pane, or the whole image again
+    /// with `None`. Passes added afterwards take it from
+    /// [`render_pass::RenderPass::set_render_region`].
+    ///
+    /// Frames set their viewport and scissor to the region, so [`Renderer::viewport`], the
+    /// default camera, and [`Renderer::coords`] all follow it. A pass's clear color still
+    /// covers the whole image, the rest of the window is left for UI drawn afterwards.
+    pub fn set_render_region(&mut self, region: Option<Rect>) {
+        self.render_region = region;
+        for pass in self.render_passes.iter_mut() {
+            pass.set_render_region(region);
+        }
+    }
+
+    pub fn render_region(&self) -> Option<Rect> {
+        self.render_region
+    }
+
+    /// Keeps the window above every other window, see [`Conf::always_on_top`] for platform
+    /// support.
+    pub fn set_always_on_top(&mut self, always_on_top: bool) {
+        self.surface.window().set_always_on_top(always_on_top);
+    }
+
+    /// The window's drawable area in physical pixels.
+    pub fn window_size(&self) -> (u32, u32) {
+        self.surface.window().inner_size().into()
+    }
+
+    /// The refresh rate in Hz of the monitor the window is on, `None` if it can't be told.
+    ///
+    /// winit doesn't report the monitor's current mode, so this is the fastest rate the
+    /// monitor offers at its current resolution, which is what it runs at unless the user
+    /// picked a slower one.
+    pub fn monitor_refresh_rate(&self) -> Option<u32> {
+        let monitor = self.surface.window().current_monitor()?;
+        let size = monitor.size();
+        monitor
+            .video_modes()
+            .filter(|mode| mode.size() == size)
+            .map(|mode| mode.refresh_rate() as u32)
+            .filter(|&rate| rate > 0)
+            .max()
+    }
+
+    /// The window's drawable area in logical pixels, the physical size divided by the
+    /// monitor's scale factor.
+    pub fn inner_logical_size(&self) -> (f64, f64) {
+        let window = self.surface.window();
+        window.inner_size().to_logical::<f64>(window.scale_factor()).into()
+    }
+
+    /// Resizes the window's drawable area to `width` by `height` physical pixels. The swapchain
+    /// follows on the next frame, after [`crate::event::EventHandler::resize`] is called.
+    pub fn set_window_size(&mut self, width: u32, height: u32) {
+        self.surface
+            .window()
+            .set_inner_size(winit::dpi::PhysicalSize::new(width, height));
+    }
+
+    /// Loads the image at `path` with the default sampler and the renderer's
+    /// [`image::ImageSpec`], see [`Renderer::image_spec`].
+    pub fn load_image<P: AsRef<std::path::Path>>(&self, path: P) -> Result<image::Image, image::ImageError> {
+        image::Image::with_spec(self.queue.clone(), self.samplers[0].clone(), path, self.image_spec)
+    }
+
+    /// How [`Renderer::load_image`] loads images, set up from [`Conf::premultiply_images`] and
+    /// [`Conf::use_placeholder_on_error`].
+    /// Pass it to [`image::Image::with_spec`] or [`image::Image::load_many`] to load other
+    /// images the same way.
+    pub fn image_spec(&self) -> image::ImageSpec {
+        self.image_spec
+    }
+
+    /// The window's title, from [`Conf::new`] or the last [`Renderer::set_title`].
+    pub fn title(&self) -> &str {
+        &self.title
+    }
+
+    pub fn set_title(&mut self, title: &str) {
+        self.surface.window().set_title(title);
+        self.title = title.to_string();
+    }
+
+    /// The region of the swapchain image drawn to in pixels, its full extent unless
+    /// [`Renderer::set_render_region`] limits it.
+    pub fn viewport(&self) -> Rect {
+        let [w, h] = self.swapchain.image_extent();
+        match self.render_region {
+            Some(region) => render_pass::clamp_region(region, w as f32, h as f32),
+            None => Rect {
+                x: 0.0,
+                y: 0.0,
+                w: w as f32,
+                h: h as f32,
+            },
+        }
+    }
+
+    /// Maps the current [`CoordinateSystem`] over the whole swapchain image to normalized device
+    /// coordinates, suitable for [`render_pass::frame::Pass::set_camera`].
+    pub fn projection(&self) -> [[f32; 4]; 4] {
+        self.coordinate_system.projection(&self.viewport())
+    }
+
+    /// Converts points between the window, the viewport, and a camera's world for the current
+    /// window size and [`CoordinateSystem`]. Ask again after a resize.
+    pub fn coords(&self) -> coordinates::CoordinateMapper {
+        let (w, h) = self.window_size();
+        let [extent_w, extent_h] = self.swapchain.image_extent();
+        coordinates::CoordinateMapper::new((w as f32, h as f32), self.viewport(), self.coordinate_system)
+            .with_extent((extent_w as f32, extent_h as f32))
+    }
+
+    /// A camera showing the current [`CoordinateSystem`], to pass to
+    /// [`render_pass::RenderPass::frame`]. Recreate it after a resize.
+    pub fn default_camera(&self) -> Arc<dyn camera::Camera> {
+        Arc::new(camera::OrthographicCamera::with_projection(self.projection()))
+    }
+}
+
+/// The window and Vulkan objects behind the renderer, for libraries such as
+/// `egui-winit-vulkano` that draw into the same frame.
+///
+/// The device and queue are the public [`Renderer::device`] and [`Renderer::queue`] fields, and
+/// each render pass's Vulkan render pass is [`render_pass::RenderPass::render_pass`]. A typical
+/// integration:
+///
+/// 1. Create the library's renderer in [`crate::event::EventHandler::start`] from
+///    [`Renderer::surface`], `queue`, and [`Renderer::output_format`], as an overlay so it
+///    loads the existing image contents instead of clearing them.
+/// 2. Forward window events to it, driving the loop from [`crate::event::EventLoop::into_raw`]
+///    if it needs winit's own event types.
+/// 3. In `draw`, chain its draw onto the future returned by the final
+///    [`render_pass::frame::PassState::Finished`], targeting [`Renderer::final_image`], and
+///    return the combined future so [`Renderer::end_frame`] presents both.
+///
+/// Recreate anything sized to the swapchain after a resize, [`Renderer::swapchain`] reports the
+/// current extent.
+#[cfg(feature = "unstable-raw")]
+impl Renderer {
+    pub fn surface(&self) -> &Arc<Surface<Window>> {
+        &self.surface
+    }
+
+    /// The window, which implements `raw_window_handle::HasRawWindowHandle`.
+    pub fn window(&self) -> &Window {
+        self.surface.window()
+    }
+
+    pub fn swapchain(&self) -> &Arc<Swapchain<Window>> {
+        &self.swapchain
+    }
+}
+
+#[cfg(all(test, target_os = "linux"))]
+mod tests {
+    use super::*;
+    use winit::platform::unix::EventLoopExtUnix;
+
+    /// A renderer on a new window, with [`test_pass_for`] its output format as render pass 0.
+    /// The event loop has to outlive the window.
+    fn test_renderer() -> (Renderer, EventLoop<()>) {
+        let event_loop = EventLoop::new_any_thread();
+        let mut renderer = Renderer::from_event_loop(Conf::new("ledge test"), &event_loop);
+        let pass = test_pass_for(renderer.queue.clone(), renderer.output_format());
+        renderer.render_passes.push(pass);
+        (renderer, event_loop)
+    }
+
+    /// Draws and presents a frame of render pass 0 with shader 0 set.
+    fn draw_frame<F>(renderer: &mut Renderer, draw: F)
+    where
+        F: FnOnce(&mut render_pass::frame::Pass) -> Result<()>,
+    {
+        let before_future = renderer.begin_frame().unwrap();
+        let final_image = renderer.final_image();
+        let camera = renderer.default_camera();
+        let mut frame = renderer.render_passes[0]
+            .frame(Color::black().into(), before_future, final_image, camera)
+            .unwrap();
+
+        let mut draw = Some(draw);
+        let mut after_future = None;
+        while let Some(state) = frame.next_pass().unwrap() {
+            match state {
+                render_pass::frame::PassState::DrawPass(mut pass) => {
+                    pass.set_shader(0);
+                    if let Some(draw) = draw.take() {
+                        draw(&mut pass).unwrap();
+                    }
+                }
+                render_pass::frame::PassState::Finished(future) => after_future = Some(future),
+            }
+        }
+
+        renderer.end_frame(after_future.unwrap());
+    }
+
+    #[test]
+    #[ignore = "needs a display and a Vulkan device"]
+    fn swapchain_info_describes_the_default_swapchain() {
+        let (renderer, _event_loop) = test_renderer();
+        let info = renderer.swapchain_info();
+        let caps = renderer
+            .device
+            .physical_device()
+            .surface_capabilities(&renderer.surface, Default::default())
+            .unwrap();
+
+        assert!(info.image_count >= caps.min_image_count.max(1));
+        assert_eq!(info.format, renderer.output_format());
+        assert_eq!(info.color_space, ColorSpace::SrgbNonLinear);
+        let extent: [u32; 2] = renderer.surface.window().inner_size().into();
+        assert_eq!(info.extent, extent);
+        assert!(info.extent[0] > 0 && info.extent[1] > 0);
+        // Vsync is off unless the conf asks for it.
+        assert_eq!(info.present_mode, PresentMode::Immediate);
+    }
+
+    #[test]
+    #[ignore = "needs a display and a Vulkan device"]
+    fn rebuilt_shaders_draw_into_the_new_render_pass() {
+        let (mut renderer, _event_loop) = test_renderer();
+
+        // The same target loaded rather than cleared, so pipelines built for the old pass
+        // would no longer match it.
+        let replacement = vulkano::single_pass_renderpass!(renderer.device.clone(),
+            attachments: {
+                color: {
+                    load: Load,
+                    store: Store,
+                    format: renderer.output_format(),
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+        .unwrap();
+        renderer.render_passes[0].render_pass = replacement.clone();
+        renderer.rebuild_pipelines().unwrap();
+
+        let shader = renderer.render_passes[0].shader(0).unwrap();
+        assert!(Arc::ptr_eq(shader.pipeline().subpass().render_pass(), &replacement));
+        draw_frame(&mut renderer, |pass| draw_quad(pass, Rect { x: 0.0, y: 0.0, w: 0.5, h: 0.5 }, Color::white()));
+    }
+
+    #[test]
+    #[ignore = "needs a display and a Vulkan device"]
+    fn set_title_updates_the_stored_title() {
+        let (mut renderer, _event_loop) = test_renderer();
+        assert_eq!(renderer.title(), "ledge test");
+
+        renderer.set_title("renamed");
+        assert_eq!(renderer.title(), "renamed");
+    }
+
+    #[test]
+    #[ignore = "needs a display and a Vulkan device"]
+    fn reconfiguring_keeps_loaded_textures() {
+        let (mut renderer, _event_loop) = test_renderer();
+        let image = Arc::new(renderer.load_image("examples/images/pokeball.png").unwrap());
+
+        renderer
+            .reconfigure(SurfaceConfig {
+                extent: Some([320, 240]),
+                ..Default::default()
+            })
+            .unwrap();
+        assert_eq!(renderer.swapchain_info().extent, [320, 240]);
+
+        draw_frame(&mut renderer, |pass| pass.draw(image.clone(), DrawInfo::default()));
+
+        let mut pass = test_pass(renderer.queue.clone());
+        let pixels = render_offscreen(&renderer.queue, &mut pass, [16, 16], Color::rgba(0, 0, 0, 0), |pass| {
+            pass.draw(image, DrawInfo::centered((8.0, 8.0), (16.0, 16.0), 0.0, Color::white()))
+        });
+        assert_ne!(pixel_at(&pixels, 16, (8, 8))[3], 0);
+    }
+
+    #[test]
+    #[ignore = "needs a display and a Vulkan device"]
+    fn gpu_frame_time_appears_after_a_few_frames() {
+        let (mut renderer, _event_loop) = test_renderer();
+        if renderer.queue.family().timestamp_valid_bits().is_none() {
+            return;
+        }
+
+        // Timestamps are read back a few frames after they're written, never waited on.
+        assert_eq!(renderer.gpu_frame_time(), None);
+        for _ in 0..6 {
+            draw_frame(&mut renderer, |pass| draw_quad(pass, Rect { x: 0.0, y: 0.0, w: 0.5, h: 0.5 }, Color::white()));
+        }
+
+        assert!(renderer.gpu_frame_time().is_some_and(|time| !time.is_zero()));
+    }
+
+    #[test]
+    #[ignore = "needs a display and a Vulkan device"]
+    fn resized_images_start_with_the_clear_color() {
+        use vulkano::buffer::{BufferUsage, CpuAccessibleBuffer};
+
+        let (mut renderer, _event_loop) = test_renderer();
+        if !renderer.swapchain.create_info().image_usage.transfer_source {
+            return;
+        }
+        renderer.set_swapchain_content(SwapchainContent::Clear(Color::red()));
+
+        // What `Interface::process_event` does on `WindowEvent::Resized`.
+        renderer.recreate_swapchain = true;
+        let before_future = renderer.begin_frame().unwrap();
+
+        let image = renderer.image_views[renderer.image_num].image().clone();
+        let [width, height] = image.dimensions().width_height();
+        let buffer = CpuAccessibleBuffer::from_iter(
+            renderer.device.clone(),
+            BufferUsage::transfer_destination(),
+            false,
+            (0..width * height * 4).map(|_| 0u8),
+        )
+        .unwrap();
+        let mut builder = AutoCommandBufferBuilder::primary(
+            renderer.device.clone(),
+            renderer.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )
+        .unwrap();
+        builder.copy_image_to_buffer(image.clone(), buffer.clone()).unwrap();
+        before_future
+            .then_execute(renderer.queue.clone(), builder.build().unwrap())
+            .unwrap()
+            .then_signal_fence_and_flush()
+            .unwrap()
+            .wait(None)
+            .unwrap();
+
+        let red = match image.format() {
+            Format::B8G8R8A8_UNORM | Format::B8G8R8A8_SRGB => [0, 0, 255, 255],
+            _ => [255, 0, 0, 255],
+        };
+        let pixels = buffer.read().unwrap();
+        assert_eq!(pixel_at(&pixels, width, (0, 0)), red);
+        assert_eq!(pixel_at(&pixels, width, (width - 1, height - 1)), red);
+    }
 }
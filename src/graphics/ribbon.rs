@@ -0,0 +1,115 @@
+use std::collections::VecDeque;
+
+use crate::graphics::immediate::ImmediateBatch;
+use crate::graphics::mesh::Mesh;
+use crate::graphics::*;
+
+/// A point in a [`Ribbon`]'s history.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct RibbonPoint {
+    pub position: (f32, f32),
+    /// Full width of the ribbon at this point, before tapering.
+    pub width: f32,
+    pub color: Color,
+}
+
+/// A trail through the last few positions of something moving, tapering in width and fading
+/// out towards its oldest point.
+///
+/// The history length trades smoothness for cost and reach. Every point adds two vertices, so
+/// the mesh is cheap to rebuild each frame even at a few hundred points, but the trail's
+/// length on screen depends on how far the head moves between pushes: a fast bullet pushed
+/// every frame needs only a short history, while a slow sword swing needs a long one to trail
+/// visibly. Sharp turns between widely spaced points fold the strip over itself, push more
+/// often rather than keeping more points when that shows.
+#[derive(Clone, Debug)]
+pub struct Ribbon {
+    points: VecDeque<RibbonPoint>,
+    capacity: usize,
+}
+
+impl Ribbon {
+    /// A ribbon remembering the last `capacity` points, at least 2.
+    pub fn new(capacity: usize) -> Self {
+        let capacity = capacity.max(2);
+        Self {
+            points: VecDeque::with_capacity(capacity),
+            capacity,
+        }
+    }
+
+    /// Adds `point` as the new head, dropping the oldest point if the history is full.
+    pub fn push(&mut self, point: RibbonPoint) {
+        if self.points.len() == self.capacity {
+            self.points.pop_front();
+        }
+        self.points.push_back(point);
+    }
+
+    pub fn clear(&mut self) {
+        self.points.clear();
+    }
+
+    pub fn len(&self) -> usize {
+        self.points.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    pub fn capacity(&self) -> usize {
+        self.capacity
+    }
+
+    /// The ribbon as a triangle list, empty until it has at least two points.
+    ///
+    /// Each point's width and alpha are scaled from nothing at the tail to their full values
+    /// at the head, and uvs run from `x = 0` at the tail to `x = 1` at the head.
+    pub fn mesh(&self) -> Mesh {
+        let count = self.points.len();
+        if count < 2 {
+            return Mesh::default();
+        }
+
+        let mut vertices = Vec::with_capacity(count * 2);
+        for (i, point) in self.points.iter().enumerate() {
+            let t = i as f32 / (count - 1) as f32;
+
+            // Average the directions to both neighbours so joints don't pinch.
+            let prev = self.points[i.saturating_sub(1)].position;
+            let next = self.points[(i + 1).min(count - 1)].position;
+            let (dx, dy) = (next.0 - prev.0, next.1 - prev.1);
+            let len = (dx * dx + dy * dy).sqrt().max(f32::EPSILON);
+            let half = point.width * t * 0.5;
+            let (nx, ny) = (-dy / len * half, dx / len * half);
+
+            let mut color: [f32; 4] = point.color.into();
+            color[3] *= t;
+
+            for (side, v) in [(1.0, 0.0), (-1.0, 1.0)] {
+                vertices.push(Vertex {
+                    pos: [point.position.0 + nx * side, point.position.1 + ny * side, 0.0],
+                    uv: [t, v],
+                    vert_color: color,
+                });
+            }
+        }
+
+        let mut indices = Vec::with_capacity((count - 1) * 6);
+        for i in 0..count as u32 - 1 {
+            let a = i * 2;
+            indices.extend_from_slice(&[a, a + 1, a + 2, a + 1, a + 3, a + 2]);
+        }
+
+        Mesh::new(vertices, indices)
+    }
+
+    /// Records the ribbon into `batch`, drawn with the rest of the batch by
+    /// [`render_pass::frame::Pass::draw_immediate`].
+    pub fn draw(&self, batch: &mut ImmediateBatch) {
+        if self.points.len() >= 2 {
+            batch.mesh(&self.mesh());
+        }
+    }
+}
@@ -0,0 +1,175 @@
+//! Settings for the samplers images are drawn with.
+//!
+//! [`SamplerConfig::build`] creates a sampler to pass to [`crate::graphics::image::Image::new`],
+//! or [`crate::graphics::renderer::Renderer::add_sampler`] keeps one in the renderer alongside the default.
+//!
+//! A sprite drawn from part of an atlas with [`crate::graphics::DrawInfo::tex_rect`] can still
+//! sample texels just outside that part when it's rotated or scaled, since linear filtering
+//! blends neighbouring texels. Clamping only helps at the edges of the whole image, so give
+//! atlas entries a transparent border of their own and use [`AddressMode::ClampToBorder`] with
+//! [`BorderColor::Transparent`] for images drawn whole, where edges would otherwise pick up
+//! texels from the opposite side under [`AddressMode::Repeat`].
+//!
+//! [`SamplerConfig::mipmap_mode`] is separate from [`SamplerConfig::filter`] and only matters
+//! for images with more than one mip level. [`FilterMode::Nearest`], the default, picks the
+//! single closest mip, which shows as visible seams where a surface shrinks from one level to
+//! the next. [`FilterMode::Linear`] blends the two closest levels, trilinear filtering when
+//! paired with a linear `filter`, for smooth transitions at the cost of a second lookup.
+
+use std::sync::Arc;
+
+use anyhow::Result;
+use vulkano::device::Device;
+use vulkano::sampler::{Filter, Sampler, SamplerAddressMode, SamplerCreateInfo, SamplerMipmapMode};
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum FilterMode {
+    /// Pixelated, each sample takes the closest texel.
+    Nearest,
+    Linear,
+}
+
+/// What happens to texture coordinates outside of 0 to 1.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum AddressMode {
+    Repeat,
+    MirroredRepeat,
+    ClampToEdge,
+    /// Samples outside the image are the sampler's [`BorderColor`].
+    ClampToBorder,
+}
+
+/// The color sampled outside the image with [`AddressMode::ClampToBorder`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub enum BorderColor {
+    Transparent,
+    OpaqueBlack,
+    OpaqueWhite,
+}
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+#[cfg_attr(feature = "serde", derive(serde::Serialize, serde::Deserialize))]
+pub struct SamplerConfig {
+    /// Filtering within a mip level, used both when minifying and magnifying.
+    pub filter: FilterMode,
+    /// Filtering between mip levels, see the [module docs](self).
+    #[cfg_attr(feature = "serde", serde(default = "default_mipmap_mode"))]
+    pub mipmap_mode: FilterMode,
+    pub address_mode: AddressMode,
+    /// Only used with [`AddressMode::ClampToBorder`].
+    pub border_color: BorderColor,
+}
+
+impl Default for SamplerConfig {
+    /// Linear filtering with nearest mip selection and repeating coordinates, the renderer's
+    /// default sampler.
+    fn default() -> Self {
+        Self {
+            filter: FilterMode::Linear,
+            mipmap_mode: FilterMode::Nearest,
+            address_mode: AddressMode::Repeat,
+            border_color: BorderColor::Transparent,
+        }
+    }
+}
+
+impl SamplerConfig {
+    /// Linear filtering, clamped to a transparent border.
+    pub fn clamp_to_transparent() -> Self {
+        Self {
+            address_mode: AddressMode::ClampToBorder,
+            ..Self::default()
+        }
+    }
+
+    /// Linear filtering within and between mip levels, for smooth transitions as images shrink.
+    pub fn trilinear() -> Self {
+        Self {
+            mipmap_mode: FilterMode::Linear,
+            ..Self::default()
+        }
+    }
+
+    pub fn build(&self, device: Arc<Device>) -> Result<Arc<Sampler>> {
+        let filter = match self.filter {
+            FilterMode::Nearest => Filter::Nearest,
+            FilterMode::Linear => Filter::Linear,
+        };
+        let mipmap_mode = match self.mipmap_mode {
+            FilterMode::Nearest => SamplerMipmapMode::Nearest,
+            FilterMode::Linear => SamplerMipmapMode::Linear,
+        };
+        let address_mode = match self.address_mode {
+            AddressMode::Repeat => SamplerAddressMode::Repeat,
+            AddressMode::MirroredRepeat => SamplerAddressMode::MirroredRepeat,
+            AddressMode::ClampToEdge => SamplerAddressMode::ClampToEdge,
+            AddressMode::ClampToBorder => SamplerAddressMode::ClampToBorder,
+        };
+        let border_color = match self.border_color {
+            BorderColor::Transparent => vulkano::sampler::BorderColor::FloatTransparentBlack,
+            BorderColor::OpaqueBlack => vulkano::sampler::BorderColor::FloatOpaqueBlack,
+            BorderColor::OpaqueWhite => vulkano::sampler::BorderColor::FloatOpaqueWhite,
+        };
+
+        Ok(Sampler::new(
+            device,
+            SamplerCreateInfo {
+                mag_filter: filter,
+                min_filter: filter,
+                mipmap_mode,
+                address_mode: [address_mode; 3],
+                border_color,
+                ..Default::default()
+            },
+        )?)
+    }
+}
+
+#[cfg(feature = "serde")]
+fn default_mipmap_mode() -> FilterMode {
+    SamplerConfig::default().mipmap_mode
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::image::Image;
+    use crate::graphics::{pixel_at, render_offscreen, test_device, test_pass, Color, DrawInfo, Rect};
+
+    /// A 4x1 red sprite sampled with `border_color` from u = -1 to 1, so its left half is border.
+    fn draw_bordered(border_color: BorderColor) -> Vec<u8> {
+        let (queue, _) = test_device();
+        let config = SamplerConfig {
+            filter: FilterMode::Nearest,
+            address_mode: AddressMode::ClampToBorder,
+            border_color,
+            ..SamplerConfig::default()
+        };
+        let sampler = config.build(queue.device().clone()).unwrap();
+        let red = Arc::new(Image::from_color(queue.clone(), sampler, Color::red()));
+
+        let mut info = DrawInfo::centered((2.0, 0.5), (4.0, 1.0), 0.0, Color::white());
+        info.tex_rect = Rect { x: -1.0, y: 0.0, w: 2.0, h: 1.0 };
+        let mut pass = test_pass(queue.clone());
+        render_offscreen(&queue, &mut pass, [4, 1], Color::black(), |pass| pass.draw(red, info))
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn samples_past_the_edge_take_the_border_color() {
+        let white = draw_bordered(BorderColor::OpaqueWhite);
+        let transparent = draw_bordered(BorderColor::Transparent);
+
+        for x in 0..2 {
+            assert_eq!(pixel_at(&white, 4, (x, 0)), [255; 4], "column {}", x);
+            assert_eq!(pixel_at(&transparent, 4, (x, 0)), [0, 0, 0, 255], "column {}", x);
+        }
+        for x in 2..4 {
+            assert_eq!(pixel_at(&white, 4, (x, 0)), [255, 0, 0, 255], "column {}", x);
+            assert_eq!(pixel_at(&transparent, 4, (x, 0)), [255, 0, 0, 255], "column {}", x);
+        }
+    }
+}
@@ -1,38 +1,69 @@
 use std::collections::HashMap;
-use std::sync::Arc;
+use std::sync::atomic::{AtomicU32, Ordering};
+use std::sync::{Arc, RwLock};
 
 use crate::graphics::{BlendMode, PipelineData};
 use vulkano::pipeline::graphics::color_blend::ColorComponents;
 use vulkano::pipeline::graphics::input_assembly::InputAssemblyState;
 use vulkano::pipeline::graphics::input_assembly::PrimitiveTopology;
+use vulkano::pipeline::graphics::rasterization::{PolygonMode as VkPolygonMode, RasterizationState};
 use vulkano::pipeline::graphics::viewport::ViewportState;
 use vulkano::pipeline::Pipeline;
 use vulkano::pipeline::PipelineBindPoint;
 use vulkano::pipeline::StateMode;
 use vulkano::{
-    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer},
-    descriptor_set::layout::DescriptorSetLayout,
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{AutoCommandBufferBuilder, PrimaryAutoCommandBuffer, SecondaryAutoCommandBuffer},
+    descriptor_set::{layout::DescriptorSetLayout, PersistentDescriptorSet, WriteDescriptorSet},
     pipeline::{
         graphics::color_blend::{
             AttachmentBlend, BlendFactor, BlendOp, ColorBlendAttachmentState, ColorBlendState,
             LogicOp,
         },
         graphics::vertex_input::VertexDefinition,
+        layout::PipelineLayout,
         GraphicsPipeline,
     },
-    device::Device,
+    device::{Device, DeviceOwned},
     render_pass::{Subpass, RenderPass},
-    shader::EntryPoint,
+    shader::{EntryPoint, ShaderModule},
 };
 
+use anyhow::{anyhow, Result};
+
 #[derive(Clone, Copy)]
 pub enum VertexTopology {
+    /// Lines drawn `width` pixels wide, see [`supported_line_width`].
+    LineList { width: f32 },
+    LineStrip { width: f32 },
     PointList,
     TriangleFan,
     TriangleList,
     TriangleStrip,
 }
 
+/// How triangles are rasterized, see [`crate::graphics::renderer::Renderer::set_wireframe`].
+#[derive(Clone, Copy, Debug, Default, PartialEq, Eq, Hash)]
+pub enum PolygonMode {
+    /// Triangles are filled in, how everything is normally drawn.
+    #[default]
+    Fill,
+    /// Only triangle edges are drawn, for seeing the triangles a mesh is made of.
+    Line,
+    /// Only triangle vertices are drawn.
+    Point,
+}
+
+impl From<PolygonMode> for VkPolygonMode {
+    fn from(mode: PolygonMode) -> Self {
+        match mode {
+            PolygonMode::Fill => VkPolygonMode::Fill,
+            PolygonMode::Line => VkPolygonMode::Line,
+            PolygonMode::Point => VkPolygonMode::Point,
+        }
+    }
+}
+
 pub enum ShaderType {
     Vertex,
     Fragment,
@@ -44,16 +75,187 @@ pub enum ShaderType {
 
 pub type ShaderId = usize;
 
-pub struct Shader<'s> {
-    pub vertex: EntryPoint<'s>,
-    pub fragment: EntryPoint<'s>,
+/// Name of the entry point used in every shader module.
+pub const SHADER_ENTRY_POINT: &str = "main";
+
+pub struct Shader {
+    pub vertex: Arc<ShaderModule>,
+    pub fragment: Arc<ShaderModule>,
     pub topology: VertexTopology,
     // pub vertex_definition: Vd,
 }
 
+impl Shader {
+    /// The built-in sprite shaders with normal mapped lighting, for batches given a normal map
+    /// with [`crate::graphics::sprite::SpriteBatch::set_normal_map`].
+    ///
+    /// Like the unlit shaders it takes [`crate::graphics::Vertex`] vertices and
+    /// [`crate::graphics::InstanceData`] instances. Its per-draw set binds the texture at 0,
+    /// the normal map at 1, and the light uniform at 2.
+    pub fn lit_sprite(device: Arc<Device>) -> Result<Arc<Self>> {
+        Ok(Arc::new(Self {
+            vertex: crate::graphics::vs::load(device.clone())?,
+            fragment: crate::graphics::lit_fs::load(device)?,
+            topology: VertexTopology::TriangleStrip,
+        }))
+    }
+
+    /// The built-in shaders for untextured shapes, which only combine the vertex and instance
+    /// colors and so have no per-draw set at all.
+    ///
+    /// Takes [`crate::graphics::Vertex`] vertices and [`crate::graphics::InstanceData`]
+    /// instances as a triangle list, like [`crate::graphics::immediate::ImmediateBatch`] records.
+    pub fn colored(device: Arc<Device>) -> Result<Arc<Self>> {
+        Ok(Arc::new(Self {
+            vertex: crate::graphics::vs::load(device.clone())?,
+            fragment: crate::graphics::colored_fs::load(device)?,
+            topology: VertexTopology::TriangleList,
+        }))
+    }
+}
+
+type PipelineFactory = dyn Fn(BlendMode, PolygonMode, &Arc<RenderPass>) -> Result<Arc<GraphicsPipeline>> + Send + Sync;
+
+/// How many pipelines a shader has compiled, split by when they were compiled.
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct PipelineStats {
+    /// Pipelines built by [`crate::graphics::warm_up`] or when the shader was created.
+    pub warm_up_compiles: u32,
+    /// Pipelines built the first time a blend mode was used while drawing.
+    pub runtime_compiles: u32,
+}
+
+/// A registered shader as listed by [`crate::graphics::render_pass::RenderPass::shaders`].
+#[derive(Clone, Debug, PartialEq)]
+pub struct ShaderInfo {
+    pub id: ShaderId,
+    /// The mode draws use unless they ask for another.
+    pub blend_mode: BlendMode,
+    /// Modes with a compiled pipeline, which switching to costs no compile.
+    pub cached_modes: Vec<BlendMode>,
+    pub stats: PipelineStats,
+}
+
+impl std::ops::Add for PipelineStats {
+    type Output = PipelineStats;
+
+    fn add(self, other: PipelineStats) -> PipelineStats {
+        PipelineStats {
+            warm_up_compiles: self.warm_up_compiles + other.warm_up_compiles,
+            runtime_compiles: self.runtime_compiles + other.runtime_compiles,
+        }
+    }
+}
+
+/// Descriptor set shaders read their per-draw resources, such as the sampled texture, from.
+///
+/// The crate's shaders put the camera uniform in set 0 and per-draw resources in set 1. Shaders
+/// numbered differently set their own index with [`ShaderProgram::set_descriptor_set_index`].
+///
+/// Per-draw sets are allocated from the device's standard descriptor pool, which adds another
+/// pool whenever the ones it has fill up and reuses sets once their draws are finished. There
+/// is no fixed pool size to run out of, so scenes with many unique descriptors per frame
+/// don't need any pool configuration.
+pub const DEFAULT_DESCRIPTOR_SET_INDEX: usize = 1;
+
+/// Set of the uniform buffer holding the params passed to
+/// [`crate::graphics::draw_with_params`], for shaders that take them without push constants.
+pub const PARAMS_DESCRIPTOR_SET_INDEX: usize = 2;
+/// Binding of the params uniform in [`PARAMS_DESCRIPTOR_SET_INDEX`].
+pub const PARAMS_BINDING: u32 = 0;
+
+type PushParams = dyn Fn(&mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>, Arc<PipelineLayout>) + Send + Sync;
+
+/// Per-draw shader parameters stashed in the [`crate::graphics::DrawState`] by
+/// [`crate::graphics::render_pass::frame::Pass::set_shader_params`].
+#[derive(Clone)]
+pub(crate) struct ShaderParams {
+    bytes: Arc<[u8]>,
+    push: Arc<PushParams>,
+}
+
+impl ShaderParams {
+    pub(crate) fn new<P: bytemuck::Pod + Send + Sync>(params: P) -> Self {
+        Self {
+            bytes: bytemuck::bytes_of(&params).into(),
+            push: Arc::new(move |builder, layout| {
+                builder.push_constants(layout, 0, params);
+            }),
+        }
+    }
+
+    /// Binds the params for `pipeline`, as push constants if it has any, otherwise as a uniform
+    /// buffer at [`PARAMS_DESCRIPTOR_SET_INDEX`].
+    pub(crate) fn bind(
+        &self,
+        builder: &mut AutoCommandBufferBuilder<SecondaryAutoCommandBuffer>,
+        pipeline: &Arc<GraphicsPipeline>,
+        device: Arc<Device>,
+    ) -> Result<()> {
+        let layout = pipeline.layout();
+        let push_size = layout
+            .push_constant_ranges()
+            .iter()
+            .map(|range| range.offset + range.size)
+            .max()
+            .unwrap_or(0);
+
+        if push_size > 0 {
+            if push_size as usize != self.bytes.len() {
+                return Err(anyhow!(
+                    "shader params are {} bytes but the shader's push constants take {} bytes",
+                    self.bytes.len(),
+                    push_size,
+                ));
+            }
+
+            (self.push)(builder, layout.clone());
+            return Ok(());
+        }
+
+        let set_layout = layout
+            .set_layouts()
+            .get(PARAMS_DESCRIPTOR_SET_INDEX)
+            .ok_or_else(|| anyhow!(
+                "shader params given to a shader without push constants or a uniform at set {} binding {}",
+                PARAMS_DESCRIPTOR_SET_INDEX,
+                PARAMS_BINDING,
+            ))?;
+
+        let buffer = CpuAccessibleBuffer::from_iter(
+            device,
+            BufferUsage::uniform_buffer(),
+            false,
+            self.bytes.iter().copied(),
+        )?;
+        let set = PersistentDescriptorSet::new(
+            set_layout.clone(),
+            [WriteDescriptorSet::buffer(PARAMS_BINDING, buffer)],
+        )?;
+
+        builder.bind_descriptor_sets(
+            PipelineBindPoint::Graphics,
+            layout.clone(),
+            PARAMS_DESCRIPTOR_SET_INDEX as u32,
+            set,
+        );
+
+        Ok(())
+    }
+}
+
 pub struct ShaderProgram {
     pipelines: PipelineObjectSet,
+    /// Pipelines rasterized other than filled, compiled the first time they're asked for.
+    variants: RwLock<HashMap<(BlendMode, PolygonMode), Arc<GraphicsPipeline>>>,
+    descriptor_set_index: usize,
+    current: Arc<GraphicsPipeline>,
     current_mode: BlendMode,
+    factory: Option<Arc<PipelineFactory>>,
+    /// The render pass `factory` builds pipelines for, `None` along with it.
+    render_pass: Option<Arc<RenderPass>>,
+    warm_up_compiles: AtomicU32,
+    runtime_compiles: AtomicU32,
 }
 
 pub trait ShaderHandle {
@@ -66,6 +268,26 @@ pub trait ShaderHandle {
     fn blend_mode(&self) -> BlendMode;
     fn layout(&self) -> &[Arc<DescriptorSetLayout>];
     fn pipeline(&self) -> Arc<GraphicsPipeline>;
+    /// The pipeline for `mode`, compiling it first if the shader can and it hasn't been built yet.
+    fn pipeline_for(&self, mode: BlendMode) -> Option<Arc<GraphicsPipeline>>;
+    /// The pipeline for `mode` rasterized as `polygon`, compiling it on first use. `None` if it
+    /// can't be built, e.g. without the device's `fill_mode_non_solid` feature.
+    fn pipeline_for_polygon(&self, mode: BlendMode, polygon: PolygonMode) -> Option<Arc<GraphicsPipeline>> {
+        match polygon {
+            PolygonMode::Fill => self.pipeline_for(mode),
+            _ => None,
+        }
+    }
+    /// Builds the pipeline for `mode` ahead of time, returns `true` if it had to be compiled.
+    fn warm_up(&self, mode: BlendMode) -> Result<bool>;
+    fn stats(&self) -> PipelineStats;
+    /// Blend modes whose pipelines are already compiled, in no particular order.
+    fn cached_modes(&self) -> Vec<BlendMode>;
+    /// The set per-draw resources are bound to, see [`DEFAULT_DESCRIPTOR_SET_INDEX`].
+    fn descriptor_set_index(&self) -> usize;
+    /// Recompiles every pipeline the shader has built against `render_pass`, which replaces
+    /// the one it was built for. Errors if the shader can't compile pipelines itself.
+    fn rebuild(&mut self, render_pass: Arc<RenderPass>) -> Result<()>;
 }
 
 impl ShaderHandle for ShaderProgram {
@@ -76,7 +298,8 @@ impl ShaderHandle for ShaderProgram {
     ) {
         command_buffer.bind_pipeline_graphics(self.pipeline().clone());
 
-        let layout = self.layout()[1].clone();
+        let index = self.descriptor_set_index;
+        let layout = self.layout()[index].clone();
 
         let (buffers, descriptors, v_count, i_count) = pipe_data.flush();
 
@@ -87,7 +310,7 @@ impl ShaderHandle for ShaderProgram {
         command_buffer.bind_descriptor_sets(
             PipelineBindPoint::Graphics,
             self.pipeline().layout().clone(),
-            1,
+            index as u32,
             set,
         );
 
@@ -101,15 +324,106 @@ impl ShaderHandle for ShaderProgram {
     }
 
     fn layout(&self) -> &[Arc<DescriptorSetLayout>] {
-        self.pipelines
-            .get(&self.current_mode)
-            .unwrap()
-            .layout()
-            .set_layouts()
+        self.current.layout().set_layouts()
     }
 
     fn pipeline(&self) -> Arc<GraphicsPipeline> {
-        self.pipelines.get(&self.current_mode).unwrap().clone()
+        self.current.clone()
+    }
+
+    fn pipeline_for(&self, mode: BlendMode) -> Option<Arc<GraphicsPipeline>> {
+        if let Some(pipeline) = self.pipelines.get(&mode) {
+            return Some(pipeline);
+        }
+
+        let pipeline = match self.compile(mode) {
+            Ok(pipeline) => pipeline,
+            Err(e) => {
+                log::error!(target: "ledge::shader", "failed to compile {:?} pipeline: {:?}", mode, e);
+                return None;
+            }
+        };
+        log::info!(target: "ledge::shader", "compiled {:?} pipeline while drawing, consider warming it up", mode);
+        self.runtime_compiles.fetch_add(1, Ordering::Relaxed);
+        Some(pipeline)
+    }
+
+    fn pipeline_for_polygon(&self, mode: BlendMode, polygon: PolygonMode) -> Option<Arc<GraphicsPipeline>> {
+        if polygon == PolygonMode::Fill {
+            return self.pipeline_for(mode);
+        }
+
+        if let Some(pipeline) = self.variants.read().unwrap().get(&(mode, polygon)) {
+            return Some(pipeline.clone());
+        }
+
+        let (factory, render_pass) = self.factory.as_ref().zip(self.render_pass.as_ref())?;
+        if !render_pass.device().enabled_features().fill_mode_non_solid {
+            return None;
+        }
+
+        match factory(mode, polygon, render_pass) {
+            Ok(pipeline) => {
+                log::debug!(target: "ledge::shader", "compiled {:?} {:?} pipeline", mode, polygon);
+                self.variants.write().unwrap().insert((mode, polygon), pipeline.clone());
+                Some(pipeline)
+            }
+            Err(e) => {
+                log::error!(target: "ledge::shader", "failed to compile {:?} {:?} pipeline: {:?}", mode, polygon, e);
+                None
+            }
+        }
+    }
+
+    fn warm_up(&self, mode: BlendMode) -> Result<bool> {
+        if self.pipelines.get(&mode).is_some() {
+            return Ok(false);
+        }
+
+        self.compile(mode)?;
+        log::debug!(target: "ledge::shader", "warmed up {:?} pipeline", mode);
+        self.warm_up_compiles.fetch_add(1, Ordering::Relaxed);
+        Ok(true)
+    }
+
+    fn stats(&self) -> PipelineStats {
+        PipelineStats {
+            warm_up_compiles: self.warm_up_compiles.load(Ordering::Relaxed),
+            runtime_compiles: self.runtime_compiles.load(Ordering::Relaxed),
+        }
+    }
+
+    fn cached_modes(&self) -> Vec<BlendMode> {
+        self.all_modes()
+    }
+
+    fn descriptor_set_index(&self) -> usize {
+        self.descriptor_set_index
+    }
+
+    fn rebuild(&mut self, render_pass: Arc<RenderPass>) -> Result<()> {
+        let factory = self
+            .factory
+            .as_ref()
+            .ok_or_else(|| anyhow!("shader program was built from a fixed pipeline"))?;
+
+        let pipelines = PipelineObjectSet::new(16);
+        let mut modes = self.all_modes();
+        if !modes.contains(&self.current_mode) {
+            modes.push(self.current_mode);
+        }
+        for mode in modes.iter().copied() {
+            pipelines.insert(mode, factory(mode, PolygonMode::Fill, &render_pass)?);
+        }
+
+        self.current = pipelines.get(&self.current_mode).unwrap();
+        self.pipelines = pipelines;
+        self.variants.write().unwrap().clear();
+        self.render_pass = Some(render_pass);
+        self.warm_up_compiles.fetch_add(modes.len() as u32, Ordering::Relaxed);
+        log::debug!(target: "ledge::shader", "rebuilt {} pipelines for a new render pass", modes.len());
+
+        Ok(())
     }
 }
 
@@ -119,61 +433,178 @@ impl ShaderProgram {
         render_pass: Arc<RenderPass>,
         vertex_type: Vd,
         vertex_order: VertexTopology,
-        vertex_shader: EntryPoint,
-        fragment_shader: EntryPoint,
+        vertex_shader: Arc<ShaderModule>,
+        fragment_shader: Arc<ShaderModule>,
         blend: BlendMode,
-    ) -> Self
-    // ) -> Result<Self, GraphicsPipelineCreationError>
+    ) -> Result<Self>
     where
-        Vd: VertexDefinition + 'static + Sync + Send,
+        Vd: VertexDefinition + Clone + 'static + Sync + Send,
     {
-        let po = new_pipeline(
-            device,
-            render_pass,
-            vertex_type,
-            vertex_order,
-            vertex_shader,
-            fragment_shader,
-            blend,
-        );
-
-        let mut pos = PipelineObjectSet::new(16);
-        pos.insert(blend, po);
-
-        Self {
+        let factory: Arc<PipelineFactory> = Arc::new(move |mode, polygon_mode, render_pass: &Arc<RenderPass>| {
+            let vertex_entry = vertex_shader
+                .entry_point(SHADER_ENTRY_POINT)
+                .ok_or_else(|| anyhow!("vertex shader has no `{}` entry point", SHADER_ENTRY_POINT))?;
+            let fragment_entry = fragment_shader
+                .entry_point(SHADER_ENTRY_POINT)
+                .ok_or_else(|| anyhow!("fragment shader has no `{}` entry point", SHADER_ENTRY_POINT))?;
+
+            new_pipeline_with_polygon_mode(
+                device.clone(),
+                render_pass.clone(),
+                vertex_type.clone(),
+                vertex_order,
+                vertex_entry,
+                fragment_entry,
+                mode,
+                polygon_mode,
+            )
+        });
+
+        let po = factory(blend, PolygonMode::Fill, &render_pass)?;
+
+        let pos = PipelineObjectSet::new(16);
+        pos.insert(blend, po.clone());
+
+        Ok(Self {
             pipelines: pos,
+            variants: RwLock::new(HashMap::new()),
+            descriptor_set_index: DEFAULT_DESCRIPTOR_SET_INDEX,
+            current: po,
             current_mode: blend,
-        }
+            factory: Some(factory),
+            render_pass: Some(render_pass),
+            warm_up_compiles: AtomicU32::new(1),
+            runtime_compiles: AtomicU32::new(0),
+        })
     }
 
+    /// Wraps an already built pipeline. The program can't compile other blend modes.
     pub fn from_pipeline(mode: BlendMode, pipeline: Arc<GraphicsPipeline>) -> Self {
-        let mut pipeline_os = PipelineObjectSet::new(16);
-        pipeline_os.insert(mode, pipeline);
+        let pipeline_os = PipelineObjectSet::new(16);
+        pipeline_os.insert(mode, pipeline.clone());
         Self {
             pipelines: pipeline_os,
+            variants: RwLock::new(HashMap::new()),
+            descriptor_set_index: DEFAULT_DESCRIPTOR_SET_INDEX,
+            current: pipeline,
             current_mode: mode,
+            factory: None,
+            render_pass: None,
+            warm_up_compiles: AtomicU32::new(0),
+            runtime_compiles: AtomicU32::new(0),
         }
     }
+
+    /// A new program using the same shaders with `mode` as its initial blend mode.
+    ///
+    /// Only `mode`'s pipeline is carried over, reusing it if this program already built it.
+    /// Other modes still compile lazily, since the shaders and render pass are shared with
+    /// this program. Errors if the program was made with [`ShaderProgram::from_pipeline`]
+    /// and `mode` isn't its pipeline's mode.
+    pub fn clone_with_blend(&self, mode: BlendMode) -> Result<Self> {
+        let (pipeline, compiled) = match self.pipelines.get(&mode) {
+            Some(pipeline) => (pipeline, false),
+            None => {
+                let (factory, render_pass) = self
+                    .factory
+                    .as_ref()
+                    .zip(self.render_pass.as_ref())
+                    .ok_or_else(|| anyhow!("shader program was built from a fixed pipeline"))?;
+                (factory(mode, PolygonMode::Fill, render_pass)?, true)
+            }
+        };
+
+        let pipelines = PipelineObjectSet::new(16);
+        pipelines.insert(mode, pipeline.clone());
+
+        Ok(Self {
+            pipelines,
+            variants: RwLock::new(HashMap::new()),
+            descriptor_set_index: self.descriptor_set_index,
+            current: pipeline,
+            current_mode: mode,
+            factory: self.factory.clone(),
+            render_pass: self.render_pass.clone(),
+            warm_up_compiles: AtomicU32::new(compiled as u32),
+            runtime_compiles: AtomicU32::new(0),
+        })
+    }
+
+    /// Combines the pipelines of both programs, keeping this program's current blend mode.
+    ///
+    /// Where both have a pipeline for the same mode this program's is kept. The programs
+    /// should have been built from the same shaders, this isn't checked.
+    pub fn merge(self, other: ShaderProgram) -> Self {
+        for mode in other.all_modes() {
+            if self.pipelines.get(&mode).is_none() {
+                self.pipelines.insert(mode, other.pipelines.get(&mode).unwrap());
+            }
+        }
+
+        let stats = self.stats() + other.stats();
+        let (factory, render_pass) = match self.factory {
+            Some(factory) => (Some(factory), self.render_pass),
+            None => (other.factory, other.render_pass),
+        };
+
+        Self {
+            factory,
+            render_pass,
+            warm_up_compiles: AtomicU32::new(stats.warm_up_compiles),
+            runtime_compiles: AtomicU32::new(stats.runtime_compiles),
+            ..self
+        }
+    }
+
+    /// Binds per-draw resources to set `index` instead of [`DEFAULT_DESCRIPTOR_SET_INDEX`].
+    ///
+    /// If `index` is the camera's set, [`crate::graphics::camera::CAMERA_UNIFORM_SET`], the
+    /// camera uniform isn't bound for this shader.
+    pub fn set_descriptor_set_index(&mut self, index: usize) {
+        self.descriptor_set_index = index;
+    }
+
+    /// Every blend mode this program currently has a built pipeline for.
+    pub fn all_modes(&self) -> Vec<BlendMode> {
+        self.pipelines.modes()
+    }
+
+    fn compile(&self, mode: BlendMode) -> Result<Arc<GraphicsPipeline>> {
+        let (factory, render_pass) = self
+            .factory
+            .as_ref()
+            .zip(self.render_pass.as_ref())
+            .ok_or_else(|| anyhow!("shader program was built from a fixed pipeline"))?;
+
+        let pipeline = factory(mode, PolygonMode::Fill, render_pass)?;
+        self.pipelines.insert(mode, pipeline.clone());
+        Ok(pipeline)
+    }
 }
 
 // This structure is to store multiple pipelines for different blend modes.
+// Pipelines are compiled lazily while drawing, so the map sits behind a lock.
 pub struct PipelineObjectSet {
-    pipelines: HashMap<BlendMode, Arc<GraphicsPipeline>>,
+    pipelines: RwLock<HashMap<BlendMode, Arc<GraphicsPipeline>>>,
 }
 
 impl PipelineObjectSet {
     pub fn new(cap: usize) -> Self {
         Self {
-            pipelines: HashMap::with_capacity(cap),
+            pipelines: RwLock::new(HashMap::with_capacity(cap)),
         }
     }
 
-    pub fn insert(&mut self, blend_mode: BlendMode, pipeline: Arc<GraphicsPipeline>) {
-        self.pipelines.insert(blend_mode, pipeline);
+    pub fn insert(&self, blend_mode: BlendMode, pipeline: Arc<GraphicsPipeline>) {
+        self.pipelines.write().unwrap().insert(blend_mode, pipeline);
+    }
+
+    pub fn get(&self, blend_mode: &BlendMode) -> Option<Arc<GraphicsPipeline>> {
+        self.pipelines.read().unwrap().get(blend_mode).cloned()
     }
 
-    pub fn get(&self, blend_mode: &BlendMode) -> Option<&Arc<GraphicsPipeline>> {
-        self.pipelines.get(blend_mode)
+    pub fn modes(&self) -> Vec<BlendMode> {
+        self.pipelines.read().unwrap().keys().copied().collect()
     }
 
     // pub fn mode(&self, mode: &BlendMode) -> Result<&GraphicsPipeline, GraphicsError> {
@@ -192,34 +623,77 @@ pub fn new_pipeline<Vd>(
     vertex_shader: EntryPoint,
     fragment_shader: EntryPoint,
     blend: BlendMode,
-// ) -> Result<Arc<GraphicsPipeline>, GraphicsPipelineCreationError>
-) -> Arc<GraphicsPipeline>
+) -> Result<Arc<GraphicsPipeline>>
 where
     Vd: VertexDefinition + 'static + Sync + Send,
 {
-    let mut pipeline = GraphicsPipeline::start()
+    new_pipeline_with_polygon_mode(
+        device,
+        render_pass,
+        vertex_type,
+        vertex_order,
+        vertex_shader,
+        fragment_shader,
+        blend,
+        PolygonMode::Fill,
+    )
+}
+
+/// Like [`new_pipeline`], rasterizing primitives as `polygon_mode`. Anything but
+/// [`PolygonMode::Fill`] needs the device's `fill_mode_non_solid` feature.
+#[allow(clippy::too_many_arguments)]
+pub fn new_pipeline_with_polygon_mode<Vd>(
+    device: Arc<Device>,
+    render_pass: Arc<RenderPass>,
+    vertex_type: Vd,
+    vertex_order: VertexTopology,
+    vertex_shader: EntryPoint,
+    fragment_shader: EntryPoint,
+    blend: BlendMode,
+    polygon_mode: PolygonMode,
+) -> Result<Arc<GraphicsPipeline>>
+where
+    Vd: VertexDefinition + 'static + Sync + Send,
+{
+    let (topology, line_width) = match vertex_order {
+        VertexTopology::LineList { width } => (PrimitiveTopology::LineList, supported_line_width(&device, width)),
+        VertexTopology::LineStrip { width } => (PrimitiveTopology::LineStrip, supported_line_width(&device, width)),
+        VertexTopology::PointList => (PrimitiveTopology::PointList, 1.0),
+        VertexTopology::TriangleFan => (PrimitiveTopology::TriangleFan, 1.0),
+        VertexTopology::TriangleList => (PrimitiveTopology::TriangleList, 1.0),
+        VertexTopology::TriangleStrip => (PrimitiveTopology::TriangleStrip, 1.0),
+    };
+
+    let subpass = Subpass::from(render_pass, 0).ok_or_else(|| anyhow!("render pass has no subpass 0"))?;
+
+    Ok(GraphicsPipeline::start()
         .vertex_input_state::<Vd>(vertex_type)
         .vertex_shader(vertex_shader, ())
-        .viewport_state(ViewportState::viewport_dynamic_scissor_irrelevant())
+        .input_assembly_state(InputAssemblyState::new().topology(topology))
+        .rasterization_state(RasterizationState {
+            polygon_mode: polygon_mode.into(),
+            line_width: StateMode::Fixed(line_width),
+            ..RasterizationState::new()
+        })
+        .viewport_state(ViewportState::viewport_dynamic_scissor_dynamic(1))
         .fragment_shader(fragment_shader, ())
         .color_blend_state(blend.into())
-        .render_pass(Subpass::from(render_pass.clone(), 0).unwrap());
-
-    pipeline = match vertex_order {
-        VertexTopology::PointList => pipeline
-            .input_assembly_state(InputAssemblyState::new().topology(PrimitiveTopology::PointList)),
-        VertexTopology::TriangleFan => pipeline.input_assembly_state(
-            InputAssemblyState::new().topology(PrimitiveTopology::TriangleFan),
-        ),
-        VertexTopology::TriangleList => pipeline.input_assembly_state(
-            InputAssemblyState::new().topology(PrimitiveTopology::TriangleList),
-        ),
-        VertexTopology::TriangleStrip => pipeline.input_assembly_state(
-            InputAssemblyState::new().topology(PrimitiveTopology::TriangleStrip),
-        ),
-    };
+        .render_pass(subpass)
+        .build(device.clone())?)
+}
 
-    pipeline.build(device.clone()).unwrap()
+/// The closest line width to `width` the device can draw.
+///
+/// Widths other than 1.0 need the `wide_lines` feature, which the renderer enables when the
+/// device has it. Without it this is always 1.0, otherwise `width` clamped to the device's
+/// supported range.
+pub fn supported_line_width(device: &Arc<Device>, width: f32) -> f32 {
+    if !device.enabled_features().wide_lines {
+        return 1.0;
+    }
+
+    let [min, max] = device.physical_device().properties().line_width_range;
+    width.max(min).min(max)
 }
 
 impl From<BlendMode> for ColorBlendState {
@@ -252,19 +726,297 @@ impl From<BlendMode> for ColorBlendState {
             BlendMode::Alpha => {
                 attach = Some(AttachmentBlend::alpha());
             }
+            BlendMode::PremultipliedAlpha => {
+                attach = Some(AttachmentBlend {
+                    color_op: BlendOp::Add,
+                    color_source: BlendFactor::One,
+                    color_destination: BlendFactor::OneMinusSrcAlpha,
+                    alpha_op: BlendOp::Add,
+                    alpha_source: BlendFactor::One,
+                    alpha_destination: BlendFactor::OneMinusSrcAlpha,
+                });
+            }
             BlendMode::Invert => {
                 logic_op = Some(StateMode::Fixed(LogicOp::Invert));
             }
         };
 
-        return ColorBlendState {
-            logic_op: logic_op,
+        ColorBlendState {
+            logic_op,
             attachments: vec![ColorBlendAttachmentState {
                 blend: attach,
                 color_write_mask: ColorComponents::all(),
                 color_write_enable: StateMode::Fixed(true),
             }],
             blend_constants: StateMode::Fixed(blend_constants),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graphics::{test_device, InstanceData, Vertex};
+    use vulkano::pipeline::graphics::vertex_input::BuffersDefinition;
+
+    fn program(device: Arc<Device>, blend: BlendMode) -> ShaderProgram {
+        let shader = Shader {
+            vertex: crate::graphics::vs::load(device.clone()).unwrap(),
+            fragment: crate::graphics::fs::load(device.clone()).unwrap(),
+            topology: VertexTopology::TriangleStrip,
         };
+        program_of(device, &shader, blend)
+    }
+
+    /// A program running `shader` on a single [`Vertex`] and [`InstanceData`] pass.
+    fn program_of(device: Arc<Device>, shader: &Shader, blend: BlendMode) -> ShaderProgram {
+        let render_pass = vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: vulkano::format::Format::B8G8R8A8_SRGB,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+        .unwrap();
+
+        ShaderProgram::new(
+            device.clone(),
+            render_pass,
+            BuffersDefinition::new().vertex::<Vertex>().instance::<InstanceData>(),
+            shader.topology,
+            shader.vertex.clone(),
+            shader.fragment.clone(),
+            blend,
+        )
+        .unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn cloned_blend_variants_merge_back_together() {
+        let (queue, _) = test_device();
+        let alpha = program(queue.device().clone(), BlendMode::Alpha);
+
+        let add = alpha.clone_with_blend(BlendMode::Add).unwrap();
+        assert_eq!(add.all_modes(), vec![BlendMode::Add]);
+
+        let merged = alpha.merge(add);
+        let modes = merged.all_modes();
+        assert_eq!(modes.len(), 2);
+        assert!(modes.contains(&BlendMode::Alpha) && modes.contains(&BlendMode::Add));
+    }
+
+    /// Whether any stage of `shader` reads a texture or sampler.
+    fn samples_textures(shader: &Shader) -> bool {
+        use vulkano::descriptor_set::layout::DescriptorType;
+
+        [&shader.vertex, &shader.fragment].iter().any(|module| {
+            module
+                .entry_point(SHADER_ENTRY_POINT)
+                .unwrap()
+                .descriptor_requirements()
+                .flat_map(|(_, requirements)| requirements.descriptor_types.iter())
+                .any(|ty| matches!(ty, DescriptorType::Sampler | DescriptorType::CombinedImageSampler | DescriptorType::SampledImage))
+        })
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn colored_shaders_bind_no_sampler() {
+        let (queue, _) = test_device();
+        let device = queue.device().clone();
+
+        assert!(!samples_textures(&Shader::colored(device.clone()).unwrap()));
+        assert!(samples_textures(&Shader::lit_sprite(device).unwrap()));
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn lit_programs_bind_the_texture_and_normal_map() {
+        use vulkano::descriptor_set::layout::DescriptorType;
+
+        let (queue, _) = test_device();
+        let device = queue.device().clone();
+        let program = program_of(device.clone(), &Shader::lit_sprite(device).unwrap(), BlendMode::Alpha);
+
+        let bindings = program.layout()[DEFAULT_DESCRIPTOR_SET_INDEX].bindings();
+        let samplers = bindings
+            .iter()
+            .filter(|(_, binding)| binding.descriptor_type == DescriptorType::CombinedImageSampler)
+            .map(|(index, _)| *index)
+            .collect::<Vec<_>>();
+        assert_eq!(samplers, vec![0, 1]);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn many_per_draw_sets_allocate_in_one_frame() {
+        let (queue, sampler) = test_device();
+        let program = program(queue.device().clone(), BlendMode::Alpha);
+        let image = crate::graphics::image::Image::placeholder(queue, sampler);
+        let layout = program.layout()[DEFAULT_DESCRIPTOR_SET_INDEX].clone();
+
+        // All held at once, like the sets of a frame's draws until it's finished.
+        let sets = (0..10_000)
+            .map(|_| {
+                PersistentDescriptorSet::new(
+                    layout.clone(),
+                    [WriteDescriptorSet::image_view_sampler(0, image.view(), image.sampler().clone())],
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap();
+        assert_eq!(sets.len(), 10_000);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn wide_line_pipelines_build_where_supported() {
+        let (queue, _) = test_device();
+        let device = queue.device().clone();
+        let render_pass = vulkano::single_pass_renderpass!(device.clone(),
+            attachments: {
+                color: {
+                    load: Clear,
+                    store: Store,
+                    format: vulkano::format::Format::B8G8R8A8_SRGB,
+                    samples: 1,
+                }
+            },
+            pass: {
+                color: [color],
+                depth_stencil: {}
+            }
+        )
+        .unwrap();
+
+        let pipeline = new_pipeline(
+            device.clone(),
+            render_pass,
+            BuffersDefinition::new().vertex::<Vertex>().instance::<InstanceData>(),
+            VertexTopology::LineList { width: 3.0 },
+            crate::graphics::vs::load(device.clone()).unwrap().entry_point(SHADER_ENTRY_POINT).unwrap(),
+            crate::graphics::fs::load(device.clone()).unwrap().entry_point(SHADER_ENTRY_POINT).unwrap(),
+            BlendMode::Alpha,
+        )
+        .unwrap();
+
+        let [min, max] = device.physical_device().properties().line_width_range;
+        let expected = if device.enabled_features().wide_lines { 3.0f32.max(min).min(max) } else { 1.0 };
+        assert_eq!(supported_line_width(&device, 3.0), expected);
+        assert_eq!(pipeline.rasterization_state().line_width, StateMode::Fixed(expected));
+    }
+
+    mod set_zero_vs {
+        vulkano_shaders::shader! {
+            ty: "vertex",
+            src: "
+                #version 450
+                layout(location = 0) in vec3 pos;
+                layout(location = 1) in vec2 uv;
+                layout(location = 0) out vec2 v_uv;
+
+                // Covers the whole target, so no camera is needed.
+                void main() {
+                    v_uv = uv;
+                    gl_Position = vec4(pos.xy * 2.0 - 1.0, 0.0, 1.0);
+                }
+            "
+        }
+    }
+
+    mod set_zero_fs {
+        vulkano_shaders::shader! {
+            ty: "fragment",
+            src: "
+                #version 450
+                layout(location = 0) in vec2 v_uv;
+                layout(location = 0) out vec4 f_color;
+                layout(set = 0, binding = 0) uniform sampler2D tex;
+
+                void main() {
+                    f_color = texture(tex, v_uv);
+                }
+            "
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn textures_bind_to_set_zero_when_the_shader_asks() {
+        use crate::graphics::image::Image;
+        use crate::graphics::{pixel_at, render_offscreen, test_pass, Color, DrawInfo};
+
+        let (queue, sampler) = test_device();
+        let device = queue.device().clone();
+        let mut pass = test_pass(queue.clone());
+
+        let mut program = ShaderProgram::new(
+            device.clone(),
+            pass.subpass(0).unwrap().render_pass().clone(),
+            BuffersDefinition::new().vertex::<Vertex>().instance::<InstanceData>(),
+            VertexTopology::TriangleStrip,
+            set_zero_vs::load(device.clone()).unwrap(),
+            set_zero_fs::load(device).unwrap(),
+            BlendMode::Alpha,
+        )
+        .unwrap();
+        program.set_descriptor_set_index(0);
+        assert_eq!(program.descriptor_set_index(), 0);
+        let shader = pass.register_program(program);
+
+        let red = Arc::new(Image::from_color(queue.clone(), sampler, Color::red()));
+        let pixels = render_offscreen(&queue, &mut pass, [8, 8], Color::black(), |pass| {
+            pass.draw_with(red, shader, DrawInfo::default())
+        });
+
+        assert_eq!(pixel_at(&pixels, 8, (0, 0)), [255, 0, 0, 255]);
+        assert_eq!(pixel_at(&pixels, 8, (7, 7)), [255, 0, 0, 255]);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn premultiplied_gradients_blend_like_straight_ones() {
+        use crate::graphics::image::Image;
+        use crate::graphics::{pixel_at, render_offscreen, test_pass, Color, DrawInfo};
+
+        let (queue, sampler) = test_device();
+        let white = Arc::new(Image::from_color(queue.clone(), sampler, Color::white()));
+        let background = Color::rgba(0, 0, 255, 255);
+
+        // Red fading in from transparent at the left edge to opaque at the right.
+        let draw_gradient = |mode: BlendMode| {
+            let mut info = DrawInfo::centered((8.0, 0.5), (16.0, 1.0), 0.0, Color::white());
+            let (clear, red) = (Color::rgba(255, 0, 0, 0), Color::red());
+            info.set_corner_colors([clear, red, clear, red]);
+            info.set_premultiply(mode == BlendMode::PremultipliedAlpha);
+
+            let mut pass = test_pass(queue.clone());
+            render_offscreen(&queue, &mut pass, [16, 1], background, |pass| {
+                pass.set_blend_mode(mode);
+                pass.draw(white.clone(), info)
+            })
+        };
+        let straight = draw_gradient(BlendMode::Alpha);
+        let premultiplied = draw_gradient(BlendMode::PremultipliedAlpha);
+
+        for x in 0..16 {
+            let alpha = (x as f32 + 0.5) / 16.0;
+            // Only the colors, straight alpha blends the alpha channel by alpha as well.
+            let expected = [255.0 * alpha, 0.0, 255.0 * (1.0 - alpha)];
+            for pixels in [&straight, &premultiplied] {
+                let pixel = pixel_at(pixels, 16, (x, 0));
+                for (channel, expected) in pixel.iter().zip(expected) {
+                    assert!((*channel as f32 - expected).abs() <= 2.0, "column {}: {:?} != {:?}", x, pixel, expected);
+                }
+            }
+        }
     }
 }
@@ -0,0 +1,24 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(binding = 0, set = 0) uniform sampler2D t_input;
+// An N by N by N cube indexed by red, green, and blue.
+layout(binding = 1, set = 0) uniform sampler3D t_lut;
+
+layout(push_constant) uniform Params {
+    float intensity;
+} params;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    vec4 color = texture(t_input, v_uv);
+    float size = float(textureSize(t_lut, 0).x);
+
+    // Sample texel centers so 0 and 1 land on the first and last entries, not their edges.
+    vec3 coord = clamp(color.rgb, 0.0, 1.0) * ((size - 1.0) / size) + 0.5 / size;
+    vec3 graded = texture(t_lut, coord).rgb;
+
+    f_color = vec4(mix(color.rgb, graded, params.intensity), color.a);
+}
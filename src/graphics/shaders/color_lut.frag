@@ -0,0 +1,37 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(binding = 0, set = 0) uniform sampler2D t_input;
+// An N*N by N strip of N blue slices, red increasing across each slice and green down it.
+layout(binding = 1, set = 0) uniform sampler2D t_lut;
+
+layout(push_constant) uniform Params {
+    float intensity;
+} params;
+
+layout(location = 0) out vec4 f_color;
+
+vec3 lookup(vec3 color, float size, float slice) {
+    vec2 uv = vec2(
+        (slice * size + color.r * (size - 1.0) + 0.5) / (size * size),
+        (color.g * (size - 1.0) + 0.5) / size
+    );
+    return texture(t_lut, uv).rgb;
+}
+
+void main() {
+    vec4 color = texture(t_input, v_uv);
+    vec3 c = clamp(color.rgb, 0.0, 1.0);
+    float size = float(textureSize(t_lut, 0).y);
+
+    float blue = c.b * (size - 1.0);
+    float slice = floor(blue);
+    vec3 graded = mix(
+        lookup(c, size, slice),
+        lookup(c, size, min(slice + 1.0, size - 1.0)),
+        blue - slice
+    );
+
+    f_color = vec4(mix(color.rgb, graded, params.intensity), color.a);
+}
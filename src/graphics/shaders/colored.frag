@@ -0,0 +1,15 @@
+#version 450
+
+// Untextured shapes: the vertex color times the instance color, with no sampler bound.
+
+layout(location=1) in vec4 v_color;
+layout(location=2) flat in uint v_flags; // Bit 1 set: premultiply by alpha.
+
+layout(location=0) out vec4 f_color;
+
+void main() {
+    f_color = v_color;
+    if ((v_flags & 2u) != 0u) {
+        f_color.rgb *= f_color.a;
+    }
+}
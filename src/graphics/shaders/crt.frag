@@ -0,0 +1,28 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(binding = 0, set = 0) uniform sampler2D t_input;
+
+layout(push_constant) uniform Params {
+    float curvature;
+    float scanline_intensity;
+} params;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    vec2 centered = v_uv * 2.0 - 1.0;
+    centered += centered * (centered.yx * centered.yx) * params.curvature;
+    vec2 uv = centered * 0.5 + 0.5;
+
+    if (uv.x < 0.0 || uv.x > 1.0 || uv.y < 0.0 || uv.y > 1.0) {
+        f_color = vec4(0.0, 0.0, 0.0, 1.0);
+        return;
+    }
+
+    vec4 color = texture(t_input, uv);
+    float rows = float(textureSize(t_input, 0).y);
+    float scanline = 0.5 + 0.5 * cos(uv.y * rows * 3.14159265);
+    f_color = vec4(color.rgb * (1.0 - params.scanline_intensity * scanline), color.a);
+}
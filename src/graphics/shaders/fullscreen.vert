@@ -0,0 +1,11 @@
+#version 450
+
+// A single triangle covering the whole viewport, drawn with 3 vertices and no vertex buffer.
+
+layout(location = 0) out vec2 v_uv;
+
+void main() {
+    vec2 pos = vec2(float((gl_VertexIndex << 1) & 2), float(gl_VertexIndex & 2));
+    v_uv = pos;
+    gl_Position = vec4(pos * 2.0 - 1.0, 0.0, 1.0);
+}
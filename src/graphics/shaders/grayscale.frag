@@ -0,0 +1,17 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(binding = 0, set = 0) uniform sampler2D t_input;
+
+layout(push_constant) uniform Params {
+    float amount;
+} params;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    vec4 color = texture(t_input, v_uv);
+    float luma = dot(color.rgb, vec3(0.2126, 0.7152, 0.0722));
+    f_color = vec4(mix(color.rgb, vec3(luma), params.amount), color.a);
+}
@@ -0,0 +1,11 @@
+#version 450
+
+layout(location = 0) in vec2 v_local;
+layout(location = 1) in vec4 v_color;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    float falloff = clamp(1.0 - length(v_local), 0.0, 1.0);
+    f_color = vec4(v_color.rgb * falloff * falloff, 1.0);
+}
@@ -0,0 +1,21 @@
+#version 450
+
+// A quad around each light instance, drawn as a 4 vertex triangle strip with no vertex buffer.
+
+layout(location = 0) in vec2 center;
+layout(location = 1) in float radius;
+layout(location = 2) in vec4 color; // Premultiplied by the light's intensity.
+
+layout(push_constant) uniform Camera {
+    mat4 view_proj;
+} camera;
+
+layout(location = 0) out vec2 v_local;
+layout(location = 1) out vec4 v_color;
+
+void main() {
+    vec2 local = vec2(float(gl_VertexIndex & 2), float((gl_VertexIndex << 1) & 2)) - 1.0;
+    v_local = local;
+    v_color = color;
+    gl_Position = camera.view_proj * vec4(center + local * radius, 0.0, 1.0);
+}
@@ -0,0 +1,12 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(binding = 0, set = 0) uniform sampler2D t_light;
+
+layout(location = 0) out vec4 f_color;
+
+// Blended as source * destination, so the scene is multiplied by the light map.
+void main() {
+    f_color = vec4(texture(t_light, v_uv).rgb, 1.0);
+}
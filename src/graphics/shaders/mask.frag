@@ -0,0 +1,18 @@
+#version 450
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec4 v_color;
+
+layout(binding=0,set=1) uniform sampler2D t_tex;
+
+layout(push_constant) uniform MaskParams {
+    uint inverted;
+} params;
+
+layout(location=0) out vec4 f_mask;
+
+// Writes the sprite's coverage into the red channel of the mask.
+void main() {
+    float coverage = texture(t_tex, v_uv).a * v_color.a;
+    f_mask = vec4(params.inverted != 0u ? 1.0 - coverage : coverage);
+}
@@ -0,0 +1,60 @@
+#version 450
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec4 v_color;
+layout(location=2) flat in uint v_flags;
+layout(location=3) in vec2 v_local;
+layout(location=5) flat in float v_radius;
+
+layout(binding=0,set=1) uniform sampler2D t_tex;
+layout(binding=1,set=1) uniform sampler2D t_mask; // Covers the whole render target.
+
+layout(location=0) out vec4 f_color;
+
+// Signed distance from p to a rectangle of half extents b centered on the origin, with corners
+// rounded by r. Negative inside.
+float rounded_rect(vec2 p, vec2 b, float r) {
+    vec2 q = abs(p) - b + r;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - r;
+}
+
+void main() {
+    // Bit 2 set: the texture's colors are premultiplied, so the tint's and any coverage's
+    // alpha scale the color as well.
+    bool premultiplied = (v_flags & 4u) != 0u;
+    vec4 tint = v_color;
+    if (premultiplied) {
+        tint.rgb *= tint.a;
+    }
+
+    if ((v_flags & 1u) != 0u) {
+        f_color = tint;
+    } else {
+        f_color = texture(t_tex, v_uv) * tint;
+    }
+
+    if (v_radius > 0.0) {
+        // The quad's size in pixels, from how far across it each pixel steps.
+        vec2 step = vec2(length(vec2(dFdx(v_local.x), dFdy(v_local.x))), length(vec2(dFdx(v_local.y), dFdy(v_local.y))));
+        vec2 size = 1.0 / max(step, 1e-6);
+        float d = rounded_rect((v_local - 0.5) * size, size * 0.5, min(v_radius, min(size.x, size.y) * 0.5));
+        float coverage = clamp(0.5 - d, 0.0, 1.0);
+        if (coverage <= 0.0) {
+            discard;
+        }
+        f_color.a *= coverage;
+        if (premultiplied) {
+            f_color.rgb *= coverage;
+        }
+    }
+
+    float mask = texture(t_mask, gl_FragCoord.xy / vec2(textureSize(t_mask, 0))).r;
+    f_color.a *= mask;
+    if (premultiplied) {
+        f_color.rgb *= mask;
+    }
+
+    if ((v_flags & 2u) != 0u && !premultiplied) {
+        f_color.rgb *= f_color.a;
+    }
+}
@@ -0,0 +1,18 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(binding = 0, set = 0) uniform sampler2D t_input;
+
+layout(push_constant) uniform Params {
+    float block_size; // In pixels of the input.
+} params;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    vec2 size = vec2(textureSize(t_input, 0));
+    vec2 block = vec2(max(params.block_size, 1.0)) / size;
+    vec2 uv = (floor(v_uv / block) + 0.5) * block;
+    f_color = texture(t_input, uv);
+}
@@ -0,0 +1,6 @@
+#version 450
+
+// Color writes are masked off, shadows only mark the stencil.
+
+void main() {
+}
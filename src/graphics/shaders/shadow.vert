@@ -0,0 +1,13 @@
+#version 450
+
+// Shadow geometry in world units, only written to the light map's stencil.
+
+layout(location = 0) in vec3 pos;
+
+layout(push_constant) uniform Camera {
+    mat4 view_proj;
+} camera;
+
+void main() {
+    gl_Position = camera.view_proj * vec4(pos, 1.0);
+}
@@ -7,6 +7,9 @@ layout(location = 2) in vec4 vert_color; // Color value.
 layout(location = 3) in vec4 src; // Chooses the texture to use in the texture array.
 layout(location = 4) in vec4 color;
 layout(location = 5) in mat4 transform;
+layout(location = 9) in uint flags; // Bit 0 set: the color replaces the texture. Bit 1 set: premultiply by alpha.
+layout(location = 10) in float corner_radius; // In pixels.
+layout(location = 11) in uvec4 corner_colors; // RGBA8 tints of the quad's (0, 0), (1, 0), (0, 1), (1, 1) corners.
 
 layout(binding=0,set=0) uniform mvp {
     mat4 mvp;
@@ -15,10 +18,20 @@ layout(binding=0,set=0) uniform mvp {
 
 layout(location = 0) out vec2 v_uv;
 layout(location = 1) out vec4 v_color;
+layout(location = 2) flat out uint v_flags;
+layout(location = 3) out vec2 v_local; // Position within the quad, (0, 0) to (1, 1).
+layout(location = 5) flat out float v_radius;
+layout(location = 6) flat out vec2 v_axis; // Direction of the quad's x axis, for turning normals.
 
 void main() {
     v_uv = vec2(uv * src.zw + src.xy);
-    v_color = vert_color * color;
+    vec4 top = mix(unpackUnorm4x8(corner_colors.x), unpackUnorm4x8(corner_colors.y), pos.x);
+    vec4 bottom = mix(unpackUnorm4x8(corner_colors.z), unpackUnorm4x8(corner_colors.w), pos.x);
+    v_color = vert_color * color * mix(top, bottom, pos.y);
+    v_flags = flags;
+    v_local = pos.xy;
+    v_radius = corner_radius;
+    v_axis = transform[0].xy / max(length(transform[0].xy), 1e-6);
     vec4 position = transform * vec4(pos, 1.0);
     // gl_Position = camera.model * camera.view * camera.projection * position;
     gl_Position = camera.mvp * position;
@@ -0,0 +1,64 @@
+#version 450
+
+layout(location=0) in vec2 v_uv;
+layout(location=1) in vec4 v_color;
+layout(location=2) flat in uint v_flags;
+layout(location=3) in vec2 v_local;
+layout(location=5) flat in float v_radius;
+layout(location=6) flat in vec2 v_axis;
+
+layout(binding=0,set=1) uniform sampler2D t_tex;
+layout(binding=1,set=1) uniform sampler2D t_normal;
+layout(binding=2,set=1) uniform light {
+    vec4 direction; // Towards the light, xyz.
+    vec4 color;
+    vec4 ambient;
+} u_light;
+
+layout(location=0) out vec4 f_color;
+
+float rounded_rect(vec2 p, vec2 b, float r) {
+    vec2 q = abs(p) - b + r;
+    return length(max(q, 0.0)) + min(max(q.x, q.y), 0.0) - r;
+}
+
+void main() {
+    // Bit 2 set: the texture's colors are premultiplied, so the tint's and any coverage's
+    // alpha scale the color as well.
+    bool premultiplied = (v_flags & 4u) != 0u;
+    vec4 tint = v_color;
+    if (premultiplied) {
+        tint.rgb *= tint.a;
+    }
+
+    if ((v_flags & 1u) != 0u) {
+        f_color = tint;
+    } else {
+        f_color = texture(t_tex, v_uv) * tint;
+    }
+
+    // The normal map is in the sprite's own space, turn it with the sprite.
+    vec3 n = texture(t_normal, v_uv).xyz * 2.0 - 1.0;
+    n.xy = vec2(v_axis.x * n.x - v_axis.y * n.y, v_axis.y * n.x + v_axis.x * n.y);
+    float diffuse = max(dot(normalize(n), normalize(u_light.direction.xyz)), 0.0);
+    f_color.rgb *= u_light.ambient.rgb + u_light.color.rgb * diffuse;
+
+    if (v_radius > 0.0) {
+        // The quad's size in pixels, from how far across it each pixel steps.
+        vec2 step = vec2(length(vec2(dFdx(v_local.x), dFdy(v_local.x))), length(vec2(dFdx(v_local.y), dFdy(v_local.y))));
+        vec2 size = 1.0 / max(step, 1e-6);
+        float d = rounded_rect((v_local - 0.5) * size, size * 0.5, min(v_radius, min(size.x, size.y) * 0.5));
+        float coverage = clamp(0.5 - d, 0.0, 1.0);
+        if (coverage <= 0.0) {
+            discard;
+        }
+        f_color.a *= coverage;
+        if (premultiplied) {
+            f_color.rgb *= coverage;
+        }
+    }
+
+    if ((v_flags & 2u) != 0u && !premultiplied) {
+        f_color.rgb *= f_color.a;
+    }
+}
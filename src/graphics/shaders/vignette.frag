@@ -0,0 +1,19 @@
+#version 450
+
+layout(location = 0) in vec2 v_uv;
+
+layout(binding = 0, set = 0) uniform sampler2D t_input;
+
+layout(push_constant) uniform Params {
+    float radius;
+    float softness;
+} params;
+
+layout(location = 0) out vec4 f_color;
+
+void main() {
+    vec4 color = texture(t_input, v_uv);
+    float dist = distance(v_uv, vec2(0.5));
+    float shade = smoothstep(params.radius, params.radius - max(params.softness, 1e-4), dist);
+    f_color = vec4(color.rgb * shade, color.a);
+}
@@ -1,45 +1,1275 @@
+use crate::graphics::atlas::AtlasFrame;
+use crate::graphics::render_pass::frame::Pass;
+use crate::graphics::shader::ShaderId;
 use crate::graphics::*;
+use vulkano::buffer::cpu_access::WriteLock;
+use vulkano::buffer::TypedBufferAccess;
+use anyhow::anyhow;
+use cgmath::{Matrix4, SquareMatrix, Vector2, Vector4};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::Mutex;
+use std::{fmt, path};
+
+/// Number of instance buffers a batch cycles through when written via
+/// [`SpriteBatch::map_instances`] or rebuilt for a draw, one per frame that may be in flight.
+pub const INSTANCE_SLOTS: usize = 3;
+
+/// Magic number at the start of [`SpriteBatch::serialize_to_binary`] output.
+pub const BATCH_MAGIC: [u8; 4] = *b"LDGB";
+/// Version of the binary batch format written by this build.
+///
+/// Only this version loads, since each one changed the layout of [`InstanceData`]:
+///
+/// - 1: `src`, `color`, and `transform`.
+/// - 2: adds `flags`, `corner_radius`, and `corner_colors`. Some builds before it wrote
+///   version 1 files with `flags` and `corner_radius` already added, those are rejected too.
+pub const BATCH_VERSION: u32 = 2;
+const BATCH_HEADER_LEN: usize = 12;
+
+/// Default maximum number of instances drawn by a single draw call of a [`SpriteBatch`].
+pub const DEFAULT_CHUNK_SIZE: u32 = 65536;
+
+/// An instance buffer and the memory it's counted against.
+type InstanceBuffer = (Arc<CpuAccessibleBuffer<[InstanceData]>>, memory::TrackedAllocation);
+
+/// Why [`SpriteBatch::merge`] refused to combine two batches.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MergeError {
+    /// The batches draw from different images.
+    TextureMismatch,
+}
+
+impl fmt::Display for MergeError {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        match self {
+            MergeError::TextureMismatch => write!(f, "sprite batches use different textures"),
+        }
+    }
+}
+
+impl std::error::Error for MergeError {}
+
+/// The directional light a [`SpriteBatch`] with a normal map is lit by.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct SpriteLight {
+    /// Points from the sprites towards the light, +z facing out of the screen. Doesn't need
+    /// to be normalized.
+    pub direction: (f32, f32, f32),
+    pub color: Color,
+    /// Light every pixel receives whichever way it faces.
+    pub ambient: Color,
+}
+
+impl Default for SpriteLight {
+    fn default() -> Self {
+        Self {
+            direction: (0.0, 0.0, 1.0),
+            color: Color::white(),
+            ambient: Color::black(),
+        }
+    }
+}
+
+/// [`SpriteLight`] as laid out in the lit shader's uniform block.
+#[repr(C)]
+#[derive(Clone, Copy, Debug, Default, Zeroable, Pod)]
+struct LightUniform {
+    direction: [f32; 4],
+    color: [f32; 4],
+    ambient: [f32; 4],
+}
+
+impl From<SpriteLight> for LightUniform {
+    fn from(light: SpriteLight) -> Self {
+        let (x, y, z) = light.direction;
+        Self {
+            direction: [x, y, z, 0.0],
+            color: light.color.into(),
+            ambient: light.ambient.into(),
+        }
+    }
+}
 
 pub struct SpriteBatch {
     image: image::Image,
+    normal_map: Option<image::Image>,
+    light: SpriteLight,
     sprites: Vec<InstanceData>,
+    depths: Vec<f32>,
+    auto_sort: bool,
     // blend_mode: Option<BlendMode>,
+    mapped: Vec<Arc<CpuAccessibleBuffer<[InstanceData]>>>,
+    mapped_memory: Option<memory::TrackedAllocation>,
+    /// Number of valid instances at the start of each mapped buffer.
+    mapped_len: usize,
+    slot: usize,
+    /// Up to [`INSTANCE_SLOTS`] instance buffers drawn from when the batch isn't mapped, kept
+    /// across frames and rewritten in place, see [`SpriteBatch::rebuild_if_dirty`].
+    instance_buffers: Mutex<Vec<InstanceBuffer>>,
+    /// Index of the instance buffer holding the latest instances.
+    instance_slot: AtomicUsize,
+    /// Whether the buffer at `instance_slot` holds the current instances.
+    instances_uploaded: AtomicBool,
+    dirty: bool,
+    half_texel_inset: bool,
+    /// Cached [`Bounded::bounds`], cleared whenever the instances change.
+    bounds: Mutex<Option<Option<Rect>>>,
+    chunk_size: u32,
 }
 
 impl SpriteBatch {
     pub fn new(image: image::Image) -> Self {
         Self {
             image,
+            normal_map: None,
+            light: SpriteLight::default(),
             sprites: Vec::new(),
+            depths: Vec::new(),
+            auto_sort: false,
             // blend_mode: None,
+            mapped: Vec::new(),
+            mapped_memory: None,
+            mapped_len: 0,
+            slot: 0,
+            instance_buffers: Mutex::new(Vec::new()),
+            instance_slot: AtomicUsize::new(0),
+            instances_uploaded: AtomicBool::new(false),
+            dirty: true,
+            half_texel_inset: false,
+            bounds: Mutex::new(None),
+            chunk_size: DEFAULT_CHUNK_SIZE,
         }
     }
 
-    pub fn insert(&mut self, info: DrawInfo) -> usize {
-        self.sprites.push(info.into());
+    /// Lights the batch with `image` as a tangent space normal map, sampled with the same uvs
+    /// as the batch's texture.
+    ///
+    /// The batch must then be drawn with a shader that takes the normal map and light, such as
+    /// [`shader::Shader::lit_sprite`]. Normals turn with each sprite's rotation.
+    pub fn set_normal_map(&mut self, image: image::Image) {
+        self.normal_map = Some(image);
+    }
+
+    /// Goes back to drawing the batch unlit, with the built-in unlit shaders or your own.
+    pub fn clear_normal_map(&mut self) {
+        self.normal_map = None;
+    }
+
+    pub fn normal_map(&self) -> Option<&image::Image> {
+        self.normal_map.as_ref()
+    }
+
+    /// Sets the light used while the batch has a normal map.
+    pub fn set_light(&mut self, light: SpriteLight) {
+        self.light = light;
+    }
+
+    pub fn light(&self) -> SpriteLight {
+        self.light
+    }
+
+    /// The normal map and light writes for the lit shader's per-draw set, empty when unlit.
+    fn lighting_writes(&self, queue: &Arc<Queue>) -> Result<Vec<WriteDescriptorSet>> {
+        let normal_map = match &self.normal_map {
+            Some(normal_map) => normal_map,
+            None => return Ok(Vec::new()),
+        };
+
+        let light = CpuAccessibleBuffer::from_data(
+            queue.device().clone(),
+            BufferUsage::uniform_buffer(),
+            false,
+            LightUniform::from(self.light),
+        )?;
+
+        Ok(vec![
+            WriteDescriptorSet::image_view_sampler(1, normal_map.view(), normal_map.sampler().clone()),
+            WriteDescriptorSet::buffer(2, light),
+        ])
+    }
+
+    /// Insets the uvs of instances inserted from now on by half a texel of the batch's image,
+    /// so linear filtering at non-integer scales doesn't pull in texels from around each
+    /// sprite's region of a sheet. Off by default, see [`crate::graphics::atlas`] for when it
+    /// helps. Don't combine it with [`Atlas::set_half_texel_inset`](crate::graphics::atlas::Atlas::set_half_texel_inset)
+    /// on the same frames, or they're inset twice.
+    pub fn set_half_texel_inset(&mut self, inset: bool) {
+        self.half_texel_inset = inset;
+    }
+
+    pub fn half_texel_inset(&self) -> bool {
+        self.half_texel_inset
+    }
+
+    pub fn insert(&mut self, mut info: DrawInfo) -> usize {
+        let depth = info.transform.z();
+        if self.half_texel_inset {
+            info.tex_rect = info.tex_rect.inset(
+                0.5 / self.image.width().max(1) as f32,
+                0.5 / self.image.height().max(1) as f32,
+            );
+        }
+
+        if self.auto_sort {
+            let idx = self.depths.partition_point(|d| *d <= depth);
+            self.sprites.insert(idx, info.into());
+            self.depths.insert(idx, depth);
+        } else {
+            self.sprites.push(info.into());
+            self.depths.push(depth);
+        }
+
+        self.mark_dirty();
         self.sprites.len()
     }
 
+    /// Flags the instances for re-upload on the next draw and drops the cached bounds.
+    fn mark_dirty(&mut self) {
+        self.dirty = true;
+        *self.instances_uploaded.get_mut() = false;
+        *self.bounds.get_mut().unwrap() = None;
+    }
+
+    /// Inserts `frame` of the atlas the batch draws from with its pivot on `position`, see
+    /// [`AtlasFrame::draw_info`].
+    pub fn insert_frame(&mut self, frame: &AtlasFrame, position: (f32, f32), scale: f32, rotation: f32, color: Color) -> usize {
+        self.insert(frame.draw_info(position, scale, rotation, color))
+    }
+
+    /// Inserts `frame` stretched over the rectangle `size` pixels across with its top left on
+    /// `position`, see [`AtlasFrame::nine_patch_draws`]. Returns the index of each slice.
+    pub fn insert_nine_patch(&mut self, frame: &AtlasFrame, position: (f32, f32), size: (f32, f32), color: Color) -> Vec<usize> {
+        frame
+            .nine_patch_draws(position, size, color)
+            .into_iter()
+            .map(|info| self.insert(info))
+            .collect()
+    }
+
     pub fn remove(&mut self, idx: usize) {
         self.sprites.remove(idx);
+        self.depths.remove(idx);
+        self.mark_dirty();
     }
 
     pub fn clear(&mut self) {
         self.sprites.clear();
+        self.depths.clear();
+        self.mark_dirty();
+    }
+
+    /// Points the batch at a different texture, for reusing pooled batches across sheets.
+    ///
+    /// Existing instances are cleared, since their uvs were for the old sheet, and so is any
+    /// normal map. The instance list and instance buffers keep their capacity, so refilling the
+    /// batch to a similar size allocates nothing.
+    pub fn set_image(&mut self, image: image::Image) {
+        self.image = image;
+        self.normal_map = None;
+        self.clear();
+    }
+
+    pub fn image(&self) -> &image::Image {
+        &self.image
     }
 
+    /// Number of instances the batch can hold before its instance list reallocates.
+    pub fn capacity(&self) -> usize {
+        self.sprites.capacity()
+    }
+
+    /// Reorders the instances back to front by the z of their transform, so that with alpha
+    /// blending sprites with a higher z are drawn over those with a lower one.
+    ///
+    /// Sprites are otherwise drawn in insertion order. The sort is stable, so sprites sharing a
+    /// z keep their insertion order, and costs `O(n log n)` plus a full re-upload of the
+    /// instances on the next draw, so only call it when the order actually matters. Indices
+    /// used with [`SpriteBatch::remove`] refer to the sorted order afterwards.
+    pub fn sort_by_depth(&mut self) {
+        let mut order = (0..self.sprites.len()).collect::<Vec<_>>();
+        order.sort_by(|a, b| self.depths[*a].total_cmp(&self.depths[*b]));
+
+        self.sprites = order.iter().map(|i| self.sprites[*i]).collect();
+        self.depths = order.iter().map(|i| self.depths[*i]).collect();
+        self.mark_dirty();
+    }
+
+    /// Keeps the batch sorted by depth as sprites are inserted, see [`SpriteBatch::sort_by_depth`].
+    ///
+    /// Each insert then costs `O(n)` instead of amortized `O(1)`. Turning it on sorts the
+    /// sprites already in the batch.
+    pub fn set_auto_sort(&mut self, auto_sort: bool) {
+        if auto_sort && !self.auto_sort {
+            self.sort_by_depth();
+        }
+        self.auto_sort = auto_sort;
+    }
+
+    /// Number of instances the batch draws, including those added with
+    /// [`SpriteBatch::extend_from_iter`].
     pub fn count(&self) -> usize {
-        self.sprites.len()
+        if self.dirty || self.mapped.is_empty() {
+            self.sprites.len()
+        } else {
+            self.mapped_len
+        }
+    }
+
+    /// Appends `other`'s instances to this batch so both are drawn by the same draw calls.
+    ///
+    /// Only batches sharing the same image can be merged, `other` is left unchanged.
+    pub fn merge(&mut self, other: &SpriteBatch) -> Result<(), MergeError> {
+        if !self.image.same_texture(&other.image) {
+            return Err(MergeError::TextureMismatch);
+        }
+
+        self.sprites.extend_from_slice(&other.sprites);
+        self.depths.extend_from_slice(&other.depths);
+        if self.auto_sort {
+            self.sort_by_depth();
+        }
+        self.mark_dirty();
+        Ok(())
+    }
+
+    /// Sets the most instances a single draw call will cover, larger batches are split into
+    /// several draws over sub-ranges of the same instance buffer.
+    pub fn set_chunk_size(&mut self, chunk_size: u32) {
+        self.chunk_size = chunk_size.max(1);
+    }
+
+    /// Number of draw calls the batch currently issues when drawn.
+    pub fn draw_calls(&self) -> u32 {
+        (self.count() as u32).div_ceil(self.chunk_size)
+    }
+
+    /// Serializes the batch's instances as a 4 byte magic number, a 4 byte version, a 4 byte
+    /// instance count, then the raw `InstanceData` bytes. Integers are little endian.
+    pub fn serialize_to_binary(&self) -> Vec<u8> {
+        encode_batch(&self.sprites)
+    }
+
+    /// Rebuilds a batch written by [`SpriteBatch::serialize_to_binary`], loading its texture from `image_path`.
+    ///
+    /// Depths aren't serialized, the instances keep their order but every sprite is treated as
+    /// having a z of `0.0` by [`SpriteBatch::sort_by_depth`].
+    pub fn deserialize_from_binary<P: AsRef<path::Path>>(
+        queue: Arc<Queue>,
+        sampler: Arc<Sampler>,
+        image_path: P,
+        bytes: &[u8],
+    ) -> Result<Self> {
+        let sprites = decode_batch(bytes)?;
+
+        let mut batch = Self::new(image::Image::new(queue, sampler, image_path)?);
+        batch.depths = vec![0.0; sprites.len()];
+        batch.sprites = sprites;
+        Ok(batch)
+    }
+
+    /// Writes instance data directly into GPU visible memory, skipping the copy from the
+    /// batch's own list when drawing.
+    ///
+    /// Each call moves to the next of [`INSTANCE_SLOTS`] persistently mapped buffers so the
+    /// buffer handed out is never one the GPU may still be reading. The slot is seeded with
+    /// the previous slot's contents, or with the sprites added through [`SpriteBatch::insert`]
+    /// if those changed since the last call.
+    ///
+    /// The returned lock must be dropped before the batch is drawn. Instances written through
+    /// it skip the adjustment translucent tints of premultiplied images get, see
+    /// [`image::Image::is_premultiplied`], so keep their tints opaque. Writes made through it
+    /// aren't reflected back into the batch's `DrawInfo` list, and any later `insert`,
+    /// `remove`, or `clear` replaces them the next time the instances are mapped. Returns an
+    /// error if the GPU still holds the buffer, which means more frames are in flight than
+    /// there are slots.
+    pub fn map_instances(&mut self, queue: Arc<Queue>) -> Result<WriteLock<'_, [InstanceData]>> {
+        if self.mapped.is_empty() || self.dirty && self.mapped[0].len() != self.sprites.len() as u64 {
+            self.mapped = (0..INSTANCE_SLOTS)
+                .map(|_| unsafe {
+                    CpuAccessibleBuffer::uninitialized_array(
+                        queue.device().clone(),
+                        self.sprites.len().max(1) as u64,
+                        BufferUsage::vertex_buffer(),
+                        true,
+                    )
+                })
+                .collect::<Result<Vec<_>, _>>()?;
+            self.mapped_memory = Some(memory::TrackedAllocation::new(
+                memory::MemoryCategory::Buffers,
+                (INSTANCE_SLOTS * self.sprites.len().max(1) * std::mem::size_of::<InstanceData>()) as u64,
+            ));
+        }
+
+        let previous = self.slot;
+        self.slot = (self.slot + 1) % INSTANCE_SLOTS;
+
+        let mut lock = self.mapped[self.slot].write()?;
+        if self.dirty {
+            self.write_instances(&mut lock[..self.sprites.len()]);
+            self.mapped_len = self.sprites.len();
+            self.dirty = false;
+        } else {
+            lock.copy_from_slice(&self.mapped[previous].read()?);
+        }
+
+        Ok(lock)
+    }
+
+    /// Appends instances straight into the mapped instance buffer as `iter` yields them,
+    /// without collecting them into the batch's own list first. Returns how many were added.
+    ///
+    /// Meant for large procedurally generated batches such as tilemaps, where building a
+    /// `Vec` and then uploading it would hold every instance twice. The mapped buffers double
+    /// in size whenever they fill up. Like writes through [`SpriteBatch::map_instances`], the
+    /// added instances only live in the mapped buffers: they aren't serialized, sorted, or
+    /// merged, and the next `insert`, `remove`, or `clear` drops them.
+    pub fn extend_from_iter<I: IntoIterator<Item = DrawInfo>>(&mut self, queue: Arc<Queue>, iter: I) -> Result<usize> {
+        drop(self.map_instances(queue.clone())?);
+
+        let flags = self.texture_flags();
+        let mut iter = iter.into_iter().peekable();
+        let mut added = 0;
+
+        loop {
+            let buffer = self.mapped[self.slot].clone();
+            let mut lock = buffer.write()?;
+            let capacity = lock.len();
+
+            while self.mapped_len < capacity {
+                match iter.next() {
+                    Some(info) => {
+                        let mut instance: InstanceData = info.into();
+                        instance.flags |= flags;
+                        lock[self.mapped_len] = instance;
+                        self.mapped_len += 1;
+                        added += 1;
+                    }
+                    None => return Ok(added),
+                }
+            }
+
+            drop(lock);
+            if iter.peek().is_none() {
+                return Ok(added);
+            }
+
+            self.grow(&queue, capacity * 2)?;
+        }
+    }
+
+    /// Reallocates the mapped buffers with room for `capacity` instances, keeping the current
+    /// slot's contents.
+    fn grow(&mut self, queue: &Arc<Queue>, capacity: usize) -> Result<()> {
+        let mapped = (0..INSTANCE_SLOTS)
+            .map(|_| unsafe {
+                CpuAccessibleBuffer::<[InstanceData]>::uninitialized_array(
+                    queue.device().clone(),
+                    capacity as u64,
+                    BufferUsage::vertex_buffer(),
+                    true,
+                )
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        mapped[self.slot].write()?[..self.mapped_len]
+            .copy_from_slice(&self.mapped[self.slot].read()?[..self.mapped_len]);
+
+        self.mapped = mapped;
+        self.mapped_memory = Some(memory::TrackedAllocation::new(
+            memory::MemoryCategory::Buffers,
+            (INSTANCE_SLOTS * capacity * std::mem::size_of::<InstanceData>()) as u64,
+        ));
+
+        Ok(())
     }
 }
 
 impl Drawable for SpriteBatch {
-    fn draw(&self, context: &mut GraphicsContext, _info: DrawInfo) {
-        context.draw(Box::new(
-            DefaultPipelineData::new(context.device.clone())
-                .vertex_buffer(QUAD_VERTICES.to_vec())
-                .instance_buffer(self.sprites.clone())
-                .sampled_image(0, self.image.inner().clone(), context.samplers[0].clone()),
+    fn stats(&self) -> DrawStats {
+        DrawStats {
+            draw_calls: self.draw_calls(),
+            instances: self.count() as u32,
+        }
+    }
+
+    fn draw(&self, queue: Arc<Queue>, shader_handle: &dyn ShaderHandle, state: &DrawState, _info: DrawInfo) -> Result<SecondaryAutoCommandBuffer> {
+        self.draw_instances(queue, shader_handle, state, 0..self.count() as u32)
+    }
+}
+
+impl Bounded for SpriteBatch {
+    /// The union of every instance's quad after its transform, cached until the batch next
+    /// changes. Only covers the batch's own instance list, not instances written through
+    /// [`SpriteBatch::map_instances`] or added with [`SpriteBatch::extend_from_iter`].
+    fn bounds(&self) -> Option<Rect> {
+        let mut cached = self.bounds.lock().unwrap();
+        if let Some(bounds) = *cached {
+            return bounds;
+        }
+
+        let bounds = self
+            .sprites
+            .iter()
+            .map(|instance| quad_bounds(instance.transform.into()))
+            .reduce(|a, b| a.union(&b));
+        *cached = Some(bounds);
+        bounds
+    }
+}
+
+impl SpriteBatch {
+    /// Draws only `count` instances starting at `start`, without building a smaller batch.
+    ///
+    /// Meant for paging through or picking a level of detail from one large batch. The range
+    /// is clamped to the batch's instances, so it may draw fewer than `count` or nothing.
+    pub fn draw_range(self: &Arc<Self>, pass: &mut Pass, shader: ShaderId, start: usize, count: usize) -> Result<()> {
+        let len = self.count();
+        let start = start.min(len);
+        let end = start.saturating_add(count).min(len);
+
+        let range = SpriteBatchRange {
+            batch: self.clone(),
+            instances: start as u32..end as u32,
+        };
+        pass.draw_with(Arc::new(range), shader, DrawInfo::default())
+    }
+
+    fn draw_instances(
+        &self,
+        queue: Arc<Queue>,
+        shader_handle: &dyn ShaderHandle,
+        state: &DrawState,
+        instances: std::ops::Range<u32>,
+    ) -> Result<SecondaryAutoCommandBuffer> {
+        let lighting = self.lighting_writes(&queue)?;
+
+        if !self.dirty && !self.mapped.is_empty() {
+            return self.image.draw_instances_with(
+                queue,
+                shader_handle,
+                state,
+                self.mapped[self.slot].clone(),
+                instances,
+                self.chunk_size,
+                lighting,
+            );
+        }
+
+        let instance_buffer = self.rebuild_if_dirty(&queue)?;
+        self.image.draw_instances_with(queue, shader_handle, state, instance_buffer, instances, self.chunk_size, lighting)
+    }
+
+    /// The persistent instance buffer, with the batch's instances written into it if they
+    /// changed since it was last drawn.
+    ///
+    /// Instances are written in place into a buffer that fits them and that nothing but the
+    /// batch holds. A buffer recorded into a command buffer is held by it until its frame has
+    /// finished, even before submission when the GPU lock isn't taken yet, so drawing, changing
+    /// and drawing a batch again in one frame writes into another buffer. When none is free a
+    /// new buffer is allocated, doubling the capacity when it's outgrown. Once there are
+    /// [`INSTANCE_SLOTS`] it replaces one that's too small, or else the oldest, which the frames
+    /// still using it keep alive.
+    pub(crate) fn rebuild_if_dirty(&self, queue: &Arc<Queue>) -> Result<Arc<CpuAccessibleBuffer<[InstanceData]>>> {
+        let mut ring = self.instance_buffers.lock().unwrap();
+        let current = self.instance_slot.load(Ordering::Relaxed);
+
+        if self.instances_uploaded.load(Ordering::Relaxed) {
+            if let Some((buffer, _)) = ring.get(current) {
+                return Ok(buffer.clone());
+            }
+        }
+
+        // Starting at the current buffer keeps it across frames when nothing else needs it.
+        let len = self.sprites.len();
+        let free = (0..ring.len())
+            .map(|offset| (current + offset) % ring.len())
+            .find(|&index| ring[index].0.len() >= len as u64 && Arc::strong_count(&ring[index].0) == 1);
+        if let Some(index) = free {
+            if let Ok(mut lock) = ring[index].0.write() {
+                self.write_instances(&mut lock[..len]);
+                drop(lock);
+                self.instance_slot.store(index, Ordering::Relaxed);
+                self.instances_uploaded.store(true, Ordering::Relaxed);
+                return Ok(ring[index].0.clone());
+            }
+        }
+
+        let previous = ring.iter().map(|(buffer, _)| buffer.len() as usize).max().unwrap_or(0);
+        let capacity = if len > previous {
+            len.max(previous * 2).max(1)
+        } else {
+            previous.max(1)
+        };
+
+        let buffer = unsafe {
+            CpuAccessibleBuffer::<[InstanceData]>::uninitialized_array(
+                queue.device().clone(),
+                capacity as u64,
+                BufferUsage::vertex_buffer(),
+                true,
+            )?
+        };
+        self.write_instances(&mut buffer.write()?[..len]);
+
+        let entry = (
+            buffer.clone(),
+            memory::TrackedAllocation::new(
+                memory::MemoryCategory::Buffers,
+                (capacity * std::mem::size_of::<InstanceData>()) as u64,
+            ),
+        );
+        let index = if ring.len() < INSTANCE_SLOTS {
+            ring.push(entry);
+            ring.len() - 1
+        } else {
+            let index = (0..ring.len())
+                .find(|&index| Arc::strong_count(&ring[index].0) == 1)
+                .unwrap_or((current + 1) % ring.len());
+            ring[index] = entry;
+            index
+        };
+        self.instance_slot.store(index, Ordering::Relaxed);
+        self.instances_uploaded.store(true, Ordering::Relaxed);
+        Ok(buffer)
+    }
+
+    /// Copies the instances into `target`, flagged for the image's alpha.
+    fn write_instances(&self, target: &mut [InstanceData]) {
+        target.copy_from_slice(&self.sprites);
+        let flags = self.texture_flags();
+        if flags != 0 {
+            for instance in target {
+                instance.flags |= flags;
+            }
+        }
+    }
+
+    /// The instance flags every instance needs for the batch's image, see
+    /// [`image::Image::is_premultiplied`].
+    fn texture_flags(&self) -> u32 {
+        if self.image.is_premultiplied() {
+            INSTANCE_FLAG_PREMULTIPLIED_TEXTURE
+        } else {
+            0
+        }
+    }
+}
+
+/// `instances` in the format written by [`SpriteBatch::serialize_to_binary`].
+fn encode_batch(instances: &[InstanceData]) -> Vec<u8> {
+    let data: &[u8] = bytemuck::cast_slice(instances);
+
+    let mut bytes = Vec::with_capacity(BATCH_HEADER_LEN + data.len());
+    bytes.extend_from_slice(&BATCH_MAGIC);
+    bytes.extend_from_slice(&BATCH_VERSION.to_le_bytes());
+    bytes.extend_from_slice(&(instances.len() as u32).to_le_bytes());
+    bytes.extend_from_slice(data);
+    bytes
+}
+
+/// The instances in data written by [`encode_batch`], rejecting any other [`BATCH_VERSION`].
+fn decode_batch(bytes: &[u8]) -> Result<Vec<InstanceData>> {
+    if bytes.len() < BATCH_HEADER_LEN {
+        return Err(anyhow!("sprite batch data too short for header: {} bytes", bytes.len()));
+    }
+
+    if bytes[0..4] != BATCH_MAGIC {
+        return Err(anyhow!("sprite batch data has bad magic number {:?}", &bytes[0..4]));
+    }
+
+    let version = u32::from_le_bytes(bytes[4..8].try_into().unwrap());
+    if version < BATCH_VERSION {
+        return Err(anyhow!(
+            "sprite batch version {} predates the current instance layout (version {}), serialize it again",
+            version,
+            BATCH_VERSION,
         ));
     }
+    if version != BATCH_VERSION {
+        return Err(anyhow!("unsupported sprite batch version {}", version));
+    }
+
+    // Any length but the exact one means the instances were written with another layout.
+    let count = u32::from_le_bytes(bytes[8..12].try_into().unwrap()) as usize;
+    let len = count * std::mem::size_of::<InstanceData>();
+    let data = &bytes[BATCH_HEADER_LEN..];
+    if data.len() != len {
+        return Err(anyhow!(
+            "sprite batch data holds {} bytes, expected {} for {} instances",
+            data.len(),
+            len,
+            count,
+        ));
+    }
+
+    let mut sprites = vec![InstanceData::default(); count];
+    bytemuck::cast_slice_mut::<InstanceData, u8>(&mut sprites).copy_from_slice(data);
+    Ok(sprites)
+}
+
+/// A contiguous range of a [`SpriteBatch`]'s instances, drawn by [`SpriteBatch::draw_range`].
+struct SpriteBatchRange {
+    batch: Arc<SpriteBatch>,
+    instances: std::ops::Range<u32>,
+}
+
+impl Drawable for SpriteBatchRange {
+    fn stats(&self) -> DrawStats {
+        let count = self.instances.len() as u32;
+        DrawStats {
+            draw_calls: count.div_ceil(self.batch.chunk_size),
+            instances: count,
+        }
+    }
+
+    fn draw(&self, queue: Arc<Queue>, shader_handle: &dyn ShaderHandle, state: &DrawState, _info: DrawInfo) -> Result<SecondaryAutoCommandBuffer> {
+        self.batch.draw_instances(queue, shader_handle, state, self.instances.clone())
+    }
+}
+
+/// Collects sprites drawn from any number of images over a frame and draws them as one
+/// [`SpriteBatch`] per image.
+///
+/// Unlike a `SpriteBatch`, which is tied to a single image, sprites can be queued in whatever
+/// order is convenient. [`SpriteRenderer::flush`] sorts them by [`DrawInfo::layer`], then
+/// [`DrawInfo::order`], then image, so every run of sprites sharing an image within the same
+/// layer and order becomes one batch. That keeps descriptor set binds and draw calls down to
+/// one per image per layer rather than one per texture switch.
+///
+/// The sort is stable, so sprites with the same layer, order, and image keep their queued
+/// order, but sprites from different images within a layer and order don't. Give overlapping
+/// sprites different orders, or turn the image sort off with
+/// [`SpriteRenderer::set_sort_by_texture`], when they need to layer a particular way.
+pub struct SpriteRenderer {
+    sprites: Vec<(image::Image, DrawInfo)>,
+    sort_by_texture: bool,
+    last_batches: usize,
+    last_culled: usize,
+}
+
+impl Default for SpriteRenderer {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl SpriteRenderer {
+    pub fn new() -> Self {
+        Self {
+            sprites: Vec::new(),
+            sort_by_texture: true,
+            last_batches: 0,
+            last_culled: 0,
+        }
+    }
+
+    /// Queues a sprite of `image` to be drawn on the next flush.
+    pub fn draw(&mut self, image: &image::Image, info: DrawInfo) {
+        self.sprites.push((image.clone(), info));
+    }
+
+    /// Whether flushing groups sprites by image within each layer and order, on by default.
+    pub fn set_sort_by_texture(&mut self, sort_by_texture: bool) {
+        self.sort_by_texture = sort_by_texture;
+    }
+
+    /// Number of sprites queued since the last flush.
+    pub fn len(&self) -> usize {
+        self.sprites.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.sprites.is_empty()
+    }
+
+    /// Drops everything queued without drawing it.
+    pub fn clear(&mut self) {
+        self.sprites.clear();
+    }
+
+    /// Number of batches the queued sprites would be drawn as if flushed now.
+    pub fn batch_count(&self) -> usize {
+        let mut textures = self
+            .draw_order()
+            .into_iter()
+            .map(|i| self.sprites[i].0.texture_key())
+            .collect::<Vec<_>>();
+        textures.dedup();
+        textures.len()
+    }
+
+    /// Number of batches drawn by the most recent flush.
+    pub fn last_batch_count(&self) -> usize {
+        self.last_batches
+    }
+
+    /// Drops queued sprites that fall entirely outside what a camera with the view-projection
+    /// `mvp` sees, so they're never uploaded, and returns how many were dropped. Pass it the
+    /// camera's `as_mvp()`, from a [`camera::Camera2D`] or any [`camera::Camera`].
+    ///
+    /// The view is found by unprojecting the corners of clip space through the inverse of
+    /// `mvp`, so zoomed and rotated cameras cull against the region they actually show. Each
+    /// sprite is tested by its world-space bounding box, which keeps rotated sprites whose
+    /// corners only just reach into view. Meant for cameras that project orthographically; a
+    /// matrix that can't be inverted culls nothing.
+    pub fn cull_against(&mut self, mvp: [[f32; 4]; 4]) -> usize {
+        let view = match view_quad(mvp.into()) {
+            Some(view) => view,
+            None => {
+                self.last_culled = 0;
+                return 0;
+            }
+        };
+
+        let before = self.sprites.len();
+        self.sprites.retain(|(_, info)| view_overlaps(&view, &sprite_bounds(info)));
+        self.last_culled = before - self.sprites.len();
+        self.last_culled
+    }
+
+    /// Number of sprites dropped by the most recent [`SpriteRenderer::cull_against`].
+    pub fn last_culled_count(&self) -> usize {
+        self.last_culled
+    }
+
+    /// Indices of the queued sprites in the order they'll be drawn.
+    fn draw_order(&self) -> Vec<usize> {
+        let mut order = (0..self.sprites.len()).collect::<Vec<_>>();
+        order.sort_by_key(|i| {
+            let (image, info) = &self.sprites[*i];
+            let texture = if self.sort_by_texture { image.texture_key() } else { 0 };
+            (info.sort_key(), texture)
+        });
+        order
+    }
+
+    /// Draws everything queued since the last flush with `shader`, one batch per contiguous
+    /// run of sprites sharing an image in the sorted order, and empties the queue.
+    pub fn flush(&mut self, pass: &mut Pass, shader: ShaderId) -> Result<()> {
+        let order = self.draw_order();
+        let mut sprites = std::mem::take(&mut self.sprites).into_iter().map(Some).collect::<Vec<_>>();
+
+        let mut batch: Option<SpriteBatch> = None;
+        let mut batches = Vec::new();
+        for (image, info) in order.into_iter().filter_map(|i| sprites[i].take()) {
+            match &batch {
+                Some(current) if current.image.same_texture(&image) => {}
+                _ => batches.extend(batch.replace(SpriteBatch::new(image))),
+            }
+            batch.as_mut().unwrap().insert(info);
+        }
+        batches.extend(batch);
+
+        self.last_batches = batches.len();
+        for batch in batches {
+            pass.draw_with(Arc::new(batch), shader, DrawInfo::default())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// The world-space corners of what a camera with `mvp` sees, in winding order.
+fn view_quad(mvp: Matrix4<f32>) -> Option<[Vector2<f32>; 4]> {
+    let inverse = mvp.invert()?;
+    let unproject = |x: f32, y: f32| {
+        let p = inverse * Vector4::new(x, y, 0.0, 1.0);
+        Vector2::new(p.x / p.w, p.y / p.w)
+    };
+
+    Some([unproject(-1.0, -1.0), unproject(1.0, -1.0), unproject(1.0, 1.0), unproject(-1.0, 1.0)])
+}
+
+/// The world-space bounding box of a sprite's quad, as its min and max corners.
+fn sprite_bounds(info: &DrawInfo) -> (Vector2<f32>, Vector2<f32>) {
+    quad_corner_bounds(info.transform.as_mat4())
+}
+
+/// The bounding box of the unit quad after `matrix`.
+pub(crate) fn quad_bounds(matrix: Matrix4<f32>) -> Rect {
+    let (min, max) = quad_corner_bounds(matrix);
+    Rect {
+        x: min.x,
+        y: min.y,
+        w: max.x - min.x,
+        h: max.y - min.y,
+    }
+}
+
+fn quad_corner_bounds(matrix: Matrix4<f32>) -> (Vector2<f32>, Vector2<f32>) {
+    let corners = [(0.0, 0.0), (1.0, 0.0), (0.0, 1.0), (1.0, 1.0)]
+        .map(|(x, y)| (matrix * Vector4::new(x, y, 0.0, 1.0)).truncate().truncate());
+
+    corners.iter().skip(1).fold((corners[0], corners[0]), |(min, max), c| {
+        (
+            Vector2::new(min.x.min(c.x), min.y.min(c.y)),
+            Vector2::new(max.x.max(c.x), max.y.max(c.y)),
+        )
+    })
+}
+
+/// Whether the view quad and a bounding box overlap, tested along the box's axes and the
+/// quad's edge normals. Both are convex, so no separating axis among those means they touch.
+fn view_overlaps(view: &[Vector2<f32>; 4], (min, max): &(Vector2<f32>, Vector2<f32>)) -> bool {
+    let view_min = view.iter().fold(view[0], |m, c| Vector2::new(m.x.min(c.x), m.y.min(c.y)));
+    let view_max = view.iter().fold(view[0], |m, c| Vector2::new(m.x.max(c.x), m.y.max(c.y)));
+    if view_max.x < min.x || view_min.x > max.x || view_max.y < min.y || view_min.y > max.y {
+        return false;
+    }
+
+    let bounds = [*min, Vector2::new(max.x, min.y), *max, Vector2::new(min.x, max.y)];
+    (0..2).all(|i| {
+        let edge = view[i + 1] - view[i];
+        let axis = Vector2::new(-edge.y, edge.x);
+        let project = |points: &[Vector2<f32>; 4]| {
+            points.iter().map(|p| axis.x * p.x + axis.y * p.y).fold((f32::MAX, f32::MIN), |(lo, hi), d| (lo.min(d), hi.max(d)))
+        };
+        let (view_lo, view_hi) = project(view);
+        let (lo, hi) = project(&bounds);
+        view_hi >= lo && hi >= view_lo
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn header(version: u32, count: u32) -> Vec<u8> {
+        let mut bytes = BATCH_MAGIC.to_vec();
+        bytes.extend_from_slice(&version.to_le_bytes());
+        bytes.extend_from_slice(&count.to_le_bytes());
+        bytes
+    }
+
+    #[test]
+    fn batch_round_trip_keeps_instance_flags() {
+        let mut info = DrawInfo::with_color(Color::red());
+        info.set_premultiply(true);
+        let instances = vec![InstanceData::from(info), InstanceData::default()];
+
+        let decoded = decode_batch(&encode_batch(&instances)).unwrap();
+        assert_eq!(decoded.len(), 2);
+        assert_eq!(decoded[0].flags, INSTANCE_FLAG_PREMULTIPLY);
+        assert_eq!(bytemuck::cast_slice::<_, u8>(&decoded), bytemuck::cast_slice::<_, u8>(&instances));
+    }
+
+    #[test]
+    fn hundred_instance_batch_round_trips() {
+        let instances = (0..100)
+            .map(|i| InstanceData::from(DrawInfo::with_all((i as f32, -(i as f32), 0.0), 2.0, i as f32 * 0.1, Color::red())))
+            .collect::<Vec<_>>();
+
+        let bytes = encode_batch(&instances);
+        assert_eq!(bytes.len(), BATCH_HEADER_LEN + 100 * std::mem::size_of::<InstanceData>());
+        let decoded = decode_batch(&bytes).unwrap();
+        assert_eq!(bytemuck::cast_slice::<_, u8>(&decoded), bytemuck::cast_slice::<_, u8>(&instances));
+    }
+
+    #[test]
+    fn batch_round_trip_keeps_corner_radius() {
+        let mut info = DrawInfo::with_color(Color::white());
+        info.set_corner_radius(4.0);
+
+        let decoded = decode_batch(&encode_batch(&[info.into()])).unwrap();
+        assert_eq!(decoded[0].corner_radius, 4.0);
+    }
+
+    #[test]
+    fn batches_with_another_instance_size_are_rejected() {
+        // Builds that added corner_radius but not corner_colors wrote 104 byte instances.
+        let mut bytes = header(BATCH_VERSION, 1);
+        bytes.extend_from_slice(&[0; 104]);
+        assert!(decode_batch(&bytes).is_err());
+
+        let mut bytes = encode_batch(&[InstanceData::default()]);
+        bytes.push(0);
+        assert!(decode_batch(&bytes).is_err());
+    }
+
+    #[test]
+    fn batches_from_older_versions_are_rejected() {
+        // Version 1 instances were 96 bytes: src, color, and transform.
+        let mut bytes = header(1, 1);
+        bytes.extend_from_slice(&[0; 96]);
+
+        let error = decode_batch(&bytes).unwrap_err().to_string();
+        assert!(error.contains("version 1 predates"), "{}", error);
+    }
+
+    fn batch(queue: &Arc<Queue>, sampler: Arc<Sampler>) -> SpriteBatch {
+        let mut batch = SpriteBatch::new(image::Image::placeholder(queue.clone(), sampler));
+        batch.insert(DrawInfo::with_color(Color::white()));
+        batch
+    }
+
+    /// Whether [`SpriteRenderer::cull_against`] keeps a sprite of `size` centered on `center`.
+    fn kept(mvp: Matrix4<f32>, center: (f32, f32), size: f32) -> bool {
+        let info = DrawInfo::centered(center, (size, size), 0.0, Color::white());
+        view_overlaps(&view_quad(mvp).unwrap(), &sprite_bounds(&info))
+    }
+
+    /// An 800x600 view zoomed in 4x, showing x in -100..100 and y in -75..75.
+    fn zoomed_camera() -> Matrix4<f32> {
+        let mut camera = camera::Camera2D::new(800.0, 600.0);
+        camera.set_zoom(4.0);
+        camera.as_mvp().into()
+    }
+
+    #[test]
+    fn zoomed_camera_culls_sprites_just_outside_its_view() {
+        let mvp = zoomed_camera();
+        assert!(kept(mvp, (104.0, 0.0), 10.0));
+        assert!(!kept(mvp, (106.0, 0.0), 10.0));
+        assert!(kept(mvp, (0.0, -79.0), 10.0));
+        assert!(!kept(mvp, (0.0, -81.0), 10.0));
+    }
+
+    #[test]
+    fn rotated_camera_culls_against_its_turned_view() {
+        let mvp = Matrix4::from_angle_z(cgmath::Deg(45.0)) * zoomed_camera();
+
+        // Past the unrotated view's right edge, but inside the turned view's corner.
+        assert!(kept(mvp, (106.0, -21.0), 2.0));
+        assert!(!kept(zoomed_camera(), (106.0, -21.0), 2.0));
+        // Inside the unrotated view's corner, which the turned view no longer covers.
+        assert!(!kept(mvp, (95.0, 70.0), 2.0));
+        assert!(kept(zoomed_camera(), (95.0, 70.0), 2.0));
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn bounds_follow_an_instance_that_moves() {
+        let (queue, sampler) = test_device();
+        let mut batch = SpriteBatch::new(image::Image::placeholder(queue, sampler));
+        assert_eq!(batch.bounds(), None);
+
+        batch.insert(DrawInfo::centered((0.0, 0.0), (10.0, 10.0), 0.0, Color::white()));
+        assert_eq!(batch.bounds(), Some(Rect { x: -5.0, y: -5.0, w: 10.0, h: 10.0 }));
+
+        batch.remove(0);
+        batch.insert(DrawInfo::centered((100.0, 0.0), (10.0, 10.0), 0.0, Color::white()));
+        assert_eq!(batch.bounds(), Some(Rect { x: 95.0, y: -5.0, w: 10.0, h: 10.0 }));
+
+        batch.clear();
+        assert_eq!(batch.bounds(), None);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn large_batches_split_into_chunked_draws() {
+        let (queue, sampler) = test_device();
+        let mut batch = SpriteBatch::new(image::Image::placeholder(queue, sampler));
+        for _ in 0..100_000 {
+            batch.insert(DrawInfo::default());
+        }
+
+        batch.set_chunk_size(10_000);
+        assert_eq!(batch.draw_calls(), 10);
+        batch.insert(DrawInfo::default());
+        assert_eq!(batch.draw_calls(), 11);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn sort_by_depth_reorders_the_instance_buffer() {
+        let (queue, sampler) = test_device();
+        let mut batch = SpriteBatch::new(image::Image::placeholder(queue.clone(), sampler));
+        for (z, color) in [(0.5, Color::red()), (0.1, Color::black()), (0.5, Color::grey()), (0.3, Color::white())] {
+            batch.insert(DrawInfo::with_position_color(0.0, 0.0, z, color));
+        }
+
+        batch.sort_by_depth();
+        let buffer = batch.rebuild_if_dirty(&queue).unwrap();
+        let colors = buffer.read().unwrap().iter().map(|i| i.color).collect::<Vec<_>>();
+        let expected = [Color::black(), Color::white(), Color::red(), Color::grey()].map(<[f32; 4]>::from);
+        assert_eq!(colors, expected);
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn set_image_clears_instances_but_keeps_capacity() {
+        let (queue, sampler) = test_device();
+        let mut batch = SpriteBatch::new(image::Image::placeholder(queue.clone(), sampler.clone()));
+        for _ in 0..100 {
+            batch.insert(DrawInfo::default());
+        }
+        let capacity = batch.capacity();
+
+        let sheet = image::Image::from_color(queue, sampler, Color::red());
+        batch.set_image(sheet.clone());
+        assert_eq!(batch.count(), 0);
+        assert_eq!(batch.capacity(), capacity);
+        assert!(batch.image().same_texture(&sheet));
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn buffer_arc_stays_stable_across_frames_when_capacity_is_sufficient() {
+        let (queue, sampler) = test_device();
+        let mut batch = batch(&queue, sampler);
+
+        // Only a weak reference is kept, as if the frame drawing the buffer had finished.
+        let first = Arc::downgrade(&batch.rebuild_if_dirty(&queue).unwrap());
+        batch.remove(0);
+        batch.insert(DrawInfo::with_color(Color::red()));
+        let second = batch.rebuild_if_dirty(&queue).unwrap();
+
+        assert!(Arc::ptr_eq(&first.upgrade().unwrap(), &second));
+        assert_eq!(second.read().unwrap()[0].color, <[f32; 4]>::from(Color::red()));
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn recorded_buffer_keeps_its_instances_when_redrawn_in_the_same_frame() {
+        let (queue, sampler) = test_device();
+        let mut batch = batch(&queue, sampler);
+
+        // Held the way a recorded but unsubmitted command buffer holds it.
+        let recorded = batch.rebuild_if_dirty(&queue).unwrap();
+        batch.remove(0);
+        batch.insert(DrawInfo::with_color(Color::red()));
+        let redrawn = batch.rebuild_if_dirty(&queue).unwrap();
+
+        assert!(!Arc::ptr_eq(&recorded, &redrawn));
+        assert_eq!(recorded.read().unwrap()[0].color, <[f32; 4]>::from(Color::white()));
+        assert_eq!(redrawn.read().unwrap()[0].color, <[f32; 4]>::from(Color::red()));
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn mapped_writes_hold_up_over_a_thousand_frames() {
+        let (queue, sampler) = test_device();
+        let white = image::Image::from_color(queue.clone(), sampler, Color::white());
+        let mut batch = SpriteBatch::new(white);
+        batch.insert(DrawInfo::centered((0.5, 0.5), (1.0, 1.0), 0.0, Color::white()));
+        let mut pass = crate::graphics::test_pass(queue.clone());
+
+        let mut pixels = Vec::new();
+        for frame in 0..1000 {
+            let x = (frame % 16) as f32 + 0.5;
+            let mut instances = batch.map_instances(queue.clone()).unwrap();
+            instances[0] = DrawInfo::centered((x, 0.5), (1.0, 1.0), 0.0, Color::white()).into();
+            drop(instances);
+
+            let drawn = Arc::new(batch);
+            pixels = crate::graphics::render_offscreen(&queue, &mut pass, [16, 1], Color::black(), |pass| {
+                pass.draw(drawn.clone(), DrawInfo::default())
+            });
+            batch = Arc::try_unwrap(drawn).ok().unwrap();
+        }
+
+        // The last frame, 999, put the sprite in column 999 % 16 = 7.
+        for x in 0..16 {
+            let expected = if x == 7 { [255; 4] } else { [0, 0, 0, 255] };
+            assert_eq!(crate::graphics::pixel_at(&pixels, 16, (x, 0)), expected, "column {}", x);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn extending_by_ten_thousand_grows_the_mapped_buffers() {
+        let (queue, sampler) = test_device();
+        let mut batch = batch(&queue, sampler);
+        let info = |i: usize| DrawInfo::with_position_color(i as f32, (i / 100) as f32, 0.0, Color::rgba((i % 256) as u8, 0, 0, 255));
+
+        let added = batch.extend_from_iter(queue, (0..10_000).map(info)).unwrap();
+        assert_eq!(added, 10_000);
+        assert_eq!(batch.mapped_len, 10_001);
+
+        let instances = batch.mapped[batch.slot].read().unwrap();
+        assert!(instances.len() >= 10_001);
+        assert_eq!(instances[0].color, InstanceData::from(DrawInfo::with_color(Color::white())).color);
+        for (i, instance) in instances[1..10_001].iter().enumerate() {
+            let expected = InstanceData::from(info(i));
+            assert_eq!(instance.transform, expected.transform, "instance {}", i);
+            assert_eq!(instance.color, expected.color, "instance {}", i);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn interleaved_textures_flush_as_one_batch_each() {
+        let (queue, sampler) = test_device();
+        let red = image::Image::from_color(queue.clone(), sampler.clone(), Color::red());
+        let white = image::Image::from_color(queue.clone(), sampler, Color::white());
+
+        let mut sprites = SpriteRenderer::new();
+        for i in 0..6 {
+            let image = if i % 2 == 0 { &red } else { &white };
+            sprites.draw(image, DrawInfo::centered((i as f32 + 0.5, 0.5), (1.0, 1.0), 0.0, Color::white()));
+        }
+        assert_eq!(sprites.batch_count(), 2);
+        sprites.set_sort_by_texture(false);
+        assert_eq!(sprites.batch_count(), 6);
+        sprites.set_sort_by_texture(true);
+
+        let mut pass = crate::graphics::test_pass(queue.clone());
+        crate::graphics::render_offscreen(&queue, &mut pass, [6, 1], Color::black(), |pass| {
+            sprites.flush(pass, 0)?;
+            assert_eq!(pass.stats(), DrawStats { draw_calls: 2, instances: 6 });
+            Ok(())
+        });
+        assert_eq!(sprites.last_batch_count(), 2);
+        assert!(sprites.is_empty());
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn ranges_draw_their_instances_and_clamp_to_the_batch() {
+        let (queue, sampler) = test_device();
+        let white = image::Image::from_color(queue.clone(), sampler, Color::white());
+        let mut batch = SpriteBatch::new(white);
+        for x in 0..100 {
+            batch.insert(DrawInfo::centered((x as f32 + 0.5, 0.5), (1.0, 1.0), 0.0, Color::white()));
+        }
+        let batch = Arc::new(batch);
+
+        let mut pass = crate::graphics::test_pass(queue.clone());
+        let pixels = crate::graphics::render_offscreen(&queue, &mut pass, [100, 1], Color::black(), |pass| {
+            batch.draw_range(pass, 0, 10, 10)?;
+            assert_eq!(pass.stats().instances, 10);
+            batch.draw_range(pass, 0, 95, 10)?;
+            assert_eq!(pass.stats().instances, 15);
+            batch.draw_range(pass, 0, 150, 10)?;
+            assert_eq!(pass.stats().instances, 15);
+            Ok(())
+        });
+
+        for x in 0..100 {
+            let drawn = (10..20).contains(&x) || x >= 95;
+            let expected = if drawn { [255; 4] } else { [0, 0, 0, 255] };
+            assert_eq!(crate::graphics::pixel_at(&pixels, 100, (x, 0)), expected, "column {}", x);
+        }
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn merged_batches_draw_in_one_call() {
+        let (queue, sampler) = test_device();
+        let white = image::Image::from_color(queue.clone(), sampler.clone(), Color::white());
+        let mut first = SpriteBatch::new(white.clone());
+        let mut second = SpriteBatch::new(white);
+        for x in 0..3 {
+            first.insert(DrawInfo::centered((x as f32 + 0.5, 0.5), (1.0, 1.0), 0.0, Color::white()));
+        }
+        for x in 3..5 {
+            second.insert(DrawInfo::centered((x as f32 + 0.5, 0.5), (1.0, 1.0), 0.0, Color::white()));
+        }
+
+        first.merge(&second).unwrap();
+        assert_eq!(first.count(), 5);
+        assert_eq!(second.count(), 2);
+
+        let other = SpriteBatch::new(image::Image::from_color(queue.clone(), sampler, Color::red()));
+        assert_eq!(first.merge(&other), Err(MergeError::TextureMismatch));
+        assert_eq!(first.count(), 5);
+
+        let first = Arc::new(first);
+        let mut pass = crate::graphics::test_pass(queue.clone());
+        crate::graphics::render_offscreen(&queue, &mut pass, [5, 1], Color::black(), |pass| {
+            pass.draw(first, DrawInfo::default())?;
+            assert_eq!(pass.stats(), DrawStats { draw_calls: 1, instances: 5 });
+            Ok(())
+        });
+    }
 }
@@ -0,0 +1,268 @@
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+
+use vulkano::{
+    buffer::{BufferUsage, CpuAccessibleBuffer},
+    command_buffer::{AutoCommandBufferBuilder, CommandBufferUsage},
+    device::Queue,
+    image::{ImageAccess, ImmutableImage},
+    sync::{self, FenceSignalFuture, GpuFuture},
+};
+
+use anyhow::Result;
+
+use crate::graphics::image::Image;
+
+/// Signalled once a flush of pending uploads has finished on the GPU.
+type UploadFence = Arc<FenceSignalFuture<Box<dyn GpuFuture + Send + Sync>>>;
+
+/// Tracks whether a deferred image upload has finished on the GPU.
+pub struct UploadState {
+    fence: Mutex<Option<UploadFence>>,
+    done: AtomicBool,
+}
+
+impl UploadState {
+    fn new() -> Self {
+        Self {
+            fence: Mutex::new(None),
+            done: AtomicBool::new(false),
+        }
+    }
+
+    /// `true` once the upload has been submitted and the GPU has finished copying it.
+    pub fn is_ready(&self) -> bool {
+        if self.done.load(Ordering::Acquire) {
+            return true;
+        }
+
+        let fence = self.fence.lock().unwrap();
+        let ready = match fence.as_ref() {
+            Some(fence) => fence.wait(Some(Duration::from_secs(0))).is_ok(),
+            None => false,
+        };
+
+        if ready {
+            self.done.store(true, Ordering::Release);
+        }
+
+        ready
+    }
+}
+
+struct PendingUpload {
+    staging: Arc<CpuAccessibleBuffer<[u8]>>,
+    image: Arc<dyn ImageAccess>,
+    state: Arc<UploadState>,
+}
+
+/// Collects image uploads so they can be copied in a single submission instead of one
+/// blocking submit per image.
+///
+/// Uploads queued with [`crate::graphics::image::Image::new_deferred`] are flushed at the
+/// start of the next frame, or earlier with [`UploadQueue::flush`].
+pub struct UploadQueue {
+    queue: Arc<Queue>,
+    placeholder: Image,
+    pending: Vec<PendingUpload>,
+    submissions: u32,
+}
+
+impl UploadQueue {
+    pub fn new(queue: Arc<Queue>, sampler: Arc<vulkano::sampler::Sampler>) -> Self {
+        Self {
+            placeholder: Image::placeholder(queue.clone(), sampler),
+            queue,
+            pending: Vec::new(),
+            submissions: 0,
+        }
+    }
+
+    /// The image drawn in place of images whose upload hasn't finished.
+    pub fn placeholder(&self) -> &Image {
+        &self.placeholder
+    }
+
+    pub(crate) fn queue(&self) -> &Arc<Queue> {
+        &self.queue
+    }
+
+    pub(crate) fn push(&mut self, data: Vec<u8>, image: Arc<ImmutableImage>) -> Result<Arc<UploadState>> {
+        let staging = CpuAccessibleBuffer::from_iter(
+            self.queue.device().clone(),
+            BufferUsage::transfer_source(),
+            false,
+            data,
+        )?;
+
+        let state = Arc::new(UploadState::new());
+        self.pending.push(PendingUpload {
+            staging,
+            image,
+            state: state.clone(),
+        });
+
+        Ok(state)
+    }
+
+    /// Number of uploads waiting for the next flush.
+    pub fn pending(&self) -> usize {
+        self.pending.len()
+    }
+
+    /// Number of transfer submissions made so far.
+    pub fn submissions(&self) -> u32 {
+        self.submissions
+    }
+
+    /// Copies every pending upload in one command buffer and submits it.
+    ///
+    /// Returns the future of the transfer so work depending on the images can be ordered after
+    /// it, or `None` if nothing was pending.
+    pub fn flush(&mut self) -> Result<Option<Box<dyn GpuFuture>>> {
+        if self.pending.is_empty() {
+            return Ok(None);
+        }
+
+        let mut builder = AutoCommandBufferBuilder::primary(
+            self.queue.device().clone(),
+            self.queue.family(),
+            CommandBufferUsage::OneTimeSubmit,
+        )?;
+
+        for upload in self.pending.iter() {
+            builder.copy_buffer_to_image(upload.staging.clone(), upload.image.clone())?;
+        }
+
+        let command_buffer = builder.build()?;
+        let future = sync::now(self.queue.device().clone())
+            .then_execute(self.queue.clone(), command_buffer)?
+            .boxed_send_sync();
+        let fence = Arc::new(future.then_signal_fence_and_flush()?);
+        self.submissions += 1;
+        log::debug!(target: "ledge::asset", "submitted {} image uploads", self.pending.len());
+
+        for upload in self.pending.drain(..) {
+            *upload.state.fence.lock().unwrap() = Some(fence.clone());
+        }
+
+        Ok(Some(fence.boxed()))
+    }
+}
+
+/// A group of deferred image loads tracked together, e.g. everything a loading screen waits on.
+///
+/// Images are decoded when queued and uploaded by the [`UploadQueue`] they were queued on,
+/// so progress only advances once that queue is flushed.
+#[derive(Default)]
+pub struct LoadBatch {
+    images: Vec<Image>,
+}
+
+impl LoadBatch {
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Queues the image at `path`, returning its index in [`LoadBatch::images`].
+    pub fn load<P: AsRef<std::path::Path>>(
+        &mut self,
+        uploads: &mut UploadQueue,
+        sampler: Arc<vulkano::sampler::Sampler>,
+        path: P,
+    ) -> Result<usize> {
+        self.images.push(Image::new_deferred(uploads, sampler, path)?);
+        Ok(self.images.len() - 1)
+    }
+
+    pub fn len(&self) -> usize {
+        self.images.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.images.is_empty()
+    }
+
+    /// Number of images whose upload has finished.
+    pub fn loaded(&self) -> usize {
+        self.images.iter().filter(|image| image.is_ready()).count()
+    }
+
+    /// Fraction of the batch that has finished loading, `1.0` for an empty batch.
+    pub fn progress(&self) -> f32 {
+        if self.images.is_empty() {
+            1.0
+        } else {
+            self.loaded() as f32 / self.images.len() as f32
+        }
+    }
+
+    pub fn is_complete(&self) -> bool {
+        self.images.iter().all(|image| image.is_ready())
+    }
+
+    /// The loaded images in the order they were queued, `None` until every one is ready.
+    pub fn images(&self) -> Option<&[Image]> {
+        if self.is_complete() {
+            Some(&self.images)
+        } else {
+            None
+        }
+    }
+
+    /// Takes the images out of a completed batch, giving the batch back if it isn't done.
+    pub fn into_images(self) -> Result<Vec<Image>, Self> {
+        if self.is_complete() {
+            Ok(self.images)
+        } else {
+            Err(self)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn thirty_deferred_loads_take_one_submission() {
+        let (queue, sampler) = crate::graphics::test_device();
+        let mut uploads = UploadQueue::new(queue, sampler.clone());
+
+        let images = (0..30)
+            .map(|_| Image::new_deferred(&mut uploads, sampler.clone(), "examples/images/pokeball.png").unwrap())
+            .collect::<Vec<_>>();
+        assert_eq!(uploads.pending(), 30);
+
+        uploads.flush().unwrap().unwrap().then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+
+        assert_eq!(uploads.submissions(), 1);
+        assert_eq!(uploads.pending(), 0);
+        assert!(images.iter().all(|image| image.is_ready()));
+        assert!(uploads.flush().unwrap().is_none());
+    }
+
+    #[test]
+    #[ignore = "needs a Vulkan device"]
+    fn load_batches_reach_full_progress_once_flushed() {
+        let (queue, sampler) = crate::graphics::test_device();
+        let mut uploads = UploadQueue::new(queue, sampler.clone());
+
+        let mut batch = LoadBatch::new();
+        assert_eq!(batch.progress(), 1.0);
+        for _ in 0..3 {
+            batch.load(&mut uploads, sampler.clone(), "examples/images/pokeball.png").unwrap();
+        }
+        assert_eq!(batch.len(), 3);
+        assert_eq!(batch.progress(), 0.0);
+        assert!(batch.images().is_none());
+
+        uploads.flush().unwrap().unwrap().then_signal_fence_and_flush().unwrap().wait(None).unwrap();
+
+        assert_eq!(batch.loaded(), 3);
+        assert_eq!(batch.progress(), 1.0);
+        assert_eq!(batch.into_images().ok().map(|images| images.len()), Some(3));
+    }
+}
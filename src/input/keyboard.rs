@@ -1,22 +1,126 @@
+#[cfg(feature = "window")]
 use std::collections::HashSet;
-// use winit::event::ModifiersState;
+#[cfg(feature = "window")]
+pub use winit::event::ModifiersState;
+#[cfg(feature = "window")]
 pub use winit::event::VirtualKeyCode as KeyCode;
+#[cfg(feature = "window")]
+pub use winit::event::ScanCode;
 
+/// The platform's raw key code, what winit calls a `ScanCode` when the `window` feature is on.
+#[cfg(not(feature = "window"))]
+pub type ScanCode = u32;
+
+/// Scancodes of keys by their position on a US QWERTY keyboard.
+///
+/// A [`KeyCode`] names the symbol printed on a key, so it depends on the user's layout:
+/// `KeyCode::W` is the key labelled W, which is where Z sits on AZERTY. A scancode names the
+/// key's physical position regardless of layout, so `scancode::W` is always the key just
+/// above and between `A` and `S`. Bind movement and other position based controls to
+/// scancodes with [`KeyboardContext::is_scancode_pressed`], and text or mnemonic shortcuts
+/// ("I for inventory") to key codes.
+///
+/// Scancodes are platform specific, these match what winit reports on Windows, Linux, and
+/// macOS for the named positions.
+pub mod scancode {
+    use super::ScanCode;
+
+    #[cfg(not(target_os = "macos"))]
+    mod codes {
+        use super::ScanCode;
+
+        pub const ESCAPE: ScanCode = 1;
+        pub const Q: ScanCode = 16;
+        pub const W: ScanCode = 17;
+        pub const E: ScanCode = 18;
+        pub const R: ScanCode = 19;
+        pub const A: ScanCode = 30;
+        pub const S: ScanCode = 31;
+        pub const D: ScanCode = 32;
+        pub const F: ScanCode = 33;
+        pub const Z: ScanCode = 44;
+        pub const X: ScanCode = 45;
+        pub const C: ScanCode = 46;
+        pub const LSHIFT: ScanCode = 42;
+        pub const LCONTROL: ScanCode = 29;
+        pub const SPACE: ScanCode = 57;
+    }
+
+    #[cfg(target_os = "macos")]
+    mod codes {
+        use super::ScanCode;
+
+        pub const ESCAPE: ScanCode = 53;
+        pub const Q: ScanCode = 12;
+        pub const W: ScanCode = 13;
+        pub const E: ScanCode = 14;
+        pub const R: ScanCode = 15;
+        pub const A: ScanCode = 0;
+        pub const S: ScanCode = 1;
+        pub const D: ScanCode = 2;
+        pub const F: ScanCode = 3;
+        pub const Z: ScanCode = 6;
+        pub const X: ScanCode = 7;
+        pub const C: ScanCode = 8;
+        pub const LSHIFT: ScanCode = 56;
+        pub const LCONTROL: ScanCode = 59;
+        pub const SPACE: ScanCode = 49;
+    }
+
+    pub use codes::*;
+
+    /// The scancode of the key at the position `key` occupies on a US QWERTY keyboard, for
+    /// the positions listed in this module.
+    #[cfg(feature = "window")]
+    pub fn from_qwerty(key: super::KeyCode) -> Option<ScanCode> {
+        use super::KeyCode;
+
+        Some(match key {
+            KeyCode::Escape => ESCAPE,
+            KeyCode::Q => Q,
+            KeyCode::W => W,
+            KeyCode::E => E,
+            KeyCode::R => R,
+            KeyCode::A => A,
+            KeyCode::S => S,
+            KeyCode::D => D,
+            KeyCode::F => F,
+            KeyCode::Z => Z,
+            KeyCode::X => X,
+            KeyCode::C => C,
+            KeyCode::LShift => LSHIFT,
+            KeyCode::LControl => LCONTROL,
+            KeyCode::Space => SPACE,
+            _ => return None,
+        })
+    }
+}
+
+/// Tracks held keys both by their layout dependent [`KeyCode`] and their physical [`ScanCode`],
+/// see [`scancode`] for when to use which.
+#[cfg(feature = "window")]
 pub struct KeyboardContext {
     pressed_keys: HashSet<KeyCode>,
+    pressed_scancodes: HashSet<ScanCode>,
     last_pressed: Option<KeyCode>,
     pub current_pressed: Option<KeyCode>,
+    modifiers: ModifiersState,
 }
 
+#[cfg(feature = "window")]
 impl KeyboardContext {
+    #[cfg(feature = "graphics")]
     pub(crate) fn new() -> Self {
         Self {
             pressed_keys: HashSet::with_capacity(128),
+            pressed_scancodes: HashSet::with_capacity(128),
             last_pressed: None,
             current_pressed: None,
+            modifiers: ModifiersState::empty(),
         }
     }
 
+    #[cfg(feature = "graphics")]
     pub(crate) fn set_key(&mut self, key: KeyCode, pressed: bool) {
         if pressed {
             let _ = self.pressed_keys.insert(key);
@@ -30,6 +134,45 @@ impl KeyboardContext {
         // self.set_key_modifier(key, pressed);
     }
 
+    #[cfg(feature = "graphics")]
+    pub(crate) fn set_scancode(&mut self, scancode: ScanCode, pressed: bool) {
+        if pressed {
+            let _ = self.pressed_scancodes.insert(scancode);
+        } else {
+            let _ = self.pressed_scancodes.remove(&scancode);
+        }
+    }
+
+    /// Whether the key labelled `key` in the user's layout is held.
+    pub fn is_key_pressed(&self, key: KeyCode) -> bool {
+        self.pressed_keys.contains(&key)
+    }
+
+    /// Whether the key at the physical position `code` is held, regardless of layout.
+    pub fn is_scancode_pressed(&self, code: ScanCode) -> bool {
+        self.pressed_scancodes.contains(&code)
+    }
+
+    pub fn pressed_scancodes(&self) -> &HashSet<ScanCode> {
+        &self.pressed_scancodes
+    }
+
+    #[cfg(feature = "graphics")]
+    pub(crate) fn set_modifiers(&mut self, modifiers: ModifiersState) {
+        self.modifiers = modifiers;
+    }
+
+    /// The key pressed before [`current_pressed`](Self::current_pressed), for detecting
+    /// sequences such as double taps.
+    pub fn last_pressed(&self) -> Option<KeyCode> {
+        self.last_pressed
+    }
+
+    /// The modifier keys (shift, ctrl, alt, logo) currently held.
+    pub fn modifiers(&self) -> ModifiersState {
+        self.modifiers
+    }
+
     // pub(crate) fn pressed_keys(&self) -> &HashSet<KeyCode> {
     //     &self.pressed_keys
     // }
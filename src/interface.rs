@@ -1,5 +1,6 @@
 use crate::conf::*;
 use crate::error::*;
+use crate::event::{EventLoop, EventProxy, KeyEvent, WindowEvent};
 
 #[allow(unused)]
 pub struct InterfaceBuilder {
@@ -14,11 +15,11 @@ impl InterfaceBuilder {
         Self {
             game_name: game_name.to_string(),
             author: author.to_string(),
-            configuration: configuration,
+            configuration,
         }
     }
 
-    pub fn build(self) -> GameResult<(Interface, winit::event_loop::EventLoop<()>)> {
+    pub fn build<E: 'static>(self) -> GameResult<(Interface<E>, EventLoop<E>)> {
         Interface::from_conf(self.configuration)
     }
 
@@ -31,78 +32,128 @@ impl InterfaceBuilder {
         self.configuration.window_mode = mode;
         self
     }
+
+    /// See [`Conf::use_placeholder_on_error`].
+    pub fn use_placeholder_on_error(mut self, enabled: bool) -> Self {
+        self.configuration = self.configuration.use_placeholder_on_error(enabled);
+        self
+    }
+
+    /// See [`Conf::apply_args`], returns the arguments it didn't recognize.
+    pub fn apply_args<I: IntoIterator<Item = String>>(&mut self, args: I) -> Result<Vec<String>, ArgsError> {
+        self.configuration.apply_args(args)
+    }
 }
 
-pub struct Interface {
+/// Everything the game needs at runtime, `E` is the type of user events that other threads can
+/// send to the event loop through [`Interface::event_proxy`].
+pub struct Interface<E: 'static = ()> {
     pub renderer: crate::graphics::renderer::Renderer,
     pub keyboard_context: crate::input::keyboard::KeyboardContext,
     pub mouse_context: crate::input::mouse::MouseContext,
     pub timer_state: crate::timer::TimerState,
+    /// Set once the window has received a close request.
+    pub close_requested: bool,
+    proxy: EventProxy<E>,
+    user_events: Vec<E>,
 }
 
-impl Interface {
-    pub fn from_conf(instance_conf: Conf) -> GameResult<(Self, winit::event_loop::EventLoop<()>)> {
+impl<E: 'static> Interface<E> {
+    pub fn from_conf(instance_conf: Conf) -> GameResult<(Self, EventLoop<E>)> {
         let (renderer, event_loop) =
             crate::graphics::renderer::Renderer::new(instance_conf);
-        let interface_ctx = Interface {
+        let event_loop = EventLoop::new(event_loop);
+        let interface_ctx = Self::with_renderer(renderer, event_loop.proxy());
+
+        Ok((interface_ctx, event_loop))
+    }
+
+    /// Builds the interface on an event loop the caller already owns, e.g. one shared with
+    /// another library. The caller runs the loop, forwarding window events to the renderer
+    /// and input contexts instead of using [`crate::event::run`].
+    #[cfg(feature = "unstable-raw")]
+    pub fn from_event_loop(instance_conf: Conf, event_loop: &winit::event_loop::EventLoop<E>) -> GameResult<Self> {
+        let renderer = crate::graphics::renderer::Renderer::from_event_loop(instance_conf, event_loop);
+        Ok(Self::with_renderer(renderer, EventProxy::new(event_loop)))
+    }
+
+    fn with_renderer(renderer: crate::graphics::renderer::Renderer, proxy: EventProxy<E>) -> Self {
+        let mut timer_state = crate::timer::TimerState::new();
+        timer_state.set_refresh_rate(renderer.monitor_refresh_rate());
+
+        Interface {
             renderer,
             keyboard_context: crate::input::keyboard::KeyboardContext::new(),
             mouse_context: crate::input::mouse::MouseContext::new(),
-            timer_state: crate::timer::TimerState::new(),
-        };
+            timer_state,
+            close_requested: false,
+            proxy,
+            user_events: Vec::new(),
+        }
+    }
 
-        Ok((interface_ctx, event_loop))
+    /// A handle other threads can use to send user events to the event loop.
+    ///
+    /// Events sent through it are collected by [`crate::event::run`] and held until they're
+    /// taken with [`Interface::pending_user_events`].
+    pub fn event_proxy(&self) -> EventProxy<E> {
+        self.proxy.clone()
     }
 
-    pub fn process_event(&mut self, event: &winit::event::Event<()>) {
-        match event {
-            // Window events.
-            winit::event::Event::WindowEvent { event, .. } => match event {
-                winit::event::WindowEvent::Resized(_) => {
-                    self.renderer.recreate_swapchain = true;
-                }
-                winit::event::WindowEvent::CursorMoved { position, .. } => {
-                    self.mouse_context
-                        .set_last_position((position.x, position.y));
-                }
-                winit::event::WindowEvent::MouseInput { state, button, .. } => {
-                    let button = match button {
-                        winit::event::MouseButton::Left => crate::input::mouse::MouseButton::Left,
-                        winit::event::MouseButton::Right => crate::input::mouse::MouseButton::Right,
-                        winit::event::MouseButton::Middle => {
-                            crate::input::mouse::MouseButton::Middle
-                        }
-                        winit::event::MouseButton::Other(val) => {
-                            crate::input::mouse::MouseButton::Misc(*val)
-                        }
-                    };
-
-                    let pressed = match state {
-                        winit::event::ElementState::Pressed => true,
-                        winit::event::ElementState::Released => false,
-                    };
-
-                    self.mouse_context.set_button(button, pressed);
-                }
-                winit::event::WindowEvent::KeyboardInput {
-                    input:
-                        winit::event::KeyboardInput {
-                            state,
-                            virtual_keycode: Some(keycode),
-                            ..
-                        },
-                    ..
-                } => {
-                    let pressed = match state {
-                        winit::event::ElementState::Pressed => true,
-                        winit::event::ElementState::Released => false,
-                    };
-                    self.keyboard_context.set_key(*keycode, pressed);
+    /// The cursor's last position in the renderer's [`crate::graphics::CoordinateSystem`].
+    pub fn cursor_position(&self) -> (f32, f32) {
+        let (x, y) = self.mouse_context.last_position;
+        self.renderer.coords().window_to_viewport((x as f32, y as f32))
+    }
+
+    /// Takes every user event received since the last call.
+    ///
+    /// Events are kept until they're taken, with no limit on how many, so a game whose
+    /// threads send user events should call this every update to keep the queue from growing.
+    pub fn pending_user_events(&mut self) -> Vec<E> {
+        std::mem::take(&mut self.user_events)
+    }
+
+    pub(crate) fn push_user_event(&mut self, event: E) {
+        self.user_events.push(event);
+    }
+
+    /// Routes a window event into the input contexts and renderer state.
+    ///
+    /// Handles keyboard input and modifier changes, cursor movement and mouse buttons, window
+    /// resizes (flagging the swapchain for recreation), and close requests (setting
+    /// `close_requested`). Everything else, including actually exiting the event loop, is left
+    /// to the application. Returns `true` if the event was one of the handled kinds.
+    pub fn process_event(&mut self, event: &WindowEvent) -> bool {
+        match *event {
+            WindowEvent::CloseRequested => {
+                self.close_requested = true;
+                true
+            }
+            WindowEvent::Resized { .. } => {
+                self.renderer.recreate_swapchain = true;
+                true
+            }
+            WindowEvent::CursorMoved { x, y } => {
+                self.mouse_context.set_last_position((x, y));
+                true
+            }
+            WindowEvent::MouseButton { button, pressed } => {
+                self.mouse_context.set_button(button, pressed);
+                true
+            }
+            WindowEvent::Key(KeyEvent { key, scancode, pressed }) => {
+                self.keyboard_context.set_scancode(scancode, pressed);
+                if let Some(key) = key {
+                    self.keyboard_context.set_key(key, pressed);
                 }
-                _ => {}
-            },
-            // Others.
-            _ => {}
+                true
+            }
+            WindowEvent::ModifiersChanged(modifiers) => {
+                self.keyboard_context.set_modifiers(modifiers);
+                true
+            }
+            _ => false,
         }
     }
 }
@@ -15,7 +15,10 @@
 //! that handle drawing or interfacing with the backend and handling audio production respectively.
 //!
 //! # Basic Graphics Example
-//! ```
+//! Written against the old `GraphicsContext` API, kept for its overview of the moving parts
+//! rather than as code that compiles today.
+//!
+//! ```ignore
 //! use winit::{
 //!     event_loop::{ControlFlow},
 //!     event::{Event, WindowEvent}
@@ -164,10 +167,20 @@
 /// Graphics and other configuration options.
 pub mod conf;
 /// The ```graphics``` module handles all drawing operations for any type implementing the ```Drawable``` trait.
+#[cfg(feature = "graphics")]
 pub mod graphics;
+/// Only the math types without the `graphics` feature, see the module docs.
+#[cfg(not(feature = "graphics"))]
+#[path = "graphics/headless.rs"]
+pub mod graphics;
+
+/// Decoding asset files on the CPU, available without the `graphics` feature.
+pub mod asset;
 
+#[cfg(feature = "graphics")]
 pub mod event;
 
+#[cfg(feature = "graphics")]
 pub mod interface;
 
 /// TODO: Add some audio module.
@@ -178,8 +191,60 @@ pub mod input;
 /// A module that stores timing data.
 pub mod timer;
 
+/// A minimal logger for the `log` facade used throughout the crate.
+#[cfg(feature = "simple-logger")]
+pub mod logger;
+#[cfg(feature = "simple-logger")]
+pub use logger::init_simple_logger;
+
 // pub mod scene;
 
+/// The winit types that appear in ledge's public API, so apps don't need their own winit
+/// dependency pinned to the same version.
+#[cfg(feature = "window")]
+pub use winit::event::{ModifiersState, ScanCode, VirtualKeyCode};
+
+/// The windowing and Vulkan crates ledge is built on, for power users who need what ledge's
+/// own types don't expose. Nothing here is covered by ledge's semver guarantees.
+#[cfg(feature = "unstable-raw")]
+pub mod raw {
+    pub use vulkano;
+    pub use winit;
+}
+
+/// The types most apps need, `use ledge::prelude::*;` brings them all into scope. Without the
+/// `graphics` feature only the configuration, error, math, and input types are in it.
+///
+/// ```
+/// use ledge::prelude::*;
+///
+/// fn layout(_: Option<(Conf, Color, Rect, Transform, ScanCode)>) -> GameResult {
+///     Ok(())
+/// }
+///
+/// # #[cfg(feature = "graphics")]
+/// fn assets(_: Option<(DrawInfo, Camera2D, KeyCode, Image, SpriteBatch, Interface, Renderer)>) {}
+/// ```
 pub mod prelude {
-    pub use crate::*;
+    pub use crate::conf::Conf;
+    pub use crate::error::{GameError, GameResult};
+    #[cfg(feature = "graphics")]
+    pub use crate::event::{self, run, EventHandler, EventLoop, EventProxy, KeyEvent, WindowEvent};
+    #[cfg(feature = "graphics")]
+    pub use crate::graphics::camera::{Camera, Camera2D, OrthographicCamera, PerspectiveCamera};
+    #[cfg(feature = "graphics")]
+    pub use crate::graphics::image::Image;
+    #[cfg(feature = "graphics")]
+    pub use crate::graphics::renderer::Renderer;
+    #[cfg(feature = "graphics")]
+    pub use crate::graphics::sprite::SpriteBatch;
+    #[cfg(feature = "graphics")]
+    pub use crate::graphics::{BlendMode, CoordinateSystem, DrawInfo, Drawable};
+    pub use crate::graphics::{self, Color, Rect, Transform};
+    #[cfg(feature = "window")]
+    pub use crate::input::keyboard::{KeyCode, ModifiersState};
+    pub use crate::input::keyboard::{scancode, ScanCode};
+    pub use crate::input::mouse::MouseButton;
+    #[cfg(feature = "graphics")]
+    pub use crate::interface::{Interface, InterfaceBuilder};
 }
@@ -0,0 +1,30 @@
+use log::{LevelFilter, Log, Metadata, Record, SetLoggerError};
+
+/// Writes every record at or above the level given to [`init_simple_logger`] to stderr.
+struct SimpleLogger;
+
+static LOGGER: SimpleLogger = SimpleLogger;
+
+impl Log for SimpleLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if self.enabled(record.metadata()) {
+            eprintln!("[{:<5} {}] {}", record.level(), record.target(), record.args());
+        }
+    }
+
+    fn flush(&self) {}
+}
+
+/// Installs a logger printing `level` and above to stderr, for apps that don't set up a
+/// logger of their own.
+///
+/// Fails if a logger has already been installed.
+pub fn init_simple_logger(level: LevelFilter) -> Result<(), SetLoggerError> {
+    log::set_logger(&LOGGER)?;
+    log::set_max_level(level);
+    Ok(())
+}
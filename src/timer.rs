@@ -1,10 +1,16 @@
 use std::time;
 
+/// The frame rate [`crate::event::run`] paces to when there's no target and the monitor's
+/// refresh rate is unknown.
+pub const DEFAULT_FPS: u32 = 60;
+
 pub struct TimerState {
     _initial_instant: time::Instant,
     last_instant: time::Instant,
     frame_times: Vec<time::Duration>,
     pub accumulator: time::Duration,
+    target_fps: Option<u32>,
+    refresh_rate: Option<u32>,
 }
 
 impl Default for TimerState {
@@ -20,9 +26,36 @@ impl TimerState {
             last_instant: time::Instant::now(),
             frame_times: Vec::new(),
             accumulator: time::Duration::from_secs(0),
+            target_fps: None,
+            refresh_rate: None,
         }
     }
 
+    /// Paces frames to `fps` instead of the monitor's refresh rate, `None` to go back to it.
+    pub fn set_target_fps(&mut self, fps: Option<u32>) {
+        self.target_fps = fps.filter(|&fps| fps > 0);
+    }
+
+    pub fn target_fps(&self) -> Option<u32> {
+        self.target_fps
+    }
+
+    /// The refresh rate in Hz of the monitor the window is on, kept up to date by
+    /// [`crate::event::run`] as the window moves between monitors.
+    pub fn set_refresh_rate(&mut self, hz: Option<u32>) {
+        self.refresh_rate = hz.filter(|&hz| hz > 0);
+    }
+
+    pub fn refresh_rate(&self) -> Option<u32> {
+        self.refresh_rate
+    }
+
+    /// How long [`crate::event::run`] lets a frame take before starting the next, see
+    /// [`frame_budget`].
+    pub fn frame_budget(&self) -> time::Duration {
+        frame_budget(self.target_fps, self.refresh_rate)
+    }
+
     pub fn tick(&mut self) {
         let now = time::Instant::now();
         let frame_time = now - self.last_instant;
@@ -48,6 +81,16 @@ impl TimerState {
     }
 }
 
+/// One frame at `target_fps` if it's set, otherwise one refresh of a monitor running at
+/// `refresh_rate` Hz, otherwise one frame at [`DEFAULT_FPS`].
+pub fn frame_budget(target_fps: Option<u32>, refresh_rate: Option<u32>) -> time::Duration {
+    let fps = target_fps
+        .or(refresh_rate)
+        .filter(|&fps| fps > 0)
+        .unwrap_or(DEFAULT_FPS);
+    fps_as_duration(fps)
+}
+
 pub fn fps_as_duration(fps: u32) -> time::Duration {
     let target_dt_seconds = 1.0 / f64::from(fps);
     f64_to_duration(target_dt_seconds)
@@ -59,3 +102,42 @@ pub fn f64_to_duration(t: f64) -> time::Duration {
     let nanos = t.fract() * 1e9;
     time::Duration::new(seconds as u64, nanos as u32)
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn detected_refresh_rate_sets_the_budget() {
+        let mut timer = TimerState::new();
+        assert_eq!(timer.frame_budget(), fps_as_duration(DEFAULT_FPS));
+
+        timer.set_refresh_rate(Some(144));
+        assert_eq!(timer.frame_budget(), fps_as_duration(144));
+
+        // The window moved to another monitor.
+        timer.set_refresh_rate(Some(75));
+        assert_eq!(timer.frame_budget(), fps_as_duration(75));
+    }
+
+    #[test]
+    fn target_fps_overrides_the_refresh_rate() {
+        let mut timer = TimerState::new();
+        timer.set_refresh_rate(Some(144));
+        timer.set_target_fps(Some(30));
+        assert_eq!(timer.frame_budget(), fps_as_duration(30));
+
+        timer.set_target_fps(None);
+        assert_eq!(timer.frame_budget(), fps_as_duration(144));
+    }
+
+    #[test]
+    fn unknown_refresh_rates_fall_back_to_the_default() {
+        assert_eq!(frame_budget(None, Some(0)), fps_as_duration(DEFAULT_FPS));
+        assert_eq!(frame_budget(Some(0), None), fps_as_duration(DEFAULT_FPS));
+
+        let mut timer = TimerState::new();
+        timer.set_refresh_rate(Some(0));
+        assert_eq!(timer.refresh_rate(), None);
+    }
+}